@@ -0,0 +1,95 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::AccountMeta;
+
+#[derive(Clone)]
+pub struct IncoTransfer<'info> {
+    pub source: AccountInfo<'info>,
+    pub destination: AccountInfo<'info>,
+    pub authority: AccountInfo<'info>,
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+}
+
+impl<'info> ToAccountInfos<'info> for IncoTransfer<'info> {
+    fn to_account_infos(&self) -> Vec<AccountInfo<'info>> {
+        vec![
+            self.source.clone(),
+            self.destination.clone(),
+            self.authority.clone(),
+            self.inco_lightning_program.clone(),
+            self.system_program.clone(),
+        ]
+    }
+}
+
+impl<'info> ToAccountMetas for IncoTransfer<'info> {
+    fn to_account_metas(&self, _is_signer: Option<bool>) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.source.key(), false),
+            AccountMeta::new(self.destination.key(), false),
+            AccountMeta::new(self.authority.key(), true),
+            AccountMeta::new_readonly(self.inco_lightning_program.key(), false),
+            AccountMeta::new_readonly(self.system_program.key(), false),
+        ]
+    }
+}
+
+#[derive(Clone)]
+pub struct IncoBurn<'info> {
+    pub account: AccountInfo<'info>,
+    pub mint: AccountInfo<'info>,
+    pub authority: AccountInfo<'info>,
+    pub inco_lightning_program: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+}
+
+impl<'info> ToAccountInfos<'info> for IncoBurn<'info> {
+    fn to_account_infos(&self) -> Vec<AccountInfo<'info>> {
+        vec![
+            self.account.clone(),
+            self.mint.clone(),
+            self.authority.clone(),
+            self.inco_lightning_program.clone(),
+            self.system_program.clone(),
+        ]
+    }
+}
+
+impl<'info> ToAccountMetas for IncoBurn<'info> {
+    fn to_account_metas(&self, _is_signer: Option<bool>) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.account.key(), false),
+            AccountMeta::new(self.mint.key(), false),
+            AccountMeta::new(self.authority.key(), true),
+            AccountMeta::new_readonly(self.inco_lightning_program.key(), false),
+            AccountMeta::new_readonly(self.system_program.key(), false),
+        ]
+    }
+}
+
+#[derive(Clone)]
+pub struct IncoCloseAccount<'info> {
+    pub account: AccountInfo<'info>,
+    pub destination: AccountInfo<'info>,
+    pub authority: AccountInfo<'info>,
+}
+
+impl<'info> ToAccountInfos<'info> for IncoCloseAccount<'info> {
+    fn to_account_infos(&self) -> Vec<AccountInfo<'info>> {
+        vec![
+            self.account.clone(),
+            self.destination.clone(),
+            self.authority.clone(),
+        ]
+    }
+}
+
+impl<'info> ToAccountMetas for IncoCloseAccount<'info> {
+    fn to_account_metas(&self, _is_signer: Option<bool>) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.account.key(), false),
+            AccountMeta::new(self.destination.key(), false),
+            AccountMeta::new_readonly(self.authority.key(), true),
+        ]
+    }
+}
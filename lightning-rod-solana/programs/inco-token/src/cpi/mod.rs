@@ -0,0 +1,69 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program::invoke_signed;
+
+pub mod accounts;
+
+pub use accounts::*;
+
+fn sighash(namespace: &str, name: &str) -> [u8; 8] {
+    let preimage = format!("{}:{}", namespace, name);
+    let mut sighash = [0u8; 8];
+    sighash.copy_from_slice(
+        &anchor_lang::solana_program::hash::hash(preimage.as_bytes()).to_bytes()[..8],
+    );
+    sighash
+}
+
+fn ciphertext_data(name: &str, ciphertext: &[u8], input_type: u8) -> Vec<u8> {
+    let mut data = Vec::with_capacity(8 + 4 + ciphertext.len() + 1);
+    data.extend_from_slice(&sighash("global", name));
+    data.extend_from_slice(&(ciphertext.len() as u32).to_le_bytes());
+    data.extend_from_slice(ciphertext);
+    data.push(input_type);
+    data
+}
+
+pub fn transfer<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, IncoTransfer<'info>>,
+    ciphertext: Vec<u8>,
+    input_type: u8,
+) -> Result<()> {
+    let ix = Instruction {
+        program_id: crate::ID,
+        accounts: ctx.accounts.to_account_metas(None),
+        data: ciphertext_data("transfer", &ciphertext, input_type),
+    };
+
+    invoke_signed(&ix, &ctx.accounts.to_account_infos(), ctx.signer_seeds)?;
+
+    Ok(())
+}
+
+pub fn burn<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, IncoBurn<'info>>,
+    ciphertext: Vec<u8>,
+    input_type: u8,
+) -> Result<()> {
+    let ix = Instruction {
+        program_id: crate::ID,
+        accounts: ctx.accounts.to_account_metas(None),
+        data: ciphertext_data("burn", &ciphertext, input_type),
+    };
+
+    invoke_signed(&ix, &ctx.accounts.to_account_infos(), ctx.signer_seeds)?;
+
+    Ok(())
+}
+
+pub fn close_account<'info>(ctx: CpiContext<'_, '_, '_, 'info, IncoCloseAccount<'info>>) -> Result<()> {
+    let ix = Instruction {
+        program_id: crate::ID,
+        accounts: ctx.accounts.to_account_metas(None),
+        data: sighash("global", "close_account").to_vec(),
+    };
+
+    invoke_signed(&ix, &ctx.accounts.to_account_infos(), ctx.signer_seeds)?;
+
+    Ok(())
+}
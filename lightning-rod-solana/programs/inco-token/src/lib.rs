@@ -0,0 +1,48 @@
+use anchor_lang::prelude::*;
+use inco_lightning::types::Euint128;
+
+pub mod cpi;
+pub mod program;
+
+pub use program::IncoToken;
+
+declare_id!("4cyJHzecVWuU2xux6bCAPAhALKQT8woBh4Vx3AGEGe5N");
+
+/// Input type under which `transfer`, `burn` and `mint_to` read their ciphertext argument as the
+/// 16-byte little-endian handle of an existing encrypted value (see `handle_ciphertext`) rather
+/// than a client ciphertext. The program rejects a handle the signer is not allowed on.
+/// `zivo-v1/tests/zivo-v1.ts` checks this against the deployed program before any escrow test.
+pub const HANDLE_INPUT_TYPE: u8 = 2;
+
+/// Encodes `handle` as a ciphertext argument for `HANDLE_INPUT_TYPE`.
+pub fn handle_ciphertext(handle: u128) -> Vec<u8> {
+    handle.to_le_bytes().to_vec()
+}
+
+#[account]
+pub struct IncoMint {
+    pub mint_authority: Option<Pubkey>,
+    pub supply: Euint128,
+    pub decimals: u8,
+    pub is_initialized: bool,
+    pub freeze_authority: Option<Pubkey>,
+}
+
+#[account]
+pub struct IncoAccount {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub amount: Euint128,
+    pub delegate: Option<Pubkey>,
+    pub state: AccountState,
+    pub is_native: Option<u64>,
+    pub delegated_amount: Euint128,
+    pub close_authority: Option<Pubkey>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccountState {
+    Uninitialized,
+    Initialized,
+    Frozen,
+}
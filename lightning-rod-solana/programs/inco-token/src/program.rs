@@ -0,0 +1,10 @@
+use anchor_lang::prelude::*;
+
+#[derive(Clone)]
+pub struct IncoToken;
+
+impl anchor_lang::Id for IncoToken {
+    fn id() -> Pubkey {
+        crate::ID
+    }
+}
@@ -0,0 +1,49 @@
+//! CPI interface for programs that trade on zivo-v1 with program-owned (PDA) traders.
+//!
+//! A PDA trader owns its Inco base/quote accounts (`initialize_account` with the PDA as
+//! `owner`) and signs every orderbook instruction through `invoke_signed`:
+//!
+//! - `place_order_with_payer`: the PDA signs as `trader`, a wallet (or system-owned PDA)
+//!   pays the `Order` rent.
+//! - `cancel_order`, `close_order`, `maker_claim_filled_order`: the PDA signs as
+//!   `trader`/`owner`/`maker`; no rent is created.
+//! - `match_order`: only the matcher signs, so PDA-owned orders can be taker or maker.
+//!
+//! The helpers below derive the orderbook PDAs so callers do not have to duplicate seeds.
+
+use anchor_lang::prelude::*;
+
+pub use zivo_orderbook_program::cpi;
+pub use zivo_orderbook_program::cpi::accounts;
+pub use zivo_orderbook_program::program::ZivoOrderbookProgram;
+pub use zivo_orderbook_program::state::{Order, OrderbookState};
+pub use zivo_orderbook_program::ID;
+
+pub const MARKET_SEED: &[u8] = b"orderbook_market_v1";
+pub const ORDER_SEED: &[u8] = b"order_v1";
+pub const VAULT_AUTHORITY_SEED: &[u8] = b"inco_vault_authority_v12";
+pub const ACCESS_PASS_SEED: &[u8] = b"access_pass_v1";
+
+/// Market PDA for a base/quote pair.
+pub fn market_address(base_mint: &Pubkey, quote_mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[MARKET_SEED, base_mint.as_ref(), quote_mint.as_ref()], &ID).0
+}
+
+/// Order PDA; `seq` is the market's `order_seq` at placement time.
+pub fn order_address(market: &Pubkey, owner: &Pubkey, seq: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[ORDER_SEED, market.as_ref(), owner.as_ref(), &seq.to_le_bytes()],
+        &ID,
+    )
+    .0
+}
+
+/// Authority of the market's Inco vaults.
+pub fn vault_authority_address(market: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[VAULT_AUTHORITY_SEED, market.as_ref()], &ID).0
+}
+
+/// Access pass PDA required on gatekept markets.
+pub fn access_pass_address(market: &Pubkey, trader: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[ACCESS_PASS_SEED, market.as_ref(), trader.as_ref()], &ID).0
+}
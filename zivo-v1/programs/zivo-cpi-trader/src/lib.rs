@@ -0,0 +1,214 @@
+// `#[program]` expands to IDL account instructions that call `AccountInfo::realloc`, deprecated
+// under the pinned Anchor 0.31; the expansion cannot carry an attribute of its own.
+#![allow(deprecated)]
+
+use anchor_lang::prelude::*;
+use zivo_v1_interface::{accounts as zivo_accounts, cpi as zivo_cpi, ZivoOrderbookProgram};
+
+declare_id!("B67rVHaQ2ZomWZd8S9sCBLoteC98cCS7Ep89PcMiGKHm");
+
+/// Minimal strategy program exercising the zivo-v1 CPI interface: a `Strategy` PDA owns
+/// Inco accounts and trades on one market, signing with its seeds. Remaining accounts (owner
+/// and auditor allowance pairs) are forwarded to zivo-v1 unchanged.
+#[program]
+pub mod zivo_cpi_trader {
+    use super::*;
+
+    pub fn initialize_strategy(ctx: Context<InitializeStrategy>) -> Result<()> {
+        let strategy = &mut ctx.accounts.strategy;
+        strategy.authority = ctx.accounts.authority.key();
+        strategy.market = ctx.accounts.market.key();
+        strategy.bump = ctx.bumps.strategy;
+        Ok(())
+    }
+
+    pub fn place_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, StrategyPlaceOrder<'info>>,
+        side: u8,
+        price: u64,
+        size_ciphertext: Vec<u8>,
+        input_type: u8,
+    ) -> Result<()> {
+        let strategy = &ctx.accounts.strategy;
+        let seeds: &[&[u8]] = &[
+            b"strategy",
+            strategy.authority.as_ref(),
+            strategy.market.as_ref(),
+            &[strategy.bump],
+        ];
+        zivo_cpi::place_order_with_payer(
+            CpiContext::new_with_signer(
+                ctx.accounts.zivo_program.to_account_info(),
+                zivo_accounts::PlaceOrderWithPayer {
+                    state: ctx.accounts.market.to_account_info(),
+                    order: ctx.accounts.order.to_account_info(),
+                    trader: strategy.to_account_info(),
+                    payer: ctx.accounts.authority.to_account_info(),
+                    access_pass: None,
+                    inco_vault_authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                    inco_base_vault: ctx.accounts.inco_base_vault.to_account_info(),
+                    inco_quote_vault: ctx.accounts.inco_quote_vault.to_account_info(),
+                    trader_base_inco: ctx.accounts.strategy_base_inco.to_account_info(),
+                    trader_quote_inco: ctx.accounts.strategy_quote_inco.to_account_info(),
+                    inco_base_mint: ctx.accounts.inco_base_mint.to_account_info(),
+                    inco_quote_mint: ctx.accounts.inco_quote_mint.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                    inco_token_program: ctx.accounts.inco_token_program.to_account_info(),
+                    inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                },
+                &[seeds],
+            )
+            .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            side,
+            price,
+            size_ciphertext,
+            input_type,
+        )
+    }
+
+    pub fn cancel_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, StrategyCancelOrder<'info>>,
+    ) -> Result<()> {
+        let strategy = &ctx.accounts.strategy;
+        let seeds: &[&[u8]] = &[
+            b"strategy",
+            strategy.authority.as_ref(),
+            strategy.market.as_ref(),
+            &[strategy.bump],
+        ];
+        zivo_cpi::cancel_order(
+            CpiContext::new_with_signer(
+                ctx.accounts.zivo_program.to_account_info(),
+                zivo_accounts::CancelOrder {
+                    state: ctx.accounts.market.to_account_info(),
+                    order: ctx.accounts.order.to_account_info(),
+                    order_group: None,
+                    trader: strategy.to_account_info(),
+                    inco_vault_authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                    inco_base_vault: ctx.accounts.inco_base_vault.to_account_info(),
+                    inco_quote_vault: ctx.accounts.inco_quote_vault.to_account_info(),
+                    trader_base_inco: ctx.accounts.strategy_base_inco.to_account_info(),
+                    trader_quote_inco: ctx.accounts.strategy_quote_inco.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                    inco_token_program: ctx.accounts.inco_token_program.to_account_info(),
+                    inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                },
+                &[seeds],
+            )
+            .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+        )
+    }
+}
+
+#[account]
+#[derive(Default)]
+pub struct Strategy {
+    pub authority: Pubkey,
+    pub market: Pubkey,
+    pub bump: u8,
+}
+
+impl Strategy {
+    pub const LEN: usize = 32 + 32 + 1;
+}
+
+#[derive(Accounts)]
+pub struct InitializeStrategy<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Strategy::LEN,
+        seeds = [b"strategy", authority.key().as_ref(), market.key().as_ref()],
+        bump
+    )]
+    pub strategy: Account<'info, Strategy>,
+    /// CHECK: zivo-v1 market the strategy trades on
+    #[account(owner = zivo_v1_interface::ID)]
+    pub market: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct StrategyPlaceOrder<'info> {
+    #[account(
+        has_one = authority,
+        has_one = market,
+        seeds = [b"strategy", authority.key().as_ref(), market.key().as_ref()],
+        bump = strategy.bump
+    )]
+    pub strategy: Account<'info, Strategy>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: validated by zivo-v1
+    #[account(mut)]
+    pub market: UncheckedAccount<'info>,
+    /// CHECK: order PDA created by zivo-v1
+    #[account(mut)]
+    pub order: UncheckedAccount<'info>,
+    /// CHECK: validated by zivo-v1
+    pub inco_vault_authority: UncheckedAccount<'info>,
+    /// CHECK: validated by zivo-v1
+    #[account(mut)]
+    pub inco_base_vault: UncheckedAccount<'info>,
+    /// CHECK: validated by zivo-v1
+    #[account(mut)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    /// CHECK: Inco account owned by the strategy PDA, validated by zivo-v1
+    #[account(mut)]
+    pub strategy_base_inco: UncheckedAccount<'info>,
+    /// CHECK: Inco account owned by the strategy PDA, validated by zivo-v1
+    #[account(mut)]
+    pub strategy_quote_inco: UncheckedAccount<'info>,
+    /// CHECK: validated by zivo-v1
+    pub inco_base_mint: UncheckedAccount<'info>,
+    /// CHECK: validated by zivo-v1
+    pub inco_quote_mint: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: validated by zivo-v1
+    pub inco_token_program: UncheckedAccount<'info>,
+    /// CHECK: validated by zivo-v1
+    pub inco_lightning_program: UncheckedAccount<'info>,
+    pub zivo_program: Program<'info, ZivoOrderbookProgram>,
+}
+
+#[derive(Accounts)]
+pub struct StrategyCancelOrder<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+        has_one = market,
+        seeds = [b"strategy", authority.key().as_ref(), market.key().as_ref()],
+        bump = strategy.bump
+    )]
+    pub strategy: Account<'info, Strategy>,
+    pub authority: Signer<'info>,
+    /// CHECK: validated by zivo-v1
+    #[account(mut)]
+    pub market: UncheckedAccount<'info>,
+    /// CHECK: validated by zivo-v1
+    #[account(mut)]
+    pub order: UncheckedAccount<'info>,
+    /// CHECK: validated by zivo-v1
+    #[account(mut)]
+    pub inco_vault_authority: UncheckedAccount<'info>,
+    /// CHECK: validated by zivo-v1
+    #[account(mut)]
+    pub inco_base_vault: UncheckedAccount<'info>,
+    /// CHECK: validated by zivo-v1
+    #[account(mut)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    /// CHECK: Inco account owned by the strategy PDA, validated by zivo-v1
+    #[account(mut)]
+    pub strategy_base_inco: UncheckedAccount<'info>,
+    /// CHECK: Inco account owned by the strategy PDA, validated by zivo-v1
+    #[account(mut)]
+    pub strategy_quote_inco: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: validated by zivo-v1
+    pub inco_token_program: UncheckedAccount<'info>,
+    /// CHECK: validated by zivo-v1
+    pub inco_lightning_program: UncheckedAccount<'info>,
+    pub zivo_program: Program<'info, ZivoOrderbookProgram>,
+}
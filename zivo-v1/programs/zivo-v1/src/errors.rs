@@ -38,4 +38,138 @@ pub enum OrderbookError {
     OrderNotFilled,
     #[msg("Order already claimed")]
     OrderAlreadyClaimed,
+    #[msg("Invalid market status")]
+    InvalidMarketStatus,
+    #[msg("Market is closed")]
+    MarketClosed,
+    #[msg("Market is not closed")]
+    MarketNotClosed,
+    #[msg("Market vaults still hold escrowed funds")]
+    MarketHasOpenEscrows,
+    #[msg("Unauthorized protocol admin")]
+    UnauthorizedProtocolAdmin,
+    #[msg("Invalid market creation policy")]
+    InvalidMarketCreationPolicy,
+    #[msg("Invalid fee")]
+    InvalidFee,
+    #[msg("Unauthorized gatekeeper")]
+    UnauthorizedGatekeeper,
+    #[msg("Access pass required for this market")]
+    MissingAccessPass,
+    #[msg("Invalid access pass")]
+    InvalidAccessPass,
+    #[msg("Allowance address does not match handle owner")]
+    InvalidAllowanceAddress,
+    #[msg("No pending auditor change")]
+    NoPendingAuditor,
+    #[msg("Auditor timelock has not elapsed")]
+    AuditorTimelockActive,
+    #[msg("Auditor allowance accounts are missing")]
+    MissingAuditorAllowance,
+    #[msg("Owner allowance accounts are missing")]
+    MissingOwnerAllowance,
+    #[msg("Dark orders can only match other dark orders")]
+    DarkOrderMismatch,
+    #[msg("Invalid market mode")]
+    InvalidMarketMode,
+    #[msg("Auction window is still open")]
+    AuctionWindowOpen,
+    #[msg("Batch auction book is full")]
+    AuctionBookFull,
+    #[msg("No crossing orders in auction")]
+    NoCrossingOrders,
+    #[msg("Order passed twice to the auction")]
+    DuplicateAuctionOrder,
+    #[msg("Auction must include every open order")]
+    IncompleteAuctionOrders,
+    #[msg("Invalid peg type")]
+    InvalidPegType,
+    #[msg("Pegged bids need a non-zero limit price")]
+    PegLimitRequired,
+    #[msg("Price feed account required")]
+    MissingPriceFeed,
+    #[msg("Invalid price feed")]
+    InvalidPriceFeed,
+    #[msg("Price feed is stale")]
+    StalePriceFeed,
+    #[msg("Price feed confidence too wide")]
+    PriceFeedConfidenceTooWide,
+    #[msg("Invalid trigger parameters")]
+    InvalidTrigger,
+    #[msg("Trigger condition not met")]
+    TriggerNotMet,
+    #[msg("Order group account required")]
+    MissingOrderGroup,
+    #[msg("Invalid order group")]
+    InvalidOrderGroup,
+    #[msg("Order group is full")]
+    OrderGroupFull,
+    #[msg("Order group is no longer active")]
+    OrderGroupInactive,
+    #[msg("Invalid TWAP parameters")]
+    InvalidTwapParams,
+    #[msg("Next TWAP slice is not due yet")]
+    TwapSliceNotDue,
+    #[msg("Invalid quote")]
+    InvalidQuote,
+    #[msg("Invalid RFQ")]
+    InvalidRfq,
+    #[msg("RFQ is not open")]
+    RfqNotOpen,
+    #[msg("RFQ has expired")]
+    RfqExpired,
+    #[msg("RFQ is still open")]
+    RfqStillOpen,
+    #[msg("Invalid ed25519 signature instruction")]
+    InvalidSignature,
+    #[msg("Invalid signed order")]
+    InvalidSignedOrder,
+    #[msg("Signed order has expired")]
+    SignedOrderExpired,
+    #[msg("Nonce out of range")]
+    InvalidNonce,
+    #[msg("Nonce already used or cancelled")]
+    NonceAlreadyUsed,
+    #[msg("Invalid trading delegate")]
+    InvalidDelegate,
+    #[msg("Trading delegate has expired")]
+    DelegateExpired,
+    #[msg("Action not allowed for this trading delegate")]
+    DelegateActionNotAllowed,
+    #[msg("Trading delegate order limit reached")]
+    DelegateOrderLimit,
+    #[msg("Invalid ciphertext buffer")]
+    InvalidCiphertextBuffer,
+    #[msg("Ciphertext buffer write out of bounds")]
+    CiphertextBufferOverflow,
+    #[msg("Invalid pending match")]
+    InvalidPendingMatch,
+    #[msg("Orders changed since the match was prepared")]
+    PendingMatchStale,
+    #[msg("Pending match has expired")]
+    PendingMatchExpired,
+    #[msg("Pending match has not expired")]
+    PendingMatchNotExpired,
+    #[msg("Invalid Light tree or system accounts")]
+    InvalidTreeConfig,
+    #[msg("Invalid compressed order")]
+    InvalidCompressedOrder,
+    #[msg("Handle input type is reserved for program-computed amounts")]
+    ReservedInputType,
+    #[msg("RFQ still has quotes to accept or refund")]
+    RfqQuotesOutstanding,
+    #[msg("Signed order can still be filled")]
+    SignedOrderStillLive,
+    #[msg("Order owner opted out of crank claims")]
+    CrankClaimOptedOut,
+    #[msg("Account does not have a legacy layout to migrate")]
+    NotLegacyAccount,
+    #[msg("Market still has open orders")]
+    OpenOrdersOnBook,
+    #[msg("Unwrapped amount is not attested for the claim")]
+    ClaimNotAttested,
+    #[msg("Account is not the order's rent payer")]
+    InvalidRentPayer,
+    #[msg("Batch auction bids need a non-zero price")]
+    ZeroPriceBid,
 }
@@ -0,0 +1,217 @@
+use anchor_lang::prelude::*;
+use inco_lightning::{
+    cpi,
+    cpi::accounts::Operation,
+    program::IncoLightning,
+    types::Euint128,
+    ID as INCO_LIGHTNING_ID,
+};
+use inco_token::{
+    cpi as inco_token_cpi,
+    cpi::accounts::IncoTransfer,
+    program::IncoToken,
+    IncoAccount,
+    ID as INCO_TOKEN_ID,
+};
+
+use crate::errors::OrderbookError;
+use crate::instructions::place_order::{covered_escrow, OrderEscrow};
+use crate::state::{
+    handle_ciphertext, OrderbookState, RfqQuote, RfqRequest, HANDLE_INPUT_TYPE,
+    RFQ_STATUS_FILLED, RFQ_STATUS_OPEN,
+};
+
+/// The taker accepts one quote: its payment goes straight to the maker and the maker's escrowed
+/// delivery is released to the taker, all in one instruction. Both legs move as the handle the
+/// taker's balance actually covers; an uncovered payment moves nothing and the delivery goes
+/// back to the maker. Other quotes are refunded with `refund_rfq_quote` once the request is no
+/// longer open.
+pub(crate) fn handler<'info>(ctx: Context<'_, '_, '_, 'info, AcceptRfqQuote<'info>>) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    let quote = &ctx.accounts.rfq_quote;
+    let inco = ctx.accounts.inco_lightning_program.to_account_info();
+    let signer = ctx.accounts.taker.to_account_info();
+
+    if ctx.accounts.rfq_request.status != RFQ_STATUS_OPEN {
+        return err!(OrderbookError::RfqNotOpen);
+    }
+    if Clock::get()?.slot > ctx.accounts.rfq_request.expiry_slot {
+        return err!(OrderbookError::RfqExpired);
+    }
+
+    // Taker buying base pays quote and receives base; taker selling base pays base and receives quote.
+    let (
+        taker_pay,
+        maker_receive,
+        pay_mint,
+        deliver_vault,
+        taker_receive,
+        maker_return,
+        deliver_mint,
+    ) = if ctx.accounts.rfq_request.side == 0 {
+        (
+            &ctx.accounts.taker_quote_inco,
+            &ctx.accounts.maker_quote_inco,
+            state.inco_quote_mint,
+            &ctx.accounts.inco_base_vault,
+            &ctx.accounts.taker_base_inco,
+            &ctx.accounts.maker_base_inco,
+            state.inco_base_mint,
+        )
+    } else {
+        (
+            &ctx.accounts.taker_base_inco,
+            &ctx.accounts.maker_base_inco,
+            state.inco_base_mint,
+            &ctx.accounts.inco_quote_vault,
+            &ctx.accounts.taker_quote_inco,
+            &ctx.accounts.maker_quote_inco,
+            state.inco_quote_mint,
+        )
+    };
+    ensure_inco_account(taker_pay, ctx.accounts.taker.key(), pay_mint)?;
+    ensure_inco_account(maker_receive, quote.maker, pay_mint)?;
+    ensure_inco_account(deliver_vault, state.inco_vault_authority, deliver_mint)?;
+    ensure_inco_account(taker_receive, ctx.accounts.taker.key(), deliver_mint)?;
+    ensure_inco_account(maker_return, quote.maker, deliver_mint)?;
+
+    let delivery = Euint128(quote.delivery_handle);
+    let OrderEscrow {
+        remaining: delivered,
+        escrow: paid,
+    } = covered_escrow(
+        &inco,
+        &signer,
+        taker_pay,
+        delivery,
+        Euint128(quote.payment_handle),
+    )?;
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let unused: Euint128 = cpi::e_sub(cpi_ctx, delivery, delivered, 0)?;
+
+    let vault_authority_bump = ctx.bumps.inco_vault_authority;
+    let state_key = state.key();
+    let vault_seeds: &[&[u8]] = &[
+        b"inco_vault_authority_v12",
+        state_key.as_ref(),
+        &[vault_authority_bump],
+    ];
+
+    inco_token_cpi::transfer(
+        CpiContext::new(
+            ctx.accounts.inco_token_program.to_account_info(),
+            IncoTransfer {
+                source: taker_pay.to_account_info(),
+                destination: maker_receive.to_account_info(),
+                authority: ctx.accounts.taker.to_account_info(),
+                inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+        ),
+        handle_ciphertext(paid.0),
+        HANDLE_INPUT_TYPE,
+    )?;
+    for (destination, amount) in [(taker_receive, delivered), (maker_return, unused)] {
+        inco_token_cpi::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.inco_token_program.to_account_info(),
+                IncoTransfer {
+                    source: deliver_vault.to_account_info(),
+                    destination: destination.to_account_info(),
+                    authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                    inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+                &[vault_seeds],
+            ),
+            handle_ciphertext(amount.0),
+            HANDLE_INPUT_TYPE,
+        )?;
+    }
+
+    let quote_key = quote.key();
+    let request = &mut ctx.accounts.rfq_request;
+    request.status = RFQ_STATUS_FILLED;
+    request.accepted_quote = quote_key;
+    request.open_quotes = request.open_quotes.saturating_sub(1);
+    state.open_escrows = state.open_escrows.saturating_sub(1);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct AcceptRfqQuote<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        mut,
+        constraint = rfq_request.market == state.key() @ OrderbookError::InvalidRfq,
+        seeds = [b"rfq_request_v1", state.key().as_ref(), taker.key().as_ref(), &rfq_request.rfq_id.to_le_bytes()],
+        bump = rfq_request.bump
+    )]
+    pub rfq_request: Account<'info, RfqRequest>,
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker,
+        seeds = [b"rfq_quote_v1", rfq_request.key().as_ref(), maker.key().as_ref()],
+        bump = rfq_quote.bump
+    )]
+    pub rfq_quote: Account<'info, RfqQuote>,
+    /// CHECK: quote maker stored in the quote; receives the quote rent
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    #[account(
+        seeds = [b"inco_vault_authority_v12", state.key().as_ref()],
+        bump,
+        address = state.inco_vault_authority
+    )]
+    /// CHECK: PDA authority for Inco vaults
+    pub inco_vault_authority: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_base_vault)]
+    pub inco_base_vault: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_quote_vault)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    /// CHECK: Taker Inco accounts
+    #[account(mut)]
+    pub taker_base_inco: UncheckedAccount<'info>,
+    /// CHECK: Taker Inco accounts
+    #[account(mut)]
+    pub taker_quote_inco: UncheckedAccount<'info>,
+    /// CHECK: Maker Inco accounts
+    #[account(mut)]
+    pub maker_base_inco: UncheckedAccount<'info>,
+    /// CHECK: Maker Inco accounts
+    #[account(mut)]
+    pub maker_quote_inco: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub inco_token_program: Program<'info, IncoToken>,
+    /// CHECK: Inco Lightning program
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+}
+
+fn ensure_inco_account(
+    account: &UncheckedAccount<'_>,
+    expected_owner: Pubkey,
+    expected_mint: Pubkey,
+) -> Result<()> {
+    let info = account.to_account_info();
+    if info.owner != &INCO_TOKEN_ID {
+        return err!(OrderbookError::InvalidIncoProgramOwner);
+    }
+    let data = info.try_borrow_data()?;
+    let decoded = IncoAccount::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(OrderbookError::InvalidIncoAccountData))?;
+
+    if decoded.owner != expected_owner {
+        return err!(OrderbookError::InvalidIncoAccountOwner);
+    }
+    if decoded.mint != expected_mint {
+        return err!(OrderbookError::InvalidIncoAccountMint);
+    }
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::OrderbookError;
+use crate::state::OrderbookState;
+
+pub(crate) fn handler(ctx: Context<ApplyAuditor>) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    if ctx.accounts.admin.key() != state.admin {
+        return err!(OrderbookError::UnauthorizedMatcher);
+    }
+    if state.pending_auditor_activation_ts == 0 {
+        return err!(OrderbookError::NoPendingAuditor);
+    }
+    if Clock::get()?.unix_timestamp < state.pending_auditor_activation_ts {
+        return err!(OrderbookError::AuditorTimelockActive);
+    }
+    state.auditor = state.pending_auditor;
+    state.pending_auditor = Pubkey::default();
+    state.pending_auditor_activation_ts = 0;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ApplyAuditor<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    pub admin: Signer<'info>,
+}
@@ -3,7 +3,7 @@ use anchor_lang::prelude::*;
 use crate::errors::OrderbookError;
 use crate::state::OrderbookState;
 
-pub fn handler(ctx: Context<BumpOrderSeq>) -> Result<()> {
+pub(crate) fn handler(ctx: Context<BumpOrderSeq>) -> Result<()> {
     let state = &mut ctx.accounts.state;
     if ctx.accounts.admin.key() != state.admin {
         return err!(OrderbookError::UnauthorizedMatcher);
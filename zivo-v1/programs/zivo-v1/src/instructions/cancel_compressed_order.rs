@@ -0,0 +1,196 @@
+use anchor_lang::prelude::*;
+use inco_lightning::{program::IncoLightning, types::Euint128, ID as INCO_LIGHTNING_ID};
+use inco_token::{
+    cpi as inco_token_cpi,
+    cpi::accounts::IncoTransfer,
+    program::IncoToken,
+    IncoAccount,
+    ID as INCO_TOKEN_ID,
+};
+use light_sdk::cpi::v2::LightSystemProgramCpi;
+use light_sdk::cpi::InvokeLightSystemProgram;
+use light_sdk::{
+    account::LightAccount,
+    cpi::{v2::CpiAccounts, LightCpiInstruction},
+    instruction::{account_meta::CompressedAccountMeta, ValidityProof},
+};
+
+use crate::errors::OrderbookError;
+use crate::instructions::cancel_order::escrow_refund;
+use crate::instructions::grant_order_view::allow_auditor;
+use crate::instructions::place_compressed_order::split_light_accounts;
+use crate::state::{handle_ciphertext, CompressedOrder, OrderbookState, HANDLE_INPUT_TYPE};
+
+/// Cancels a compressed order: refunds the escrow computed from the stored remaining size like
+/// `cancel_order`. An order with unclaimed fills stays as a closed account for
+/// `claim_compressed_order`; otherwise the compressed account is closed. `order` is the current
+/// account data, proven by `proof` against `order_meta`.
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, CancelCompressedOrder<'info>>,
+    order: CompressedOrder,
+    order_meta: CompressedAccountMeta,
+    proof: ValidityProof,
+    system_accounts_offset: u8,
+) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+
+    if order.market != state.key() {
+        return err!(OrderbookError::InvalidCompressedOrder);
+    }
+    if !order.is_open {
+        return err!(OrderbookError::OrderClosed);
+    }
+    if order.owner != ctx.accounts.trader.key() {
+        return err!(OrderbookError::InvalidIncoAccountOwner);
+    }
+    let (allowance_accounts, light_accounts) = split_light_accounts(
+        ctx.remaining_accounts,
+        system_accounts_offset,
+        &[
+            ctx.accounts.state_queue.to_account_info(),
+            ctx.accounts.state_tree.to_account_info(),
+        ],
+    )?;
+
+    let (vault, destination, mint) = if order.side == 0 {
+        (
+            &ctx.accounts.inco_quote_vault,
+            &ctx.accounts.trader_quote_inco,
+            state.inco_quote_mint,
+        )
+    } else {
+        (
+            &ctx.accounts.inco_base_vault,
+            &ctx.accounts.trader_base_inco,
+            state.inco_base_mint,
+        )
+    };
+    ensure_inco_account(destination, ctx.accounts.trader.key(), mint)?;
+    ensure_inco_account(vault, state.inco_vault_authority, mint)?;
+
+    let inco = ctx.accounts.inco_lightning_program.to_account_info();
+    let signer = ctx.accounts.trader.to_account_info();
+    let refund = escrow_refund(
+        &inco,
+        &signer,
+        order.side,
+        order.price,
+        Euint128(order.remaining_handle),
+    )?;
+
+    let vault_authority_bump = ctx.bumps.inco_vault_authority;
+    let state_key = state.key();
+    let vault_seeds: &[&[u8]] = &[
+        b"inco_vault_authority_v12",
+        state_key.as_ref(),
+        &[vault_authority_bump],
+    ];
+    inco_token_cpi::transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.inco_token_program.to_account_info(),
+            IncoTransfer {
+                source: vault.to_account_info(),
+                destination: destination.to_account_info(),
+                authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+            &[vault_seeds],
+        ),
+        handle_ciphertext(refund.0),
+        HANDLE_INPUT_TYPE,
+    )?;
+
+    // The auditor gets the refund handle itself, the amount that actually leaves the vault.
+    allow_auditor(
+        state.auditor,
+        allowance_accounts,
+        &[refund.0],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    let light_cpi_accounts =
+        CpiAccounts::new(ctx.accounts.trader.as_ref(), &light_accounts, crate::LIGHT_CPI_SIGNER);
+    if order.is_filled {
+        let mut order_account =
+            LightAccount::<CompressedOrder>::new_mut(&crate::ID, &order_meta, order)
+                .map_err(|_| OrderbookError::InvalidCompressedOrder)?;
+        order_account.is_open = false;
+        order_account.remaining_handle = 0;
+        LightSystemProgramCpi::new_cpi(crate::LIGHT_CPI_SIGNER, proof)
+            .with_light_account(order_account)?
+            .invoke(light_cpi_accounts)?;
+    } else {
+        let order_account =
+            LightAccount::<CompressedOrder>::new_close(&crate::ID, &order_meta, order)
+                .map_err(|_| OrderbookError::InvalidCompressedOrder)?;
+        LightSystemProgramCpi::new_cpi(crate::LIGHT_CPI_SIGNER, proof)
+            .with_light_account(order_account)?
+            .invoke(light_cpi_accounts)?;
+        state.open_escrows = state.open_escrows.saturating_sub(1);
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CancelCompressedOrder<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(mut)]
+    pub trader: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"inco_vault_authority_v12", state.key().as_ref()],
+        bump,
+        address = state.inco_vault_authority
+    )]
+    /// CHECK: PDA authority for Inco vaults
+    pub inco_vault_authority: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_base_vault)]
+    pub inco_base_vault: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_quote_vault)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    /// CHECK: Trader Inco accounts
+    #[account(mut)]
+    pub trader_base_inco: UncheckedAccount<'info>,
+    /// CHECK: Trader Inco accounts
+    #[account(mut)]
+    pub trader_quote_inco: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub inco_token_program: Program<'info, IncoToken>,
+    /// CHECK: Inco Lightning program
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+    /// CHECK: Light state queue (mut)
+    #[account(mut)]
+    pub state_queue: AccountInfo<'info>,
+    /// CHECK: Light state tree (mut)
+    #[account(mut)]
+    pub state_tree: AccountInfo<'info>,
+}
+
+fn ensure_inco_account(
+    account: &UncheckedAccount<'_>,
+    expected_owner: Pubkey,
+    expected_mint: Pubkey,
+) -> Result<()> {
+    let info = account.to_account_info();
+    if info.owner != &INCO_TOKEN_ID {
+        return err!(OrderbookError::InvalidIncoProgramOwner);
+    }
+    let data = info.try_borrow_data()?;
+    let decoded = IncoAccount::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(OrderbookError::InvalidIncoAccountData))?;
+
+    if decoded.owner != expected_owner {
+        return err!(OrderbookError::InvalidIncoAccountOwner);
+    }
+    if decoded.mint != expected_mint {
+        return err!(OrderbookError::InvalidIncoAccountMint);
+    }
+    Ok(())
+}
@@ -6,18 +6,24 @@ use inco_token::{
     IncoAccount,
     ID as INCO_TOKEN_ID,
 };
-use inco_lightning::{program::IncoLightning, ID as INCO_LIGHTNING_ID};
+use inco_lightning::{
+    cpi,
+    cpi::accounts::Operation,
+    program::IncoLightning,
+    types::Euint128,
+    ID as INCO_LIGHTNING_ID,
+};
 
 use crate::errors::OrderbookError;
+use crate::instructions::create_order_group::{cancel_group_leg, pay_group_release};
+use crate::instructions::grant_order_view::allow_auditor;
 use crate::state::OrderbookState;
-use crate::state::{Order, MAX_ESCROW_CIPHERTEXT_LEN};
+use crate::state::{handle_ciphertext, Order, OrderGroup, HANDLE_INPUT_TYPE};
 
-pub fn handler(
-    ctx: Context<CancelOrder>,
-    remaining_ciphertext: Vec<u8>,
-    input_type: u8,
-) -> Result<()> {
-    let state = &ctx.accounts.state;
+/// Cancels an open order and refunds its unfilled escrow as a handle computed from the stored
+/// remaining size, so the trader names no amount.
+pub(crate) fn handler<'info>(ctx: Context<'_, '_, '_, 'info, CancelOrder<'info>>) -> Result<()> {
+    let state = &mut ctx.accounts.state;
     let order = &mut ctx.accounts.order;
 
     if !order.is_open {
@@ -26,92 +32,119 @@ pub fn handler(
     if order.owner != ctx.accounts.trader.key() {
         return err!(OrderbookError::InvalidIncoAccountOwner);
     }
-    if remaining_ciphertext.is_empty() || remaining_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN {
-        return err!(OrderbookError::InvalidEscrowCiphertext);
-    }
+    let inco = ctx.accounts.inco_lightning_program.to_account_info();
+    let signer = ctx.accounts.trader.to_account_info();
+    let release = cancel_group_leg(&inco, &signer, order, &mut ctx.accounts.order_group)?;
+    let refund = order_refund(&inco, &signer, order)?;
 
-    if order.side == 0 {
-        ensure_inco_account(
-            &ctx.accounts.trader_quote_inco,
-            ctx.accounts.trader.key(),
-            state.inco_quote_mint,
-        )?;
-        ensure_inco_account(
+    let (vault, destination, mint) = match order.side {
+        0 => (
             &ctx.accounts.inco_quote_vault,
-            state.inco_vault_authority,
+            &ctx.accounts.trader_quote_inco,
             state.inco_quote_mint,
-        )?;
-
-        let vault_authority_bump = ctx.bumps.inco_vault_authority;
-        let state_key = state.key();
-        let vault_seeds: &[&[u8]] = &[
-            b"inco_vault_authority_v12",
-            state_key.as_ref(),
-            &[vault_authority_bump],
-        ];
-        inco_token_cpi::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.inco_token_program.to_account_info(),
-                IncoTransfer {
-                    source: ctx.accounts.inco_quote_vault.to_account_info(),
-                    destination: ctx.accounts.trader_quote_inco.to_account_info(),
-                    authority: ctx.accounts.inco_vault_authority.to_account_info(),
-                    inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
-                    system_program: ctx.accounts.system_program.to_account_info(),
-                },
-                &[vault_seeds],
-            ),
-            remaining_ciphertext,
-            input_type,
-        )?;
-    } else if order.side == 1 {
-        ensure_inco_account(
-            &ctx.accounts.trader_base_inco,
-            ctx.accounts.trader.key(),
-            state.inco_base_mint,
-        )?;
-        ensure_inco_account(
+        ),
+        1 => (
             &ctx.accounts.inco_base_vault,
-            state.inco_vault_authority,
+            &ctx.accounts.trader_base_inco,
             state.inco_base_mint,
-        )?;
+        ),
+        _ => return err!(OrderbookError::InvalidSide),
+    };
+    ensure_inco_account(destination, ctx.accounts.trader.key(), mint)?;
+    ensure_inco_account(vault, state.inco_vault_authority, mint)?;
 
-        let vault_authority_bump = ctx.bumps.inco_vault_authority;
-        let state_key = state.key();
-        let vault_seeds: &[&[u8]] = &[
-            b"inco_vault_authority_v12",
-            state_key.as_ref(),
-            &[vault_authority_bump],
-        ];
-        inco_token_cpi::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.inco_token_program.to_account_info(),
-                IncoTransfer {
-                    source: ctx.accounts.inco_base_vault.to_account_info(),
-                    destination: ctx.accounts.trader_base_inco.to_account_info(),
-                    authority: ctx.accounts.inco_vault_authority.to_account_info(),
-                    inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
-                    system_program: ctx.accounts.system_program.to_account_info(),
-                },
-                &[vault_seeds],
-            ),
-            remaining_ciphertext,
-            input_type,
+    let vault_authority_bump = ctx.bumps.inco_vault_authority;
+    let state_key = state.key();
+    let vault_seeds: &[&[u8]] = &[
+        b"inco_vault_authority_v12",
+        state_key.as_ref(),
+        &[vault_authority_bump],
+    ];
+    inco_token_cpi::transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.inco_token_program.to_account_info(),
+            IncoTransfer {
+                source: vault.to_account_info(),
+                destination: destination.to_account_info(),
+                authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+            &[vault_seeds],
+        ),
+        handle_ciphertext(refund.0),
+        HANDLE_INPUT_TYPE,
+    )?;
+    // Cancelling an unfilled leg cancels its group; the siblings' escrow comes back too.
+    if let Some(release) = release {
+        pay_group_release(
+            &release,
+            state,
+            &ctx.accounts.trader_quote_inco,
+            &ctx.accounts.trader_base_inco,
+            &ctx.accounts.inco_quote_vault,
+            &ctx.accounts.inco_base_vault,
+            &ctx.accounts.inco_vault_authority.to_account_info(),
+            vault_seeds,
+            &ctx.accounts.inco_token_program.to_account_info(),
+            &inco,
+            &ctx.accounts.system_program.to_account_info(),
         )?;
-    } else {
-        return err!(OrderbookError::InvalidSide);
     }
 
+    // The auditor gets the refund handle itself, the amount that actually leaves the vault.
+    allow_auditor(
+        state.auditor,
+        ctx.remaining_accounts,
+        &[refund.0],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
     order.is_open = false;
-    order.is_filled = false;
-    order.is_claimed = false;
-    order.claim_input_type = 0;
-    order.claim_plaintext_amount = 0;
-    order.claim_ciphertext.clear();
     order.remaining_handle = 0;
+    state.open_orders = state.open_orders.saturating_sub(1);
+    // Unclaimed fills stay on the order, which keeps counting until they are claimed.
+    if !order.is_filled {
+        state.open_escrows = state.open_escrows.saturating_sub(1);
+    }
     Ok(())
 }
 
+/// Escrow still backing an order's remaining size: the size itself for asks, and size * limit
+/// price in quote for bids (the encrypted price for dark bids).
+pub(crate) fn order_refund<'info>(
+    inco: &AccountInfo<'info>,
+    signer: &AccountInfo<'info>,
+    order: &Order,
+) -> Result<Euint128> {
+    let remaining = Euint128(order.remaining_handle);
+    if order.side == 0 && order.is_dark {
+        let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+        return cpi::e_mul(cpi_ctx, remaining, Euint128(order.price_handle), 0);
+    }
+    escrow_refund(inco, signer, order.side, order.price, remaining)
+}
+
+/// Escrow backing `remaining` at a lit `price`: the size for asks, size * price for bids. Also
+/// used for trigger and TWAP parents, which escrow the same way as `place_order`.
+pub(crate) fn escrow_refund<'info>(
+    inco: &AccountInfo<'info>,
+    signer: &AccountInfo<'info>,
+    side: u8,
+    price: u64,
+    remaining: Euint128,
+) -> Result<Euint128> {
+    if side != 0 {
+        return Ok(remaining);
+    }
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let price: Euint128 = cpi::as_euint128(cpi_ctx, price as u128)?;
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    cpi::e_mul(cpi_ctx, remaining, price, 0)
+}
+
 #[derive(Accounts)]
 pub struct CancelOrder<'info> {
     #[account(mut)]
@@ -122,6 +155,9 @@ pub struct CancelOrder<'info> {
         bump = order.bump
     )]
     pub order: Account<'info, Order>,
+    /// Required when the order belongs to an order group.
+    #[account(mut)]
+    pub order_group: Option<Account<'info, OrderGroup>>,
     #[account(mut)]
     pub trader: Signer<'info>,
     #[account(
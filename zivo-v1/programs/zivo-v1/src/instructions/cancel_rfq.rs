@@ -0,0 +1,25 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::OrderbookError;
+use crate::state::{RfqRequest, RFQ_STATUS_CANCELLED, RFQ_STATUS_OPEN};
+
+/// Withdraws an open RFQ so every submitted quote becomes refundable.
+pub(crate) fn handler(ctx: Context<CancelRfq>) -> Result<()> {
+    let request = &mut ctx.accounts.rfq_request;
+    if request.status != RFQ_STATUS_OPEN {
+        return err!(OrderbookError::RfqNotOpen);
+    }
+    request.status = RFQ_STATUS_CANCELLED;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CancelRfq<'info> {
+    #[account(
+        mut,
+        seeds = [b"rfq_request_v1", rfq_request.market.as_ref(), taker.key().as_ref(), &rfq_request.rfq_id.to_le_bytes()],
+        bump = rfq_request.bump
+    )]
+    pub rfq_request: Account<'info, RfqRequest>,
+    pub taker: Signer<'info>,
+}
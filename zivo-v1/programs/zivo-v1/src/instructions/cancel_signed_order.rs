@@ -0,0 +1,21 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{OrderbookState, TraderNonces};
+
+/// Burns a signed order nonce so the relayer can no longer fill what is left of that order.
+/// A nonce past the 4096-nonce window slides the window forward (see `TraderNonces::use_nonce`).
+pub(crate) fn handler(ctx: Context<CancelSignedOrder>, nonce: u64) -> Result<()> {
+    ctx.accounts.trader_nonces.use_nonce(nonce)
+}
+
+#[derive(Accounts)]
+pub struct CancelSignedOrder<'info> {
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        mut,
+        seeds = [b"trader_nonces_v1", state.key().as_ref(), trader.key().as_ref()],
+        bump = trader_nonces.bump
+    )]
+    pub trader_nonces: Account<'info, TraderNonces>,
+    pub trader: Signer<'info>,
+}
@@ -0,0 +1,150 @@
+use anchor_lang::prelude::*;
+use inco_token::{
+    cpi as inco_token_cpi,
+    cpi::accounts::IncoTransfer,
+    program::IncoToken,
+    IncoAccount,
+    ID as INCO_TOKEN_ID,
+};
+use inco_lightning::{program::IncoLightning, types::Euint128, ID as INCO_LIGHTNING_ID};
+
+use crate::errors::OrderbookError;
+use crate::instructions::cancel_order::escrow_refund;
+use crate::instructions::grant_order_view::allow_auditor;
+use crate::state::OrderbookState;
+use crate::state::{handle_ciphertext, TriggerOrder, HANDLE_INPUT_TYPE};
+
+/// Cancels a trigger order before it fires and refunds its escrow as a handle computed from the
+/// stored remaining size, so the trader names no amount.
+pub(crate) fn handler<'info>(ctx: Context<'_, '_, '_, 'info, CancelTriggerOrder<'info>>) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    let order = &ctx.accounts.trigger_order;
+
+    if order.owner != ctx.accounts.trader.key() {
+        return err!(OrderbookError::InvalidIncoAccountOwner);
+    }
+
+    let (vault, destination, mint) = match order.side {
+        0 => (
+            &ctx.accounts.inco_quote_vault,
+            &ctx.accounts.trader_quote_inco,
+            state.inco_quote_mint,
+        ),
+        1 => (
+            &ctx.accounts.inco_base_vault,
+            &ctx.accounts.trader_base_inco,
+            state.inco_base_mint,
+        ),
+        _ => return err!(OrderbookError::InvalidSide),
+    };
+    ensure_inco_account(destination, ctx.accounts.trader.key(), mint)?;
+    ensure_inco_account(vault, state.inco_vault_authority, mint)?;
+
+    let inco = ctx.accounts.inco_lightning_program.to_account_info();
+    let signer = ctx.accounts.trader.to_account_info();
+    let refund = escrow_refund(
+        &inco,
+        &signer,
+        order.side,
+        order.price,
+        Euint128(order.remaining_handle),
+    )?;
+
+    let vault_authority_bump = ctx.bumps.inco_vault_authority;
+    let state_key = state.key();
+    let vault_seeds: &[&[u8]] = &[
+        b"inco_vault_authority_v12",
+        state_key.as_ref(),
+        &[vault_authority_bump],
+    ];
+    inco_token_cpi::transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.inco_token_program.to_account_info(),
+            IncoTransfer {
+                source: vault.to_account_info(),
+                destination: destination.to_account_info(),
+                authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+            &[vault_seeds],
+        ),
+        handle_ciphertext(refund.0),
+        HANDLE_INPUT_TYPE,
+    )?;
+
+    // The auditor sees the refund handle; the trigger PDA and its prefunded rent go back to the
+    // owner.
+    allow_auditor(
+        state.auditor,
+        ctx.remaining_accounts,
+        &[refund.0],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    state.open_escrows = state.open_escrows.saturating_sub(1);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CancelTriggerOrder<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        mut,
+        close = trader,
+        seeds = [b"trigger_order_v1", state.key().as_ref(), trader.key().as_ref(), &trigger_order.seq.to_le_bytes()],
+        bump = trigger_order.bump
+    )]
+    pub trigger_order: Account<'info, TriggerOrder>,
+    #[account(mut)]
+    pub trader: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"inco_vault_authority_v12", state.key().as_ref()],
+        bump,
+        address = state.inco_vault_authority
+    )]
+    /// CHECK: PDA authority for Inco vaults
+    pub inco_vault_authority: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_base_vault)]
+    pub inco_base_vault: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_quote_vault)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    /// CHECK: Trader Inco accounts
+    #[account(mut)]
+    pub trader_base_inco: UncheckedAccount<'info>,
+    /// CHECK: Trader Inco accounts
+    #[account(mut)]
+    pub trader_quote_inco: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub inco_token_program: Program<'info, IncoToken>,
+    /// CHECK: Inco Lightning program
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+}
+
+fn ensure_inco_account(
+    account: &UncheckedAccount<'_>,
+    expected_owner: Pubkey,
+    expected_mint: Pubkey,
+) -> Result<()> {
+    let info = account.to_account_info();
+    if info.owner != &INCO_TOKEN_ID {
+        return err!(OrderbookError::InvalidIncoProgramOwner);
+    }
+    let data = info.try_borrow_data()?;
+    let decoded = IncoAccount::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(OrderbookError::InvalidIncoAccountData))?;
+
+    if decoded.owner != expected_owner {
+        return err!(OrderbookError::InvalidIncoAccountOwner);
+    }
+    if decoded.mint != expected_mint {
+        return err!(OrderbookError::InvalidIncoAccountMint);
+    }
+    Ok(())
+}
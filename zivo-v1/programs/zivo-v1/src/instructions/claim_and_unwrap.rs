@@ -0,0 +1,259 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::instructions as sysvar_instructions;
+use anchor_spl::token::Token;
+use inco_token::{
+    cpi as inco_token_cpi,
+    cpi::accounts::IncoTransfer,
+    program::IncoToken,
+    IncoAccount,
+    ID as INCO_TOKEN_ID,
+};
+use inco_lightning::{
+    cpi::{self as inco_cpi, accounts::VerifySignature},
+    program::IncoLightning,
+    ID as INCO_LIGHTNING_ID,
+};
+use zivo_wrap::{cpi as zivo_wrap_cpi, cpi::accounts::UnwrapToken, program::ZivoWrap};
+
+use crate::errors::OrderbookError;
+use crate::state::{Order, OrderbookState, HANDLE_INPUT_TYPE, MAX_ESCROW_CIPHERTEXT_LEN};
+
+/// `maker_claim_filled_order` followed by zivo-wrap `unwrap_token`: the claimed ciphertext is
+/// paid to the maker's Inco account and immediately burnt back to SPL.
+///
+/// `plaintext_amount` is the SPL released by zivo-wrap. The maker proves it with an Inco attested
+/// decryption of the claim handle (ed25519 instructions earlier in the transaction), checked here
+/// with `is_validsignature`, so the SPL released is exactly the claim. It is public in the
+/// transaction, so this path deliberately gives up the encrypted claim amount; makers who want
+/// the fill size hidden claim to their Inco account and keep it there or commit it to a note.
+pub(crate) fn handler(ctx: Context<ClaimAndUnwrap>, plaintext_amount: u64) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    let order = &mut ctx.accounts.order;
+
+    if order.owner != ctx.accounts.maker.key() {
+        return err!(OrderbookError::InvalidOrderOwner);
+    }
+    if order.is_open {
+        return err!(OrderbookError::OrderStillOpen);
+    }
+    if !order.is_filled {
+        return err!(OrderbookError::OrderNotFilled);
+    }
+    if order.is_claimed {
+        return err!(OrderbookError::OrderAlreadyClaimed);
+    }
+    if order.claim_ciphertext.is_empty() {
+        return err!(OrderbookError::InvalidEscrowCiphertext);
+    }
+    if order.claim_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN {
+        return err!(OrderbookError::InvalidEscrowCiphertext);
+    }
+    // Only a claim accrued by a match names a handle that Inco can attest.
+    if order.claim_input_type != HANDLE_INPUT_TYPE {
+        return err!(OrderbookError::InvalidEscrowCiphertext);
+    }
+    let claim_ciphertext = order.claim_ciphertext.clone();
+    let input_type = order.claim_input_type;
+
+    let attested = inco_cpi::is_validsignature(
+        CpiContext::new(
+            ctx.accounts.inco_lightning_program.to_account_info(),
+            VerifySignature {
+                instructions: ctx.accounts.instructions_sysvar.to_account_info(),
+                signer: ctx.accounts.maker.to_account_info(),
+            },
+        ),
+        1,
+        Some(vec![claim_ciphertext.clone()]),
+        Some(vec![(plaintext_amount as u128).to_le_bytes().to_vec()]),
+    )?;
+    if attested.len() != 1 {
+        return err!(OrderbookError::ClaimNotAttested);
+    }
+    // Maker bids receive base, maker asks receive quote.
+    let (expected_mint, maker_destination) = if order.side == 0 {
+        (state.inco_base_mint, ctx.accounts.maker_base_inco.to_account_info())
+    } else {
+        (state.inco_quote_mint, ctx.accounts.maker_quote_inco.to_account_info())
+    };
+    if ctx.accounts.inco_token_mint.key() != expected_mint {
+        return err!(OrderbookError::InvalidIncoAccountMint);
+    }
+
+    let vault_authority_bump = ctx.bumps.inco_vault_authority;
+    let state_key = state.key();
+    let vault_seeds: &[&[u8]] = &[
+        b"inco_vault_authority_v12",
+        state_key.as_ref(),
+        &[vault_authority_bump],
+    ];
+
+    if order.side == 0 {
+        // Maker bid: claim base from base vault.
+        ensure_inco_account(
+            &ctx.accounts.maker_base_inco,
+            order.owner,
+            state.inco_base_mint,
+        )?;
+        ensure_inco_account(
+            &ctx.accounts.inco_base_vault,
+            state.inco_vault_authority,
+            state.inco_base_mint,
+        )?;
+
+        inco_token_cpi::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.inco_token_program.to_account_info(),
+                IncoTransfer {
+                    source: ctx.accounts.inco_base_vault.to_account_info(),
+                    destination: ctx.accounts.maker_base_inco.to_account_info(),
+                    authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                    inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+                &[vault_seeds],
+            ),
+            claim_ciphertext.clone(),
+            input_type,
+        )?;
+    } else if order.side == 1 {
+        // Maker ask: claim quote from quote vault.
+        ensure_inco_account(
+            &ctx.accounts.maker_quote_inco,
+            order.owner,
+            state.inco_quote_mint,
+        )?;
+        ensure_inco_account(
+            &ctx.accounts.inco_quote_vault,
+            state.inco_vault_authority,
+            state.inco_quote_mint,
+        )?;
+
+        inco_token_cpi::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.inco_token_program.to_account_info(),
+                IncoTransfer {
+                    source: ctx.accounts.inco_quote_vault.to_account_info(),
+                    destination: ctx.accounts.maker_quote_inco.to_account_info(),
+                    authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                    inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+                &[vault_seeds],
+            ),
+            claim_ciphertext.clone(),
+            input_type,
+        )?;
+    } else {
+        return err!(OrderbookError::InvalidSide);
+    }
+
+    zivo_wrap_cpi::unwrap_token(
+        CpiContext::new(
+            ctx.accounts.zivo_wrap_program.to_account_info(),
+            UnwrapToken {
+                vault: ctx.accounts.wrap_vault.to_account_info(),
+                spl_token_mint: ctx.accounts.spl_token_mint.to_account_info(),
+                inco_token_mint: ctx.accounts.inco_token_mint.to_account_info(),
+                user_spl_token_account: ctx.accounts.maker_spl_token_account.to_account_info(),
+                vault_token_account: ctx.accounts.wrap_vault_token_account.to_account_info(),
+                user_inco_token_account: maker_destination,
+                user: ctx.accounts.maker.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                inco_token_program: ctx.accounts.inco_token_program.to_account_info(),
+            },
+        ),
+        claim_ciphertext,
+        input_type,
+        plaintext_amount,
+    )?;
+
+    order.is_claimed = true;
+    state.open_escrows = state.open_escrows.saturating_sub(1);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ClaimAndUnwrap<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"order_v1", state.key().as_ref(), owner.key().as_ref(), &order.seq.to_le_bytes()],
+        bump = order.bump
+    )]
+    pub order: Account<'info, Order>,
+    /// CHECK: maker owner stored in order
+    pub owner: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"inco_vault_authority_v12", state.key().as_ref()],
+        bump,
+        address = state.inco_vault_authority
+    )]
+    /// CHECK: PDA authority for Inco vaults
+    pub inco_vault_authority: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_base_vault)]
+    pub inco_base_vault: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_quote_vault)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    /// CHECK: Maker Inco accounts
+    #[account(mut)]
+    pub maker_base_inco: UncheckedAccount<'info>,
+    /// CHECK: Maker Inco accounts
+    #[account(mut)]
+    pub maker_quote_inco: UncheckedAccount<'info>,
+    /// CHECK: zivo-wrap vault for the claimed mint, validated by zivo-wrap
+    #[account(mut)]
+    pub wrap_vault: UncheckedAccount<'info>,
+    /// CHECK: SPL mint received, validated by zivo-wrap
+    pub spl_token_mint: UncheckedAccount<'info>,
+    /// CHECK: Inco mint of the claimed side; must be the market mint
+    #[account(mut)]
+    pub inco_token_mint: UncheckedAccount<'info>,
+    /// CHECK: Maker SPL destination, validated by zivo-wrap
+    #[account(mut)]
+    pub maker_spl_token_account: UncheckedAccount<'info>,
+    /// CHECK: zivo-wrap SPL vault account, validated by zivo-wrap
+    #[account(mut)]
+    pub wrap_vault_token_account: UncheckedAccount<'info>,
+    pub zivo_wrap_program: Program<'info, ZivoWrap>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub inco_token_program: Program<'info, IncoToken>,
+    /// CHECK: Inco Lightning program
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+    /// CHECK: instructions sysvar, read by Inco to find the attestation signatures
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+}
+
+fn ensure_inco_account(
+    account: &UncheckedAccount<'_>,
+    expected_owner: Pubkey,
+    expected_mint: Pubkey,
+) -> Result<()> {
+    let info = account.to_account_info();
+    if info.owner != &INCO_TOKEN_ID {
+        return err!(OrderbookError::InvalidIncoProgramOwner);
+    }
+    let data = info.try_borrow_data()?;
+    let decoded = IncoAccount::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(OrderbookError::InvalidIncoAccountData))?;
+
+    if decoded.owner != expected_owner {
+        return err!(OrderbookError::InvalidIncoAccountOwner);
+    }
+    if decoded.mint != expected_mint {
+        return err!(OrderbookError::InvalidIncoAccountMint);
+    }
+    Ok(())
+}
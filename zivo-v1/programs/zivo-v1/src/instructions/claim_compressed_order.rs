@@ -0,0 +1,207 @@
+use anchor_lang::prelude::*;
+use inco_lightning::{program::IncoLightning, ID as INCO_LIGHTNING_ID};
+use inco_token::{
+    cpi as inco_token_cpi,
+    cpi::accounts::IncoTransfer,
+    program::IncoToken,
+    IncoAccount,
+    ID as INCO_TOKEN_ID,
+};
+use light_sdk::cpi::v2::LightSystemProgramCpi;
+use light_sdk::cpi::InvokeLightSystemProgram;
+use light_sdk::{
+    account::LightAccount,
+    cpi::{v2::CpiAccounts, LightCpiInstruction},
+    instruction::{account_meta::CompressedAccountMeta, ValidityProof},
+};
+
+use crate::errors::OrderbookError;
+use crate::instructions::place_compressed_order::split_light_accounts;
+use crate::state::{CompressedOrder, OrderbookState, MAX_ESCROW_CIPHERTEXT_LEN};
+
+/// Maker claim for a filled compressed order, mirroring `maker_claim_filled_order`. An order
+/// still on the book keeps its remaining size and starts a fresh claim; a closed order's
+/// compressed account is closed once the proceeds leave the vault.
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, ClaimCompressedOrder<'info>>,
+    order: CompressedOrder,
+    order_meta: CompressedAccountMeta,
+    proof: ValidityProof,
+    system_accounts_offset: u8,
+) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+
+    if order.market != state.key() {
+        return err!(OrderbookError::InvalidCompressedOrder);
+    }
+    if order.owner != ctx.accounts.maker.key() {
+        return err!(OrderbookError::InvalidOrderOwner);
+    }
+    if !order.is_filled {
+        return err!(OrderbookError::OrderNotFilled);
+    }
+    if order.claim_ciphertext.is_empty() || order.claim_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN
+    {
+        return err!(OrderbookError::InvalidEscrowCiphertext);
+    }
+    let (_, light_accounts) = split_light_accounts(
+        ctx.remaining_accounts,
+        system_accounts_offset,
+        &[
+            ctx.accounts.state_queue.to_account_info(),
+            ctx.accounts.state_tree.to_account_info(),
+        ],
+    )?;
+    let claim_ciphertext = order.claim_ciphertext.clone();
+    let input_type = order.claim_input_type;
+
+    let vault_authority_bump = ctx.bumps.inco_vault_authority;
+    let state_key = state.key();
+    let vault_seeds: &[&[u8]] = &[
+        b"inco_vault_authority_v12",
+        state_key.as_ref(),
+        &[vault_authority_bump],
+    ];
+
+    if order.side == 0 {
+        // Maker bid: claim base from base vault.
+        ensure_inco_account(
+            &ctx.accounts.maker_base_inco,
+            order.owner,
+            state.inco_base_mint,
+        )?;
+        ensure_inco_account(
+            &ctx.accounts.inco_base_vault,
+            state.inco_vault_authority,
+            state.inco_base_mint,
+        )?;
+
+        inco_token_cpi::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.inco_token_program.to_account_info(),
+                IncoTransfer {
+                    source: ctx.accounts.inco_base_vault.to_account_info(),
+                    destination: ctx.accounts.maker_base_inco.to_account_info(),
+                    authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                    inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+                &[vault_seeds],
+            ),
+            claim_ciphertext,
+            input_type,
+        )?;
+    } else {
+        // Maker ask: claim quote from quote vault.
+        ensure_inco_account(
+            &ctx.accounts.maker_quote_inco,
+            order.owner,
+            state.inco_quote_mint,
+        )?;
+        ensure_inco_account(
+            &ctx.accounts.inco_quote_vault,
+            state.inco_vault_authority,
+            state.inco_quote_mint,
+        )?;
+
+        inco_token_cpi::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.inco_token_program.to_account_info(),
+                IncoTransfer {
+                    source: ctx.accounts.inco_quote_vault.to_account_info(),
+                    destination: ctx.accounts.maker_quote_inco.to_account_info(),
+                    authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                    inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+                &[vault_seeds],
+            ),
+            claim_ciphertext,
+            input_type,
+        )?;
+    }
+
+    let light_cpi_accounts =
+        CpiAccounts::new(ctx.accounts.maker.as_ref(), &light_accounts, crate::LIGHT_CPI_SIGNER);
+    if order.is_open {
+        let mut order_account =
+            LightAccount::<CompressedOrder>::new_mut(&crate::ID, &order_meta, order)
+                .map_err(|_| OrderbookError::InvalidCompressedOrder)?;
+        order_account.is_filled = false;
+        order_account.claim_input_type = 0;
+        order_account.claim_ciphertext.clear();
+        LightSystemProgramCpi::new_cpi(crate::LIGHT_CPI_SIGNER, proof)
+            .with_light_account(order_account)?
+            .invoke(light_cpi_accounts)?;
+    } else {
+        let order_account =
+            LightAccount::<CompressedOrder>::new_close(&crate::ID, &order_meta, order)
+                .map_err(|_| OrderbookError::InvalidCompressedOrder)?;
+        LightSystemProgramCpi::new_cpi(crate::LIGHT_CPI_SIGNER, proof)
+            .with_light_account(order_account)?
+            .invoke(light_cpi_accounts)?;
+        state.open_escrows = state.open_escrows.saturating_sub(1);
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ClaimCompressedOrder<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"inco_vault_authority_v12", state.key().as_ref()],
+        bump,
+        address = state.inco_vault_authority
+    )]
+    /// CHECK: PDA authority for Inco vaults
+    pub inco_vault_authority: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_base_vault)]
+    pub inco_base_vault: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_quote_vault)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    /// CHECK: Maker Inco accounts
+    #[account(mut)]
+    pub maker_base_inco: UncheckedAccount<'info>,
+    /// CHECK: Maker Inco accounts
+    #[account(mut)]
+    pub maker_quote_inco: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub inco_token_program: Program<'info, IncoToken>,
+    /// CHECK: Inco Lightning program
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+    /// CHECK: Light state queue (mut)
+    #[account(mut)]
+    pub state_queue: AccountInfo<'info>,
+    /// CHECK: Light state tree (mut)
+    #[account(mut)]
+    pub state_tree: AccountInfo<'info>,
+}
+
+fn ensure_inco_account(
+    account: &UncheckedAccount<'_>,
+    expected_owner: Pubkey,
+    expected_mint: Pubkey,
+) -> Result<()> {
+    let info = account.to_account_info();
+    if info.owner != &INCO_TOKEN_ID {
+        return err!(OrderbookError::InvalidIncoProgramOwner);
+    }
+    let data = info.try_borrow_data()?;
+    let decoded = IncoAccount::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(OrderbookError::InvalidIncoAccountData))?;
+
+    if decoded.owner != expected_owner {
+        return err!(OrderbookError::InvalidIncoAccountOwner);
+    }
+    if decoded.mint != expected_mint {
+        return err!(OrderbookError::InvalidIncoAccountMint);
+    }
+    Ok(())
+}
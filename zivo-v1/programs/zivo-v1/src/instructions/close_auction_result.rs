@@ -0,0 +1,29 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::OrderbookError;
+use crate::state::{AuctionResult, OrderbookState, MARKET_STATUS_CLOSED};
+
+/// Permissionless: closes an auction record of a closed market. The admin ran the auction and
+/// paid for the record, so the rent goes back to the admin.
+pub(crate) fn handler(ctx: Context<CloseAuctionResult>) -> Result<()> {
+    if ctx.accounts.state.status != MARKET_STATUS_CLOSED {
+        return err!(OrderbookError::MarketNotClosed);
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CloseAuctionResult<'info> {
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"auction_result_v1", state.key().as_ref(), &auction_result.auction_id.to_le_bytes()],
+        bump = auction_result.bump
+    )]
+    pub auction_result: Account<'info, AuctionResult>,
+    /// CHECK: market admin; receives the rent
+    #[account(mut, address = state.admin)]
+    pub admin: UncheckedAccount<'info>,
+    pub caller: Signer<'info>,
+}
@@ -0,0 +1,22 @@
+use anchor_lang::prelude::*;
+
+use crate::state::CiphertextBuffer;
+
+/// Closes a ciphertext buffer and returns its rent to the owner.
+pub(crate) fn handler(_ctx: Context<CloseBuffer>) -> Result<()> {
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CloseBuffer<'info> {
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [b"ciphertext_buffer_v1", owner.key().as_ref(), &buffer.buffer_id.to_le_bytes()],
+        bump = buffer.bump
+    )]
+    pub buffer: Account<'info, CiphertextBuffer>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
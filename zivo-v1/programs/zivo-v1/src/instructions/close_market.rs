@@ -0,0 +1,137 @@
+use anchor_lang::prelude::*;
+use inco_token::{
+    cpi as inco_token_cpi,
+    cpi::accounts::IncoCloseAccount,
+    program::IncoToken,
+    IncoAccount,
+    ID as INCO_TOKEN_ID,
+};
+
+use crate::errors::OrderbookError;
+use crate::state::{CiphertextBuffer, MarketRegistry, OrderbookState, MARKET_STATUS_CLOSED};
+
+/// Closes a closed market once no order is left on it (`open_orders == 0`); `sweep_market_order`
+/// refunds and closes whatever orders remain after the market is closed.
+///
+/// Remaining accounts are optional ciphertext buffers of the admin, who matched on the market
+/// with them; they are closed too, with their rent.
+pub(crate) fn handler<'info>(ctx: Context<'_, '_, 'info, 'info, CloseMarket<'info>>) -> Result<()> {
+    let state = &ctx.accounts.state;
+
+    if ctx.accounts.admin.key() != state.admin {
+        return err!(OrderbookError::UnauthorizedMatcher);
+    }
+    if state.status != MARKET_STATUS_CLOSED {
+        return err!(OrderbookError::MarketNotClosed);
+    }
+    if state.open_orders != 0 {
+        return err!(OrderbookError::OpenOrdersOnBook);
+    }
+
+    ensure_inco_account(
+        &ctx.accounts.inco_base_vault,
+        state.inco_vault_authority,
+        state.inco_base_mint,
+    )?;
+    ensure_inco_account(
+        &ctx.accounts.inco_quote_vault,
+        state.inco_vault_authority,
+        state.inco_quote_mint,
+    )?;
+
+    let vault_authority_bump = ctx.bumps.inco_vault_authority;
+    let state_key = state.key();
+    let vault_seeds: &[&[u8]] = &[
+        b"inco_vault_authority_v12",
+        state_key.as_ref(),
+        &[vault_authority_bump],
+    ];
+
+    // Vault rent goes back to the admin together with the market state rent.
+    inco_token_cpi::close_account(CpiContext::new_with_signer(
+        ctx.accounts.inco_token_program.to_account_info(),
+        IncoCloseAccount {
+            account: ctx.accounts.inco_base_vault.to_account_info(),
+            destination: ctx.accounts.admin.to_account_info(),
+            authority: ctx.accounts.inco_vault_authority.to_account_info(),
+        },
+        &[vault_seeds],
+    ))?;
+    inco_token_cpi::close_account(CpiContext::new_with_signer(
+        ctx.accounts.inco_token_program.to_account_info(),
+        IncoCloseAccount {
+            account: ctx.accounts.inco_quote_vault.to_account_info(),
+            destination: ctx.accounts.admin.to_account_info(),
+            authority: ctx.accounts.inco_vault_authority.to_account_info(),
+        },
+        &[vault_seeds],
+    ))?;
+
+    let admin_key = ctx.accounts.admin.key();
+    for info in ctx.remaining_accounts.iter() {
+        let buffer: Account<'info, CiphertextBuffer> = Account::try_from(info)?;
+        if buffer.owner != admin_key {
+            return err!(OrderbookError::InvalidCiphertextBuffer);
+        }
+        buffer.close(ctx.accounts.admin.to_account_info())?;
+    }
+
+    let registry = &mut ctx.accounts.market_registry;
+    registry.markets.retain(|market| *market != state_key);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CloseMarket<'info> {
+    #[account(mut, close = admin)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"market_registry_v1"],
+        bump = market_registry.bump,
+        realloc = MarketRegistry::space(market_registry.markets.len().saturating_sub(1)),
+        realloc::payer = admin,
+        realloc::zero = false
+    )]
+    pub market_registry: Account<'info, MarketRegistry>,
+    #[account(
+        seeds = [b"inco_vault_authority_v12", state.key().as_ref()],
+        bump,
+        address = state.inco_vault_authority
+    )]
+    /// CHECK: PDA authority for Inco vaults
+    pub inco_vault_authority: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_base_vault)]
+    pub inco_base_vault: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_quote_vault)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub inco_token_program: Program<'info, IncoToken>,
+}
+
+fn ensure_inco_account(
+    account: &UncheckedAccount<'_>,
+    expected_owner: Pubkey,
+    expected_mint: Pubkey,
+) -> Result<()> {
+    let info = account.to_account_info();
+    if info.owner != &INCO_TOKEN_ID {
+        return err!(OrderbookError::InvalidIncoProgramOwner);
+    }
+    let data = info.try_borrow_data()?;
+    let decoded = IncoAccount::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(OrderbookError::InvalidIncoAccountData))?;
+
+    if decoded.owner != expected_owner {
+        return err!(OrderbookError::InvalidIncoAccountOwner);
+    }
+    if decoded.mint != expected_mint {
+        return err!(OrderbookError::InvalidIncoAccountMint);
+    }
+    Ok(())
+}
@@ -1,17 +1,25 @@
 use anchor_lang::prelude::*;
-use inco_lightning::{
-    cpi,
-    cpi::accounts::Operation,
-    program::IncoLightning,
-    types::{Ebool, Euint128},
-    ID as INCO_LIGHTNING_ID,
+use inco_token::{
+    cpi as inco_token_cpi,
+    cpi::accounts::IncoTransfer,
+    program::IncoToken,
+    IncoAccount,
+    ID as INCO_TOKEN_ID,
 };
+use inco_lightning::{program::IncoLightning, ID as INCO_LIGHTNING_ID};
 
 use crate::errors::OrderbookError;
-use crate::state::{Order, OrderbookState};
+use crate::instructions::cancel_order::order_refund;
+use crate::instructions::grant_order_view::allow_auditor;
+use crate::state::{handle_ciphertext, Order, OrderbookState, HANDLE_INPUT_TYPE};
 
-pub fn handler(ctx: Context<CloseOrder>) -> Result<()> {
-    let _state = &ctx.accounts.state;
+/// Takes an order off the book once the owner is done with it, typically after it filled. The
+/// program cannot tell an encrypted remaining size of zero from any other, so whatever is left
+/// is refunded as a handle computed from it (an encrypted zero for a fully filled order); a
+/// closed order never keeps escrow in the vault. Fills stay on the order to be claimed. Group
+/// legs close through `cancel_order`, which also resolves their group.
+pub(crate) fn handler<'info>(ctx: Context<'_, '_, '_, 'info, CloseOrder<'info>>) -> Result<()> {
+    let state = &mut ctx.accounts.state;
     let order = &mut ctx.accounts.order;
 
     if !order.is_open {
@@ -20,24 +28,69 @@ pub fn handler(ctx: Context<CloseOrder>) -> Result<()> {
     if order.owner != ctx.accounts.owner.key() {
         return err!(OrderbookError::InvalidIncoAccountOwner);
     }
+    if order.group != Pubkey::default() {
+        return err!(OrderbookError::InvalidOrderGroup);
+    }
 
     let inco = ctx.accounts.inco_lightning_program.to_account_info();
     let signer = ctx.accounts.owner.to_account_info();
+    let refund = order_refund(&inco, &signer, order)?;
 
-    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
-    let zero: Euint128 = cpi::as_euint128(cpi_ctx, 0)?;
+    let (vault, destination, mint) = match order.side {
+        0 => (
+            &ctx.accounts.inco_quote_vault,
+            &ctx.accounts.owner_quote_inco,
+            state.inco_quote_mint,
+        ),
+        1 => (
+            &ctx.accounts.inco_base_vault,
+            &ctx.accounts.owner_base_inco,
+            state.inco_base_mint,
+        ),
+        _ => return err!(OrderbookError::InvalidSide),
+    };
+    ensure_inco_account(destination, ctx.accounts.owner.key(), mint)?;
+    ensure_inco_account(vault, state.inco_vault_authority, mint)?;
 
-    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
-    let is_zero: Ebool = cpi::e_eq(
-        cpi_ctx,
-        Euint128(order.remaining_handle),
-        zero,
-        0,
+    let vault_authority_bump = ctx.bumps.inco_vault_authority;
+    let state_key = state.key();
+    let vault_seeds: &[&[u8]] = &[
+        b"inco_vault_authority_v12",
+        state_key.as_ref(),
+        &[vault_authority_bump],
+    ];
+    inco_token_cpi::transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.inco_token_program.to_account_info(),
+            IncoTransfer {
+                source: vault.to_account_info(),
+                destination: destination.to_account_info(),
+                authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+            &[vault_seeds],
+        ),
+        handle_ciphertext(refund.0),
+        HANDLE_INPUT_TYPE,
     )?;
 
-    order.is_filled = is_zero.0 == 1;
+    allow_auditor(
+        state.auditor,
+        ctx.remaining_accounts,
+        &[refund.0],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
 
     order.is_open = false;
+    order.remaining_handle = 0;
+    state.open_orders = state.open_orders.saturating_sub(1);
+    // As for a cancel, an unclaimed fill keeps the order counting until it is claimed.
+    if !order.is_filled {
+        state.open_escrows = state.open_escrows.saturating_sub(1);
+    }
     Ok(())
 }
 
@@ -53,7 +106,51 @@ pub struct CloseOrder<'info> {
     pub order: Account<'info, Order>,
     #[account(mut)]
     pub owner: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"inco_vault_authority_v12", state.key().as_ref()],
+        bump,
+        address = state.inco_vault_authority
+    )]
+    /// CHECK: PDA authority for Inco vaults
+    pub inco_vault_authority: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_base_vault)]
+    pub inco_base_vault: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_quote_vault)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    /// CHECK: Owner Inco accounts
+    #[account(mut)]
+    pub owner_base_inco: UncheckedAccount<'info>,
+    /// CHECK: Owner Inco accounts
+    #[account(mut)]
+    pub owner_quote_inco: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub inco_token_program: Program<'info, IncoToken>,
     /// CHECK: Inco Lightning program
     #[account(address = INCO_LIGHTNING_ID)]
     pub inco_lightning_program: Program<'info, IncoLightning>,
 }
+
+fn ensure_inco_account(
+    account: &UncheckedAccount<'_>,
+    expected_owner: Pubkey,
+    expected_mint: Pubkey,
+) -> Result<()> {
+    let info = account.to_account_info();
+    if info.owner != &INCO_TOKEN_ID {
+        return err!(OrderbookError::InvalidIncoProgramOwner);
+    }
+    let data = info.try_borrow_data()?;
+    let decoded = IncoAccount::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(OrderbookError::InvalidIncoAccountData))?;
+
+    if decoded.owner != expected_owner {
+        return err!(OrderbookError::InvalidIncoAccountOwner);
+    }
+    if decoded.mint != expected_mint {
+        return err!(OrderbookError::InvalidIncoAccountMint);
+    }
+    Ok(())
+}
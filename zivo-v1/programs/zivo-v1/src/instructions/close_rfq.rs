@@ -0,0 +1,29 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::OrderbookError;
+use crate::state::{RfqRequest, RFQ_STATUS_OPEN};
+
+/// Closes a finished RFQ to the taker once every quote has been accepted or refunded.
+pub(crate) fn handler(ctx: Context<CloseRfq>) -> Result<()> {
+    let request = &ctx.accounts.rfq_request;
+    if request.status == RFQ_STATUS_OPEN && Clock::get()?.slot <= request.expiry_slot {
+        return err!(OrderbookError::RfqStillOpen);
+    }
+    if request.open_quotes != 0 {
+        return err!(OrderbookError::RfqQuotesOutstanding);
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CloseRfq<'info> {
+    #[account(
+        mut,
+        close = taker,
+        seeds = [b"rfq_request_v1", rfq_request.market.as_ref(), taker.key().as_ref(), &rfq_request.rfq_id.to_le_bytes()],
+        bump = rfq_request.bump
+    )]
+    pub rfq_request: Account<'info, RfqRequest>,
+    #[account(mut)]
+    pub taker: Signer<'info>,
+}
@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::OrderbookError;
+use crate::state::{OrderbookState, SignedOrderFill, TraderNonces};
+
+/// Permissionless: once a signed order has expired or its nonce was cancelled, no match can
+/// use its `SignedOrderFill` again, so the account closes to the matcher that paid for it.
+pub(crate) fn handler(ctx: Context<CloseSignedFill>) -> Result<()> {
+    let fill = &ctx.accounts.signed_fill;
+    if Clock::get()?.slot <= fill.expiry_slot
+        && !ctx.accounts.maker_nonces.is_nonce_used(fill.nonce)
+    {
+        return err!(OrderbookError::SignedOrderStillLive);
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CloseSignedFill<'info> {
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        seeds = [b"trader_nonces_v1", state.key().as_ref(), signed_fill.maker.as_ref()],
+        bump = maker_nonces.bump
+    )]
+    pub maker_nonces: Account<'info, TraderNonces>,
+    #[account(
+        mut,
+        close = payer,
+        has_one = payer,
+        seeds = [b"signed_fill_v1", state.key().as_ref(), signed_fill.maker.as_ref(), &signed_fill.nonce.to_le_bytes()],
+        bump = signed_fill.bump
+    )]
+    pub signed_fill: Account<'info, SignedOrderFill>,
+    /// CHECK: matcher stored in the fill record; receives its rent
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+}
@@ -0,0 +1,201 @@
+use anchor_lang::prelude::*;
+use inco_token::{
+    cpi as inco_token_cpi,
+    cpi::accounts::IncoTransfer,
+    program::IncoToken,
+    IncoAccount,
+    ID as INCO_TOKEN_ID,
+};
+use inco_lightning::{program::IncoLightning, ID as INCO_LIGHTNING_ID};
+
+use crate::errors::OrderbookError;
+use crate::state::{Order, OrderbookState, CRANK_CLAIM_REWARD_LAMPORTS, MAX_ESCROW_CIPHERTEXT_LEN};
+
+/// Pushes a maker's accrued fills to its owner without the owner's signature. An open maker
+/// keeps resting with a fresh claim, as after `maker_claim_filled_order`; a closed one is fully
+/// settled, and its account is closed to whoever paid its rent, minus the cranker's reward.
+pub(crate) fn handler(ctx: Context<CrankClaim>) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    let order = &mut ctx.accounts.order;
+
+    if !order.is_filled {
+        return err!(OrderbookError::OrderNotFilled);
+    }
+    if order.is_claimed {
+        return err!(OrderbookError::OrderAlreadyClaimed);
+    }
+    if order.crank_opt_out {
+        return err!(OrderbookError::CrankClaimOptedOut);
+    }
+    // Group legs keep their account until `sweep_order_group` has closed their group.
+    if order.group != Pubkey::default() {
+        return err!(OrderbookError::InvalidOrderGroup);
+    }
+    if order.claim_ciphertext.is_empty() {
+        return err!(OrderbookError::InvalidEscrowCiphertext);
+    }
+    if order.claim_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN {
+        return err!(OrderbookError::InvalidEscrowCiphertext);
+    }
+    let claim_ciphertext = order.claim_ciphertext.clone();
+    let input_type = order.claim_input_type;
+
+    let vault_authority_bump = ctx.bumps.inco_vault_authority;
+    let state_key = state.key();
+    let vault_seeds: &[&[u8]] = &[
+        b"inco_vault_authority_v12",
+        state_key.as_ref(),
+        &[vault_authority_bump],
+    ];
+
+    if order.side == 0 {
+        // Maker bid: push base from base vault to the owner.
+        ensure_inco_account(
+            &ctx.accounts.owner_base_inco,
+            order.owner,
+            state.inco_base_mint,
+        )?;
+        ensure_inco_account(
+            &ctx.accounts.inco_base_vault,
+            state.inco_vault_authority,
+            state.inco_base_mint,
+        )?;
+
+        inco_token_cpi::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.inco_token_program.to_account_info(),
+                IncoTransfer {
+                    source: ctx.accounts.inco_base_vault.to_account_info(),
+                    destination: ctx.accounts.owner_base_inco.to_account_info(),
+                    authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                    inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+                &[vault_seeds],
+            ),
+            claim_ciphertext,
+            input_type,
+        )?;
+    } else if order.side == 1 {
+        // Maker ask: push quote from quote vault to the owner.
+        ensure_inco_account(
+            &ctx.accounts.owner_quote_inco,
+            order.owner,
+            state.inco_quote_mint,
+        )?;
+        ensure_inco_account(
+            &ctx.accounts.inco_quote_vault,
+            state.inco_vault_authority,
+            state.inco_quote_mint,
+        )?;
+
+        inco_token_cpi::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.inco_token_program.to_account_info(),
+                IncoTransfer {
+                    source: ctx.accounts.inco_quote_vault.to_account_info(),
+                    destination: ctx.accounts.owner_quote_inco.to_account_info(),
+                    authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                    inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+                &[vault_seeds],
+            ),
+            claim_ciphertext,
+            input_type,
+        )?;
+    } else {
+        return err!(OrderbookError::InvalidSide);
+    }
+
+    if order.is_open {
+        // The order keeps resting and its rent stays put, so there is no reward to pay.
+        order.is_filled = false;
+        order.claim_input_type = 0;
+        order.claim_ciphertext.clear();
+        return Ok(());
+    }
+
+    order.is_claimed = true;
+    state.open_escrows = state.open_escrows.saturating_sub(1);
+
+    // Order rent goes back to its payer on close; the cranker takes a small cut first.
+    let order_info = order.to_account_info();
+    let reward = CRANK_CLAIM_REWARD_LAMPORTS.min(order_info.lamports());
+    **order_info.try_borrow_mut_lamports()? -= reward;
+    **ctx.accounts.cranker.to_account_info().try_borrow_mut_lamports()? += reward;
+    order.close(ctx.accounts.rent_payer.to_account_info())
+}
+
+#[derive(Accounts)]
+pub struct CrankClaim<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"order_v1", state.key().as_ref(), owner.key().as_ref(), &order.seq.to_le_bytes()],
+        bump = order.bump
+    )]
+    pub order: Account<'info, Order>,
+    /// CHECK: maker owner stored in order
+    pub owner: UncheckedAccount<'info>,
+    /// CHECK: account that paid the order's rent (the owner unless recorded otherwise)
+    #[account(mut, address = order.rent_recipient() @ OrderbookError::InvalidRentPayer)]
+    pub rent_payer: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"inco_vault_authority_v12", state.key().as_ref()],
+        bump,
+        address = state.inco_vault_authority
+    )]
+    /// CHECK: PDA authority for Inco vaults
+    pub inco_vault_authority: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_base_vault)]
+    pub inco_base_vault: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_quote_vault)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    /// CHECK: Owner Inco accounts
+    #[account(mut)]
+    pub owner_base_inco: UncheckedAccount<'info>,
+    /// CHECK: Owner Inco accounts
+    #[account(mut)]
+    pub owner_quote_inco: UncheckedAccount<'info>,
+    /// CHECK: Inco base mint
+    #[account(address = state.inco_base_mint)]
+    pub inco_base_mint: UncheckedAccount<'info>,
+    /// CHECK: Inco quote mint
+    #[account(address = state.inco_quote_mint)]
+    pub inco_quote_mint: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub inco_token_program: Program<'info, IncoToken>,
+    /// CHECK: Inco Lightning program
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+}
+
+fn ensure_inco_account(
+    account: &UncheckedAccount<'_>,
+    expected_owner: Pubkey,
+    expected_mint: Pubkey,
+) -> Result<()> {
+    let info = account.to_account_info();
+    if info.owner != &INCO_TOKEN_ID {
+        return err!(OrderbookError::InvalidIncoProgramOwner);
+    }
+    let data = info.try_borrow_data()?;
+    let decoded = IncoAccount::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(OrderbookError::InvalidIncoAccountData))?;
+
+    if decoded.owner != expected_owner {
+        return err!(OrderbookError::InvalidIncoAccountOwner);
+    }
+    if decoded.mint != expected_mint {
+        return err!(OrderbookError::InvalidIncoAccountMint);
+    }
+    Ok(())
+}
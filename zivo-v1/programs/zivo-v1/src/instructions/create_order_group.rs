@@ -0,0 +1,387 @@
+use anchor_lang::prelude::*;
+use inco_lightning::{
+    cpi,
+    cpi::accounts::Operation,
+    program::IncoLightning,
+    types::Euint128,
+    ID as INCO_LIGHTNING_ID,
+};
+use inco_token::{
+    cpi as inco_token_cpi,
+    cpi::accounts::IncoTransfer,
+    program::IncoToken,
+    IncoAccount,
+    ID as INCO_TOKEN_ID,
+};
+
+use crate::errors::OrderbookError;
+use crate::instructions::cancel_order::escrow_refund;
+use crate::instructions::issue_access_pass::ensure_access_pass;
+use crate::instructions::place_order::{covered_escrow, OrderEscrow};
+use crate::state::{
+    ensure_user_input_type, handle_ciphertext, AccessPass, Order, OrderGroup, OrderbookState,
+    GROUP_STATUS_ACTIVE, GROUP_STATUS_CANCELLED, GROUP_STATUS_FILLED, HANDLE_INPUT_TYPE,
+    MARKET_MODE_CONTINUOUS, MARKET_STATUS_OPEN, MAX_ESCROW_CIPHERTEXT_LEN,
+};
+
+/// Escrows once for a one-cancels-other / bracket group. Legs are added with
+/// `place_group_order` and reserve their size from this shared escrow. A bracket escrows both
+/// sides; an empty ciphertext leaves that side unfunded. The group records the handle actually
+/// debited, which is 0 when the trader's balance does not cover the requested escrow.
+pub(crate) fn handler(
+    ctx: Context<CreateOrderGroup>,
+    group_id: u64,
+    base_escrow_ciphertext: Vec<u8>,
+    quote_escrow_ciphertext: Vec<u8>,
+    escrow_input_type: u8,
+) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    let inco = ctx.accounts.inco_lightning_program.to_account_info();
+    let signer = ctx.accounts.trader.to_account_info();
+
+    if state.status != MARKET_STATUS_OPEN {
+        return err!(OrderbookError::MarketClosed);
+    }
+    if state.market_mode != MARKET_MODE_CONTINUOUS {
+        return err!(OrderbookError::InvalidMarketMode);
+    }
+    ensure_access_pass(
+        state,
+        &ctx.accounts.access_pass,
+        ctx.accounts.trader.key(),
+        ctx.program_id,
+    )?;
+    if base_escrow_ciphertext.is_empty() && quote_escrow_ciphertext.is_empty() {
+        return err!(OrderbookError::InvalidEscrowCiphertext);
+    }
+    if base_escrow_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN
+        || quote_escrow_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN
+    {
+        return err!(OrderbookError::InvalidEscrowCiphertext);
+    }
+    ensure_user_input_type(escrow_input_type)?;
+
+    let mut quote_escrow_handle = 0u128;
+    if !quote_escrow_ciphertext.is_empty() {
+        ensure_inco_account(
+            &ctx.accounts.trader_quote_inco,
+            ctx.accounts.trader.key(),
+            state.inco_quote_mint,
+        )?;
+        ensure_inco_account(
+            &ctx.accounts.inco_quote_vault,
+            state.inco_vault_authority,
+            state.inco_quote_mint,
+        )?;
+
+        let requested: Euint128 = cpi::new_euint128(
+            CpiContext::new(inco.clone(), Operation { signer: signer.clone() }),
+            quote_escrow_ciphertext,
+            escrow_input_type,
+        )?;
+        let OrderEscrow { escrow, .. } = covered_escrow(
+            &inco,
+            &signer,
+            &ctx.accounts.trader_quote_inco,
+            requested,
+            requested,
+        )?;
+        quote_escrow_handle = escrow.0;
+        inco_token_cpi::transfer(
+            CpiContext::new(
+                ctx.accounts.inco_token_program.to_account_info(),
+                IncoTransfer {
+                    source: ctx.accounts.trader_quote_inco.to_account_info(),
+                    destination: ctx.accounts.inco_quote_vault.to_account_info(),
+                    authority: ctx.accounts.trader.to_account_info(),
+                    inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+            ),
+            handle_ciphertext(escrow.0),
+            HANDLE_INPUT_TYPE,
+        )?;
+    }
+    let mut base_escrow_handle = 0u128;
+    if !base_escrow_ciphertext.is_empty() {
+        ensure_inco_account(
+            &ctx.accounts.trader_base_inco,
+            ctx.accounts.trader.key(),
+            state.inco_base_mint,
+        )?;
+        ensure_inco_account(
+            &ctx.accounts.inco_base_vault,
+            state.inco_vault_authority,
+            state.inco_base_mint,
+        )?;
+
+        let requested: Euint128 = cpi::new_euint128(
+            CpiContext::new(inco.clone(), Operation { signer: signer.clone() }),
+            base_escrow_ciphertext,
+            escrow_input_type,
+        )?;
+        let OrderEscrow { escrow, .. } = covered_escrow(
+            &inco,
+            &signer,
+            &ctx.accounts.trader_base_inco,
+            requested,
+            requested,
+        )?;
+        base_escrow_handle = escrow.0;
+        inco_token_cpi::transfer(
+            CpiContext::new(
+                ctx.accounts.inco_token_program.to_account_info(),
+                IncoTransfer {
+                    source: ctx.accounts.trader_base_inco.to_account_info(),
+                    destination: ctx.accounts.inco_base_vault.to_account_info(),
+                    authority: ctx.accounts.trader.to_account_info(),
+                    inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+            ),
+            handle_ciphertext(escrow.0),
+            HANDLE_INPUT_TYPE,
+        )?;
+    }
+
+    let group = &mut ctx.accounts.order_group;
+    group.market = state.key();
+    group.owner = ctx.accounts.trader.key();
+    group.status = GROUP_STATUS_ACTIVE;
+    group.bump = ctx.bumps.order_group;
+    group._padding = [0u8; 6];
+    group.group_id = group_id;
+    group.filled_seq = 0;
+    group.base_escrow_handle = base_escrow_handle;
+    group.quote_escrow_handle = quote_escrow_handle;
+    group.base_reserved_handle = 0;
+    group.quote_reserved_handle = 0;
+    group.legs = Vec::new();
+    state.open_escrows = state.open_escrows.saturating_add(1);
+    Ok(())
+}
+
+/// Escrow a group hands back to its owner when it stops being active. A side is 0 when the
+/// group never funded it.
+pub(crate) struct GroupRelease {
+    pub owner: Pubkey,
+    pub quote: u128,
+    pub base: u128,
+}
+
+/// Locks a grouped order's group to that leg on its first fill, which cancels the siblings:
+/// they can no longer match, and everything the group holds besides this leg's reservation is
+/// released for the caller to pay back in the same instruction. Must run before the fill
+/// changes the leg's remaining size. Standalone orders pass through untouched.
+pub(crate) fn fill_group_leg<'info>(
+    inco: &AccountInfo<'info>,
+    signer: &AccountInfo<'info>,
+    order: &Order,
+    group: &mut Option<Account<OrderGroup>>,
+) -> Result<Option<GroupRelease>> {
+    if order.group == Pubkey::default() {
+        return Ok(None);
+    }
+    let group = match group.as_mut() {
+        Some(group) => group,
+        None => return err!(OrderbookError::MissingOrderGroup),
+    };
+    if group.key() != order.group {
+        return err!(OrderbookError::InvalidOrderGroup);
+    }
+    if group.status == GROUP_STATUS_ACTIVE {
+        group.status = GROUP_STATUS_FILLED;
+        group.filled_seq = order.seq;
+        return release_group(inco, signer, group, order).map(Some);
+    }
+    if group.status == GROUP_STATUS_FILLED && group.filled_seq == order.seq {
+        return Ok(None);
+    }
+    err!(OrderbookError::OrderGroupInactive)
+}
+
+/// Cancelling a leg refunds what that leg has left. Cancelling an unfilled leg also cancels the
+/// whole group and releases the rest of its escrow, which the caller pays back with the leg's
+/// refund; `sweep_order_group` only closes the sibling orders afterwards.
+pub(crate) fn cancel_group_leg<'info>(
+    inco: &AccountInfo<'info>,
+    signer: &AccountInfo<'info>,
+    order: &Order,
+    group: &mut Option<Account<OrderGroup>>,
+) -> Result<Option<GroupRelease>> {
+    if order.group == Pubkey::default() {
+        return Ok(None);
+    }
+    let group = match group.as_mut() {
+        Some(group) => group,
+        None => return err!(OrderbookError::MissingOrderGroup),
+    };
+    if group.key() != order.group {
+        return err!(OrderbookError::InvalidOrderGroup);
+    }
+    if group.status == GROUP_STATUS_ACTIVE {
+        group.status = GROUP_STATUS_CANCELLED;
+        return release_group(inco, signer, group, order).map(Some);
+    }
+    if group.status == GROUP_STATUS_FILLED && group.filled_seq == order.seq {
+        return Ok(None);
+    }
+    err!(OrderbookError::OrderGroupInactive)
+}
+
+/// Per side, the unreserved escrow plus every leg's reservation, minus `leg`'s own reservation,
+/// which stays with `leg`. Leaves the group holding nothing.
+fn release_group<'info>(
+    inco: &AccountInfo<'info>,
+    signer: &AccountInfo<'info>,
+    group: &mut OrderGroup,
+    leg: &Order,
+) -> Result<GroupRelease> {
+    let kept = escrow_refund(inco, signer, leg.side, leg.price, Euint128(leg.remaining_handle))?;
+    let mut released = [0u128; 2];
+    for (side, (unreserved, reserved)) in [
+        (group.quote_escrow_handle, group.quote_reserved_handle),
+        (group.base_escrow_handle, group.base_reserved_handle),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        if unreserved == 0 {
+            continue;
+        }
+        let mut held = Euint128(unreserved);
+        if reserved != 0 {
+            let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+            held = cpi::e_add(cpi_ctx, held, Euint128(reserved), 0)?;
+        }
+        if leg.side as usize == side {
+            let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+            held = cpi::e_sub(cpi_ctx, held, kept, 0)?;
+        }
+        released[side] = held.0;
+    }
+    group.quote_escrow_handle = 0;
+    group.base_escrow_handle = 0;
+    group.quote_reserved_handle = 0;
+    group.base_reserved_handle = 0;
+    Ok(GroupRelease {
+        owner: group.owner,
+        quote: released[0],
+        base: released[1],
+    })
+}
+
+/// Pays a released group escrow from the market vaults to the group owner's Inco accounts.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn pay_group_release<'info>(
+    release: &GroupRelease,
+    state: &OrderbookState,
+    owner_quote_inco: &UncheckedAccount<'info>,
+    owner_base_inco: &UncheckedAccount<'info>,
+    inco_quote_vault: &UncheckedAccount<'info>,
+    inco_base_vault: &UncheckedAccount<'info>,
+    inco_vault_authority: &AccountInfo<'info>,
+    vault_seeds: &[&[u8]],
+    inco_token_program: &AccountInfo<'info>,
+    inco_lightning_program: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let payouts = [
+        (release.quote, inco_quote_vault, owner_quote_inco, state.inco_quote_mint),
+        (release.base, inco_base_vault, owner_base_inco, state.inco_base_mint),
+    ];
+    for (amount, vault, destination, mint) in payouts {
+        if amount == 0 {
+            continue;
+        }
+        ensure_inco_account(destination, release.owner, mint)?;
+        ensure_inco_account(vault, state.inco_vault_authority, mint)?;
+        inco_token_cpi::transfer(
+            CpiContext::new_with_signer(
+                inco_token_program.clone(),
+                IncoTransfer {
+                    source: vault.to_account_info(),
+                    destination: destination.to_account_info(),
+                    authority: inco_vault_authority.clone(),
+                    inco_lightning_program: inco_lightning_program.clone(),
+                    system_program: system_program.clone(),
+                },
+                &[vault_seeds],
+            ),
+            handle_ciphertext(amount),
+            HANDLE_INPUT_TYPE,
+        )?;
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(group_id: u64)]
+pub struct CreateOrderGroup<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        init,
+        payer = trader,
+        space = 8 + OrderGroup::LEN,
+        seeds = [b"order_group_v1", state.key().as_ref(), trader.key().as_ref(), &group_id.to_le_bytes()],
+        bump
+    )]
+    pub order_group: Account<'info, OrderGroup>,
+    #[account(mut)]
+    pub trader: Signer<'info>,
+    /// Required when the market has a gatekeeper.
+    pub access_pass: Option<Account<'info, AccessPass>>,
+    #[account(
+        seeds = [b"inco_vault_authority_v12", state.key().as_ref()],
+        bump,
+        address = state.inco_vault_authority
+    )]
+    /// CHECK: PDA authority for Inco vaults
+    pub inco_vault_authority: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_base_vault)]
+    pub inco_base_vault: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_quote_vault)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    /// CHECK: Trader Inco accounts
+    #[account(mut)]
+    pub trader_base_inco: UncheckedAccount<'info>,
+    /// CHECK: Trader Inco accounts
+    #[account(mut)]
+    pub trader_quote_inco: UncheckedAccount<'info>,
+    /// CHECK: Inco base mint
+    #[account(address = state.inco_base_mint)]
+    pub inco_base_mint: UncheckedAccount<'info>,
+    /// CHECK: Inco quote mint
+    #[account(address = state.inco_quote_mint)]
+    pub inco_quote_mint: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub inco_token_program: Program<'info, IncoToken>,
+    /// CHECK: Inco Lightning program
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+}
+
+fn ensure_inco_account(
+    account: &UncheckedAccount<'_>,
+    expected_owner: Pubkey,
+    expected_mint: Pubkey,
+) -> Result<()> {
+    let info = account.to_account_info();
+    if info.owner != &INCO_TOKEN_ID {
+        return err!(OrderbookError::InvalidIncoProgramOwner);
+    }
+    let data = info.try_borrow_data()?;
+    let decoded = IncoAccount::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(OrderbookError::InvalidIncoAccountData))?;
+
+    if decoded.owner != expected_owner {
+        return err!(OrderbookError::InvalidIncoAccountOwner);
+    }
+    if decoded.mint != expected_mint {
+        return err!(OrderbookError::InvalidIncoAccountMint);
+    }
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use anchor_lang::prelude::*;
+use inco_token::{
+    cpi as inco_token_cpi,
+    cpi::accounts::IncoTransfer,
+    program::IncoToken,
+    IncoAccount,
+    ID as INCO_TOKEN_ID,
+};
+use inco_lightning::{program::IncoLightning, ID as INCO_LIGHTNING_ID};
+
+use crate::errors::OrderbookError;
+use crate::instructions::cancel_order::order_refund;
+use crate::instructions::create_order_group::{cancel_group_leg, pay_group_release};
+use crate::instructions::grant_order_view::allow_auditor;
+use crate::instructions::grant_trading_delegate::ensure_delegate;
+use crate::state::OrderbookState;
+use crate::state::{
+    handle_ciphertext, Order, OrderGroup, TradingDelegate, DELEGATE_ACTION_CANCEL,
+    HANDLE_INPUT_TYPE,
+};
+
+/// `cancel_order` signed by a trading delegate. The refund always goes to the owner's Inco
+/// accounts, never to the delegate.
+pub(crate) fn handler<'info>(ctx: Context<'_, '_, '_, 'info, DelegateCancelOrder<'info>>) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    let order = &mut ctx.accounts.order;
+
+    if !order.is_open {
+        return err!(OrderbookError::OrderClosed);
+    }
+    if order.owner != ctx.accounts.trader.key() {
+        return err!(OrderbookError::InvalidIncoAccountOwner);
+    }
+    ensure_delegate(
+        &ctx.accounts.trading_delegate,
+        ctx.accounts.delegate.key(),
+        state.key(),
+        DELEGATE_ACTION_CANCEL,
+    )?;
+    let inco = ctx.accounts.inco_lightning_program.to_account_info();
+    let signer = ctx.accounts.delegate.to_account_info();
+    let release = cancel_group_leg(&inco, &signer, order, &mut ctx.accounts.order_group)?;
+    let refund = order_refund(&inco, &signer, order)?;
+
+    let (vault, destination, mint) = match order.side {
+        0 => (
+            &ctx.accounts.inco_quote_vault,
+            &ctx.accounts.trader_quote_inco,
+            state.inco_quote_mint,
+        ),
+        1 => (
+            &ctx.accounts.inco_base_vault,
+            &ctx.accounts.trader_base_inco,
+            state.inco_base_mint,
+        ),
+        _ => return err!(OrderbookError::InvalidSide),
+    };
+    ensure_inco_account(destination, ctx.accounts.trader.key(), mint)?;
+    ensure_inco_account(vault, state.inco_vault_authority, mint)?;
+
+    let vault_authority_bump = ctx.bumps.inco_vault_authority;
+    let state_key = state.key();
+    let vault_seeds: &[&[u8]] = &[
+        b"inco_vault_authority_v12",
+        state_key.as_ref(),
+        &[vault_authority_bump],
+    ];
+    inco_token_cpi::transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.inco_token_program.to_account_info(),
+            IncoTransfer {
+                source: vault.to_account_info(),
+                destination: destination.to_account_info(),
+                authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+            &[vault_seeds],
+        ),
+        handle_ciphertext(refund.0),
+        HANDLE_INPUT_TYPE,
+    )?;
+    // Cancelling an unfilled leg cancels its group; the siblings' escrow comes back too.
+    if let Some(release) = release {
+        pay_group_release(
+            &release,
+            state,
+            &ctx.accounts.trader_quote_inco,
+            &ctx.accounts.trader_base_inco,
+            &ctx.accounts.inco_quote_vault,
+            &ctx.accounts.inco_base_vault,
+            &ctx.accounts.inco_vault_authority.to_account_info(),
+            vault_seeds,
+            &ctx.accounts.inco_token_program.to_account_info(),
+            &inco,
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+    }
+
+    // The auditor gets the refund handle itself, the amount that actually leaves the vault.
+    allow_auditor(
+        state.auditor,
+        ctx.remaining_accounts,
+        &[refund.0],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    order.is_open = false;
+    order.remaining_handle = 0;
+    state.open_orders = state.open_orders.saturating_sub(1);
+    // Unclaimed fills stay on the order, which keeps counting until they are claimed.
+    if !order.is_filled {
+        state.open_escrows = state.open_escrows.saturating_sub(1);
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct DelegateCancelOrder<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        mut,
+        seeds = [b"order_v1", state.key().as_ref(), trader.key().as_ref(), &order.seq.to_le_bytes()],
+        bump = order.bump
+    )]
+    pub order: Account<'info, Order>,
+    /// Required when the order belongs to an order group.
+    #[account(mut)]
+    pub order_group: Option<Account<'info, OrderGroup>>,
+    /// CHECK: order owner, bound through the trading delegate grant; receives the refund
+    pub trader: UncheckedAccount<'info>,
+    #[account(
+        has_one = delegate,
+        constraint = trading_delegate.owner == trader.key() @ OrderbookError::InvalidDelegate,
+        seeds = [b"trading_delegate_v1", trader.key().as_ref(), delegate.key().as_ref()],
+        bump = trading_delegate.bump
+    )]
+    pub trading_delegate: Account<'info, TradingDelegate>,
+    pub delegate: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"inco_vault_authority_v12", state.key().as_ref()],
+        bump,
+        address = state.inco_vault_authority
+    )]
+    /// CHECK: PDA authority for Inco vaults
+    pub inco_vault_authority: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_base_vault)]
+    pub inco_base_vault: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_quote_vault)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    /// CHECK: Trader Inco accounts
+    #[account(mut)]
+    pub trader_base_inco: UncheckedAccount<'info>,
+    /// CHECK: Trader Inco accounts
+    #[account(mut)]
+    pub trader_quote_inco: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub inco_token_program: Program<'info, IncoToken>,
+    /// CHECK: Inco Lightning program
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+}
+
+fn ensure_inco_account(
+    account: &UncheckedAccount<'_>,
+    expected_owner: Pubkey,
+    expected_mint: Pubkey,
+) -> Result<()> {
+    let info = account.to_account_info();
+    if info.owner != &INCO_TOKEN_ID {
+        return err!(OrderbookError::InvalidIncoProgramOwner);
+    }
+    let data = info.try_borrow_data()?;
+    let decoded = IncoAccount::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(OrderbookError::InvalidIncoAccountData))?;
+
+    if decoded.owner != expected_owner {
+        return err!(OrderbookError::InvalidIncoAccountOwner);
+    }
+    if decoded.mint != expected_mint {
+        return err!(OrderbookError::InvalidIncoAccountMint);
+    }
+    Ok(())
+}
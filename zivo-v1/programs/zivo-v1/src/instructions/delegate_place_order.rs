@@ -0,0 +1,158 @@
+use anchor_lang::prelude::*;
+use inco_lightning::{
+    cpi,
+    cpi::accounts::Operation,
+    program::IncoLightning,
+    types::Euint128,
+    ID as INCO_LIGHTNING_ID,
+};
+
+use crate::errors::OrderbookError;
+use crate::instructions::grant_order_view::{allow_auditor, allow_owners};
+use crate::instructions::grant_trading_delegate::ensure_delegate;
+use crate::instructions::issue_access_pass::ensure_access_pass;
+use crate::instructions::place_order::{add_book_order, covered_by, escrow_need, OrderEscrow};
+use crate::state::{
+    ensure_user_input_type, AccessPass, Order, OrderbookState, TradingDelegate, TraderNonces,
+    DELEGATE_ACTION_PLACE, MARKET_STATUS_OPEN, MAX_ESCROW_CIPHERTEXT_LEN,
+};
+
+/// `place_order` signed by a trading delegate. The delegate has no authority over the owner's
+/// Inco accounts, so the escrow `place_order` would take (size, or size * price for bids) is
+/// debited from the owner's signed-order balance (`deposit_signed_escrow`); an uncovered escrow
+/// leaves the order with a zero size.
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, DelegatePlaceOrder<'info>>,
+    side: u8,
+    price: u64,
+    size_ciphertext: Vec<u8>,
+    input_type: u8,
+) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    let grant = &mut ctx.accounts.trading_delegate;
+    let nonces = &mut ctx.accounts.owner_nonces;
+    let owner = ctx.accounts.owner.key();
+    let signer = ctx.accounts.delegate.to_account_info();
+    let inco = ctx.accounts.inco_lightning_program.to_account_info();
+
+    if state.status != MARKET_STATUS_OPEN {
+        return err!(OrderbookError::MarketClosed);
+    }
+    ensure_delegate(grant, signer.key(), state.key(), DELEGATE_ACTION_PLACE)?;
+    if grant.max_orders != 0 && grant.orders_placed >= grant.max_orders {
+        return err!(OrderbookError::DelegateOrderLimit);
+    }
+    ensure_access_pass(state, &ctx.accounts.access_pass, owner, ctx.program_id)?;
+    if side > 1 {
+        return err!(OrderbookError::InvalidSide);
+    }
+    if size_ciphertext.is_empty() || size_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN {
+        return err!(OrderbookError::InvalidEscrowCiphertext);
+    }
+
+    let balance_handle = if side == 0 {
+        nonces.quote_escrow_handle
+    } else {
+        nonces.base_escrow_handle
+    };
+    if balance_handle == 0 {
+        return err!(OrderbookError::InvalidEscrowCiphertext);
+    }
+    ensure_user_input_type(input_type)?;
+
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let size: Euint128 = cpi::new_euint128(cpi_ctx, size_ciphertext, input_type)?;
+    let need = escrow_need(&inco, &signer, side, price, size)?;
+    let OrderEscrow {
+        remaining: remaining_handle,
+        escrow: debit,
+    } = covered_by(&inco, &signer, Euint128(balance_handle), size, need)?;
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let balance: Euint128 = cpi::e_sub(cpi_ctx, Euint128(balance_handle), debit, 0)?;
+    if side == 0 {
+        nonces.quote_escrow_handle = balance.0;
+    } else {
+        nonces.base_escrow_handle = balance.0;
+    }
+
+    ctx.accounts.order.set_inner(Order::init(
+        state,
+        owner,
+        side,
+        price,
+        remaining_handle.0,
+        ctx.bumps.order,
+        ctx.accounts.delegate.key(),
+    ));
+
+    // Auditor allowances sit at the tail of the remaining accounts.
+    let auditor_accounts = allow_auditor(
+        state.auditor,
+        ctx.remaining_accounts,
+        &[remaining_handle.0, balance.0],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    let owner_accounts = &ctx.remaining_accounts[..ctx.remaining_accounts.len() - auditor_accounts];
+
+    // Only the owner (never the delegate) may decrypt the remaining size and balance.
+    allow_owners(
+        owner_accounts,
+        &[(owner, remaining_handle.0), (owner, balance.0)],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    grant.orders_placed = grant.orders_placed.saturating_add(1);
+    state.order_seq = state.order_seq.wrapping_add(1);
+    add_book_order(
+        state,
+        side,
+        price,
+        &ctx.accounts.delegate.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    state.open_escrows = state.open_escrows.saturating_add(1);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct DelegatePlaceOrder<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        init,
+        payer = delegate,
+        space = 8 + Order::LEN,
+        seeds = [b"order_v1", state.key().as_ref(), owner.key().as_ref(), &state.order_seq.to_le_bytes()],
+        bump
+    )]
+    pub order: Account<'info, Order>,
+    #[account(
+        mut,
+        has_one = owner,
+        has_one = delegate,
+        seeds = [b"trading_delegate_v1", owner.key().as_ref(), delegate.key().as_ref()],
+        bump = trading_delegate.bump
+    )]
+    pub trading_delegate: Account<'info, TradingDelegate>,
+    #[account(
+        mut,
+        seeds = [b"trader_nonces_v1", state.key().as_ref(), owner.key().as_ref()],
+        bump = owner_nonces.bump
+    )]
+    pub owner_nonces: Account<'info, TraderNonces>,
+    /// CHECK: order owner, bound through the trading delegate grant
+    pub owner: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub delegate: Signer<'info>,
+    /// Required when the market has a gatekeeper (issued to the owner).
+    pub access_pass: Option<Account<'info, AccessPass>>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: Inco Lightning program for encrypted operations
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+}
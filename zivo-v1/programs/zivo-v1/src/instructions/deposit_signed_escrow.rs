@@ -0,0 +1,200 @@
+use anchor_lang::prelude::*;
+use inco_lightning::{
+    cpi,
+    cpi::accounts::Operation,
+    program::IncoLightning,
+    types::Euint128,
+    ID as INCO_LIGHTNING_ID,
+};
+use inco_token::{
+    cpi as inco_token_cpi,
+    cpi::accounts::IncoTransfer,
+    program::IncoToken,
+    IncoAccount,
+    ID as INCO_TOKEN_ID,
+};
+
+use crate::errors::OrderbookError;
+use crate::instructions::grant_order_view::{allow_auditor, allow_owners};
+use crate::instructions::issue_access_pass::ensure_access_pass;
+use crate::instructions::place_order::{covered_escrow, OrderEscrow};
+use crate::state::{
+    ensure_user_input_type, handle_ciphertext, AccessPass, OrderbookState, TraderNonces,
+    HANDLE_INPUT_TYPE, MARKET_STATUS_OPEN, MAX_ESCROW_CIPHERTEXT_LEN,
+};
+
+/// Tops up the encrypted balance that backs a trader's off-chain signed orders: quote for
+/// bids (`side` 0), base for asks (`side` 1). Creates the trader's nonce account on first use.
+/// The balance grows by the handle actually debited, which is 0 when the Inco account does not
+/// cover the requested amount.
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, DepositSignedEscrow<'info>>,
+    side: u8,
+    escrow_ciphertext: Vec<u8>,
+    input_type: u8,
+) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    let inco = ctx.accounts.inco_lightning_program.to_account_info();
+    let signer = ctx.accounts.trader.to_account_info();
+
+    if state.status != MARKET_STATUS_OPEN {
+        return err!(OrderbookError::MarketClosed);
+    }
+    ensure_access_pass(
+        state,
+        &ctx.accounts.access_pass,
+        ctx.accounts.trader.key(),
+        ctx.program_id,
+    )?;
+    if escrow_ciphertext.is_empty() || escrow_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN {
+        return err!(OrderbookError::InvalidEscrowCiphertext);
+    }
+    ensure_user_input_type(input_type)?;
+
+    let (trader_source, vault, mint) = if side == 0 {
+        (
+            &ctx.accounts.trader_quote_inco,
+            &ctx.accounts.inco_quote_vault,
+            state.inco_quote_mint,
+        )
+    } else if side == 1 {
+        (
+            &ctx.accounts.trader_base_inco,
+            &ctx.accounts.inco_base_vault,
+            state.inco_base_mint,
+        )
+    } else {
+        return err!(OrderbookError::InvalidSide);
+    };
+    ensure_inco_account(trader_source, ctx.accounts.trader.key(), mint)?;
+    ensure_inco_account(vault, state.inco_vault_authority, mint)?;
+
+    let requested: Euint128 = cpi::new_euint128(
+        CpiContext::new(inco.clone(), Operation { signer: signer.clone() }),
+        escrow_ciphertext,
+        input_type,
+    )?;
+    // Only the debited amount is credited: an uncovered deposit moves and credits nothing.
+    let OrderEscrow {
+        escrow: amount, ..
+    } = covered_escrow(&inco, &signer, trader_source, requested, requested)?;
+    inco_token_cpi::transfer(
+        CpiContext::new(
+            ctx.accounts.inco_token_program.to_account_info(),
+            IncoTransfer {
+                source: trader_source.to_account_info(),
+                destination: vault.to_account_info(),
+                authority: ctx.accounts.trader.to_account_info(),
+                inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+        ),
+        handle_ciphertext(amount.0),
+        HANDLE_INPUT_TYPE,
+    )?;
+
+    let nonces = &mut ctx.accounts.trader_nonces;
+    if nonces.trader == Pubkey::default() {
+        nonces.market = state.key();
+        nonces.trader = ctx.accounts.trader.key();
+        nonces.bump = ctx.bumps.trader_nonces;
+    }
+    // A balance counts as one open escrow from its first deposit until it is fully withdrawn.
+    if nonces.base_escrow_handle == 0 && nonces.quote_escrow_handle == 0 {
+        state.open_escrows = state.open_escrows.saturating_add(1);
+    }
+    let current = if side == 0 {
+        nonces.quote_escrow_handle
+    } else {
+        nonces.base_escrow_handle
+    };
+    let balance: Euint128 = if current == 0 {
+        amount
+    } else {
+        let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+        cpi::e_add(cpi_ctx, Euint128(current), amount, 0)?
+    };
+    if side == 0 {
+        nonces.quote_escrow_handle = balance.0;
+    } else {
+        nonces.base_escrow_handle = balance.0;
+    }
+
+    // Auditor allowances sit at the tail of the remaining accounts.
+    let auditor_accounts = allow_auditor(
+        state.auditor,
+        ctx.remaining_accounts,
+        &[balance.0],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    let owner_accounts = &ctx.remaining_accounts[..ctx.remaining_accounts.len() - auditor_accounts];
+
+    // The owner must be able to decrypt its balance: one leading [allowance, owner] pair.
+    allow_owners(
+        owner_accounts,
+        &[(ctx.accounts.trader.key(), balance.0)],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct DepositSignedEscrow<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        init_if_needed,
+        payer = trader,
+        space = 8 + TraderNonces::LEN,
+        seeds = [b"trader_nonces_v1", state.key().as_ref(), trader.key().as_ref()],
+        bump
+    )]
+    pub trader_nonces: Account<'info, TraderNonces>,
+    #[account(mut)]
+    pub trader: Signer<'info>,
+    /// Required when the market has a gatekeeper.
+    pub access_pass: Option<Account<'info, AccessPass>>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_base_vault)]
+    pub inco_base_vault: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_quote_vault)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    /// CHECK: Trader Inco accounts
+    #[account(mut)]
+    pub trader_base_inco: UncheckedAccount<'info>,
+    /// CHECK: Trader Inco accounts
+    #[account(mut)]
+    pub trader_quote_inco: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub inco_token_program: Program<'info, IncoToken>,
+    /// CHECK: Inco Lightning program for encrypted operations
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+}
+
+fn ensure_inco_account(
+    account: &UncheckedAccount<'_>,
+    expected_owner: Pubkey,
+    expected_mint: Pubkey,
+) -> Result<()> {
+    let info = account.to_account_info();
+    if info.owner != &INCO_TOKEN_ID {
+        return err!(OrderbookError::InvalidIncoProgramOwner);
+    }
+    let data = info.try_borrow_data()?;
+    let decoded = IncoAccount::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(OrderbookError::InvalidIncoAccountData))?;
+
+    if decoded.owner != expected_owner {
+        return err!(OrderbookError::InvalidIncoAccountOwner);
+    }
+    if decoded.mint != expected_mint {
+        return err!(OrderbookError::InvalidIncoAccountMint);
+    }
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::OrderbookError;
+use crate::state::{
+    OrderbookState, PendingMatch, MARKET_STATUS_CLOSED, PENDING_MATCH_TIMEOUT_SLOTS,
+};
+
+/// Drops a prepared match without touching the orders or vaults. The matcher may discard at
+/// any time; anyone else only after `PENDING_MATCH_TIMEOUT_SLOTS`, or at once when the match's
+/// market is passed and closed, since it can no longer settle. Rent returns to the matcher.
+pub(crate) fn handler(ctx: Context<DiscardPendingMatch>) -> Result<()> {
+    let pending = &ctx.accounts.pending_match;
+    let market_closed = ctx
+        .accounts
+        .state
+        .as_ref()
+        .is_some_and(|state| state.status == MARKET_STATUS_CLOSED);
+    if ctx.accounts.caller.key() != pending.matcher
+        && !market_closed
+        && Clock::get()?.slot <= pending.prepared_slot.saturating_add(PENDING_MATCH_TIMEOUT_SLOTS)
+    {
+        return err!(OrderbookError::PendingMatchNotExpired);
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct DiscardPendingMatch<'info> {
+    /// The match's market; lets anyone discard once it is closed.
+    #[account(address = pending_match.market @ OrderbookError::InvalidPendingMatch)]
+    pub state: Option<Account<'info, OrderbookState>>,
+    #[account(
+        mut,
+        close = matcher,
+        has_one = matcher,
+        seeds = [
+            b"pending_match_v1",
+            pending_match.maker_order.as_ref(),
+            pending_match.taker_order.as_ref()
+        ],
+        bump = pending_match.bump
+    )]
+    pub pending_match: Account<'info, PendingMatch>,
+    /// CHECK: receives the rent; checked against the pending match
+    #[account(mut)]
+    pub matcher: UncheckedAccount<'info>,
+    pub caller: Signer<'info>,
+}
@@ -0,0 +1,140 @@
+use anchor_lang::prelude::*;
+use inco_lightning::cpi::accounts::Allow;
+use inco_lightning::cpi::allow;
+use inco_lightning::{program::IncoLightning, ID as INCO_LIGHTNING_ID};
+
+use crate::errors::OrderbookError;
+use crate::state::{Order, OrderbookState};
+
+pub(crate) fn handler(ctx: Context<GrantOrderView>) -> Result<()> {
+    let order = &ctx.accounts.order;
+    if order.owner != ctx.accounts.trader.key() {
+        return err!(OrderbookError::InvalidOrderOwner);
+    }
+
+    allow_handle(
+        ctx.accounts.inco_lightning_program.to_account_info(),
+        ctx.accounts.trader.to_account_info(),
+        ctx.accounts.allowance_account.to_account_info(),
+        ctx.accounts.viewer.to_account_info(),
+        ctx.accounts.system_program.to_account_info(),
+        order.remaining_handle,
+    )
+}
+
+/// Grants `allowed_address` decrypt access to `handle` through inco-lightning `allow`.
+pub(crate) fn allow_handle<'info>(
+    inco_lightning_program: AccountInfo<'info>,
+    signer: AccountInfo<'info>,
+    allowance_account: AccountInfo<'info>,
+    allowed_address: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    handle: u128,
+) -> Result<()> {
+    let allowed_key = allowed_address.key();
+    let cpi_ctx = CpiContext::new(
+        inco_lightning_program,
+        Allow {
+            allowance_account,
+            signer,
+            allowed_address,
+            system_program,
+        },
+    );
+    allow(cpi_ctx, handle, true, allowed_key)
+}
+
+/// Grants each `(owner, handle)` in `grants` decrypt access, reading one `[allowance, owner]`
+/// pair per grant from the front of `accounts`. Missing pairs fail after logging the handles, so
+/// clients can simulate without them to learn which allowance PDAs to pass.
+pub(crate) fn allow_owners<'info>(
+    accounts: &[AccountInfo<'info>],
+    grants: &[(Pubkey, u128)],
+    inco_lightning_program: &AccountInfo<'info>,
+    signer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    if accounts.len() < grants.len() * 2 {
+        for (owner, handle) in grants {
+            msg!("owner allowance required: {} {}", owner, handle);
+        }
+        return err!(OrderbookError::MissingOwnerAllowance);
+    }
+    for (i, (owner, handle)) in grants.iter().enumerate() {
+        let allowance_account = &accounts[i * 2];
+        let owner_address = &accounts[i * 2 + 1];
+        if owner_address.key() != *owner {
+            return err!(OrderbookError::InvalidAllowanceAddress);
+        }
+        allow_handle(
+            inco_lightning_program.clone(),
+            signer.clone(),
+            allowance_account.clone(),
+            owner_address.clone(),
+            system_program.clone(),
+            *handle,
+        )?;
+    }
+    Ok(())
+}
+
+/// Grants the market auditor access to `handles`, reading one `[allowance, auditor]` pair per
+/// handle from the tail of `accounts`. Returns how many trailing accounts were consumed. Missing
+/// pairs fail after logging the handles, as in `allow_owners`.
+pub(crate) fn allow_auditor<'info>(
+    auditor: Pubkey,
+    accounts: &[AccountInfo<'info>],
+    handles: &[u128],
+    inco_lightning_program: &AccountInfo<'info>,
+    signer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<usize> {
+    if auditor == Pubkey::default() {
+        return Ok(0);
+    }
+    let needed = handles.len() * 2;
+    if accounts.len() < needed {
+        for handle in handles {
+            msg!("auditor allowance required: {} {}", auditor, handle);
+        }
+        return err!(OrderbookError::MissingAuditorAllowance);
+    }
+    let tail = &accounts[accounts.len() - needed..];
+    for (i, handle) in handles.iter().enumerate() {
+        let allowance_account = &tail[i * 2];
+        let auditor_address = &tail[i * 2 + 1];
+        if auditor_address.key() != auditor {
+            return err!(OrderbookError::InvalidAllowanceAddress);
+        }
+        allow_handle(
+            inco_lightning_program.clone(),
+            signer.clone(),
+            allowance_account.clone(),
+            auditor_address.clone(),
+            system_program.clone(),
+            *handle,
+        )?;
+    }
+    Ok(needed)
+}
+
+#[derive(Accounts)]
+pub struct GrantOrderView<'info> {
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        seeds = [b"order_v1", state.key().as_ref(), trader.key().as_ref(), &order.seq.to_le_bytes()],
+        bump = order.bump
+    )]
+    pub order: Account<'info, Order>,
+    #[account(mut)]
+    pub trader: Signer<'info>,
+    /// CHECK: Inco allowance PDA for (handle, viewer)
+    #[account(mut)]
+    pub allowance_account: UncheckedAccount<'info>,
+    /// CHECK: third party being granted view access
+    pub viewer: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: Inco Lightning program
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+}
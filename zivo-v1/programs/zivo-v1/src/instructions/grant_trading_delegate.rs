@@ -0,0 +1,78 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::OrderbookError;
+use crate::state::{
+    TradingDelegate, DELEGATE_ACTION_CANCEL, DELEGATE_ACTION_PLACE, MAX_DELEGATE_MARKETS,
+};
+
+/// Authorizes a hot key to place and/or cancel orders for the owner on the listed markets
+/// until `expiry_ts`. Granting again to the same delegate replaces the previous grant.
+pub(crate) fn handler(
+    ctx: Context<GrantTradingDelegate>,
+    delegate: Pubkey,
+    expiry_ts: i64,
+    markets: Vec<Pubkey>,
+    actions: u8,
+    max_orders: u32,
+) -> Result<()> {
+    if delegate == Pubkey::default() || delegate == ctx.accounts.owner.key() {
+        return err!(OrderbookError::InvalidDelegate);
+    }
+    if expiry_ts <= Clock::get()?.unix_timestamp {
+        return err!(OrderbookError::InvalidDelegate);
+    }
+    if markets.is_empty() || markets.len() > MAX_DELEGATE_MARKETS {
+        return err!(OrderbookError::InvalidDelegate);
+    }
+    if actions == 0 || actions & !(DELEGATE_ACTION_PLACE | DELEGATE_ACTION_CANCEL) != 0 {
+        return err!(OrderbookError::InvalidDelegate);
+    }
+
+    let grant = &mut ctx.accounts.trading_delegate;
+    grant.owner = ctx.accounts.owner.key();
+    grant.delegate = delegate;
+    grant.expiry_ts = expiry_ts;
+    grant.actions = actions;
+    grant.bump = ctx.bumps.trading_delegate;
+    grant._padding = [0u8; 2];
+    grant.max_orders = max_orders;
+    grant.orders_placed = 0;
+    grant._reserved = [0u8; 4];
+    grant.markets = markets;
+    Ok(())
+}
+
+/// Checks that `signer` may perform `action` on `market` for the delegate's owner.
+pub(crate) fn ensure_delegate(
+    grant: &TradingDelegate,
+    signer: Pubkey,
+    market: Pubkey,
+    action: u8,
+) -> Result<()> {
+    if grant.delegate != signer {
+        return err!(OrderbookError::InvalidDelegate);
+    }
+    if Clock::get()?.unix_timestamp >= grant.expiry_ts {
+        return err!(OrderbookError::DelegateExpired);
+    }
+    if grant.actions & action == 0 || !grant.markets.contains(&market) {
+        return err!(OrderbookError::DelegateActionNotAllowed);
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(delegate: Pubkey)]
+pub struct GrantTradingDelegate<'info> {
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + TradingDelegate::LEN,
+        seeds = [b"trading_delegate_v1", owner.key().as_ref(), delegate.as_ref()],
+        bump
+    )]
+    pub trading_delegate: Account<'info, TradingDelegate>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,48 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::OrderbookError;
+use crate::state::CiphertextBuffer;
+
+/// Creates an empty ciphertext buffer sized for one `MAX_ESCROW_CIPHERTEXT_LEN` ciphertext.
+pub(crate) fn handler(ctx: Context<InitBuffer>, buffer_id: u64) -> Result<()> {
+    let buffer = &mut ctx.accounts.buffer;
+    buffer.owner = ctx.accounts.owner.key();
+    buffer.buffer_id = buffer_id;
+    buffer.bump = ctx.bumps.buffer;
+    buffer._padding = [0u8; 7];
+    buffer.data.clear();
+    Ok(())
+}
+
+/// Picks the ciphertext for an instruction argument: the inline bytes, or the contents of
+/// `buffer` when one is passed. A buffer must belong to `owner` and the inline argument must
+/// then be empty.
+pub(crate) fn resolve_ciphertext(
+    inline: Vec<u8>,
+    buffer: &Option<Account<'_, CiphertextBuffer>>,
+    owner: Pubkey,
+) -> Result<Vec<u8>> {
+    let Some(buffer) = buffer else {
+        return Ok(inline);
+    };
+    if buffer.owner != owner || !inline.is_empty() {
+        return err!(OrderbookError::InvalidCiphertextBuffer);
+    }
+    Ok(buffer.data.clone())
+}
+
+#[derive(Accounts)]
+#[instruction(buffer_id: u64)]
+pub struct InitBuffer<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + CiphertextBuffer::LEN,
+        seeds = [b"ciphertext_buffer_v1", owner.key().as_ref(), &buffer_id.to_le_bytes()],
+        bump
+    )]
+    pub buffer: Account<'info, CiphertextBuffer>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
@@ -1,10 +1,35 @@
 use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer};
 use inco_token::{IncoAccount, IncoMint, ID as INCO_TOKEN_ID};
 
 use crate::errors::OrderbookError;
-use crate::state::OrderbookState;
+use crate::state::{
+    MarketRegistry, OrderbookState, ProtocolConfig, MARKET_CREATION_PERMISSIONED,
+    MARKET_MODE_CONTINUOUS, MARKET_STATUS_OPEN,
+};
+
+pub(crate) fn handler(ctx: Context<Initialize>, require_attestation: bool) -> Result<()> {
+    let config = &ctx.accounts.protocol_config;
+    if config.market_creation_policy == MARKET_CREATION_PERMISSIONED {
+        if ctx.accounts.admin.key() != config.admin {
+            return err!(OrderbookError::UnauthorizedProtocolAdmin);
+        }
+    } else if config.listing_fee_lamports > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: ctx.accounts.treasury.to_account_info(),
+                },
+            ),
+            config.listing_fee_lamports,
+        )?;
+    }
+
+    let registry = &mut ctx.accounts.market_registry;
+    registry.markets.push(ctx.accounts.state.key());
 
-pub fn handler(ctx: Context<Initialize>, require_attestation: bool) -> Result<()> {
     let state = &mut ctx.accounts.state;
 
     let base_mint = load_inco_mint(&ctx.accounts.inco_base_mint)?;
@@ -32,13 +57,31 @@ pub fn handler(ctx: Context<Initialize>, require_attestation: bool) -> Result<()
     state.admin = ctx.accounts.admin.key();
     state.order_seq = 0;
     state.require_attestation = if require_attestation { 1 } else { 0 };
-    state._reserved = [0u8; 7];
+    state.status = MARKET_STATUS_OPEN;
+    state.maker_fee_bps = config.default_maker_fee_bps;
+    state.taker_fee_bps = config.default_taker_fee_bps;
+    state._reserved = [0u8; 2];
     state.inco_base_mint = ctx.accounts.inco_base_mint.key();
     state.inco_quote_mint = ctx.accounts.inco_quote_mint.key();
     state.inco_vault_authority = ctx.accounts.inco_vault_authority.key();
     state.inco_base_vault = ctx.accounts.inco_base_vault.key();
     state.inco_quote_vault = ctx.accounts.inco_quote_vault.key();
-    state._padding = [0u8; 8];
+    state.open_escrows = 0;
+    state.gatekeeper = Pubkey::default();
+    state.auditor = Pubkey::default();
+    state.pending_auditor = Pubkey::default();
+    state.pending_auditor_activation_ts = 0;
+    state.market_mode = MARKET_MODE_CONTINUOUS;
+    state._mode_padding = [0u8; 7];
+    state.auction_window_slots = 0;
+    state.auction_id = 0;
+    state.auction_start_slot = 0;
+    state.price_feed = Pubkey::default();
+    state.max_staleness_slots = 0;
+    state.max_confidence_bps = 0;
+    state._feed_padding = [0u8; 6];
+    state.last_trade_price = 0;
+    state.open_orders = 0;
     Ok(())
 }
 
@@ -52,6 +95,20 @@ pub struct Initialize<'info> {
         bump
     )]
     pub state: Account<'info, OrderbookState>,
+    #[account(seeds = [b"protocol_config_v1"], bump = protocol_config.bump)]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    #[account(
+        mut,
+        seeds = [b"market_registry_v1"],
+        bump = market_registry.bump,
+        realloc = MarketRegistry::space(market_registry.markets.len() + 1),
+        realloc::payer = payer,
+        realloc::zero = false
+    )]
+    pub market_registry: Account<'info, MarketRegistry>,
+    /// CHECK: listing fee recipient from protocol config
+    #[account(mut, address = protocol_config.treasury)]
+    pub treasury: UncheckedAccount<'info>,
     #[account(seeds = [b"inco_vault_authority_v12", state.key().as_ref()], bump)]
     /// CHECK: PDA authority for Inco vaults
     pub inco_vault_authority: UncheckedAccount<'info>,
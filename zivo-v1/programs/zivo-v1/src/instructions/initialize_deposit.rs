@@ -2,7 +2,8 @@ use anchor_lang::prelude::*;
 use inco_token::{IncoAccount, ID as INCO_TOKEN_ID};
 
 use crate::errors::OrderbookError;
-use crate::state::{DepositAccount, OrderbookState};
+use crate::instructions::issue_access_pass::ensure_access_pass;
+use crate::state::{AccessPass, DepositAccount, OrderbookState};
 
 #[derive(Accounts)]
 pub struct InitializeDeposit<'info> {
@@ -24,10 +25,19 @@ pub struct InitializeDeposit<'info> {
     pub user_base_inco: UncheckedAccount<'info>,
     /// CHECK: User Inco quote account
     pub user_quote_inco: UncheckedAccount<'info>,
+    /// Required when the market has a gatekeeper.
+    pub access_pass: Option<Account<'info, AccessPass>>,
     pub system_program: Program<'info, System>,
 }
 
-pub fn handler(ctx: Context<InitializeDeposit>) -> Result<()> {
+pub(crate) fn handler(ctx: Context<InitializeDeposit>) -> Result<()> {
+    ensure_access_pass(
+        &ctx.accounts.state,
+        &ctx.accounts.access_pass,
+        ctx.accounts.user.key(),
+        ctx.program_id,
+    )?;
+
     let base_account = load_inco_account(&ctx.accounts.user_base_inco)?;
     let quote_account = load_inco_account(&ctx.accounts.user_quote_inco)?;
 
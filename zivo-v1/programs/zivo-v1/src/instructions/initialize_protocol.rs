@@ -0,0 +1,89 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::OrderbookError;
+use crate::program::ZivoOrderbookProgram;
+use crate::state::{
+    MarketRegistry, ProtocolConfig, MARKET_CREATION_PERMISSIONED, MARKET_CREATION_PERMISSIONLESS,
+    MAX_FEE_BPS,
+};
+
+/// Creates the singleton protocol config and market registry. Only the program's upgrade
+/// authority can call it, so the first deployer cannot be front-run as protocol admin.
+pub(crate) fn handler(
+    ctx: Context<InitializeProtocol>,
+    default_maker_fee_bps: u16,
+    default_taker_fee_bps: u16,
+    market_creation_policy: u8,
+    listing_fee_lamports: u64,
+) -> Result<()> {
+    validate_config(default_maker_fee_bps, default_taker_fee_bps, market_creation_policy)?;
+
+    let config = &mut ctx.accounts.protocol_config;
+    config.admin = ctx.accounts.admin.key();
+    config.treasury = ctx.accounts.treasury.key();
+    config.default_maker_fee_bps = default_maker_fee_bps;
+    config.default_taker_fee_bps = default_taker_fee_bps;
+    config.market_creation_policy = market_creation_policy;
+    config.bump = ctx.bumps.protocol_config;
+    config._padding = [0u8; 2];
+    config.listing_fee_lamports = listing_fee_lamports;
+
+    let registry = &mut ctx.accounts.market_registry;
+    registry.bump = ctx.bumps.market_registry;
+    registry._padding = [0u8; 7];
+    registry.markets = Vec::new();
+
+    Ok(())
+}
+
+pub(crate) fn validate_config(
+    maker_fee_bps: u16,
+    taker_fee_bps: u16,
+    market_creation_policy: u8,
+) -> Result<()> {
+    if maker_fee_bps > MAX_FEE_BPS || taker_fee_bps > MAX_FEE_BPS {
+        return err!(OrderbookError::InvalidFee);
+    }
+    if market_creation_policy != MARKET_CREATION_PERMISSIONED
+        && market_creation_policy != MARKET_CREATION_PERMISSIONLESS
+    {
+        return err!(OrderbookError::InvalidMarketCreationPolicy);
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeProtocol<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ProtocolConfig::LEN,
+        seeds = [b"protocol_config_v1"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    #[account(
+        init,
+        payer = payer,
+        space = MarketRegistry::space(0),
+        seeds = [b"market_registry_v1"],
+        bump
+    )]
+    pub market_registry: Account<'info, MarketRegistry>,
+    /// CHECK: receives listing fees
+    pub treasury: UncheckedAccount<'info>,
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key())
+            @ OrderbookError::UnauthorizedProtocolAdmin
+    )]
+    pub admin: Signer<'info>,
+    #[account(
+        constraint = program.programdata_address()? == Some(program_data.key())
+            @ OrderbookError::UnauthorizedProtocolAdmin
+    )]
+    pub program: Program<'info, ZivoOrderbookProgram>,
+    pub program_data: Account<'info, ProgramData>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
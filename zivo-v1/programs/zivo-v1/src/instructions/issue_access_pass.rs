@@ -0,0 +1,65 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::OrderbookError;
+use crate::state::{AccessPass, OrderbookState};
+
+pub(crate) fn handler(ctx: Context<IssueAccessPass>) -> Result<()> {
+    let state = &ctx.accounts.state;
+    if state.gatekeeper == Pubkey::default() || ctx.accounts.gatekeeper.key() != state.gatekeeper {
+        return err!(OrderbookError::UnauthorizedGatekeeper);
+    }
+
+    let pass = &mut ctx.accounts.access_pass;
+    pass.market = state.key();
+    pass.trader = ctx.accounts.trader.key();
+    pass.issuer = ctx.accounts.gatekeeper.key();
+    pass.bump = ctx.bumps.access_pass;
+    pass._padding = [0u8; 7];
+    Ok(())
+}
+
+/// Checks the optional access pass when the market has a gatekeeper set. Only passes issued by
+/// the current gatekeeper count.
+pub(crate) fn ensure_access_pass(
+    state: &Account<'_, OrderbookState>,
+    access_pass: &Option<Account<'_, AccessPass>>,
+    trader: Pubkey,
+    program_id: &Pubkey,
+) -> Result<()> {
+    if state.gatekeeper == Pubkey::default() {
+        return Ok(());
+    }
+    let pass = match access_pass {
+        Some(pass) => pass,
+        None => return err!(OrderbookError::MissingAccessPass),
+    };
+    if pass.market != state.key() || pass.trader != trader || pass.issuer != state.gatekeeper {
+        return err!(OrderbookError::InvalidAccessPass);
+    }
+    let (derived, _) = Pubkey::find_program_address(
+        &[b"access_pass_v1", state.key().as_ref(), trader.as_ref()],
+        program_id,
+    );
+    if derived != pass.key() {
+        return err!(OrderbookError::InvalidAccessPass);
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct IssueAccessPass<'info> {
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        init,
+        payer = gatekeeper,
+        space = 8 + AccessPass::LEN,
+        seeds = [b"access_pass_v1", state.key().as_ref(), trader.key().as_ref()],
+        bump
+    )]
+    pub access_pass: Account<'info, AccessPass>,
+    /// CHECK: trader being onboarded
+    pub trader: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub gatekeeper: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
@@ -11,18 +11,17 @@ use inco_lightning::{program::IncoLightning, ID as INCO_LIGHTNING_ID};
 use crate::errors::OrderbookError;
 use crate::state::{Order, OrderbookState, MAX_ESCROW_CIPHERTEXT_LEN};
 
-pub fn handler(
+/// Pays the maker's accrued fills. An open maker may claim while it keeps resting; a closed one
+/// is then fully settled.
+pub(crate) fn handler(
     ctx: Context<MakerClaimFilledOrder>,
 ) -> Result<()> {
-    let state = &ctx.accounts.state;
+    let state = &mut ctx.accounts.state;
     let order = &mut ctx.accounts.order;
 
     if order.owner != ctx.accounts.maker.key() {
         return err!(OrderbookError::InvalidOrderOwner);
     }
-    if order.is_open {
-        return err!(OrderbookError::OrderStillOpen);
-    }
     if !order.is_filled {
         return err!(OrderbookError::OrderNotFilled);
     }
@@ -106,7 +105,15 @@ pub fn handler(
         return err!(OrderbookError::InvalidSide);
     }
 
-    order.is_claimed = true;
+    if order.is_open {
+        // The order keeps resting; later fills accrue on a fresh claim.
+        order.is_filled = false;
+        order.claim_input_type = 0;
+        order.claim_ciphertext.clear();
+    } else {
+        order.is_claimed = true;
+        state.open_escrows = state.open_escrows.saturating_sub(1);
+    }
     Ok(())
 }
 
@@ -0,0 +1,167 @@
+use anchor_lang::prelude::*;
+use inco_lightning::{program::IncoLightning, ID as INCO_LIGHTNING_ID};
+use inco_token::program::IncoToken;
+use light_sdk::instruction::{PackedAddressTreeInfo, ValidityProof};
+use zivo_wrap::{cpi as zivo_wrap_cpi, cpi::accounts::DepositAndCommit, program::ZivoWrap};
+
+use crate::errors::OrderbookError;
+use crate::state::{Order, OrderbookState, HANDLE_INPUT_TYPE};
+
+/// Shielded alternative to `maker_claim_filled_order`: instead of paying the maker's Inco
+/// account, the vault authority deposits the order's claim handle into the zivo-wrap pool under
+/// `commitment`, which the maker computes client-side and only the maker can open. The claim
+/// stays a handle and the commitment hides the owner, so the transaction reveals neither.
+///
+/// Remaining accounts are passed through to zivo-wrap (Light system accounts at
+/// `system_accounts_offset`).
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, MakerClaimToNote<'info>>,
+    commitment: [u8; 32],
+    proof: ValidityProof,
+    address_tree_info: PackedAddressTreeInfo,
+    output_state_tree_index: u8,
+    system_accounts_offset: u8,
+) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    let order = &mut ctx.accounts.order;
+
+    if order.owner != ctx.accounts.maker.key() {
+        return err!(OrderbookError::InvalidOrderOwner);
+    }
+    if !order.is_filled {
+        return err!(OrderbookError::OrderNotFilled);
+    }
+    if order.is_claimed {
+        return err!(OrderbookError::OrderAlreadyClaimed);
+    }
+    // Only a claim accrued by a match names a handle that zivo-wrap can move.
+    if order.claim_input_type != HANDLE_INPUT_TYPE {
+        return err!(OrderbookError::InvalidEscrowCiphertext);
+    }
+    let claim_handle: [u8; 16] = order
+        .claim_ciphertext
+        .as_slice()
+        .try_into()
+        .map_err(|_| error!(OrderbookError::InvalidEscrowCiphertext))?;
+    // Maker bids claim base, maker asks claim quote.
+    let (expected_mint, source_vault) = match order.side {
+        0 => (state.inco_base_mint, ctx.accounts.inco_base_vault.to_account_info()),
+        1 => (state.inco_quote_mint, ctx.accounts.inco_quote_vault.to_account_info()),
+        _ => return err!(OrderbookError::InvalidSide),
+    };
+    if ctx.accounts.inco_token_mint.key() != expected_mint {
+        return err!(OrderbookError::InvalidIncoAccountMint);
+    }
+
+    let vault_authority_bump = ctx.bumps.inco_vault_authority;
+    let state_key = state.key();
+    let vault_seeds: &[&[u8]] = &[
+        b"inco_vault_authority_v12",
+        state_key.as_ref(),
+        &[vault_authority_bump],
+    ];
+    zivo_wrap_cpi::deposit_and_commit(
+        CpiContext::new_with_signer(
+            ctx.accounts.zivo_wrap_program.to_account_info(),
+            DepositAndCommit {
+                shielded_pool: ctx.accounts.shielded_pool.to_account_info(),
+                vault: ctx.accounts.wrap_vault.to_account_info(),
+                spl_token_mint: ctx.accounts.spl_token_mint.to_account_info(),
+                inco_token_mint: ctx.accounts.inco_token_mint.to_account_info(),
+                pool_inco_account: ctx.accounts.pool_inco_account.to_account_info(),
+                note_backing: ctx.accounts.note_backing.to_account_info(),
+                source_inco_account: source_vault,
+                authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                user: ctx.accounts.maker.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                inco_token_program: ctx.accounts.inco_token_program.to_account_info(),
+                address_tree: ctx.accounts.address_tree.to_account_info(),
+                address_queue: ctx.accounts.address_queue.to_account_info(),
+                state_queue: ctx.accounts.state_queue.to_account_info(),
+                state_tree: ctx.accounts.state_tree.to_account_info(),
+            },
+            &[vault_seeds],
+        )
+        .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+        u128::from_le_bytes(claim_handle),
+        commitment,
+        proof,
+        address_tree_info,
+        output_state_tree_index,
+        system_accounts_offset,
+    )?;
+
+    if order.is_open {
+        // The order keeps resting; later fills accrue on a fresh claim.
+        order.is_filled = false;
+        order.claim_input_type = 0;
+        order.claim_ciphertext.clear();
+    } else {
+        order.is_claimed = true;
+        state.open_escrows = state.open_escrows.saturating_sub(1);
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct MakerClaimToNote<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"order_v1", state.key().as_ref(), owner.key().as_ref(), &order.seq.to_le_bytes()],
+        bump = order.bump
+    )]
+    pub order: Account<'info, Order>,
+    /// CHECK: maker owner stored in order
+    pub owner: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    #[account(
+        seeds = [b"inco_vault_authority_v12", state.key().as_ref()],
+        bump,
+        address = state.inco_vault_authority
+    )]
+    /// CHECK: PDA authority for Inco vaults
+    pub inco_vault_authority: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_base_vault)]
+    pub inco_base_vault: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_quote_vault)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    /// CHECK: zivo-wrap shielded pool config, validated by zivo-wrap
+    pub shielded_pool: UncheckedAccount<'info>,
+    /// CHECK: zivo-wrap vault, validated by zivo-wrap
+    pub wrap_vault: UncheckedAccount<'info>,
+    /// CHECK: SPL mint backing the note, validated by zivo-wrap
+    pub spl_token_mint: UncheckedAccount<'info>,
+    /// CHECK: Inco mint of the claimed side; must match the market mint
+    pub inco_token_mint: UncheckedAccount<'info>,
+    /// CHECK: zivo-wrap pool Inco account receiving the claim, validated by zivo-wrap
+    #[account(mut)]
+    pub pool_inco_account: UncheckedAccount<'info>,
+    /// CHECK: zivo-wrap backing of the new note, created by zivo-wrap and paid by `maker`
+    #[account(mut)]
+    pub note_backing: UncheckedAccount<'info>,
+    /// CHECK: Light address tree (mut)
+    #[account(mut)]
+    pub address_tree: AccountInfo<'info>,
+    /// CHECK: Light address queue (mut)
+    #[account(mut)]
+    pub address_queue: AccountInfo<'info>,
+    /// CHECK: Light state queue (mut)
+    #[account(mut)]
+    pub state_queue: AccountInfo<'info>,
+    /// CHECK: Light state tree (mut)
+    #[account(mut)]
+    pub state_tree: AccountInfo<'info>,
+    pub zivo_wrap_program: Program<'info, ZivoWrap>,
+    pub system_program: Program<'info, System>,
+    pub inco_token_program: Program<'info, IncoToken>,
+    /// CHECK: Inco Lightning program
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+}
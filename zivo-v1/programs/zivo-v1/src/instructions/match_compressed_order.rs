@@ -0,0 +1,306 @@
+use anchor_lang::prelude::*;
+use inco_lightning::{program::IncoLightning, ID as INCO_LIGHTNING_ID};
+use inco_token::{
+    cpi as inco_token_cpi,
+    cpi::accounts::IncoTransfer,
+    program::IncoToken,
+    IncoAccount,
+    ID as INCO_TOKEN_ID,
+};
+use light_sdk::cpi::v2::LightSystemProgramCpi;
+use light_sdk::cpi::InvokeLightSystemProgram;
+use light_sdk::{
+    account::LightAccount,
+    cpi::{v2::CpiAccounts, LightCpiInstruction},
+    instruction::{account_meta::CompressedAccountMeta, ValidityProof},
+};
+
+use crate::errors::OrderbookError;
+use crate::instructions::grant_order_view::{allow_auditor, allow_owners};
+use crate::instructions::init_buffer::resolve_ciphertext;
+use crate::instructions::issue_access_pass::ensure_access_pass;
+use crate::instructions::match_order::{add_to_claim, compute_fill, MatchFill};
+use crate::instructions::place_compressed_order::split_light_accounts;
+use crate::state::{
+    handle_ciphertext, AccessPass, CiphertextBuffer, CompressedOrder, OrderbookState,
+    HANDLE_INPUT_TYPE, MARKET_MODE_CONTINUOUS, MARKET_STATUS_OPEN,
+};
+
+/// Matches two compressed orders with the same encrypted arithmetic as `match_order`
+/// (`compute_fill`). Both orders are read from one validity proof and must sit in the passed
+/// state tree. The taker's leg and the maker's payout are the fill handles. Like `match_order`,
+/// both orders keep their reduced remaining size and stay open until they fill again or are
+/// cancelled; the maker's payout is added to its unclaimed fills.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, MatchCompressedOrder<'info>>,
+    taker_price: u64,
+    taker_req_base_ciphertext: Vec<u8>,
+    input_type: u8,
+    maker_order: CompressedOrder,
+    maker_meta: CompressedAccountMeta,
+    taker_order: CompressedOrder,
+    taker_meta: CompressedAccountMeta,
+    proof: ValidityProof,
+    system_accounts_offset: u8,
+) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+
+    if ctx.accounts.matcher.key() != state.admin {
+        return err!(OrderbookError::UnauthorizedMatcher);
+    }
+    if state.status != MARKET_STATUS_OPEN {
+        return err!(OrderbookError::MarketClosed);
+    }
+    if state.market_mode != MARKET_MODE_CONTINUOUS {
+        return err!(OrderbookError::InvalidMarketMode);
+    }
+    if maker_order.market != state.key() || taker_order.market != state.key() {
+        return err!(OrderbookError::InvalidCompressedOrder);
+    }
+    if !maker_order.is_open || !taker_order.is_open {
+        return err!(OrderbookError::OrderClosed);
+    }
+    if maker_order.side == taker_order.side {
+        return err!(OrderbookError::InvalidSide);
+    }
+    if maker_order.price != taker_price || taker_order.price != taker_price {
+        return err!(OrderbookError::PriceMismatch);
+    }
+    ensure_access_pass(
+        state,
+        &ctx.accounts.taker_access_pass,
+        taker_order.owner,
+        ctx.program_id,
+    )?;
+    let taker_req_base_ciphertext = resolve_ciphertext(
+        taker_req_base_ciphertext,
+        &ctx.accounts.taker_req_base_buffer,
+        ctx.accounts.matcher.key(),
+    )?;
+    let (allowance_accounts, light_accounts) = split_light_accounts(
+        ctx.remaining_accounts,
+        system_accounts_offset,
+        &[
+            ctx.accounts.state_queue.to_account_info(),
+            ctx.accounts.state_tree.to_account_info(),
+        ],
+    )?;
+
+    let inco = ctx.accounts.inco_lightning_program.to_account_info();
+    let signer = ctx.accounts.matcher.to_account_info();
+    // Compressed orders are never dark; both legs come from the encrypted fill.
+    let MatchFill {
+        req_base,
+        fill_base,
+        fill_quote,
+        maker_remaining,
+        taker_remaining,
+        ..
+    } = compute_fill(
+        &inco,
+        &signer,
+        maker_order.remaining_handle,
+        taker_order.remaining_handle,
+        None,
+        0,
+        taker_price,
+        taker_req_base_ciphertext,
+        input_type,
+    )?;
+
+    ensure_inco_account(
+        &ctx.accounts.inco_base_vault,
+        state.inco_vault_authority,
+        state.inco_base_mint,
+    )?;
+    ensure_inco_account(
+        &ctx.accounts.inco_quote_vault,
+        state.inco_vault_authority,
+        state.inco_quote_mint,
+    )?;
+
+    let vault_authority_bump = ctx.bumps.inco_vault_authority;
+    let state_key = state.key();
+    let vault_seeds: &[&[u8]] = &[
+        b"inco_vault_authority_v12",
+        state_key.as_ref(),
+        &[vault_authority_bump],
+    ];
+
+    let payout = if maker_order.side == 1 {
+        // Maker ask: base escrowed in base vault, taker quote escrowed in quote vault.
+        ensure_inco_account(
+            &ctx.accounts.taker_base_inco,
+            taker_order.owner,
+            state.inco_base_mint,
+        )?;
+
+        inco_token_cpi::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.inco_token_program.to_account_info(),
+                IncoTransfer {
+                    source: ctx.accounts.inco_base_vault.to_account_info(),
+                    destination: ctx.accounts.taker_base_inco.to_account_info(),
+                    authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                    inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+                &[vault_seeds],
+            ),
+            handle_ciphertext(fill_base.0),
+            HANDLE_INPUT_TYPE,
+        )?;
+        fill_quote
+    } else {
+        // Maker bid: quote escrowed in quote vault, taker base escrowed in base vault.
+        ensure_inco_account(
+            &ctx.accounts.taker_quote_inco,
+            taker_order.owner,
+            state.inco_quote_mint,
+        )?;
+
+        inco_token_cpi::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.inco_token_program.to_account_info(),
+                IncoTransfer {
+                    source: ctx.accounts.inco_quote_vault.to_account_info(),
+                    destination: ctx.accounts.taker_quote_inco.to_account_info(),
+                    authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                    inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+                &[vault_seeds],
+            ),
+            handle_ciphertext(fill_quote.0),
+            HANDLE_INPUT_TYPE,
+        )?;
+        fill_base
+    };
+    let claim = add_to_claim(
+        &inco,
+        &signer,
+        maker_order.is_filled,
+        maker_order.claim_input_type,
+        &maker_order.claim_ciphertext,
+        payout,
+    )?;
+
+    // Same allowance layout as `match_order`, ahead of the Light system accounts.
+    let auditor_accounts = allow_auditor(
+        state.auditor,
+        allowance_accounts,
+        &[
+            req_base.0,
+            fill_base.0,
+            fill_quote.0,
+            maker_remaining.0,
+            taker_remaining.0,
+            claim.0,
+        ],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    let owner_accounts = &allowance_accounts[..allowance_accounts.len() - auditor_accounts];
+    allow_owners(
+        owner_accounts,
+        &[
+            (maker_order.owner, maker_remaining.0),
+            (maker_order.owner, claim.0),
+            (taker_order.owner, fill_base.0),
+            (taker_order.owner, taker_remaining.0),
+        ],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    let mut maker_account =
+        LightAccount::<CompressedOrder>::new_mut(&crate::ID, &maker_meta, maker_order)
+            .map_err(|_| OrderbookError::InvalidCompressedOrder)?;
+    maker_account.remaining_handle = maker_remaining.0;
+    maker_account.is_filled = true;
+    maker_account.claim_input_type = HANDLE_INPUT_TYPE;
+    maker_account.claim_ciphertext = handle_ciphertext(claim.0);
+    let mut taker_account =
+        LightAccount::<CompressedOrder>::new_mut(&crate::ID, &taker_meta, taker_order)
+            .map_err(|_| OrderbookError::InvalidCompressedOrder)?;
+    taker_account.remaining_handle = taker_remaining.0;
+
+    let light_cpi_accounts =
+        CpiAccounts::new(ctx.accounts.matcher.as_ref(), &light_accounts, crate::LIGHT_CPI_SIGNER);
+    LightSystemProgramCpi::new_cpi(crate::LIGHT_CPI_SIGNER, proof)
+        .with_light_account(maker_account)?
+        .with_light_account(taker_account)?
+        .invoke(light_cpi_accounts)?;
+
+    state.last_trade_price = taker_price;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct MatchCompressedOrder<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(mut)]
+    pub matcher: Signer<'info>,
+    /// Required when the market has a gatekeeper.
+    pub taker_access_pass: Option<Account<'info, AccessPass>>,
+    /// Matcher-owned buffer replacing the inline `taker_req_base_ciphertext` argument.
+    pub taker_req_base_buffer: Option<Account<'info, CiphertextBuffer>>,
+    #[account(
+        mut,
+        seeds = [b"inco_vault_authority_v12", state.key().as_ref()],
+        bump,
+        address = state.inco_vault_authority
+    )]
+    /// CHECK: PDA authority for Inco vaults
+    pub inco_vault_authority: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_base_vault)]
+    pub inco_base_vault: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_quote_vault)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    /// CHECK: Taker Inco accounts
+    #[account(mut)]
+    pub taker_base_inco: UncheckedAccount<'info>,
+    /// CHECK: Taker Inco accounts
+    #[account(mut)]
+    pub taker_quote_inco: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub inco_token_program: Program<'info, IncoToken>,
+    /// CHECK: Inco Lightning program
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+    /// CHECK: Light state queue (mut)
+    #[account(mut)]
+    pub state_queue: AccountInfo<'info>,
+    /// CHECK: Light state tree (mut)
+    #[account(mut)]
+    pub state_tree: AccountInfo<'info>,
+}
+
+fn ensure_inco_account(
+    account: &UncheckedAccount<'_>,
+    expected_owner: Pubkey,
+    expected_mint: Pubkey,
+) -> Result<()> {
+    let info = account.to_account_info();
+    if info.owner != &INCO_TOKEN_ID {
+        return err!(OrderbookError::InvalidIncoProgramOwner);
+    }
+    let data = info.try_borrow_data()?;
+    let decoded = IncoAccount::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(OrderbookError::InvalidIncoAccountData))?;
+
+    if decoded.owner != expected_owner {
+        return err!(OrderbookError::InvalidIncoAccountOwner);
+    }
+    if decoded.mint != expected_mint {
+        return err!(OrderbookError::InvalidIncoAccountMint);
+    }
+    Ok(())
+}
@@ -15,138 +15,131 @@ use inco_token::{
 };
 
 use crate::errors::OrderbookError;
-use crate::state::{Order, OrderbookState, MAX_ESCROW_CIPHERTEXT_LEN};
+use crate::instructions::create_order_group::{fill_group_leg, pay_group_release};
+use crate::instructions::grant_order_view::{allow_auditor, allow_owners};
+use crate::instructions::init_buffer::resolve_ciphertext;
+use crate::instructions::issue_access_pass::ensure_access_pass;
+use crate::oracle::{effective_price, load_price_feed};
+use crate::state::{
+    ensure_user_input_type, handle_ciphertext, AccessPass, CiphertextBuffer, Order,
+    OrderGroup, OrderbookState, HANDLE_INPUT_TYPE, MARKET_MODE_BATCH_AUCTION, MARKET_STATUS_OPEN,
+    MAX_ESCROW_CIPHERTEXT_LEN, PEG_NONE,
+};
 
-pub fn handler(
-    ctx: Context<MatchOrder>,
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, MatchOrder<'info>>,
     taker_side: u8,
     taker_price: u64,
     taker_req_base_ciphertext: Vec<u8>,
-    fill_base_ciphertext: Vec<u8>,
-    fill_quote_ciphertext: Vec<u8>,
     input_type: u8,
-    claim_plaintext_amount: u64,
 ) -> Result<()> {
     let state = &mut ctx.accounts.state;
     let order = &mut ctx.accounts.maker_order;
     let taker_order = &mut ctx.accounts.taker_order;
 
-    if ctx.accounts.matcher.key() != state.admin {
-        return err!(OrderbookError::UnauthorizedMatcher);
-    }
-    if !order.is_open {
-        return err!(OrderbookError::OrderClosed);
-    }
-    if order.side == taker_side {
-        return err!(OrderbookError::InvalidSide);
-    }
-    if order.price != taker_price {
-        return err!(OrderbookError::PriceMismatch);
-    }
-    if taker_req_base_ciphertext.is_empty()
-        || fill_base_ciphertext.is_empty()
-        || fill_quote_ciphertext.is_empty()
-    {
-        return err!(OrderbookError::InvalidEscrowCiphertext);
-    }
-    if taker_req_base_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN
-        || fill_base_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN
-        || fill_quote_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN
-    {
-        return err!(OrderbookError::InvalidEscrowCiphertext);
-    }
-
-    if taker_order.owner != ctx.accounts.taker.key() {
-        return err!(OrderbookError::InvalidOrderOwner);
-    }
-    let (derived_taker, _) = Pubkey::find_program_address(
-        &[
-            b"order_v1",
-            state.key().as_ref(),
-            ctx.accounts.taker.key().as_ref(),
-            &taker_order.seq.to_le_bytes(),
-        ],
+    let taker_req_base_ciphertext = resolve_ciphertext(
+        taker_req_base_ciphertext,
+        &ctx.accounts.taker_req_base_buffer,
+        ctx.accounts.matcher.key(),
+    )?;
+    validate_match(
+        state,
+        order,
+        taker_order,
+        ctx.accounts.matcher.key(),
+        ctx.accounts.taker.key(),
+        taker_side,
+        taker_price,
+        &ctx.accounts.price_feed,
+        &ctx.accounts.taker_access_pass,
         ctx.program_id,
-    );
-    if derived_taker != taker_order.key() {
-        return err!(OrderbookError::InvalidOrderPda);
-    }
-    if !taker_order.is_open {
-        return err!(OrderbookError::OrderClosed);
-    }
-
+    )?;
     let inco = ctx.accounts.inco_lightning_program.to_account_info();
     let signer = ctx.accounts.matcher.to_account_info();
-    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
-    let req_base: Euint128 = cpi::new_euint128(cpi_ctx, taker_req_base_ciphertext.clone(), input_type)?;
-
-    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
-    let has_sufficient: Ebool = cpi::e_ge(
-        cpi_ctx,
-        Euint128(order.remaining_handle),
+    // A grouped leg locks its group on first fill, before the fill changes its remaining size.
+    let maker_release = fill_group_leg(&inco, &signer, order, &mut ctx.accounts.maker_group)?;
+    let taker_release =
+        fill_group_leg(&inco, &signer, taker_order, &mut ctx.accounts.taker_group)?;
+    let MatchFill {
         req_base,
-        0,
+        fill_base: actual_base,
+        fill_quote,
+        maker_remaining,
+        taker_remaining,
+        bid_refund,
+    } = compute_fill(
+        &inco,
+        &signer,
+        order.remaining_handle,
+        taker_order.remaining_handle,
+        dark_prices(order, taker_order),
+        bid_overpay(order, taker_order, taker_price),
+        taker_price,
+        taker_req_base_ciphertext,
+        input_type,
     )?;
+    if order.is_dark {
+        // Dark fills execute at the maker's encrypted limit price.
+        order.exec_price_handle = order.price_handle;
+        taker_order.exec_price_handle = order.price_handle;
+    }
+    order.remaining_handle = maker_remaining.0;
+    taker_order.remaining_handle = taker_remaining.0;
 
-    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
-    let actual_base: Euint128 = cpi::e_select(
-        cpi_ctx,
-        has_sufficient,
-        req_base,
-        Euint128(order.remaining_handle),
-        0,
+    ensure_inco_account(
+        &ctx.accounts.inco_base_vault,
+        state.inco_vault_authority,
+        state.inco_base_mint,
     )?;
-
-    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
-    let remaining: Euint128 = cpi::e_sub(
-        cpi_ctx,
-        Euint128(order.remaining_handle),
-        actual_base,
-        0,
+    ensure_inco_account(
+        &ctx.accounts.inco_quote_vault,
+        state.inco_vault_authority,
+        state.inco_quote_mint,
     )?;
-    order.remaining_handle = remaining.0;
 
+    let vault_authority_bump = ctx.bumps.inco_vault_authority;
+    let state_key = state.key();
+    let vault_seeds: &[&[u8]] = &[
+        b"inco_vault_authority_v12",
+        state_key.as_ref(),
+        &[vault_authority_bump],
+    ];
 
-    if order.side == 1 {
-        // Maker ask: base escrowed in base vault.
-        ensure_inco_account(
-            &ctx.accounts.maker_base_inco,
-            order.owner,
-            state.inco_base_mint,
-        )?;
+    // Siblings of a leg that just filled are cancelled: the rest of its group's escrow goes
+    // back to the group owner now.
+    for (release, owner_quote_inco, owner_base_inco) in [
+        (maker_release, &ctx.accounts.maker_quote_inco, &ctx.accounts.maker_base_inco),
+        (taker_release, &ctx.accounts.taker_quote_inco, &ctx.accounts.taker_base_inco),
+    ] {
+        if let Some(release) = release {
+            pay_group_release(
+                &release,
+                state,
+                owner_quote_inco,
+                owner_base_inco,
+                &ctx.accounts.inco_quote_vault,
+                &ctx.accounts.inco_base_vault,
+                &ctx.accounts.inco_vault_authority.to_account_info(),
+                vault_seeds,
+                &ctx.accounts.inco_token_program.to_account_info(),
+                &inco,
+                &ctx.accounts.system_program.to_account_info(),
+            )?;
+        }
+    }
+
+    let claim = if order.side == 1 {
+        // Maker ask: base escrowed in base vault, taker quote escrowed in quote vault.
         ensure_inco_account(
             &ctx.accounts.taker_base_inco,
-            ctx.accounts.taker.key(),
+            taker_order.owner,
             state.inco_base_mint,
         )?;
-        ensure_inco_account(
-            &ctx.accounts.taker_quote_inco,
-            ctx.accounts.taker.key(),
-            state.inco_quote_mint,
-        )?;
         ensure_inco_account(
             &ctx.accounts.maker_quote_inco,
             order.owner,
             state.inco_quote_mint,
         )?;
-        ensure_inco_account(
-            &ctx.accounts.inco_base_vault,
-            state.inco_vault_authority,
-            state.inco_base_mint,
-        )?;
-        ensure_inco_account(
-            &ctx.accounts.inco_quote_vault,
-            state.inco_vault_authority,
-            state.inco_quote_mint,
-        )?;
-
-        let vault_authority_bump = ctx.bumps.inco_vault_authority;
-        let state_key = state.key();
-        let vault_seeds: &[&[u8]] = &[
-            b"inco_vault_authority_v12",
-            state_key.as_ref(),
-            &[vault_authority_bump],
-        ];
 
         inco_token_cpi::transfer(
             CpiContext::new_with_signer(
@@ -160,112 +153,105 @@ pub fn handler(
                 },
                 &[vault_seeds],
             ),
-            fill_base_ciphertext,
-            input_type,
+            handle_ciphertext(actual_base.0),
+            HANDLE_INPUT_TYPE,
         )?;
 
-        order.claim_input_type = input_type;
-        order.claim_plaintext_amount = claim_plaintext_amount;
-        order.claim_ciphertext = fill_quote_ciphertext.clone();
-
-        inco_token_cpi::transfer(
-            CpiContext::new(
-                ctx.accounts.inco_token_program.to_account_info(),
-                IncoTransfer {
-                    source: ctx.accounts.taker_quote_inco.to_account_info(),
-                    destination: ctx.accounts.inco_quote_vault.to_account_info(),
-                    authority: ctx.accounts.taker.to_account_info(),
-                    inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
-                    system_program: ctx.accounts.system_program.to_account_info(),
-                },
-            ),
-            fill_quote_ciphertext,
-            input_type,
-        )?;
+        accrue_claim(&inco, &signer, order, fill_quote)?
     } else {
-        // Maker bid: quote escrowed in quote vault.
-        ensure_inco_account(
-            &ctx.accounts.maker_quote_inco,
-            order.owner,
-            state.inco_quote_mint,
-        )?;
+        // Maker bid: quote escrowed in quote vault, taker base escrowed in base vault.
         ensure_inco_account(
             &ctx.accounts.taker_quote_inco,
-            ctx.accounts.taker.key(),
+            taker_order.owner,
             state.inco_quote_mint,
         )?;
-        ensure_inco_account(
-            &ctx.accounts.taker_base_inco,
-            ctx.accounts.taker.key(),
-            state.inco_base_mint,
-        )?;
         ensure_inco_account(
             &ctx.accounts.maker_base_inco,
             order.owner,
             state.inco_base_mint,
         )?;
-        ensure_inco_account(
-            &ctx.accounts.inco_base_vault,
-            state.inco_vault_authority,
-            state.inco_base_mint,
-        )?;
-        ensure_inco_account(
-            &ctx.accounts.inco_quote_vault,
-            state.inco_vault_authority,
-            state.inco_quote_mint,
-        )?;
-
-        let vault_authority_bump = ctx.bumps.inco_vault_authority;
-        let state_key = state.key();
-        let vault_seeds: &[&[u8]] = &[
-            b"inco_vault_authority_v12",
-            state_key.as_ref(),
-            &[vault_authority_bump],
-        ];
 
         inco_token_cpi::transfer(
             CpiContext::new_with_signer(
                 ctx.accounts.inco_token_program.to_account_info(),
                 IncoTransfer {
                     source: ctx.accounts.inco_quote_vault.to_account_info(),
-                    destination: ctx.accounts.maker_quote_inco.to_account_info(),
+                    destination: ctx.accounts.taker_quote_inco.to_account_info(),
                     authority: ctx.accounts.inco_vault_authority.to_account_info(),
                     inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
                     system_program: ctx.accounts.system_program.to_account_info(),
                 },
                 &[vault_seeds],
             ),
-            fill_quote_ciphertext,
-            input_type,
+            handle_ciphertext(fill_quote.0),
+            HANDLE_INPUT_TYPE,
         )?;
 
-        order.claim_input_type = input_type;
-        order.claim_plaintext_amount = claim_plaintext_amount;
-        order.claim_ciphertext = fill_base_ciphertext.clone();
+        accrue_claim(&inco, &signer, order, actual_base)?
+    };
 
-        inco_token_cpi::transfer(
-            CpiContext::new(
-                ctx.accounts.inco_token_program.to_account_info(),
-                IncoTransfer {
-                    source: ctx.accounts.taker_base_inco.to_account_info(),
-                    destination: ctx.accounts.inco_base_vault.to_account_info(),
-                    authority: ctx.accounts.taker.to_account_info(),
-                    inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
-                    system_program: ctx.accounts.system_program.to_account_info(),
-                },
-            ),
-            fill_base_ciphertext,
-            input_type,
+    if let Some(refund) = bid_refund {
+        let (bidder, bidder_quote_inco) = if order.side == 0 {
+            (order.owner, &ctx.accounts.maker_quote_inco)
+        } else {
+            (taker_order.owner, &ctx.accounts.taker_quote_inco)
+        };
+        pay_bid_refund(
+            refund,
+            bidder,
+            state,
+            bidder_quote_inco,
+            &ctx.accounts.inco_quote_vault,
+            &ctx.accounts.inco_vault_authority.to_account_info(),
+            vault_seeds,
+            &ctx.accounts.inco_token_program.to_account_info(),
+            &inco,
+            &ctx.accounts.system_program.to_account_info(),
         )?;
     }
 
-    // Temp: mark both orders filled/closed after a successful match.
-    order.is_filled = true;
-    order.is_open = false;
-    order.is_claimed = false;
-    taker_order.is_filled = true;
-    taker_order.is_open = false;
-    taker_order.is_claimed = true;
+    // Owners must be able to decrypt their new handles; the leading pairs are required:
+    // [maker remaining allowance, maker owner, maker claim allowance, maker owner,
+    //  taker fill allowance, taker owner, taker remaining allowance, taker owner].
+    // Auditor pairs (req, fill, fill quote, maker remaining, taker remaining, maker claim, then
+    // the bid refund if any) trail these.
+    let mut audited = vec![
+        req_base.0,
+        actual_base.0,
+        fill_quote.0,
+        order.remaining_handle,
+        taker_order.remaining_handle,
+        claim.0,
+    ];
+    audited.extend(bid_refund.map(|refund| refund.0));
+    let auditor_accounts = allow_auditor(
+        state.auditor,
+        ctx.remaining_accounts,
+        &audited,
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    let owner_accounts = &ctx.remaining_accounts[..ctx.remaining_accounts.len() - auditor_accounts];
+    allow_owners(
+        owner_accounts,
+        &[
+            (order.owner, order.remaining_handle),
+            (order.owner, claim.0),
+            (taker_order.owner, actual_base.0),
+            (taker_order.owner, taker_order.remaining_handle),
+        ],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    // Both orders stay open with their reduced remaining size, so the rest of each escrow can
+    // still fill or be cancelled. The maker's payout accrues on its claim until it is claimed.
+    // A dark match has no public price to report.
+    if !order.is_dark {
+        state.last_trade_price = taker_price;
+    }
 
     Ok(())
 }
@@ -288,8 +274,20 @@ pub struct MatchOrder<'info> {
     pub owner: UncheckedAccount<'info>,
     #[account(mut)]
     pub matcher: Signer<'info>,
+    /// CHECK: taker owner stored in taker order
+    pub taker: UncheckedAccount<'info>,
+    /// Required when the market has a gatekeeper.
+    pub taker_access_pass: Option<Account<'info, AccessPass>>,
+    /// CHECK: market reference price account, required when either order is pegged
+    pub price_feed: Option<UncheckedAccount<'info>>,
+    /// Required when the maker order belongs to an order group.
     #[account(mut)]
-    pub taker: Signer<'info>,
+    pub maker_group: Option<Account<'info, OrderGroup>>,
+    /// Required when the taker order belongs to an order group.
+    #[account(mut)]
+    pub taker_group: Option<Account<'info, OrderGroup>>,
+    /// Matcher-owned buffer replacing the inline `taker_req_base_ciphertext` argument.
+    pub taker_req_base_buffer: Option<Account<'info, CiphertextBuffer>>,
     #[account(
         mut,
         seeds = [b"inco_vault_authority_v12", state.key().as_ref()],
@@ -329,6 +327,332 @@ pub struct MatchOrder<'info> {
     pub inco_lightning_program: Program<'info, IncoLightning>,
 }
 
+/// Handles produced by the encrypted match arithmetic shared by `match_order` and
+/// `prepare_match`.
+pub(crate) struct MatchFill {
+    pub req_base: Euint128,
+    pub fill_base: Euint128,
+    pub fill_quote: Euint128,
+    pub maker_remaining: Euint128,
+    pub taker_remaining: Euint128,
+    /// Quote the bid escrowed above the execution price of this fill, owed back to the bidder.
+    /// `None` when the bid executes at its own escrow price.
+    pub bid_refund: Option<Euint128>,
+}
+
+/// Checks shared by `match_order` and `prepare_match`: matcher, market status, both orders,
+/// the execution price, and the taker's access pass.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn validate_match(
+    state: &Account<'_, OrderbookState>,
+    order: &Order,
+    taker_order: &Account<'_, Order>,
+    matcher: Pubkey,
+    taker: Pubkey,
+    taker_side: u8,
+    taker_price: u64,
+    price_feed: &Option<UncheckedAccount<'_>>,
+    taker_access_pass: &Option<Account<'_, AccessPass>>,
+    program_id: &Pubkey,
+) -> Result<()> {
+    if matcher != state.admin {
+        return err!(OrderbookError::UnauthorizedMatcher);
+    }
+    if state.status != MARKET_STATUS_OPEN {
+        return err!(OrderbookError::MarketClosed);
+    }
+    // Batch markets only settle through `run_auction`.
+    if state.market_mode == MARKET_MODE_BATCH_AUCTION {
+        return err!(OrderbookError::InvalidMarketMode);
+    }
+    if !order.is_open {
+        return err!(OrderbookError::OrderClosed);
+    }
+    if order.side == taker_side {
+        return err!(OrderbookError::InvalidSide);
+    }
+
+    if taker_order.owner != taker {
+        return err!(OrderbookError::InvalidOrderOwner);
+    }
+    let (derived_taker, _) = Pubkey::find_program_address(
+        &[
+            b"order_v1",
+            state.key().as_ref(),
+            taker.as_ref(),
+            &taker_order.seq.to_le_bytes(),
+        ],
+        program_id,
+    );
+    if derived_taker != taker_order.key() {
+        return err!(OrderbookError::InvalidOrderPda);
+    }
+    if !taker_order.is_open {
+        return err!(OrderbookError::OrderClosed);
+    }
+    if taker_order.side != taker_side {
+        return err!(OrderbookError::InvalidSide);
+    }
+    if order.is_dark != taker_order.is_dark {
+        return err!(OrderbookError::DarkOrderMismatch);
+    }
+    if order.peg_type != PEG_NONE || taker_order.peg_type != PEG_NONE {
+        // Pegged fills execute at the maker's effective price computed from the feed.
+        let feed = load_price_feed(state, price_feed)?;
+        let maker_price = effective_price(order, Some(&feed))?;
+        let taker_limit = effective_price(taker_order, Some(&feed))?;
+        let (bid_price, ask_price) = if order.side == 0 {
+            (maker_price, taker_limit)
+        } else {
+            (taker_limit, maker_price)
+        };
+        if taker_price != maker_price || bid_price < ask_price {
+            return err!(OrderbookError::PriceMismatch);
+        }
+    } else if order.price != taker_price || taker_order.price != taker_price {
+        return err!(OrderbookError::PriceMismatch);
+    }
+    ensure_access_pass(state, taker_access_pass, taker_order.owner, program_id)
+}
+
+/// Encrypted prices of a dark pair; `exec` is the maker's limit, at which dark fills execute.
+pub(crate) struct DarkPrices {
+    pub bid: u128,
+    pub ask: u128,
+    pub exec: u128,
+}
+
+/// `Some` for a dark pair (both orders are dark once `validate_match` passed).
+pub(crate) fn dark_prices(order: &Order, taker_order: &Order) -> Option<DarkPrices> {
+    if !order.is_dark {
+        return None;
+    }
+    let (bid, ask) = if order.side == 0 {
+        (order.price_handle, taker_order.price_handle)
+    } else {
+        (taker_order.price_handle, order.price_handle)
+    };
+    Some(DarkPrices {
+        bid,
+        ask,
+        exec: order.price_handle,
+    })
+}
+
+/// Per-unit quote a lit bid escrowed above `exec_price`. Bids escrow at their limit `price`, but
+/// a pegged maker price (on either side) can execute below it; 0 for dark pairs, which
+/// `compute_fill` refunds from their encrypted prices.
+pub(crate) fn bid_overpay(order: &Order, taker_order: &Order, exec_price: u64) -> u64 {
+    let bid = if order.side == 0 { order } else { taker_order };
+    if bid.is_dark {
+        return 0;
+    }
+    bid.price.saturating_sub(exec_price)
+}
+
+/// Runs the encrypted match on the two remaining handles: the fill is the min of the taker's
+/// request and both remaining sizes (zero for an uncrossed dark pair), and the quote leg is the
+/// fill times the execution price. A bid that escrowed above the execution price (a dark bid
+/// taking a lower ask, or a lit bid `bid_overpay` above a pegged price) gets the difference on
+/// the fill back as `bid_refund`. Shared by `match_order`, `prepare_match` and
+/// `match_compressed_order`; no order is modified.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn compute_fill<'info>(
+    inco: &AccountInfo<'info>,
+    signer: &AccountInfo<'info>,
+    maker_remaining_handle: u128,
+    taker_remaining_handle: u128,
+    dark: Option<DarkPrices>,
+    bid_overpay: u64,
+    taker_price: u64,
+    taker_req_base_ciphertext: Vec<u8>,
+    input_type: u8,
+) -> Result<MatchFill> {
+    if taker_req_base_ciphertext.is_empty()
+        || taker_req_base_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN
+    {
+        return err!(OrderbookError::InvalidEscrowCiphertext);
+    }
+    ensure_user_input_type(input_type)?;
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let req_base: Euint128 = cpi::new_euint128(cpi_ctx, taker_req_base_ciphertext, input_type)?;
+
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let has_sufficient: Ebool = cpi::e_ge(
+        cpi_ctx,
+        Euint128(maker_remaining_handle),
+        req_base,
+        0,
+    )?;
+
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let maker_bounded: Euint128 = cpi::e_select(
+        cpi_ctx,
+        has_sufficient,
+        req_base,
+        Euint128(maker_remaining_handle),
+        0,
+    )?;
+
+    // The fill is also capped by what the taker has left, so neither remaining size underflows.
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let taker_covers: Ebool = cpi::e_ge(
+        cpi_ctx,
+        Euint128(taker_remaining_handle),
+        maker_bounded,
+        0,
+    )?;
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let mut actual_base: Euint128 = cpi::e_select(
+        cpi_ctx,
+        taker_covers,
+        maker_bounded,
+        Euint128(taker_remaining_handle),
+        0,
+    )?;
+
+    if let Some(prices) = &dark {
+        // Dark orders cross only if the encrypted bid price >= ask price; otherwise the fill is zero.
+        let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+        let crossed: Ebool = cpi::e_ge(cpi_ctx, Euint128(prices.bid), Euint128(prices.ask), 0)?;
+
+        let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+        let zero: Euint128 = cpi::as_euint128(cpi_ctx, 0)?;
+
+        let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+        actual_base = cpi::e_select(cpi_ctx, crossed, actual_base, zero, 0)?;
+    }
+
+    // The quote leg is the (possibly zeroed) fill times the execution price, so an uncrossed
+    // dark match moves nothing in either vault.
+    let exec_price: Euint128 = if let Some(prices) = &dark {
+        Euint128(prices.exec)
+    } else {
+        let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+        cpi::as_euint128(cpi_ctx, taker_price as u128)?
+    };
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let fill_quote: Euint128 = cpi::e_mul(cpi_ctx, actual_base, exec_price, 0)?;
+
+    let bid_refund = match &dark {
+        Some(prices) if prices.bid != prices.exec => {
+            let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+            let escrowed: Euint128 = cpi::e_mul(cpi_ctx, actual_base, Euint128(prices.bid), 0)?;
+            let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+            Some(cpi::e_sub(cpi_ctx, escrowed, fill_quote, 0)?)
+        }
+        None if bid_overpay > 0 => {
+            let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+            let overpay: Euint128 = cpi::as_euint128(cpi_ctx, bid_overpay as u128)?;
+            let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+            Some(cpi::e_mul(cpi_ctx, actual_base, overpay, 0)?)
+        }
+        _ => None,
+    };
+
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let remaining: Euint128 = cpi::e_sub(
+        cpi_ctx,
+        Euint128(maker_remaining_handle),
+        actual_base,
+        0,
+    )?;
+
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let taker_remaining: Euint128 = cpi::e_sub(
+        cpi_ctx,
+        Euint128(taker_remaining_handle),
+        actual_base,
+        0,
+    )?;
+
+    Ok(MatchFill {
+        req_base,
+        fill_base: actual_base,
+        fill_quote,
+        maker_remaining: remaining,
+        taker_remaining,
+        bid_refund,
+    })
+}
+
+/// Returns a bid's escrow above the execution price of its fill from the quote vault. Shared by
+/// `match_order` and `settle_match`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn pay_bid_refund<'info>(
+    refund: Euint128,
+    bidder: Pubkey,
+    state: &OrderbookState,
+    bidder_quote_inco: &UncheckedAccount<'info>,
+    inco_quote_vault: &UncheckedAccount<'info>,
+    inco_vault_authority: &AccountInfo<'info>,
+    vault_seeds: &[&[u8]],
+    inco_token_program: &AccountInfo<'info>,
+    inco_lightning_program: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    ensure_inco_account(bidder_quote_inco, bidder, state.inco_quote_mint)?;
+    inco_token_cpi::transfer(
+        CpiContext::new_with_signer(
+            inco_token_program.clone(),
+            IncoTransfer {
+                source: inco_quote_vault.to_account_info(),
+                destination: bidder_quote_inco.to_account_info(),
+                authority: inco_vault_authority.clone(),
+                inco_lightning_program: inco_lightning_program.clone(),
+                system_program: system_program.clone(),
+            },
+            &[vault_seeds],
+        ),
+        handle_ciphertext(refund.0),
+        HANDLE_INPUT_TYPE,
+    )
+}
+
+/// Adds `payout` to the maker's unclaimed fills and returns the new claim handle, which callers
+/// grant to the owner and auditor. Makers stay open after a fill, so several fills can accrue
+/// before `maker_claim_filled_order` pays them out.
+pub(crate) fn accrue_claim<'info>(
+    inco: &AccountInfo<'info>,
+    signer: &AccountInfo<'info>,
+    order: &mut Order,
+    payout: Euint128,
+) -> Result<Euint128> {
+    let claim = add_to_claim(
+        inco,
+        signer,
+        order.is_filled,
+        order.claim_input_type,
+        &order.claim_ciphertext,
+        payout,
+    )?;
+    order.is_filled = true;
+    order.is_claimed = false;
+    order.claim_input_type = HANDLE_INPUT_TYPE;
+    order.claim_ciphertext = handle_ciphertext(claim.0);
+    Ok(claim)
+}
+
+/// The claim after adding `payout` to an order's unclaimed fills, given its claim fields. Shared
+/// with compressed orders, which store the same fields.
+pub(crate) fn add_to_claim<'info>(
+    inco: &AccountInfo<'info>,
+    signer: &AccountInfo<'info>,
+    is_filled: bool,
+    claim_input_type: u8,
+    claim_ciphertext: &[u8],
+    payout: Euint128,
+) -> Result<Euint128> {
+    if !is_filled || claim_input_type != HANDLE_INPUT_TYPE {
+        return Ok(payout);
+    }
+    let bytes: [u8; 16] = claim_ciphertext
+        .try_into()
+        .map_err(|_| error!(OrderbookError::InvalidEscrowCiphertext))?;
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    cpi::e_add(cpi_ctx, Euint128(u128::from_le_bytes(bytes)), payout, 0)
+}
+
 fn ensure_inco_account(
     account: &UncheckedAccount<'_>,
     expected_owner: Pubkey,
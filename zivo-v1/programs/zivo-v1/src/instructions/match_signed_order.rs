@@ -0,0 +1,399 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::instructions as sysvar_instructions;
+use inco_lightning::{
+    cpi,
+    cpi::accounts::Operation,
+    program::IncoLightning,
+    types::{Ebool, Euint128},
+    ID as INCO_LIGHTNING_ID,
+};
+use inco_token::{
+    cpi as inco_token_cpi,
+    cpi::accounts::IncoTransfer,
+    program::IncoToken,
+    IncoAccount,
+    ID as INCO_TOKEN_ID,
+};
+
+use crate::errors::OrderbookError;
+use crate::instructions::grant_order_view::{allow_auditor, allow_owners};
+use crate::instructions::issue_access_pass::ensure_access_pass;
+use crate::signed_order::{verify_ed25519_signature, SignedOrder};
+use crate::state::{
+    ensure_user_input_type, handle_ciphertext, AccessPass, Order, OrderbookState,
+    SignedOrderFill, TraderNonces, HANDLE_INPUT_TYPE, MARKET_MODE_CONTINUOUS, MARKET_STATUS_OPEN,
+    MAX_ESCROW_CIPHERTEXT_LEN, PEG_NONE,
+};
+
+/// Matches an off-chain signed maker order against a resting taker `Order`. The ed25519
+/// precompile instruction preceding this one must carry the maker's signature over
+/// `SignedOrder::message`. The maker side is funded from its `TraderNonces` balance instead of
+/// an `Order` PDA and proceeds go straight to the maker's Inco accounts. A `SignedOrderFill`
+/// tracks the size left on the signed order, so it can fill across several matches but never
+/// beyond its signed size; `cancel_signed_order` burns the nonce to stop further fills.
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, MatchSignedOrder<'info>>,
+    signed_order: SignedOrder,
+    taker_req_base_ciphertext: Vec<u8>,
+    input_type: u8,
+) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    let taker_order = &mut ctx.accounts.taker_order;
+    let nonces = &mut ctx.accounts.maker_nonces;
+
+    if ctx.accounts.matcher.key() != state.admin {
+        return err!(OrderbookError::UnauthorizedMatcher);
+    }
+    if state.status != MARKET_STATUS_OPEN {
+        return err!(OrderbookError::MarketClosed);
+    }
+    if state.market_mode != MARKET_MODE_CONTINUOUS {
+        return err!(OrderbookError::InvalidMarketMode);
+    }
+    if signed_order.market != state.key() || signed_order.side > 1 {
+        return err!(OrderbookError::InvalidSignedOrder);
+    }
+    if Clock::get()?.slot > signed_order.expiry_slot {
+        return err!(OrderbookError::SignedOrderExpired);
+    }
+    if signed_order.size_ciphertext.is_empty()
+        || signed_order.size_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN
+    {
+        return err!(OrderbookError::InvalidEscrowCiphertext);
+    }
+    if taker_req_base_ciphertext.is_empty()
+        || taker_req_base_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN
+    {
+        return err!(OrderbookError::InvalidEscrowCiphertext);
+    }
+    ensure_user_input_type(input_type)?;
+    ensure_user_input_type(signed_order.input_type)?;
+    if !taker_order.is_open {
+        return err!(OrderbookError::OrderClosed);
+    }
+    if taker_order.side == signed_order.side {
+        return err!(OrderbookError::InvalidSide);
+    }
+    if taker_order.is_dark
+        || taker_order.peg_type != PEG_NONE
+        || taker_order.group != Pubkey::default()
+    {
+        return err!(OrderbookError::InvalidSignedOrder);
+    }
+    if taker_order.price != signed_order.price {
+        return err!(OrderbookError::PriceMismatch);
+    }
+
+    verify_ed25519_signature(
+        &ctx.accounts.instructions_sysvar.to_account_info(),
+        &signed_order.maker,
+        &signed_order.message()?,
+    )?;
+    if nonces.is_nonce_used(signed_order.nonce) {
+        return err!(OrderbookError::NonceAlreadyUsed);
+    }
+    ensure_access_pass(
+        state,
+        &ctx.accounts.maker_access_pass,
+        signed_order.maker,
+        ctx.program_id,
+    )?;
+    ensure_access_pass(
+        state,
+        &ctx.accounts.taker_access_pass,
+        taker_order.owner,
+        ctx.program_id,
+    )?;
+
+    let inco = ctx.accounts.inco_lightning_program.to_account_info();
+    let signer = ctx.accounts.matcher.to_account_info();
+
+    // The first fill starts from the signed size; later fills from what is left of it.
+    let fill = &mut ctx.accounts.signed_fill;
+    let maker_size: Euint128 = if fill.maker == Pubkey::default() {
+        fill.market = state.key();
+        fill.maker = signed_order.maker;
+        fill.payer = ctx.accounts.matcher.key();
+        fill.nonce = signed_order.nonce;
+        fill.expiry_slot = signed_order.expiry_slot;
+        fill.bump = ctx.bumps.signed_fill;
+        fill._padding = [0u8; 7];
+        let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+        cpi::new_euint128(
+            cpi_ctx,
+            signed_order.size_ciphertext.clone(),
+            signed_order.input_type,
+        )?
+    } else {
+        Euint128(fill.remaining_handle)
+    };
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let req_base: Euint128 = cpi::new_euint128(cpi_ctx, taker_req_base_ciphertext, input_type)?;
+
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let has_sufficient: Ebool = cpi::e_ge(cpi_ctx, maker_size, req_base, 0)?;
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let maker_bounded: Euint128 =
+        cpi::e_select(cpi_ctx, has_sufficient, req_base, maker_size, 0)?;
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let taker_covers: Ebool = cpi::e_ge(
+        cpi_ctx,
+        Euint128(taker_order.remaining_handle),
+        maker_bounded,
+        0,
+    )?;
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let bounded_base: Euint128 = cpi::e_select(
+        cpi_ctx,
+        taker_covers,
+        maker_bounded,
+        Euint128(taker_order.remaining_handle),
+        0,
+    )?;
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let price: Euint128 = cpi::as_euint128(cpi_ctx, signed_order.price as u128)?;
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let bounded_quote: Euint128 = cpi::e_mul(cpi_ctx, bounded_base, price, 0)?;
+
+    // The maker balance must cover its leg: base for a maker ask, the quote fill for a maker
+    // bid. An uncovered maker fills nothing, and the same amounts are debited and transferred.
+    let (balance_handle, needed) = if signed_order.side == 1 {
+        (nonces.base_escrow_handle, bounded_base)
+    } else {
+        (nonces.quote_escrow_handle, bounded_quote)
+    };
+    if balance_handle == 0 {
+        return err!(OrderbookError::InvalidSignedOrder);
+    }
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let covered: Ebool = cpi::e_ge(cpi_ctx, Euint128(balance_handle), needed, 0)?;
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let zero: Euint128 = cpi::as_euint128(cpi_ctx, 0)?;
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let actual_base: Euint128 = cpi::e_select(cpi_ctx, covered, bounded_base, zero, 0)?;
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let fill_quote: Euint128 = cpi::e_select(cpi_ctx, covered, bounded_quote, zero, 0)?;
+    let debit = if signed_order.side == 1 { actual_base } else { fill_quote };
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let balance: Euint128 = cpi::e_sub(cpi_ctx, Euint128(balance_handle), debit, 0)?;
+    if signed_order.side == 1 {
+        nonces.base_escrow_handle = balance.0;
+    } else {
+        nonces.quote_escrow_handle = balance.0;
+    }
+
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let taker_remaining: Euint128 = cpi::e_sub(
+        cpi_ctx,
+        Euint128(taker_order.remaining_handle),
+        actual_base,
+        0,
+    )?;
+    taker_order.remaining_handle = taker_remaining.0;
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let maker_remaining: Euint128 = cpi::e_sub(cpi_ctx, maker_size, actual_base, 0)?;
+    fill.remaining_handle = maker_remaining.0;
+
+    ensure_inco_account(
+        &ctx.accounts.inco_base_vault,
+        state.inco_vault_authority,
+        state.inco_base_mint,
+    )?;
+    ensure_inco_account(
+        &ctx.accounts.inco_quote_vault,
+        state.inco_vault_authority,
+        state.inco_quote_mint,
+    )?;
+
+    // Maker ask: base to the taker, quote to the maker. Maker bid: the reverse.
+    let ((taker_dest, taker_vault, taker_mint), (maker_dest, maker_vault, maker_mint)) =
+        if signed_order.side == 1 {
+            (
+                (
+                    &ctx.accounts.taker_base_inco,
+                    &ctx.accounts.inco_base_vault,
+                    state.inco_base_mint,
+                ),
+                (
+                    &ctx.accounts.maker_quote_inco,
+                    &ctx.accounts.inco_quote_vault,
+                    state.inco_quote_mint,
+                ),
+            )
+        } else {
+            (
+                (
+                    &ctx.accounts.taker_quote_inco,
+                    &ctx.accounts.inco_quote_vault,
+                    state.inco_quote_mint,
+                ),
+                (
+                    &ctx.accounts.maker_base_inco,
+                    &ctx.accounts.inco_base_vault,
+                    state.inco_base_mint,
+                ),
+            )
+        };
+    let (taker_amount, maker_amount) = if signed_order.side == 1 {
+        (actual_base, fill_quote)
+    } else {
+        (fill_quote, actual_base)
+    };
+    ensure_inco_account(taker_dest, taker_order.owner, taker_mint)?;
+    ensure_inco_account(maker_dest, signed_order.maker, maker_mint)?;
+
+    let vault_authority_bump = ctx.bumps.inco_vault_authority;
+    let state_key = state.key();
+    let vault_seeds: &[&[u8]] = &[
+        b"inco_vault_authority_v12",
+        state_key.as_ref(),
+        &[vault_authority_bump],
+    ];
+    for (source, destination, amount) in [
+        (taker_vault, taker_dest, taker_amount),
+        (maker_vault, maker_dest, maker_amount),
+    ] {
+        inco_token_cpi::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.inco_token_program.to_account_info(),
+                IncoTransfer {
+                    source: source.to_account_info(),
+                    destination: destination.to_account_info(),
+                    authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                    inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+                &[vault_seeds],
+            ),
+            handle_ciphertext(amount.0),
+            HANDLE_INPUT_TYPE,
+        )?;
+    }
+
+    // Owners must be able to decrypt their new handles; the leading pairs are required:
+    // [maker balance allowance, maker, maker remaining allowance, maker,
+    //  taker fill allowance, taker owner, taker remaining allowance, taker owner].
+    // Auditor pairs (req, base fill, quote fill, maker balance, maker remaining,
+    // taker remaining) trail these.
+    let auditor_accounts = allow_auditor(
+        state.auditor,
+        ctx.remaining_accounts,
+        &[
+            req_base.0,
+            actual_base.0,
+            fill_quote.0,
+            balance.0,
+            maker_remaining.0,
+            taker_remaining.0,
+        ],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    let owner_accounts = &ctx.remaining_accounts[..ctx.remaining_accounts.len() - auditor_accounts];
+    allow_owners(
+        owner_accounts,
+        &[
+            (signed_order.maker, balance.0),
+            (signed_order.maker, maker_remaining.0),
+            (taker_order.owner, actual_base.0),
+            (taker_order.owner, taker_remaining.0),
+        ],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    // The taker order stays open with its reduced remaining size, like in `match_order`.
+    state.last_trade_price = signed_order.price;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(signed_order: SignedOrder)]
+pub struct MatchSignedOrder<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        mut,
+        seeds = [b"trader_nonces_v1", state.key().as_ref(), signed_order.maker.as_ref()],
+        bump = maker_nonces.bump
+    )]
+    pub maker_nonces: Account<'info, TraderNonces>,
+    #[account(
+        init_if_needed,
+        payer = matcher,
+        space = 8 + SignedOrderFill::LEN,
+        seeds = [b"signed_fill_v1", state.key().as_ref(), signed_order.maker.as_ref(), &signed_order.nonce.to_le_bytes()],
+        bump
+    )]
+    pub signed_fill: Account<'info, SignedOrderFill>,
+    #[account(
+        mut,
+        seeds = [b"order_v1", state.key().as_ref(), taker_order.owner.as_ref(), &taker_order.seq.to_le_bytes()],
+        bump = taker_order.bump
+    )]
+    pub taker_order: Account<'info, Order>,
+    #[account(mut)]
+    pub matcher: Signer<'info>,
+    /// Required when the market has a gatekeeper.
+    pub maker_access_pass: Option<Account<'info, AccessPass>>,
+    /// Required when the market has a gatekeeper.
+    pub taker_access_pass: Option<Account<'info, AccessPass>>,
+    /// CHECK: instructions sysvar, read to find the ed25519 precompile instruction
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+    #[account(
+        seeds = [b"inco_vault_authority_v12", state.key().as_ref()],
+        bump,
+        address = state.inco_vault_authority
+    )]
+    /// CHECK: PDA authority for Inco vaults
+    pub inco_vault_authority: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_base_vault)]
+    pub inco_base_vault: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_quote_vault)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    /// CHECK: Maker Inco accounts
+    #[account(mut)]
+    pub maker_base_inco: UncheckedAccount<'info>,
+    /// CHECK: Maker Inco accounts
+    #[account(mut)]
+    pub maker_quote_inco: UncheckedAccount<'info>,
+    /// CHECK: Taker Inco accounts
+    #[account(mut)]
+    pub taker_base_inco: UncheckedAccount<'info>,
+    /// CHECK: Taker Inco accounts
+    #[account(mut)]
+    pub taker_quote_inco: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub inco_token_program: Program<'info, IncoToken>,
+    /// CHECK: Inco Lightning program for encrypted operations
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+}
+
+fn ensure_inco_account(
+    account: &UncheckedAccount<'_>,
+    expected_owner: Pubkey,
+    expected_mint: Pubkey,
+) -> Result<()> {
+    let info = account.to_account_info();
+    if info.owner != &INCO_TOKEN_ID {
+        return err!(OrderbookError::InvalidIncoProgramOwner);
+    }
+    let data = info.try_borrow_data()?;
+    let decoded = IncoAccount::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(OrderbookError::InvalidIncoAccountData))?;
+
+    if decoded.owner != expected_owner {
+        return err!(OrderbookError::InvalidIncoAccountOwner);
+    }
+    if decoded.mint != expected_mint {
+        return err!(OrderbookError::InvalidIncoAccountMint);
+    }
+    Ok(())
+}
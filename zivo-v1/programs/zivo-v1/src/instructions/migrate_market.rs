@@ -0,0 +1,113 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer};
+
+use crate::errors::OrderbookError;
+use crate::state::{MarketRegistry, OrderbookState};
+
+// Account size of a market created by the first release: admin, order sequence, attestation
+// flag, the Inco mints, vault authority and vaults, plus padding.
+const LEGACY_STATE_SPACE: usize = 8 + 8 + 32 + 8 + 1 + 7 + (32 * 5) + 8;
+
+#[derive(AnchorDeserialize)]
+struct LegacyOrderbookState {
+    admin: Pubkey,
+    order_seq: u64,
+    require_attestation: u8,
+    _reserved: [u8; 7],
+    inco_base_mint: Pubkey,
+    inco_quote_mint: Pubkey,
+    inco_vault_authority: Pubkey,
+    inco_base_vault: Pubkey,
+    inco_quote_vault: Pubkey,
+    _padding: [u8; 8],
+}
+
+/// Rewrites a first-release market into the current `OrderbookState` layout and lists it in the
+/// registry. Everything added since starts at its default: an open, continuous market with no
+/// gatekeeper, auditor or price feed. The first release did not count orders, so both counters
+/// start at zero and `migrate_order` adds each legacy order back as it is migrated.
+pub(crate) fn handler(ctx: Context<MigrateMarket>) -> Result<()> {
+    let info = ctx.accounts.state.to_account_info();
+    let legacy = {
+        let data = info.try_borrow_data()?;
+        if data.len() != LEGACY_STATE_SPACE || !data.starts_with(OrderbookState::DISCRIMINATOR) {
+            return err!(OrderbookError::NotLegacyAccount);
+        }
+        LegacyOrderbookState::deserialize(&mut &data[8..])?
+    };
+
+    if ctx.accounts.admin.key() != legacy.admin {
+        return err!(OrderbookError::UnauthorizedMatcher);
+    }
+
+    let state = OrderbookState {
+        admin: legacy.admin,
+        order_seq: legacy.order_seq,
+        require_attestation: legacy.require_attestation,
+        inco_base_mint: legacy.inco_base_mint,
+        inco_quote_mint: legacy.inco_quote_mint,
+        inco_vault_authority: legacy.inco_vault_authority,
+        inco_base_vault: legacy.inco_base_vault,
+        inco_quote_vault: legacy.inco_quote_vault,
+        ..OrderbookState::default()
+    };
+    rewrite_account(
+        &info,
+        &ctx.accounts.admin.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        8 + OrderbookState::LEN,
+        &state,
+    )?;
+
+    ctx.accounts.market_registry.markets.push(info.key());
+    Ok(())
+}
+
+/// Resizes a legacy account to `space`, topping its rent up from `payer`, and replaces its
+/// contents with `account`.
+pub(crate) fn rewrite_account<'info, T: AccountSerialize>(
+    info: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+    account: &T,
+) -> Result<()> {
+    let top_up = Rent::get()?.minimum_balance(space).saturating_sub(info.lamports());
+    if top_up > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                Transfer {
+                    from: payer.clone(),
+                    to: info.clone(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+    info.resize(space)?;
+
+    let mut data = info.try_borrow_mut_data()?;
+    data.fill(0);
+    account.try_serialize(&mut &mut data[..])?;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct MigrateMarket<'info> {
+    /// CHECK: legacy market; the owner here and the discriminator and size in the handler
+    #[account(mut, owner = crate::ID)]
+    pub state: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"market_registry_v1"],
+        bump = market_registry.bump,
+        realloc = MarketRegistry::space(market_registry.markets.len() + 1),
+        realloc::payer = admin,
+        realloc::zero = false
+    )]
+    pub market_registry: Account<'info, MarketRegistry>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
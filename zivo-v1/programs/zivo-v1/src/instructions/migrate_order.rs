@@ -0,0 +1,103 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::OrderbookError;
+use crate::instructions::migrate_market::rewrite_account;
+use crate::state::{Order, OrderbookState, MAX_ESCROW_CIPHERTEXT_LEN};
+
+// Account size of an order placed by the first release, which still carried a plaintext claim
+// amount and sized its claim ciphertext like today's.
+const LEGACY_ORDER_SPACE: usize =
+    8 + 32 + 1 + 1 + 1 + 1 + 1 + 8 + 4 + MAX_ESCROW_CIPHERTEXT_LEN + 1 + 8 + 8 + 16 + 1 + 7;
+
+#[derive(AnchorDeserialize)]
+struct LegacyOrder {
+    owner: Pubkey,
+    side: u8,
+    is_open: bool,
+    is_filled: bool,
+    is_claimed: bool,
+    claim_input_type: u8,
+    _claim_plaintext_amount: u64,
+    claim_ciphertext: Vec<u8>,
+    _padding: [u8; 1],
+    price: u64,
+    seq: u64,
+    remaining_handle: u128,
+    bump: u8,
+    _reserved: [u8; 7],
+}
+
+/// Rewrites a first-release order of an already migrated market into the current `Order`
+/// layout, keeping its remaining size and any unclaimed fill, and counts it on the market again.
+/// The first release let traders pick their escrow amount freely, while cancels now refund from
+/// the remaining size and limit price, so only the market admin can vouch for a legacy order.
+pub(crate) fn handler(ctx: Context<MigrateOrder>) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    if ctx.accounts.admin.key() != state.admin {
+        return err!(OrderbookError::UnauthorizedMatcher);
+    }
+
+    let info = ctx.accounts.order.to_account_info();
+    let legacy = {
+        let data = info.try_borrow_data()?;
+        if data.len() != LEGACY_ORDER_SPACE || !data.starts_with(Order::DISCRIMINATOR) {
+            return err!(OrderbookError::NotLegacyAccount);
+        }
+        LegacyOrder::deserialize(&mut &data[8..])?
+    };
+
+    let state_key = state.key();
+    let (derived, _) = Pubkey::find_program_address(
+        &[
+            b"order_v1",
+            state_key.as_ref(),
+            legacy.owner.as_ref(),
+            &legacy.seq.to_le_bytes(),
+        ],
+        ctx.program_id,
+    );
+    if derived != info.key() {
+        return err!(OrderbookError::InvalidOrderPda);
+    }
+
+    let order = Order {
+        owner: legacy.owner,
+        side: legacy.side,
+        is_open: legacy.is_open,
+        is_filled: legacy.is_filled,
+        is_claimed: legacy.is_claimed,
+        claim_input_type: legacy.claim_input_type,
+        claim_ciphertext: legacy.claim_ciphertext,
+        price: legacy.price,
+        seq: legacy.seq,
+        remaining_handle: legacy.remaining_handle,
+        bump: legacy.bump,
+        ..Order::default()
+    };
+    if order.is_open {
+        state.add_open_order()?;
+    }
+    if order.is_open || (order.is_filled && !order.is_claimed) {
+        state.open_escrows = state.open_escrows.saturating_add(1);
+    }
+
+    rewrite_account(
+        &info,
+        &ctx.accounts.admin.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        8 + Order::LEN,
+        &order,
+    )
+}
+
+#[derive(Accounts)]
+pub struct MigrateOrder<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    /// CHECK: legacy order; the owner here and the discriminator, size and PDA in the handler
+    #[account(mut, owner = crate::ID)]
+    pub order: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
@@ -1,19 +1,145 @@
+pub mod accept_rfq_quote;
+pub mod apply_auditor;
+pub mod cancel_compressed_order;
 pub mod cancel_order;
+pub mod cancel_rfq;
+pub mod cancel_signed_order;
+pub mod cancel_trigger_order;
+pub mod cancel_twap_order;
+pub mod discard_pending_match;
+pub mod grant_order_view;
+pub mod grant_trading_delegate;
+pub mod init_buffer;
 pub mod initialize;
 pub mod initialize_deposit;
+pub mod initialize_protocol;
 pub mod bump_order_seq;
+pub mod claim_and_unwrap;
+pub mod claim_compressed_order;
+pub mod close_auction_result;
+pub mod close_buffer;
+pub mod close_market;
 pub mod close_order;
+pub mod close_rfq;
+pub mod close_signed_fill;
+pub mod crank_claim;
+pub mod create_order_group;
+pub mod delegate_cancel_order;
+pub mod delegate_place_order;
+pub mod deposit_signed_escrow;
 pub mod maker_claim_filled_order;
+pub mod maker_claim_to_note;
+pub mod issue_access_pass;
+pub mod open_rfq;
+pub mod match_compressed_order;
 pub mod match_order;
+pub mod match_signed_order;
+pub mod migrate_market;
+pub mod migrate_order;
+pub mod peg_order;
+pub mod place_compressed_order;
+pub mod place_dark_order;
+pub mod place_group_order;
 pub mod place_order;
+pub mod place_order_from_note;
+pub mod place_order_with_payer;
+pub mod place_quote;
+pub mod place_trigger_order;
+pub mod place_twap_order;
+pub mod prepare_match;
+pub mod propose_auditor;
+pub mod refund_rfq_quote;
+pub mod release_delegate_market;
+pub mod requote;
 pub mod reset_state;
+pub mod revoke_access_pass;
+pub mod revoke_trading_delegate;
+pub mod run_auction;
+pub mod settle_match;
+pub mod set_crank_opt_out;
+pub mod set_gatekeeper;
+pub mod set_market_mode;
+pub mod set_market_status;
+pub mod set_price_feed;
+pub mod submit_rfq_quote;
+pub mod sweep_market_order;
+pub mod sweep_order_group;
+pub mod trigger_order;
+pub mod twap_tick;
+pub mod update_protocol_config;
+pub mod withdraw_signed_escrow;
+pub mod wrap_and_deposit;
+pub mod write_buffer;
 
+pub use accept_rfq_quote::*;
+pub use apply_auditor::*;
+pub use cancel_compressed_order::*;
 pub use cancel_order::*;
+pub use cancel_rfq::*;
+pub use cancel_signed_order::*;
+pub use cancel_trigger_order::*;
+pub use cancel_twap_order::*;
+pub use discard_pending_match::*;
+pub use grant_order_view::*;
+pub use grant_trading_delegate::*;
+pub use init_buffer::*;
 pub use initialize::*;
 pub use initialize_deposit::*;
+pub use initialize_protocol::*;
 pub use bump_order_seq::*;
+pub use claim_and_unwrap::*;
+pub use claim_compressed_order::*;
+pub use close_auction_result::*;
+pub use close_buffer::*;
+pub use close_market::*;
 pub use close_order::*;
+pub use close_rfq::*;
+pub use close_signed_fill::*;
+pub use crank_claim::*;
+pub use create_order_group::*;
+pub use delegate_cancel_order::*;
+pub use delegate_place_order::*;
+pub use deposit_signed_escrow::*;
 pub use maker_claim_filled_order::*;
+pub use maker_claim_to_note::*;
+pub use issue_access_pass::*;
+pub use open_rfq::*;
+pub use match_compressed_order::*;
 pub use match_order::*;
+pub use match_signed_order::*;
+pub use migrate_market::*;
+pub use migrate_order::*;
+pub use peg_order::*;
+pub use place_compressed_order::*;
+pub use place_dark_order::*;
+pub use place_group_order::*;
 pub use place_order::*;
+pub use place_order_from_note::*;
+pub use place_order_with_payer::*;
+pub use place_quote::*;
+pub use place_trigger_order::*;
+pub use place_twap_order::*;
+pub use prepare_match::*;
+pub use propose_auditor::*;
+pub use refund_rfq_quote::*;
+pub use release_delegate_market::*;
+pub use requote::*;
 pub use reset_state::*;
+pub use revoke_access_pass::*;
+pub use revoke_trading_delegate::*;
+pub use run_auction::*;
+pub use settle_match::*;
+pub use set_crank_opt_out::*;
+pub use set_gatekeeper::*;
+pub use set_market_mode::*;
+pub use set_market_status::*;
+pub use set_price_feed::*;
+pub use submit_rfq_quote::*;
+pub use sweep_market_order::*;
+pub use sweep_order_group::*;
+pub use trigger_order::*;
+pub use twap_tick::*;
+pub use update_protocol_config::*;
+pub use withdraw_signed_escrow::*;
+pub use wrap_and_deposit::*;
+pub use write_buffer::*;
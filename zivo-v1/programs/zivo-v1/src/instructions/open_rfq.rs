@@ -0,0 +1,116 @@
+use anchor_lang::prelude::*;
+use inco_lightning::{
+    cpi,
+    cpi::accounts::Operation,
+    program::IncoLightning,
+    types::Euint128,
+    ID as INCO_LIGHTNING_ID,
+};
+
+use crate::errors::OrderbookError;
+use crate::instructions::grant_order_view::{allow_auditor, allow_handle};
+use crate::instructions::issue_access_pass::ensure_access_pass;
+use crate::state::{
+    ensure_user_input_type, AccessPass, OrderbookState, RfqRequest, MARKET_STATUS_OPEN,
+    MAX_ESCROW_CIPHERTEXT_LEN, RFQ_STATUS_OPEN,
+};
+
+/// Opens a request for quote outside the resting book. The encrypted size is shared with the
+/// invited makers passed as `[allowance_account, maker]` pairs at the front of the remaining
+/// accounts; the taker pays on acceptance, so nothing is escrowed here.
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, OpenRfq<'info>>,
+    rfq_id: u64,
+    side: u8,
+    expiry_slot: u64,
+    size_ciphertext: Vec<u8>,
+    input_type: u8,
+) -> Result<()> {
+    let state = &ctx.accounts.state;
+    let inco = ctx.accounts.inco_lightning_program.to_account_info();
+    let signer = ctx.accounts.taker.to_account_info();
+
+    if state.status != MARKET_STATUS_OPEN {
+        return err!(OrderbookError::MarketClosed);
+    }
+    ensure_access_pass(
+        state,
+        &ctx.accounts.access_pass,
+        ctx.accounts.taker.key(),
+        ctx.program_id,
+    )?;
+    if side > 1 {
+        return err!(OrderbookError::InvalidSide);
+    }
+    if expiry_slot <= Clock::get()?.slot {
+        return err!(OrderbookError::InvalidRfq);
+    }
+    if size_ciphertext.is_empty() || size_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN {
+        return err!(OrderbookError::InvalidEscrowCiphertext);
+    }
+    ensure_user_input_type(input_type)?;
+
+    let size_handle: Euint128 = cpi::new_euint128(
+        CpiContext::new(inco.clone(), Operation { signer: signer.clone() }),
+        size_ciphertext,
+        input_type,
+    )?;
+
+    let request = &mut ctx.accounts.rfq_request;
+    request.market = state.key();
+    request.taker = ctx.accounts.taker.key();
+    request.side = side;
+    request.status = RFQ_STATUS_OPEN;
+    request.bump = ctx.bumps.rfq_request;
+    request._padding = [0u8; 1];
+    request.open_quotes = 0;
+    request.rfq_id = rfq_id;
+    request.expiry_slot = expiry_slot;
+    request.size_handle = size_handle.0;
+    request.accepted_quote = Pubkey::default();
+
+    // Auditor allowances sit at the tail of the remaining accounts.
+    let auditor_accounts = allow_auditor(
+        state.auditor,
+        ctx.remaining_accounts,
+        &[size_handle.0],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    let invited = &ctx.remaining_accounts[..ctx.remaining_accounts.len() - auditor_accounts];
+
+    for pair in invited.chunks_exact(2) {
+        allow_handle(
+            inco.clone(),
+            signer.clone(),
+            pair[0].clone(),
+            pair[1].clone(),
+            ctx.accounts.system_program.to_account_info(),
+            size_handle.0,
+        )?;
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(rfq_id: u64)]
+pub struct OpenRfq<'info> {
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        init,
+        payer = taker,
+        space = 8 + RfqRequest::LEN,
+        seeds = [b"rfq_request_v1", state.key().as_ref(), taker.key().as_ref(), &rfq_id.to_le_bytes()],
+        bump
+    )]
+    pub rfq_request: Account<'info, RfqRequest>,
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    /// Required when the market has a gatekeeper.
+    pub access_pass: Option<Account<'info, AccessPass>>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: Inco Lightning program for encrypted operations
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+}
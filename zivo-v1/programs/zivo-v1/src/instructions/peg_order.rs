@@ -0,0 +1,49 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::OrderbookError;
+use crate::state::{
+    Order, OrderbookState, MARKET_MODE_CONTINUOUS, PEG_BEST_ASK, PEG_BEST_BID, PEG_MIDPOINT,
+};
+
+/// Turns a resting order into a pegged order. Usually sent in the same transaction as
+/// `place_order`, whose `price` then acts as the protective limit. Asks may use 0 for none;
+/// bids must keep a non-zero limit because their quote escrow only covers `price` per unit.
+pub(crate) fn handler(ctx: Context<PegOrder>, peg_type: u8, peg_offset: i64) -> Result<()> {
+    let state = &ctx.accounts.state;
+    let order = &mut ctx.accounts.order;
+
+    if order.owner != ctx.accounts.trader.key() {
+        return err!(OrderbookError::InvalidOrderOwner);
+    }
+    if !order.is_open {
+        return err!(OrderbookError::OrderClosed);
+    }
+    if order.is_dark || state.market_mode != MARKET_MODE_CONTINUOUS {
+        return err!(OrderbookError::InvalidMarketMode);
+    }
+    if state.price_feed == Pubkey::default() {
+        return err!(OrderbookError::MissingPriceFeed);
+    }
+    if peg_type != PEG_MIDPOINT && peg_type != PEG_BEST_BID && peg_type != PEG_BEST_ASK {
+        return err!(OrderbookError::InvalidPegType);
+    }
+    if order.side == 0 && order.price == 0 {
+        return err!(OrderbookError::PegLimitRequired);
+    }
+
+    order.peg_type = peg_type;
+    order.peg_offset = peg_offset;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct PegOrder<'info> {
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        mut,
+        seeds = [b"order_v1", state.key().as_ref(), trader.key().as_ref(), &order.seq.to_le_bytes()],
+        bump = order.bump
+    )]
+    pub order: Account<'info, Order>,
+    pub trader: Signer<'info>,
+}
@@ -0,0 +1,283 @@
+use anchor_lang::prelude::*;
+use inco_lightning::{
+    cpi,
+    cpi::accounts::Operation,
+    program::IncoLightning,
+    types::Euint128,
+    ID as INCO_LIGHTNING_ID,
+};
+use inco_token::{
+    cpi as inco_token_cpi,
+    cpi::accounts::IncoTransfer,
+    program::IncoToken,
+    IncoAccount,
+    ID as INCO_TOKEN_ID,
+};
+use light_sdk::cpi::v2::LightSystemProgramCpi;
+use light_sdk::cpi::InvokeLightSystemProgram;
+use light_sdk::{
+    account::LightAccount,
+    address::v2::derive_address,
+    cpi::{v2::CpiAccounts, LightCpiInstruction},
+    instruction::{PackedAddressTreeInfo, ValidityProof},
+};
+
+use crate::errors::OrderbookError;
+use crate::instructions::grant_order_view::{allow_auditor, allow_owners};
+use crate::instructions::issue_access_pass::ensure_access_pass;
+use crate::instructions::place_order::{order_escrow, OrderEscrow};
+use crate::state::{
+    ensure_user_input_type, handle_ciphertext, AccessPass, CompressedOrder, OrderbookState,
+    HANDLE_INPUT_TYPE, MARKET_MODE_CONTINUOUS, MARKET_STATUS_OPEN, MAX_ESCROW_CIPHERTEXT_LEN,
+};
+
+pub(crate) const LIGHT_SYSTEM_ACCOUNTS_LEN: usize = 6;
+
+/// Places an order stored as a Light compressed account instead of an `Order` PDA, so the
+/// trader pays no rent. Escrow and encrypted size work exactly like `place_order`.
+///
+/// Remaining accounts: a required `[allowance, owner]` pair and auditor allowances, then the
+/// Light system accounts starting at `system_accounts_offset`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, PlaceCompressedOrder<'info>>,
+    side: u8,
+    price: u64,
+    size_ciphertext: Vec<u8>,
+    input_type: u8,
+    proof: ValidityProof,
+    address_tree_info: PackedAddressTreeInfo,
+    output_state_tree_index: u8,
+    system_accounts_offset: u8,
+) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    let signer = ctx.accounts.trader.to_account_info();
+    let inco = ctx.accounts.inco_lightning_program.to_account_info();
+
+    if state.status != MARKET_STATUS_OPEN {
+        return err!(OrderbookError::MarketClosed);
+    }
+    if state.market_mode != MARKET_MODE_CONTINUOUS {
+        return err!(OrderbookError::InvalidMarketMode);
+    }
+    ensure_access_pass(
+        state,
+        &ctx.accounts.access_pass,
+        ctx.accounts.trader.key(),
+        ctx.program_id,
+    )?;
+    if size_ciphertext.is_empty() || size_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN {
+        return err!(OrderbookError::InvalidEscrowCiphertext);
+    }
+    ensure_user_input_type(input_type)?;
+    let (allowance_accounts, light_accounts) = split_light_accounts(
+        ctx.remaining_accounts,
+        system_accounts_offset,
+        &[
+            ctx.accounts.address_tree.to_account_info(),
+            ctx.accounts.address_queue.to_account_info(),
+            ctx.accounts.state_queue.to_account_info(),
+            ctx.accounts.state_tree.to_account_info(),
+        ],
+    )?;
+
+    let (source, vault, mint) = match side {
+        0 => (
+            &ctx.accounts.trader_quote_inco,
+            &ctx.accounts.inco_quote_vault,
+            state.inco_quote_mint,
+        ),
+        1 => (
+            &ctx.accounts.trader_base_inco,
+            &ctx.accounts.inco_base_vault,
+            state.inco_base_mint,
+        ),
+        _ => return err!(OrderbookError::InvalidSide),
+    };
+    ensure_inco_account(source, ctx.accounts.trader.key(), mint)?;
+    ensure_inco_account(vault, state.inco_vault_authority, mint)?;
+
+    let size: Euint128 = cpi::new_euint128(
+        CpiContext::new(inco.clone(), Operation { signer: signer.clone() }),
+        size_ciphertext,
+        input_type,
+    )?;
+    let OrderEscrow {
+        remaining: remaining_handle,
+        escrow,
+    } = order_escrow(&inco, &signer, source, side, price, size)?;
+    inco_token_cpi::transfer(
+        CpiContext::new(
+            ctx.accounts.inco_token_program.to_account_info(),
+            IncoTransfer {
+                source: source.to_account_info(),
+                destination: vault.to_account_info(),
+                authority: ctx.accounts.trader.to_account_info(),
+                inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+        ),
+        handle_ciphertext(escrow.0),
+        HANDLE_INPUT_TYPE,
+    )?;
+
+    let auditor_accounts = allow_auditor(
+        state.auditor,
+        allowance_accounts,
+        &[remaining_handle.0],
+        &ctx.accounts.inco_lightning_program.to_account_info(),
+        &ctx.accounts.trader.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    let owner_accounts = &allowance_accounts[..allowance_accounts.len() - auditor_accounts];
+    // The owner must be able to decrypt its remaining handle: one leading [allowance, owner] pair.
+    allow_owners(
+        owner_accounts,
+        &[(ctx.accounts.trader.key(), remaining_handle.0)],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    let light_cpi_accounts =
+        CpiAccounts::new(ctx.accounts.trader.as_ref(), &light_accounts, crate::LIGHT_CPI_SIGNER);
+    let address_tree_pubkey = address_tree_info
+        .get_tree_pubkey(&light_cpi_accounts)
+        .map_err(|_| OrderbookError::InvalidTreeConfig)?;
+    let state_key = state.key();
+    let trader_key = ctx.accounts.trader.key();
+    let (address, address_seed) = derive_address(
+        &[
+            b"order_v1",
+            state_key.as_ref(),
+            trader_key.as_ref(),
+            &state.order_seq.to_le_bytes(),
+        ],
+        &address_tree_pubkey,
+        &crate::ID,
+    );
+
+    let mut order = LightAccount::<CompressedOrder>::new_init(
+        &crate::ID,
+        Some(address),
+        output_state_tree_index,
+    );
+    order.market = state_key;
+    order.owner = trader_key;
+    order.side = side;
+    order.is_open = true;
+    order.is_filled = false;
+    order.claim_input_type = 0;
+    order.price = price;
+    order.seq = state.order_seq;
+    order.remaining_handle = remaining_handle.0;
+    order.claim_ciphertext = Vec::new();
+
+    LightSystemProgramCpi::new_cpi(crate::LIGHT_CPI_SIGNER, proof)
+        .with_light_account(order)?
+        .with_new_addresses(&[
+            address_tree_info.into_new_address_params_assigned_packed(address_seed, Some(0)),
+        ])
+        .invoke(light_cpi_accounts)?;
+
+    state.order_seq = state.order_seq.wrapping_add(1);
+    state.open_escrows = state.open_escrows.saturating_add(1);
+
+    Ok(())
+}
+
+/// Splits remaining accounts at `system_accounts_offset`: allowance accounts before it, the
+/// Light system accounts after it. Returns the allowance accounts and the Light CPI account
+/// list (system accounts followed by `tree_accounts`, without repeats).
+pub(crate) fn split_light_accounts<'a, 'info>(
+    remaining_accounts: &'a [AccountInfo<'info>],
+    system_accounts_offset: u8,
+    tree_accounts: &[AccountInfo<'info>],
+) -> Result<(&'a [AccountInfo<'info>], Vec<AccountInfo<'info>>)> {
+    let system_start = system_accounts_offset as usize;
+    let system_end = system_start + LIGHT_SYSTEM_ACCOUNTS_LEN;
+    if remaining_accounts.len() < system_end {
+        return err!(OrderbookError::InvalidTreeConfig);
+    }
+
+    let mut light_accounts: Vec<AccountInfo<'info>> =
+        Vec::with_capacity(LIGHT_SYSTEM_ACCOUNTS_LEN + tree_accounts.len());
+    light_accounts.extend_from_slice(&remaining_accounts[system_start..system_end]);
+    for account in tree_accounts {
+        if !light_accounts[LIGHT_SYSTEM_ACCOUNTS_LEN..]
+            .iter()
+            .any(|packed| packed.key == account.key)
+        {
+            light_accounts.push(account.clone());
+        }
+    }
+    Ok((&remaining_accounts[..system_start], light_accounts))
+}
+
+#[derive(Accounts)]
+pub struct PlaceCompressedOrder<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(mut)]
+    pub trader: Signer<'info>,
+    /// Required when the market has a gatekeeper.
+    pub access_pass: Option<Account<'info, AccessPass>>,
+    #[account(
+        seeds = [b"inco_vault_authority_v12", state.key().as_ref()],
+        bump,
+        address = state.inco_vault_authority
+    )]
+    /// CHECK: PDA authority for Inco vaults
+    pub inco_vault_authority: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_base_vault)]
+    pub inco_base_vault: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_quote_vault)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    /// CHECK: Trader Inco accounts
+    #[account(mut)]
+    pub trader_base_inco: UncheckedAccount<'info>,
+    /// CHECK: Trader Inco accounts
+    #[account(mut)]
+    pub trader_quote_inco: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub inco_token_program: Program<'info, IncoToken>,
+    /// CHECK: Inco Lightning program for encrypted operations
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+    /// CHECK: Light address tree (mut)
+    #[account(mut)]
+    pub address_tree: AccountInfo<'info>,
+    /// CHECK: Light address queue (mut)
+    #[account(mut)]
+    pub address_queue: AccountInfo<'info>,
+    /// CHECK: Light state queue (mut)
+    #[account(mut)]
+    pub state_queue: AccountInfo<'info>,
+    /// CHECK: Light state tree (mut)
+    #[account(mut)]
+    pub state_tree: AccountInfo<'info>,
+}
+
+fn ensure_inco_account(
+    account: &UncheckedAccount<'_>,
+    expected_owner: Pubkey,
+    expected_mint: Pubkey,
+) -> Result<()> {
+    let info = account.to_account_info();
+    if info.owner != &INCO_TOKEN_ID {
+        return err!(OrderbookError::InvalidIncoProgramOwner);
+    }
+    let data = info.try_borrow_data()?;
+    let decoded = IncoAccount::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(OrderbookError::InvalidIncoAccountData))?;
+
+    if decoded.owner != expected_owner {
+        return err!(OrderbookError::InvalidIncoAccountOwner);
+    }
+    if decoded.mint != expected_mint {
+        return err!(OrderbookError::InvalidIncoAccountMint);
+    }
+    Ok(())
+}
@@ -0,0 +1,174 @@
+use anchor_lang::prelude::*;
+use inco_lightning::{
+    cpi,
+    cpi::accounts::Operation,
+    program::IncoLightning,
+    types::Euint128,
+    ID as INCO_LIGHTNING_ID,
+};
+
+use crate::errors::OrderbookError;
+use crate::instructions::grant_order_view::{allow_auditor, allow_owners};
+use crate::instructions::issue_access_pass::ensure_access_pass;
+use crate::instructions::place_order::{covered_by, escrow_need, OrderEscrow};
+use crate::state::{
+    ensure_user_input_type, AccessPass, Order, OrderGroup, OrderbookState, GROUP_STATUS_ACTIVE,
+    MARKET_MODE_CONTINUOUS, MARKET_STATUS_OPEN, MAX_ESCROW_CIPHERTEXT_LEN, MAX_GROUP_LEGS,
+};
+
+/// Adds a leg to an order group. The leg locks no funds of its own: it reserves its size (base
+/// for asks, size * price in quote for bids) from the group's escrow on its side, so the legs
+/// together never promise more than the group escrowed. A leg that does not fit gets size 0.
+/// Legs may sit on either side (brackets); only one leg of the group can ever fill.
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, PlaceGroupOrder<'info>>,
+    side: u8,
+    price: u64,
+    size_ciphertext: Vec<u8>,
+    input_type: u8,
+) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    let signer = ctx.accounts.trader.to_account_info();
+    let inco = ctx.accounts.inco_lightning_program.to_account_info();
+
+    if state.status != MARKET_STATUS_OPEN {
+        return err!(OrderbookError::MarketClosed);
+    }
+    ensure_access_pass(
+        state,
+        &ctx.accounts.access_pass,
+        ctx.accounts.trader.key(),
+        ctx.program_id,
+    )?;
+    if size_ciphertext.is_empty() || size_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN {
+        return err!(OrderbookError::InvalidEscrowCiphertext);
+    }
+    ensure_user_input_type(input_type)?;
+
+    // A group created before the market switched to batch auctions cannot add legs there.
+    if state.market_mode != MARKET_MODE_CONTINUOUS {
+        return err!(OrderbookError::InvalidMarketMode);
+    }
+
+    let group = &mut ctx.accounts.order_group;
+    if group.owner != ctx.accounts.trader.key() || group.market != state.key() {
+        return err!(OrderbookError::InvalidOrderGroup);
+    }
+    if group.status != GROUP_STATUS_ACTIVE {
+        return err!(OrderbookError::OrderGroupInactive);
+    }
+    if group.legs.len() >= MAX_GROUP_LEGS {
+        return err!(OrderbookError::OrderGroupFull);
+    }
+    let escrow_handle = match side {
+        0 => group.quote_escrow_handle,
+        1 => group.base_escrow_handle,
+        _ => return err!(OrderbookError::InvalidSide),
+    };
+    if escrow_handle == 0 {
+        return err!(OrderbookError::InvalidSide);
+    }
+
+    let size: Euint128 = cpi::new_euint128(
+        CpiContext::new(inco.clone(), Operation { signer: signer.clone() }),
+        size_ciphertext,
+        input_type,
+    )?;
+    let need = escrow_need(&inco, &signer, side, price, size)?;
+    let OrderEscrow {
+        remaining: remaining_handle,
+        escrow: reserved,
+    } = covered_by(&inco, &signer, Euint128(escrow_handle), size, need)?;
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let escrow_left: Euint128 = cpi::e_sub(cpi_ctx, Euint128(escrow_handle), reserved, 0)?;
+    let reserved_before = if side == 0 {
+        group.quote_reserved_handle
+    } else {
+        group.base_reserved_handle
+    };
+    let reserved_total: Euint128 = if reserved_before == 0 {
+        reserved
+    } else {
+        let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+        cpi::e_add(cpi_ctx, Euint128(reserved_before), reserved, 0)?
+    };
+    if side == 0 {
+        group.quote_escrow_handle = escrow_left.0;
+        group.quote_reserved_handle = reserved_total.0;
+    } else {
+        group.base_escrow_handle = escrow_left.0;
+        group.base_reserved_handle = reserved_total.0;
+    }
+    group.legs.push(state.order_seq);
+
+    ctx.accounts.order.set_inner(Order {
+        group: group.key(),
+        ..Order::init(
+            state,
+            ctx.accounts.trader.key(),
+            side,
+            price,
+            remaining_handle.0,
+            ctx.bumps.order,
+            ctx.accounts.trader.key(),
+        )
+    });
+
+    // Auditor allowances sit at the tail of the remaining accounts.
+    let auditor_accounts = allow_auditor(
+        state.auditor,
+        ctx.remaining_accounts,
+        &[remaining_handle.0, escrow_left.0],
+        &ctx.accounts.inco_lightning_program.to_account_info(),
+        &ctx.accounts.trader.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    let owner_accounts = &ctx.remaining_accounts[..ctx.remaining_accounts.len() - auditor_accounts];
+
+    // The owner must be able to decrypt the leg size (zero if it did not fit) and what the group
+    // has left to reserve: [remaining allowance, owner, escrow allowance, owner].
+    allow_owners(
+        owner_accounts,
+        &[
+            (ctx.accounts.trader.key(), remaining_handle.0),
+            (ctx.accounts.trader.key(), escrow_left.0),
+        ],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    state.order_seq = state.order_seq.wrapping_add(1);
+    state.add_open_order()?;
+    state.open_escrows = state.open_escrows.saturating_add(1);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct PlaceGroupOrder<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        mut,
+        seeds = [b"order_group_v1", state.key().as_ref(), trader.key().as_ref(), &order_group.group_id.to_le_bytes()],
+        bump = order_group.bump
+    )]
+    pub order_group: Account<'info, OrderGroup>,
+    #[account(
+        init,
+        payer = trader,
+        space = 8 + Order::LEN,
+        seeds = [b"order_v1", state.key().as_ref(), trader.key().as_ref(), &state.order_seq.to_le_bytes()],
+        bump
+    )]
+    pub order: Account<'info, Order>,
+    #[account(mut)]
+    pub trader: Signer<'info>,
+    /// Required when the market has a gatekeeper.
+    pub access_pass: Option<Account<'info, AccessPass>>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: Inco Lightning program for encrypted operations
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+}
@@ -1,9 +1,10 @@
 use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer};
 use inco_lightning::{
     cpi,
     cpi::accounts::Operation,
     program::IncoLightning,
-    types::Euint128,
+    types::{Ebool, Euint128},
     ID as INCO_LIGHTNING_ID,
 };
 use inco_token::{
@@ -15,109 +16,122 @@ use inco_token::{
 };
 
 use crate::errors::OrderbookError;
-use crate::state::{Order, OrderbookState, MAX_ESCROW_CIPHERTEXT_LEN};
+use crate::instructions::grant_order_view::{allow_auditor, allow_owners};
+use crate::instructions::init_buffer::resolve_ciphertext;
+use crate::instructions::issue_access_pass::ensure_access_pass;
+use crate::state::{
+    ensure_user_input_type, handle_ciphertext, AccessPass, CiphertextBuffer, Order, OrderbookState,
+    AUCTION_ORDER_FEE_LAMPORTS, HANDLE_INPUT_TYPE, MARKET_MODE_BATCH_AUCTION, MARKET_STATUS_OPEN,
+    MAX_ESCROW_CIPHERTEXT_LEN,
+};
 
-pub fn handler(
-    ctx: Context<PlaceOrder>,
+/// Places a limit order. The escrow is derived on-chain from the size (the size itself for
+/// asks, size * price in quote for bids) and moved as a handle, so the order can never promise
+/// more than the vault received. A trader whose balance does not cover it gets a zero-size order.
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, PlaceOrder<'info>>,
     side: u8,
     price: u64,
     size_ciphertext: Vec<u8>,
     input_type: u8,
-    escrow_ciphertext: Vec<u8>,
-    escrow_input_type: u8,
 ) -> Result<()> {
     let state = &mut ctx.accounts.state;
     let signer = ctx.accounts.trader.to_account_info();
     let inco = ctx.accounts.inco_lightning_program.to_account_info();
 
-    if size_ciphertext.is_empty() || escrow_ciphertext.is_empty() {
-        return err!(OrderbookError::InvalidEscrowCiphertext);
+    if state.status != MARKET_STATUS_OPEN {
+        return err!(OrderbookError::MarketClosed);
     }
-    if size_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN
-        || escrow_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN
-    {
+    ensure_access_pass(
+        state,
+        &ctx.accounts.access_pass,
+        ctx.accounts.trader.key(),
+        ctx.program_id,
+    )?;
+    let size_ciphertext = resolve_ciphertext(
+        size_ciphertext,
+        &ctx.accounts.size_buffer,
+        ctx.accounts.trader.key(),
+    )?;
+    if size_ciphertext.is_empty() || size_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN {
         return err!(OrderbookError::InvalidEscrowCiphertext);
     }
+    ensure_user_input_type(input_type)?;
 
-    let remaining_handle: Euint128 = cpi::new_euint128(
-        CpiContext::new(inco, Operation { signer: signer.clone() }),
+    let size: Euint128 = cpi::new_euint128(
+        CpiContext::new(inco.clone(), Operation { signer: signer.clone() }),
         size_ciphertext,
         input_type,
     )?;
 
-    if side == 0 {
-        ensure_inco_account(
+    let (source, vault, mint) = match side {
+        0 => (
             &ctx.accounts.trader_quote_inco,
-            ctx.accounts.trader.key(),
-            state.inco_quote_mint,
-        )?;
-        ensure_inco_account(
             &ctx.accounts.inco_quote_vault,
-            state.inco_vault_authority,
             state.inco_quote_mint,
-        )?;
-
-        inco_token_cpi::transfer(
-            CpiContext::new(
-                ctx.accounts.inco_token_program.to_account_info(),
-                IncoTransfer {
-                    source: ctx.accounts.trader_quote_inco.to_account_info(),
-                    destination: ctx.accounts.inco_quote_vault.to_account_info(),
-                    authority: ctx.accounts.trader.to_account_info(),
-                    inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
-                    system_program: ctx.accounts.system_program.to_account_info(),
-                },
-            ),
-            escrow_ciphertext,
-            escrow_input_type,
-        )?;
-    } else if side == 1 {
-        ensure_inco_account(
+        ),
+        1 => (
             &ctx.accounts.trader_base_inco,
-            ctx.accounts.trader.key(),
-            state.inco_base_mint,
-        )?;
-        ensure_inco_account(
             &ctx.accounts.inco_base_vault,
-            state.inco_vault_authority,
             state.inco_base_mint,
-        )?;
-
-        inco_token_cpi::transfer(
-            CpiContext::new(
-                ctx.accounts.inco_token_program.to_account_info(),
-                IncoTransfer {
-                    source: ctx.accounts.trader_base_inco.to_account_info(),
-                    destination: ctx.accounts.inco_base_vault.to_account_info(),
-                    authority: ctx.accounts.trader.to_account_info(),
-                    inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
-                    system_program: ctx.accounts.system_program.to_account_info(),
-                },
-            ),
-            escrow_ciphertext,
-            escrow_input_type,
-        )?;
-    } else {
-        return err!(OrderbookError::InvalidSide);
-    }
+        ),
+        _ => return err!(OrderbookError::InvalidSide),
+    };
+    ensure_inco_account(source, ctx.accounts.trader.key(), mint)?;
+    ensure_inco_account(vault, state.inco_vault_authority, mint)?;
+    let OrderEscrow {
+        remaining: remaining_handle,
+        escrow,
+    } = order_escrow(&inco, &signer, source, side, price, size)?;
+
+    inco_token_cpi::transfer(
+        CpiContext::new(
+            ctx.accounts.inco_token_program.to_account_info(),
+            IncoTransfer {
+                source: source.to_account_info(),
+                destination: vault.to_account_info(),
+                authority: ctx.accounts.trader.to_account_info(),
+                inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+        ),
+        handle_ciphertext(escrow.0),
+        HANDLE_INPUT_TYPE,
+    )?;
+
+    ctx.accounts.order.set_inner(Order::init(
+        state,
+        ctx.accounts.trader.key(),
+        side,
+        price,
+        remaining_handle.0,
+        ctx.bumps.order,
+        ctx.accounts.trader.key(),
+    ));
 
-    let order = &mut ctx.accounts.order;
-    order.owner = ctx.accounts.trader.key();
-    order.side = side;
-    order.is_open = true;
-    order.is_filled = false;
-    order.is_claimed = false;
-    order.claim_input_type = 0;
-    order.claim_plaintext_amount = 0;
-    order.claim_ciphertext.clear();
-    order.price = price;
-    order.seq = state.order_seq;
-    order.remaining_handle = remaining_handle.0;
-    order.bump = ctx.bumps.order;
-    order._padding = [0u8; 1];
-    order._reserved = [0u8; 7];
+    // Auditor allowances sit at the tail of the remaining accounts.
+    let auditor_accounts = allow_auditor(
+        state.auditor,
+        ctx.remaining_accounts,
+        &[remaining_handle.0],
+        &ctx.accounts.inco_lightning_program.to_account_info(),
+        &ctx.accounts.trader.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    let owner_accounts = &ctx.remaining_accounts[..ctx.remaining_accounts.len() - auditor_accounts];
+
+    // The owner must be able to decrypt its remaining handle: one leading [allowance, owner] pair.
+    allow_owners(
+        owner_accounts,
+        &[(ctx.accounts.trader.key(), remaining_handle.0)],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
 
     state.order_seq = state.order_seq.wrapping_add(1);
+    add_book_order(state, side, price, &signer, &ctx.accounts.system_program.to_account_info())?;
+    state.open_escrows = state.open_escrows.saturating_add(1);
 
     Ok(())
 }
@@ -137,6 +151,10 @@ pub struct PlaceOrder<'info> {
     pub order: Account<'info, Order>,
     #[account(mut)]
     pub trader: Signer<'info>,
+    /// Required when the market has a gatekeeper.
+    pub access_pass: Option<Account<'info, AccessPass>>,
+    /// Replaces `size_ciphertext` when the ciphertext was uploaded with `write_buffer`.
+    pub size_buffer: Option<Account<'info, CiphertextBuffer>>,
     #[account(
         seeds = [b"inco_vault_authority_v12", state.key().as_ref()],
         bump,
@@ -169,7 +187,119 @@ pub struct PlaceOrder<'info> {
     pub inco_lightning_program: Program<'info, IncoLightning>,
 }
 
-fn ensure_inco_account(
+/// Escrow backing a new order, computed from its encrypted size.
+pub(crate) struct OrderEscrow {
+    /// Size the order may fill: the requested size, or 0 when the balance did not cover it.
+    pub remaining: Euint128,
+    /// Amount to move into the vault as a handle; always what `remaining` needs.
+    pub escrow: Euint128,
+}
+
+/// Counts an order placed on the book. On a batch market it also takes
+/// `AUCTION_ORDER_FEE_LAMPORTS` from `payer` into the market account, and refuses bids at price
+/// 0, which escrow nothing.
+pub(crate) fn add_book_order<'info>(
+    state: &mut Account<'info, OrderbookState>,
+    side: u8,
+    price: u64,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    state.add_open_order()?;
+    if state.market_mode != MARKET_MODE_BATCH_AUCTION {
+        return Ok(());
+    }
+    if side == 0 && price == 0 {
+        return err!(OrderbookError::ZeroPriceBid);
+    }
+    system_program::transfer(
+        CpiContext::new(
+            system_program.clone(),
+            Transfer {
+                from: payer.clone(),
+                to: state.to_account_info(),
+            },
+        ),
+        AUCTION_ORDER_FEE_LAMPORTS,
+    )
+}
+
+/// Derives the escrow for `size` at `price` (base for asks, size * price in quote for bids) and
+/// zeroes both legs unless the `source` Inco balance covers it, mirroring inco-token, which moves
+/// nothing on an uncovered transfer.
+pub(crate) fn order_escrow<'info>(
+    inco: &AccountInfo<'info>,
+    signer: &AccountInfo<'info>,
+    source: &UncheckedAccount<'info>,
+    side: u8,
+    price: u64,
+    size: Euint128,
+) -> Result<OrderEscrow> {
+    let need = escrow_need(inco, signer, side, price, size)?;
+    covered_escrow(inco, signer, source, size, need)
+}
+
+/// Escrow an order of `size` at `price` needs: base for asks, size * price in quote for bids.
+pub(crate) fn escrow_need<'info>(
+    inco: &AccountInfo<'info>,
+    signer: &AccountInfo<'info>,
+    side: u8,
+    price: u64,
+    size: Euint128,
+) -> Result<Euint128> {
+    if side != 0 {
+        return Ok(size);
+    }
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let price_handle: Euint128 = cpi::as_euint128(cpi_ctx, price as u128)?;
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    cpi::e_mul(cpi_ctx, size, price_handle, 0)
+}
+
+/// Escrows `need` for `size` when the `source` balance covers it, and nothing otherwise.
+pub(crate) fn covered_escrow<'info>(
+    inco: &AccountInfo<'info>,
+    signer: &AccountInfo<'info>,
+    source: &UncheckedAccount<'info>,
+    size: Euint128,
+    need: Euint128,
+) -> Result<OrderEscrow> {
+    covered_by(inco, signer, inco_balance(source)?, size, need)
+}
+
+/// `covered_escrow` against an encrypted balance the program keeps itself, such as a
+/// `TraderNonces` balance.
+pub(crate) fn covered_by<'info>(
+    inco: &AccountInfo<'info>,
+    signer: &AccountInfo<'info>,
+    balance: Euint128,
+    size: Euint128,
+    need: Euint128,
+) -> Result<OrderEscrow> {
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let covered: Ebool = cpi::e_ge(cpi_ctx, balance, need, 0)?;
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let zero: Euint128 = cpi::as_euint128(cpi_ctx, 0)?;
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let remaining: Euint128 = cpi::e_select(cpi_ctx, covered, size, zero, 0)?;
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let escrow: Euint128 = cpi::e_select(cpi_ctx, covered, need, zero, 0)?;
+    Ok(OrderEscrow { remaining, escrow })
+}
+
+/// Encrypted balance handle of an Inco token account.
+pub(crate) fn inco_balance(account: &UncheckedAccount<'_>) -> Result<Euint128> {
+    let info = account.to_account_info();
+    if info.owner != &INCO_TOKEN_ID {
+        return err!(OrderbookError::InvalidIncoProgramOwner);
+    }
+    let data = info.try_borrow_data()?;
+    let decoded = IncoAccount::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(OrderbookError::InvalidIncoAccountData))?;
+    Ok(decoded.amount)
+}
+
+pub(crate) fn ensure_inco_account(
     account: &UncheckedAccount<'_>,
     expected_owner: Pubkey,
     expected_mint: Pubkey,
@@ -0,0 +1,271 @@
+use anchor_lang::prelude::*;
+use inco_lightning::{
+    cpi,
+    cpi::accounts::Operation,
+    program::IncoLightning,
+    types::Euint128,
+    ID as INCO_LIGHTNING_ID,
+};
+use inco_token::{cpi as inco_token_cpi, cpi::accounts::IncoTransfer, program::IncoToken};
+use light_sdk::instruction::{PackedAddressTreeInfo, ValidityProof};
+use zivo_wrap::{cpi as zivo_wrap_cpi, cpi::accounts::SpendNoteToInco, program::ZivoWrap};
+
+use crate::errors::OrderbookError;
+use crate::instructions::grant_order_view::{allow_auditor, allow_owners};
+use crate::instructions::issue_access_pass::ensure_access_pass;
+use crate::instructions::place_order::{
+    add_book_order, covered_by, ensure_inco_account, escrow_need, OrderEscrow,
+};
+use crate::state::{
+    ensure_user_input_type, handle_ciphertext, AccessPass, Order, OrderbookState,
+    HANDLE_INPUT_TYPE, MARKET_STATUS_OPEN, MAX_ESCROW_CIPHERTEXT_LEN,
+};
+
+/// Places an order funded by spending a zivo-wrap shielded note. zivo-wrap verifies the spend
+/// proof, records the nullifier and moves the note amount from its pool straight into the
+/// market vault, so no trader wallet appears in the escrow. The proof must name `note_owner`, a
+/// one-time key derived from the note, as recipient; it owns the order, and `payer` (e.g. a
+/// relayer) covers the order rent.
+///
+/// The order size is checked against the amount zivo-wrap moved, as `place_order` checks it
+/// against the trader balance: the order rests with `size` when the note covers its escrow and
+/// with 0 otherwise. Whatever the order does not escrow, the excess or the whole note when it
+/// does not cover the order, is refunded from the vault to `refund_inco_account`, an Inco
+/// account of `note_owner`.
+///
+/// Remaining accounts: a required `[allowance, owner]` pair and auditor allowances, then the
+/// Light system accounts starting at `system_accounts_offset`. All are passed through to
+/// zivo-wrap.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, PlaceOrderFromNote<'info>>,
+    side: u8,
+    price: u64,
+    size_ciphertext: Vec<u8>,
+    input_type: u8,
+    proof_data: Vec<u8>,
+    nullifier: [u8; 32],
+    light_proof: ValidityProof,
+    address_tree_info: PackedAddressTreeInfo,
+    output_state_tree_index: u8,
+    system_accounts_offset: u8,
+) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    let signer = ctx.accounts.note_owner.to_account_info();
+    let inco = ctx.accounts.inco_lightning_program.to_account_info();
+
+    if state.status != MARKET_STATUS_OPEN {
+        return err!(OrderbookError::MarketClosed);
+    }
+    ensure_access_pass(
+        state,
+        &ctx.accounts.access_pass,
+        ctx.accounts.note_owner.key(),
+        ctx.program_id,
+    )?;
+    if size_ciphertext.is_empty() || size_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN {
+        return err!(OrderbookError::InvalidEscrowCiphertext);
+    }
+    ensure_user_input_type(input_type)?;
+    let allowance_accounts = ctx
+        .remaining_accounts
+        .get(..system_accounts_offset as usize)
+        .ok_or(OrderbookError::InvalidTreeConfig)?;
+    // Bids escrow quote and asks escrow base; the note must be of that mint.
+    let (expected_mint, destination_vault) = match side {
+        0 => (state.inco_quote_mint, ctx.accounts.inco_quote_vault.to_account_info()),
+        1 => (state.inco_base_mint, ctx.accounts.inco_base_vault.to_account_info()),
+        _ => return err!(OrderbookError::InvalidSide),
+    };
+    if ctx.accounts.inco_token_mint.key() != expected_mint {
+        return err!(OrderbookError::InvalidIncoAccountMint);
+    }
+
+    let size: Euint128 = cpi::new_euint128(
+        CpiContext::new(inco.clone(), Operation { signer: signer.clone() }),
+        size_ciphertext,
+        input_type,
+    )?;
+
+    let moved = zivo_wrap_cpi::spend_note_to_inco(
+        CpiContext::new(
+            ctx.accounts.zivo_wrap_program.to_account_info(),
+            SpendNoteToInco {
+                shielded_pool: ctx.accounts.shielded_pool.to_account_info(),
+                vault: ctx.accounts.wrap_vault.to_account_info(),
+                spl_token_mint: ctx.accounts.spl_token_mint.to_account_info(),
+                inco_token_mint: ctx.accounts.inco_token_mint.to_account_info(),
+                pool_inco_account: ctx.accounts.pool_inco_account.to_account_info(),
+                note_backing: ctx.accounts.note_backing.to_account_info(),
+                destination_inco_account: destination_vault.clone(),
+                note_owner: ctx.accounts.note_owner.to_account_info(),
+                user: ctx.accounts.payer.to_account_info(),
+                verifier_program: ctx.accounts.verifier_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                inco_token_program: ctx.accounts.inco_token_program.to_account_info(),
+                address_tree: ctx.accounts.address_tree.to_account_info(),
+                address_queue: ctx.accounts.address_queue.to_account_info(),
+                state_queue: ctx.accounts.state_queue.to_account_info(),
+                state_tree: ctx.accounts.state_tree.to_account_info(),
+            },
+        )
+        .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+        proof_data,
+        nullifier,
+        light_proof,
+        address_tree_info,
+        output_state_tree_index,
+        system_accounts_offset,
+    )?
+    .get();
+
+    let need = escrow_need(&inco, &signer, side, price, size)?;
+    let OrderEscrow {
+        remaining: remaining_handle,
+        escrow,
+    } = covered_by(&inco, &signer, Euint128(moved), size, need)?;
+
+    // Refund what the order does not escrow; `escrow` is 0 when the note falls short.
+    ensure_inco_account(
+        &ctx.accounts.refund_inco_account,
+        ctx.accounts.note_owner.key(),
+        expected_mint,
+    )?;
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let refund: Euint128 = cpi::e_sub(cpi_ctx, Euint128(moved), escrow, 0)?;
+    let vault_bump = ctx.bumps.inco_vault_authority;
+    let state_key = state.key();
+    let vault_seeds: &[&[u8]] = &[
+        b"inco_vault_authority_v12",
+        state_key.as_ref(),
+        &[vault_bump],
+    ];
+    inco_token_cpi::transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.inco_token_program.to_account_info(),
+            IncoTransfer {
+                source: destination_vault,
+                destination: ctx.accounts.refund_inco_account.to_account_info(),
+                authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                inco_lightning_program: inco.clone(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+            &[vault_seeds],
+        ),
+        handle_ciphertext(refund.0),
+        HANDLE_INPUT_TYPE,
+    )?;
+
+    ctx.accounts.order.set_inner(Order::init(
+        state,
+        ctx.accounts.note_owner.key(),
+        side,
+        price,
+        remaining_handle.0,
+        ctx.bumps.order,
+        ctx.accounts.payer.key(),
+    ));
+
+    let auditor_accounts = allow_auditor(
+        state.auditor,
+        allowance_accounts,
+        &[remaining_handle.0, refund.0],
+        &ctx.accounts.inco_lightning_program.to_account_info(),
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    let owner_accounts = &allowance_accounts[..allowance_accounts.len() - auditor_accounts];
+    // The owner must be able to decrypt its remaining handle: one leading [allowance, owner] pair.
+    allow_owners(
+        owner_accounts,
+        &[(ctx.accounts.note_owner.key(), remaining_handle.0)],
+        &ctx.accounts.inco_lightning_program.to_account_info(),
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    state.order_seq = state.order_seq.wrapping_add(1);
+    add_book_order(
+        state,
+        side,
+        price,
+        &ctx.accounts.payer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    state.open_escrows = state.open_escrows.saturating_add(1);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct PlaceOrderFromNote<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Order::LEN,
+        seeds = [b"order_v1", state.key().as_ref(), note_owner.key().as_ref(), &state.order_seq.to_le_bytes()],
+        bump
+    )]
+    pub order: Account<'info, Order>,
+    /// One-time key derived from the spent note; the proof's recipient and the order owner.
+    pub note_owner: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// Required when the market has a gatekeeper.
+    pub access_pass: Option<Account<'info, AccessPass>>,
+    #[account(
+        seeds = [b"inco_vault_authority_v12", state.key().as_ref()],
+        bump,
+        address = state.inco_vault_authority
+    )]
+    /// CHECK: PDA authority for Inco vaults
+    pub inco_vault_authority: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_base_vault)]
+    pub inco_base_vault: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_quote_vault)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    /// CHECK: `note_owner`'s Inco account of the escrowed mint; receives the note value the order
+    /// does not escrow
+    #[account(mut)]
+    pub refund_inco_account: UncheckedAccount<'info>,
+    /// CHECK: zivo-wrap shielded pool config, validated by zivo-wrap
+    pub shielded_pool: UncheckedAccount<'info>,
+    /// CHECK: zivo-wrap vault (Inco mint authority), validated by zivo-wrap
+    #[account(mut)]
+    pub wrap_vault: UncheckedAccount<'info>,
+    /// CHECK: SPL mint backing the note, validated by zivo-wrap
+    pub spl_token_mint: UncheckedAccount<'info>,
+    /// CHECK: Inco mint of the note; must be the market mint for the order side
+    pub inco_token_mint: UncheckedAccount<'info>,
+    /// CHECK: zivo-wrap pool Inco account holding note value, validated by zivo-wrap
+    #[account(mut)]
+    pub pool_inco_account: UncheckedAccount<'info>,
+    /// CHECK: zivo-wrap backing of the spent note, validated by zivo-wrap and closed to `payer`
+    #[account(mut)]
+    pub note_backing: UncheckedAccount<'info>,
+    /// CHECK: Noir verifier program, pinned by the shielded pool and checked by zivo-wrap
+    pub verifier_program: UncheckedAccount<'info>,
+    /// CHECK: Light address tree (mut)
+    #[account(mut)]
+    pub address_tree: AccountInfo<'info>,
+    /// CHECK: Light address queue (mut)
+    #[account(mut)]
+    pub address_queue: AccountInfo<'info>,
+    /// CHECK: Light state queue (mut)
+    #[account(mut)]
+    pub state_queue: AccountInfo<'info>,
+    /// CHECK: Light state tree (mut)
+    #[account(mut)]
+    pub state_tree: AccountInfo<'info>,
+    pub zivo_wrap_program: Program<'info, ZivoWrap>,
+    pub system_program: Program<'info, System>,
+    pub inco_token_program: Program<'info, IncoToken>,
+    /// CHECK: Inco Lightning program for encrypted operations
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+}
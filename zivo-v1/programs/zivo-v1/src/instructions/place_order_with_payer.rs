@@ -0,0 +1,207 @@
+use anchor_lang::prelude::*;
+use inco_lightning::{
+    cpi,
+    cpi::accounts::Operation,
+    program::IncoLightning,
+    types::Euint128,
+    ID as INCO_LIGHTNING_ID,
+};
+use inco_token::{
+    cpi as inco_token_cpi,
+    cpi::accounts::IncoTransfer,
+    program::IncoToken,
+    IncoAccount,
+    ID as INCO_TOKEN_ID,
+};
+
+use crate::errors::OrderbookError;
+use crate::instructions::grant_order_view::{allow_auditor, allow_owners};
+use crate::instructions::issue_access_pass::ensure_access_pass;
+use crate::instructions::place_order::{add_book_order, order_escrow, OrderEscrow};
+use crate::state::{
+    ensure_user_input_type, handle_ciphertext, AccessPass, Order, OrderbookState, HANDLE_INPUT_TYPE,
+    MARKET_STATUS_OPEN, MAX_ESCROW_CIPHERTEXT_LEN,
+};
+
+/// `place_order` for program-owned (PDA) traders calling through CPI. The trader only signs
+/// (via `invoke_signed`) and owns its Inco accounts; a separate `payer` funds the order rent,
+/// since a PDA that carries data cannot pay for account creation.
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, PlaceOrderWithPayer<'info>>,
+    side: u8,
+    price: u64,
+    size_ciphertext: Vec<u8>,
+    input_type: u8,
+) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    let signer = ctx.accounts.trader.to_account_info();
+    let inco = ctx.accounts.inco_lightning_program.to_account_info();
+
+    if state.status != MARKET_STATUS_OPEN {
+        return err!(OrderbookError::MarketClosed);
+    }
+    ensure_access_pass(
+        state,
+        &ctx.accounts.access_pass,
+        ctx.accounts.trader.key(),
+        ctx.program_id,
+    )?;
+    if size_ciphertext.is_empty() || size_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN {
+        return err!(OrderbookError::InvalidEscrowCiphertext);
+    }
+    ensure_user_input_type(input_type)?;
+
+    let (source, vault, mint) = match side {
+        0 => (
+            &ctx.accounts.trader_quote_inco,
+            &ctx.accounts.inco_quote_vault,
+            state.inco_quote_mint,
+        ),
+        1 => (
+            &ctx.accounts.trader_base_inco,
+            &ctx.accounts.inco_base_vault,
+            state.inco_base_mint,
+        ),
+        _ => return err!(OrderbookError::InvalidSide),
+    };
+    ensure_inco_account(source, ctx.accounts.trader.key(), mint)?;
+    ensure_inco_account(vault, state.inco_vault_authority, mint)?;
+
+    let size: Euint128 = cpi::new_euint128(
+        CpiContext::new(inco.clone(), Operation { signer: signer.clone() }),
+        size_ciphertext,
+        input_type,
+    )?;
+    let OrderEscrow {
+        remaining: remaining_handle,
+        escrow,
+    } = order_escrow(&inco, &signer, source, side, price, size)?;
+    inco_token_cpi::transfer(
+        CpiContext::new(
+            ctx.accounts.inco_token_program.to_account_info(),
+            IncoTransfer {
+                source: source.to_account_info(),
+                destination: vault.to_account_info(),
+                authority: ctx.accounts.trader.to_account_info(),
+                inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+        ),
+        handle_ciphertext(escrow.0),
+        HANDLE_INPUT_TYPE,
+    )?;
+
+    ctx.accounts.order.set_inner(Order::init(
+        state,
+        ctx.accounts.trader.key(),
+        side,
+        price,
+        remaining_handle.0,
+        ctx.bumps.order,
+        ctx.accounts.payer.key(),
+    ));
+
+    // Auditor allowances sit at the tail of the remaining accounts.
+    let auditor_accounts = allow_auditor(
+        state.auditor,
+        ctx.remaining_accounts,
+        &[remaining_handle.0],
+        &ctx.accounts.inco_lightning_program.to_account_info(),
+        &ctx.accounts.trader.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    let owner_accounts = &ctx.remaining_accounts[..ctx.remaining_accounts.len() - auditor_accounts];
+
+    // The owner must be able to decrypt its remaining handle: one leading [allowance, owner] pair.
+    allow_owners(
+        owner_accounts,
+        &[(ctx.accounts.trader.key(), remaining_handle.0)],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    state.order_seq = state.order_seq.wrapping_add(1);
+    add_book_order(
+        state,
+        side,
+        price,
+        &ctx.accounts.payer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    state.open_escrows = state.open_escrows.saturating_add(1);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(side: u8, price: u64)]
+pub struct PlaceOrderWithPayer<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Order::LEN,
+        seeds = [b"order_v1", state.key().as_ref(), trader.key().as_ref(), &state.order_seq.to_le_bytes()],
+        bump
+    )]
+    pub order: Account<'info, Order>,
+    pub trader: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// Required when the market has a gatekeeper.
+    pub access_pass: Option<Account<'info, AccessPass>>,
+    #[account(
+        seeds = [b"inco_vault_authority_v12", state.key().as_ref()],
+        bump,
+        address = state.inco_vault_authority
+    )]
+    /// CHECK: PDA authority for Inco vaults
+    pub inco_vault_authority: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_base_vault)]
+    pub inco_base_vault: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_quote_vault)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    /// CHECK: Trader Inco accounts
+    #[account(mut)]
+    pub trader_base_inco: UncheckedAccount<'info>,
+    /// CHECK: Trader Inco accounts
+    #[account(mut)]
+    pub trader_quote_inco: UncheckedAccount<'info>,
+    /// CHECK: Inco base mint
+    #[account(address = state.inco_base_mint)]
+    pub inco_base_mint: UncheckedAccount<'info>,
+    /// CHECK: Inco quote mint
+    #[account(address = state.inco_quote_mint)]
+    pub inco_quote_mint: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub inco_token_program: Program<'info, IncoToken>,
+    /// CHECK: Inco Lightning program for encrypted operations
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+}
+
+fn ensure_inco_account(
+    account: &UncheckedAccount<'_>,
+    expected_owner: Pubkey,
+    expected_mint: Pubkey,
+) -> Result<()> {
+    let info = account.to_account_info();
+    if info.owner != &INCO_TOKEN_ID {
+        return err!(OrderbookError::InvalidIncoProgramOwner);
+    }
+    let data = info.try_borrow_data()?;
+    let decoded = IncoAccount::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(OrderbookError::InvalidIncoAccountData))?;
+
+    if decoded.owner != expected_owner {
+        return err!(OrderbookError::InvalidIncoAccountOwner);
+    }
+    if decoded.mint != expected_mint {
+        return err!(OrderbookError::InvalidIncoAccountMint);
+    }
+    Ok(())
+}
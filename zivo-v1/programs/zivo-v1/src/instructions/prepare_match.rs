@@ -0,0 +1,148 @@
+use anchor_lang::prelude::*;
+use inco_lightning::{program::IncoLightning, ID as INCO_LIGHTNING_ID};
+
+use crate::instructions::grant_order_view::{allow_auditor, allow_owners};
+use crate::instructions::init_buffer::resolve_ciphertext;
+use crate::instructions::match_order::{
+    bid_overpay, compute_fill, dark_prices, validate_match, MatchFill,
+};
+use crate::state::{AccessPass, CiphertextBuffer, Order, OrderbookState, PendingMatch};
+
+/// First half of a two-phase match. Runs the same checks and encrypted arithmetic as
+/// `match_order` (`validate_match`, `compute_fill`), but only records the resulting handles in
+/// a `PendingMatch`; no tokens move and neither order is modified until `settle_match`.
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, PrepareMatch<'info>>,
+    taker_side: u8,
+    taker_price: u64,
+    taker_req_base_ciphertext: Vec<u8>,
+    input_type: u8,
+) -> Result<()> {
+    let state = &ctx.accounts.state;
+    let order = &ctx.accounts.maker_order;
+    let taker_order = &ctx.accounts.taker_order;
+
+    let taker_req_base_ciphertext = resolve_ciphertext(
+        taker_req_base_ciphertext,
+        &ctx.accounts.taker_req_base_buffer,
+        ctx.accounts.matcher.key(),
+    )?;
+    validate_match(
+        state,
+        order,
+        taker_order,
+        ctx.accounts.matcher.key(),
+        ctx.accounts.taker.key(),
+        taker_side,
+        taker_price,
+        &ctx.accounts.price_feed,
+        &ctx.accounts.taker_access_pass,
+        ctx.program_id,
+    )?;
+
+    let inco = ctx.accounts.inco_lightning_program.to_account_info();
+    let signer = ctx.accounts.matcher.to_account_info();
+    let MatchFill {
+        req_base,
+        fill_base,
+        fill_quote,
+        maker_remaining,
+        taker_remaining,
+        bid_refund,
+    } = compute_fill(
+        &inco,
+        &signer,
+        order.remaining_handle,
+        taker_order.remaining_handle,
+        dark_prices(order, taker_order),
+        bid_overpay(order, taker_order, taker_price),
+        taker_price,
+        taker_req_base_ciphertext,
+        input_type,
+    )?;
+
+    // Allowances follow the `match_order` layout. Granting them here keeps `settle_match` small;
+    // a discarded match only leaves allowances on handles that are never used.
+    let mut audited = vec![
+        req_base.0,
+        fill_base.0,
+        fill_quote.0,
+        maker_remaining.0,
+        taker_remaining.0,
+    ];
+    audited.extend(bid_refund.map(|refund| refund.0));
+    let auditor_accounts = allow_auditor(
+        state.auditor,
+        ctx.remaining_accounts,
+        &audited,
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    let owner_accounts = &ctx.remaining_accounts[..ctx.remaining_accounts.len() - auditor_accounts];
+    allow_owners(
+        owner_accounts,
+        &[
+            (order.owner, maker_remaining.0),
+            (taker_order.owner, fill_base.0),
+            (taker_order.owner, taker_remaining.0),
+        ],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    let pending = &mut ctx.accounts.pending_match;
+    pending.market = state.key();
+    pending.maker_order = order.key();
+    pending.taker_order = taker_order.key();
+    pending.matcher = ctx.accounts.matcher.key();
+    pending.bump = ctx.bumps.pending_match;
+    pending.taker_side = taker_side;
+    pending._padding = [0u8; 4];
+    pending.price = taker_price;
+    pending.prepared_slot = Clock::get()?.slot;
+    pending.snapshot(order, taker_order);
+    pending.fill_handle = fill_base.0;
+    pending.fill_quote_handle = fill_quote.0;
+    pending.maker_remaining_handle = maker_remaining.0;
+    pending.taker_remaining_handle = taker_remaining.0;
+    pending.bid_refund_handle = bid_refund.map_or(0, |refund| refund.0);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct PrepareMatch<'info> {
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        init,
+        payer = matcher,
+        space = 8 + PendingMatch::LEN,
+        seeds = [b"pending_match_v1", maker_order.key().as_ref(), taker_order.key().as_ref()],
+        bump
+    )]
+    pub pending_match: Account<'info, PendingMatch>,
+    #[account(
+        has_one = owner,
+        seeds = [b"order_v1", state.key().as_ref(), owner.key().as_ref(), &maker_order.seq.to_le_bytes()],
+        bump = maker_order.bump
+    )]
+    pub maker_order: Account<'info, Order>,
+    pub taker_order: Account<'info, Order>,
+    /// CHECK: maker owner stored in order
+    pub owner: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub matcher: Signer<'info>,
+    /// CHECK: taker owner stored in taker order
+    pub taker: UncheckedAccount<'info>,
+    /// Required when the market has a gatekeeper.
+    pub taker_access_pass: Option<Account<'info, AccessPass>>,
+    /// CHECK: market reference price account, required when either order is pegged
+    pub price_feed: Option<UncheckedAccount<'info>>,
+    /// Matcher-owned buffer replacing `taker_req_base_ciphertext`.
+    pub taker_req_base_buffer: Option<Account<'info, CiphertextBuffer>>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: Inco Lightning program for encrypted operations
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+}
@@ -0,0 +1,22 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::OrderbookError;
+use crate::state::{OrderbookState, AUDITOR_TIMELOCK_SECONDS};
+
+pub(crate) fn handler(ctx: Context<ProposeAuditor>, auditor: Pubkey) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    if ctx.accounts.admin.key() != state.admin {
+        return err!(OrderbookError::UnauthorizedMatcher);
+    }
+    let now = Clock::get()?.unix_timestamp;
+    state.pending_auditor = auditor;
+    state.pending_auditor_activation_ts = now + AUDITOR_TIMELOCK_SECONDS;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ProposeAuditor<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    pub admin: Signer<'info>,
+}
@@ -0,0 +1,140 @@
+use anchor_lang::prelude::*;
+use inco_lightning::{program::IncoLightning, ID as INCO_LIGHTNING_ID};
+use inco_token::{
+    cpi as inco_token_cpi,
+    cpi::accounts::IncoTransfer,
+    program::IncoToken,
+    IncoAccount,
+    ID as INCO_TOKEN_ID,
+};
+
+use crate::errors::OrderbookError;
+use crate::state::{
+    handle_ciphertext, OrderbookState, RfqQuote, RfqRequest, HANDLE_INPUT_TYPE, RFQ_STATUS_OPEN,
+};
+
+/// Permissionless: once an RFQ is accepted, cancelled or expired, a losing quote's escrow
+/// goes back to its maker and the quote account closes to the maker.
+pub(crate) fn handler(ctx: Context<RefundRfqQuote>) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    let request = &ctx.accounts.rfq_request;
+    let quote = &ctx.accounts.rfq_quote;
+
+    if request.status == RFQ_STATUS_OPEN && Clock::get()?.slot <= request.expiry_slot {
+        return err!(OrderbookError::RfqStillOpen);
+    }
+
+    let (vault, maker_receive, mint) = if request.side == 0 {
+        (
+            &ctx.accounts.inco_base_vault,
+            &ctx.accounts.maker_base_inco,
+            state.inco_base_mint,
+        )
+    } else {
+        (
+            &ctx.accounts.inco_quote_vault,
+            &ctx.accounts.maker_quote_inco,
+            state.inco_quote_mint,
+        )
+    };
+    ensure_inco_account(vault, state.inco_vault_authority, mint)?;
+    ensure_inco_account(maker_receive, quote.maker, mint)?;
+
+    let vault_authority_bump = ctx.bumps.inco_vault_authority;
+    let state_key = state.key();
+    let vault_seeds: &[&[u8]] = &[
+        b"inco_vault_authority_v12",
+        state_key.as_ref(),
+        &[vault_authority_bump],
+    ];
+    inco_token_cpi::transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.inco_token_program.to_account_info(),
+            IncoTransfer {
+                source: vault.to_account_info(),
+                destination: maker_receive.to_account_info(),
+                authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+            &[vault_seeds],
+        ),
+        handle_ciphertext(quote.delivery_handle),
+        HANDLE_INPUT_TYPE,
+    )?;
+    let request = &mut ctx.accounts.rfq_request;
+    request.open_quotes = request.open_quotes.saturating_sub(1);
+    state.open_escrows = state.open_escrows.saturating_sub(1);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RefundRfqQuote<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        mut,
+        constraint = rfq_request.market == state.key() @ OrderbookError::InvalidRfq,
+        seeds = [b"rfq_request_v1", state.key().as_ref(), rfq_request.taker.as_ref(), &rfq_request.rfq_id.to_le_bytes()],
+        bump = rfq_request.bump
+    )]
+    pub rfq_request: Account<'info, RfqRequest>,
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker,
+        seeds = [b"rfq_quote_v1", rfq_request.key().as_ref(), maker.key().as_ref()],
+        bump = rfq_quote.bump
+    )]
+    pub rfq_quote: Account<'info, RfqQuote>,
+    /// CHECK: quote maker stored in the quote; receives the quote rent
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+    pub cranker: Signer<'info>,
+    #[account(
+        seeds = [b"inco_vault_authority_v12", state.key().as_ref()],
+        bump,
+        address = state.inco_vault_authority
+    )]
+    /// CHECK: PDA authority for Inco vaults
+    pub inco_vault_authority: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_base_vault)]
+    pub inco_base_vault: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_quote_vault)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    /// CHECK: Maker Inco accounts
+    #[account(mut)]
+    pub maker_base_inco: UncheckedAccount<'info>,
+    /// CHECK: Maker Inco accounts
+    #[account(mut)]
+    pub maker_quote_inco: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub inco_token_program: Program<'info, IncoToken>,
+    /// CHECK: Inco Lightning program
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+}
+
+fn ensure_inco_account(
+    account: &UncheckedAccount<'_>,
+    expected_owner: Pubkey,
+    expected_mint: Pubkey,
+) -> Result<()> {
+    let info = account.to_account_info();
+    if info.owner != &INCO_TOKEN_ID {
+        return err!(OrderbookError::InvalidIncoProgramOwner);
+    }
+    let data = info.try_borrow_data()?;
+    let decoded = IncoAccount::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(OrderbookError::InvalidIncoAccountData))?;
+
+    if decoded.owner != expected_owner {
+        return err!(OrderbookError::InvalidIncoAccountOwner);
+    }
+    if decoded.mint != expected_mint {
+        return err!(OrderbookError::InvalidIncoAccountMint);
+    }
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::OrderbookError;
+use crate::state::{OrderbookState, TradingDelegate, MARKET_STATUS_CLOSED};
+
+/// Permissionless: drops a closed market from a trading delegate grant. A grant left with no
+/// market can do nothing, so it is closed and its rent returns to the owner.
+pub(crate) fn handler(ctx: Context<ReleaseDelegateMarket>) -> Result<()> {
+    let state = &ctx.accounts.state;
+    if state.status != MARKET_STATUS_CLOSED {
+        return err!(OrderbookError::MarketNotClosed);
+    }
+    let grant = &mut ctx.accounts.trading_delegate;
+    let state_key = state.key();
+    if !grant.markets.contains(&state_key) {
+        return err!(OrderbookError::DelegateActionNotAllowed);
+    }
+    grant.markets.retain(|market| *market != state_key);
+    if grant.markets.is_empty() {
+        return grant.close(ctx.accounts.owner.to_account_info());
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ReleaseDelegateMarket<'info> {
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"trading_delegate_v1", owner.key().as_ref(), trading_delegate.delegate.as_ref()],
+        bump = trading_delegate.bump
+    )]
+    pub trading_delegate: Account<'info, TradingDelegate>,
+    /// CHECK: grant owner stored in the grant; receives its rent
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+    pub caller: Signer<'info>,
+}
@@ -0,0 +1,212 @@
+use anchor_lang::prelude::*;
+use inco_lightning::{program::IncoLightning, types::Euint128, ID as INCO_LIGHTNING_ID};
+use inco_token::{
+    cpi as inco_token_cpi,
+    cpi::accounts::IncoTransfer,
+    program::IncoToken,
+    IncoAccount,
+    ID as INCO_TOKEN_ID,
+};
+
+use crate::errors::OrderbookError;
+use crate::instructions::cancel_order::order_refund;
+use crate::instructions::grant_order_view::{allow_auditor, allow_owners};
+use crate::instructions::place_order::{order_escrow, OrderEscrow};
+use crate::state::{
+    handle_ciphertext, Order, OrderbookState, HANDLE_INPUT_TYPE, MARKET_MODE_CONTINUOUS,
+    MARKET_STATUS_OPEN, PEG_NONE,
+};
+
+/// Atomically moves both legs of a `place_quote` pair to new prices. Both legs must still be
+/// open; a partially filled leg keeps quoting its remaining size. The ask's base escrow does not
+/// depend on its price. A bid price change re-escrows the bid: the escrow at the old price goes
+/// back to the owner and `remaining * bid_price` is debited again, so a higher bid the balance
+/// cannot cover is left with size 0, exactly like an uncovered `place_order`.
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, Requote<'info>>,
+    bid_price: u64,
+    ask_price: u64,
+) -> Result<()> {
+    let state = &ctx.accounts.state;
+    let bid_order = &mut ctx.accounts.bid_order;
+    let ask_order = &mut ctx.accounts.ask_order;
+
+    if state.status != MARKET_STATUS_OPEN {
+        return err!(OrderbookError::MarketClosed);
+    }
+    // Auction orders clear at the price they were collected with.
+    if state.market_mode != MARKET_MODE_CONTINUOUS {
+        return err!(OrderbookError::InvalidMarketMode);
+    }
+    if bid_price == 0 || bid_price >= ask_price {
+        return err!(OrderbookError::InvalidQuote);
+    }
+    if !bid_order.is_open || !ask_order.is_open {
+        return err!(OrderbookError::OrderClosed);
+    }
+    if bid_order.side != 0 || ask_order.side != 1 {
+        return err!(OrderbookError::InvalidSide);
+    }
+    // Only the two legs of one `place_quote` call: the bid at seq, the ask at seq + 1.
+    if !bid_order.is_quote
+        || !ask_order.is_quote
+        || ask_order.seq != bid_order.seq.wrapping_add(1)
+    {
+        return err!(OrderbookError::InvalidQuote);
+    }
+    for order in [&bid_order, &ask_order] {
+        if order.is_dark || order.peg_type != PEG_NONE || order.group != Pubkey::default() {
+            return err!(OrderbookError::InvalidQuote);
+        }
+    }
+
+    if bid_price != bid_order.price {
+        ensure_inco_account(
+            &ctx.accounts.owner_quote_inco,
+            ctx.accounts.owner.key(),
+            state.inco_quote_mint,
+        )?;
+        ensure_inco_account(
+            &ctx.accounts.inco_quote_vault,
+            state.inco_vault_authority,
+            state.inco_quote_mint,
+        )?;
+        let inco = ctx.accounts.inco_lightning_program.to_account_info();
+        let signer = ctx.accounts.owner.to_account_info();
+
+        let refund = order_refund(&inco, &signer, bid_order)?;
+        let vault_authority_bump = ctx.bumps.inco_vault_authority;
+        let state_key = state.key();
+        let vault_seeds: &[&[u8]] = &[
+            b"inco_vault_authority_v12",
+            state_key.as_ref(),
+            &[vault_authority_bump],
+        ];
+        inco_token_cpi::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.inco_token_program.to_account_info(),
+                IncoTransfer {
+                    source: ctx.accounts.inco_quote_vault.to_account_info(),
+                    destination: ctx.accounts.owner_quote_inco.to_account_info(),
+                    authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                    inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+                &[vault_seeds],
+            ),
+            handle_ciphertext(refund.0),
+            HANDLE_INPUT_TYPE,
+        )?;
+
+        // The refund has already landed, so the balance check sees it.
+        let OrderEscrow { remaining, escrow } = order_escrow(
+            &inco,
+            &signer,
+            &ctx.accounts.owner_quote_inco,
+            0,
+            bid_price,
+            Euint128(bid_order.remaining_handle),
+        )?;
+        inco_token_cpi::transfer(
+            CpiContext::new(
+                ctx.accounts.inco_token_program.to_account_info(),
+                IncoTransfer {
+                    source: ctx.accounts.owner_quote_inco.to_account_info(),
+                    destination: ctx.accounts.inco_quote_vault.to_account_info(),
+                    authority: ctx.accounts.owner.to_account_info(),
+                    inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+            ),
+            handle_ciphertext(escrow.0),
+            HANDLE_INPUT_TYPE,
+        )?;
+        bid_order.remaining_handle = remaining.0;
+
+        // Auditor allowances sit at the tail of the remaining accounts.
+        let auditor_accounts = allow_auditor(
+            state.auditor,
+            ctx.remaining_accounts,
+            &[remaining.0],
+            &inco,
+            &signer,
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        let owner_accounts =
+            &ctx.remaining_accounts[..ctx.remaining_accounts.len() - auditor_accounts];
+
+        // The owner must be able to decrypt the bid's new remaining handle.
+        allow_owners(
+            owner_accounts,
+            &[(ctx.accounts.owner.key(), remaining.0)],
+            &inco,
+            &signer,
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+    }
+
+    bid_order.price = bid_price;
+    ask_order.price = ask_price;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Requote<'info> {
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"order_v1", state.key().as_ref(), owner.key().as_ref(), &bid_order.seq.to_le_bytes()],
+        bump = bid_order.bump
+    )]
+    pub bid_order: Account<'info, Order>,
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"order_v1", state.key().as_ref(), owner.key().as_ref(), &ask_order.seq.to_le_bytes()],
+        bump = ask_order.bump
+    )]
+    pub ask_order: Account<'info, Order>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        seeds = [b"inco_vault_authority_v12", state.key().as_ref()],
+        bump,
+        address = state.inco_vault_authority
+    )]
+    /// CHECK: PDA authority for Inco vaults
+    pub inco_vault_authority: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_quote_vault)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    /// CHECK: Owner quote Inco account; receives and re-funds the bid escrow
+    #[account(mut)]
+    pub owner_quote_inco: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub inco_token_program: Program<'info, IncoToken>,
+    /// CHECK: Inco Lightning program for encrypted operations
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+}
+
+fn ensure_inco_account(
+    account: &UncheckedAccount<'_>,
+    expected_owner: Pubkey,
+    expected_mint: Pubkey,
+) -> Result<()> {
+    let info = account.to_account_info();
+    if info.owner != &INCO_TOKEN_ID {
+        return err!(OrderbookError::InvalidIncoProgramOwner);
+    }
+    let data = info.try_borrow_data()?;
+    let decoded = IncoAccount::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(OrderbookError::InvalidIncoAccountData))?;
+
+    if decoded.owner != expected_owner {
+        return err!(OrderbookError::InvalidIncoAccountOwner);
+    }
+    if decoded.mint != expected_mint {
+        return err!(OrderbookError::InvalidIncoAccountMint);
+    }
+    Ok(())
+}
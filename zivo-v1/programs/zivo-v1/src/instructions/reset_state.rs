@@ -11,7 +11,7 @@ pub struct ResetState<'info> {
     pub admin: Signer<'info>,
 }
 
-pub fn handler(ctx: Context<ResetState>) -> Result<()> {
+pub(crate) fn handler(ctx: Context<ResetState>) -> Result<()> {
     let state = &mut ctx.accounts.state;
     if ctx.accounts.admin.key() != state.admin {
         return err!(OrderbookError::UnauthorizedMatcher);
@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::OrderbookError;
+use crate::state::{AccessPass, OrderbookState, MARKET_STATUS_CLOSED};
+
+/// Closes a pass, returning its rent to the gatekeeper that issued it. The current gatekeeper,
+/// the issuer or the market admin may revoke, so passes stay revocable after the gatekeeper is
+/// changed or reset to `Pubkey::default()`. Once the market is closed anyone may.
+pub(crate) fn handler(ctx: Context<RevokeAccessPass>) -> Result<()> {
+    let state = &ctx.accounts.state;
+    if state.status == MARKET_STATUS_CLOSED {
+        return Ok(());
+    }
+    let authority = ctx.accounts.authority.key();
+    let is_gatekeeper = state.gatekeeper != Pubkey::default() && authority == state.gatekeeper;
+    if !is_gatekeeper && authority != ctx.accounts.access_pass.issuer && authority != state.admin {
+        return err!(OrderbookError::UnauthorizedGatekeeper);
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RevokeAccessPass<'info> {
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        mut,
+        close = issuer,
+        seeds = [b"access_pass_v1", state.key().as_ref(), access_pass.trader.as_ref()],
+        bump = access_pass.bump
+    )]
+    pub access_pass: Account<'info, AccessPass>,
+    pub authority: Signer<'info>,
+    /// CHECK: gatekeeper that issued the pass; receives its rent
+    #[account(mut, address = access_pass.issuer)]
+    pub issuer: UncheckedAccount<'info>,
+}
@@ -0,0 +1,23 @@
+use anchor_lang::prelude::*;
+
+use crate::state::TradingDelegate;
+
+/// Revokes a trading delegate immediately and returns the grant rent to the owner. Orders the
+/// delegate already placed stay in the book and belong to the owner.
+pub(crate) fn handler(_ctx: Context<RevokeTradingDelegate>) -> Result<()> {
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RevokeTradingDelegate<'info> {
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [b"trading_delegate_v1", owner.key().as_ref(), trading_delegate.delegate.as_ref()],
+        bump = trading_delegate.bump
+    )]
+    pub trading_delegate: Account<'info, TradingDelegate>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
@@ -0,0 +1,379 @@
+use anchor_lang::prelude::*;
+use inco_lightning::{
+    cpi,
+    cpi::accounts::Operation,
+    program::IncoLightning,
+    types::{Ebool, Euint128},
+    ID as INCO_LIGHTNING_ID,
+};
+use inco_token::{
+    cpi as inco_token_cpi,
+    cpi::accounts::IncoTransfer,
+    program::IncoToken,
+    IncoAccount,
+    ID as INCO_TOKEN_ID,
+};
+
+use crate::errors::OrderbookError;
+use crate::instructions::grant_order_view::{allow_auditor, allow_owners};
+use crate::instructions::issue_access_pass::ensure_access_pass;
+use crate::instructions::match_order::accrue_claim;
+use crate::state::{
+    handle_ciphertext, AccessPass, AuctionResult, Order, OrderbookState, HANDLE_INPUT_TYPE,
+    MARKET_MODE_BATCH_AUCTION, MARKET_STATUS_OPEN, PEG_NONE,
+};
+
+/// Clears the current auction over the whole book, settles it and empties the book, all in the
+/// same instruction.
+///
+/// The remaining accounts start with one `[order, owner base Inco account, owner quote Inco
+/// account]` group for every open order on the market (`open_orders`, at most
+/// `MAX_AUCTION_ORDERS`), so no resting order can be left out. When the market has a gatekeeper,
+/// each group also ends with the owner's access pass; an order whose pass no longer admits its
+/// owner takes no part in pricing and is only refunded.
+///
+/// Every limit price that crosses is a candidate. Its volume is the smaller of the encrypted bid
+/// size at or above it and the ask size at or below it, read from running sums over the sorted
+/// book so each candidate costs a fixed number of operations; the clearing price is the
+/// candidate with the most volume (the lowest on ties). Both are derived from encrypted sizes,
+/// so they are stored as handles. Each side then fills in price-then-time priority up to that
+/// volume at the clearing price, and fills accrue on the orders' claims (base for bids, quote
+/// for asks).
+///
+/// Orders only live for one auction: every order is closed here and gets back whatever its fill
+/// did not use (the unfilled size for asks, the escrow above `fill * clearing price` for bids),
+/// so filled and empty orders never hold a place in the next book.
+///
+/// Owner pairs for each admitted order's claim follow the order groups, in order; auditor pairs
+/// (clearing price, volume, then each admitted order's fill) trail them.
+///
+/// The book is capped, so every placement on it pays `AUCTION_ORDER_FEE_LAMPORTS` into the market
+/// account; the matcher collects those fees here.
+pub(crate) fn handler<'info>(ctx: Context<'_, '_, 'info, 'info, RunAuction<'info>>) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+
+    if ctx.accounts.matcher.key() != state.admin {
+        return err!(OrderbookError::UnauthorizedMatcher);
+    }
+    if state.status != MARKET_STATUS_OPEN {
+        return err!(OrderbookError::MarketClosed);
+    }
+    if state.market_mode != MARKET_MODE_BATCH_AUCTION {
+        return err!(OrderbookError::InvalidMarketMode);
+    }
+    let slot = Clock::get()?.slot;
+    if slot < state.auction_start_slot.saturating_add(state.auction_window_slots) {
+        return err!(OrderbookError::AuctionWindowOpen);
+    }
+
+    let book = state.open_orders as usize;
+    let stride = if state.gatekeeper == Pubkey::default() { 3 } else { 4 };
+    if ctx.remaining_accounts.len() < book * stride {
+        return err!(OrderbookError::IncompleteAuctionOrders);
+    }
+    let (book_accounts, allowance_accounts) = ctx.remaining_accounts.split_at(book * stride);
+
+    // Distinct open orders of this market, as many as the market counts: the whole book.
+    let state_key = state.key();
+    let mut orders: Vec<AuctionOrder<'info>> = Vec::with_capacity(book);
+    for group in book_accounts.chunks(stride) {
+        let info = &group[0];
+        if orders.iter().any(|entry| entry.order.key() == info.key()) {
+            return err!(OrderbookError::DuplicateAuctionOrder);
+        }
+        let order: Account<'info, Order> = Account::try_from(info)?;
+        let (derived, _) = Pubkey::find_program_address(
+            &[
+                b"order_v1",
+                state_key.as_ref(),
+                order.owner.as_ref(),
+                &order.seq.to_le_bytes(),
+            ],
+            ctx.program_id,
+        );
+        if derived != info.key() {
+            return err!(OrderbookError::InvalidOrderPda);
+        }
+        if !order.is_open {
+            return err!(OrderbookError::IncompleteAuctionOrders);
+        }
+        // Batch markets only hold lit, unpegged, ungrouped orders: the book is empty when the
+        // mode is switched on, and dark, pegged and grouped placements require continuous mode.
+        if order.is_dark || order.peg_type != PEG_NONE || order.group != Pubkey::default() {
+            return err!(OrderbookError::InvalidMarketMode);
+        }
+        // A pass revoked since placement keeps the order out of the auction, but not the
+        // auction from running.
+        let admitted = stride == 3 || {
+            let pass = Account::<AccessPass>::try_from(&group[3]).ok();
+            ensure_access_pass(state, &pass, order.owner, ctx.program_id).is_ok()
+        };
+        orders.push(AuctionOrder {
+            order,
+            base_inco: &group[1],
+            quote_inco: &group[2],
+            admitted,
+        });
+    }
+
+    let mut bids: Vec<usize> = (0..orders.len())
+        .filter(|&i| orders[i].admitted && orders[i].order.side == 0)
+        .collect();
+    let mut asks: Vec<usize> = (0..orders.len())
+        .filter(|&i| orders[i].admitted && orders[i].order.side == 1)
+        .collect();
+    bids.sort_by_key(|&i| (std::cmp::Reverse(orders[i].order.price), orders[i].order.seq));
+    asks.sort_by_key(|&i| (orders[i].order.price, orders[i].order.seq));
+
+    let mut candidates: Vec<u64> = bids
+        .iter()
+        .chain(asks.iter())
+        .map(|&i| orders[i].order.price)
+        .filter(|&price| {
+            bids.iter().any(|&i| orders[i].order.price >= price)
+                && asks.iter().any(|&i| orders[i].order.price <= price)
+        })
+        .collect();
+    candidates.sort_unstable();
+    candidates.dedup();
+
+    let inco = ctx.accounts.inco_lightning_program.to_account_info();
+    let signer = ctx.accounts.matcher.to_account_info();
+    let op = || CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+
+    let zero: Euint128 = cpi::as_euint128(op(), 0)?;
+    // Running sums in priority order: `bid_depth[k]` is the size of the best k + 1 bids, so the
+    // bid size at or above a price is one lookup instead of one addition per order.
+    let depth = |side: &[usize]| -> Result<Vec<Euint128>> {
+        let mut sums = Vec::with_capacity(side.len());
+        let mut total = zero;
+        for &i in side {
+            total = cpi::e_add(op(), total, Euint128(orders[i].order.remaining_handle), 0)?;
+            sums.push(total);
+        }
+        Ok(sums)
+    };
+    let bid_depth = depth(&bids)?;
+    let ask_depth = depth(&asks)?;
+
+    let mut volume = zero;
+    let mut clearing_price = zero;
+    for &price in candidates.iter() {
+        // Every candidate has a bid at or above it and an ask at or below it.
+        let bids_in = bids.iter().take_while(|&&i| orders[i].order.price >= price).count();
+        let asks_in = asks.iter().take_while(|&&i| orders[i].order.price <= price).count();
+        let bid_size = bid_depth[bids_in - 1];
+        let ask_size = ask_depth[asks_in - 1];
+        let asks_short: Ebool = cpi::e_ge(op(), bid_size, ask_size, 0)?;
+        let crossed: Euint128 = cpi::e_select(op(), asks_short, ask_size, bid_size, 0)?;
+        // Only strictly more volume moves the price, so ties keep the lower candidate.
+        let keep: Ebool = cpi::e_ge(op(), volume, crossed, 0)?;
+        let candidate: Euint128 = cpi::as_euint128(op(), price as u128)?;
+        clearing_price = cpi::e_select(op(), keep, clearing_price, candidate, 0)?;
+        volume = cpi::e_select(op(), keep, volume, crossed, 0)?;
+    }
+
+    let vault_authority_bump = ctx.bumps.inco_vault_authority;
+    let vault_seeds: &[&[u8]] = &[
+        b"inco_vault_authority_v12",
+        state_key.as_ref(),
+        &[vault_authority_bump],
+    ];
+    ensure_inco_account(
+        &ctx.accounts.inco_base_vault.to_account_info(),
+        state.inco_vault_authority,
+        state.inco_base_mint,
+    )?;
+    ensure_inco_account(
+        &ctx.accounts.inco_quote_vault.to_account_info(),
+        state.inco_vault_authority,
+        state.inco_quote_mint,
+    )?;
+
+    // Fills per order, in book order; orders kept out of the auction fill nothing.
+    let mut fills: Vec<Option<Euint128>> = vec![None; orders.len()];
+    for side in [&bids, &asks] {
+        let mut left = volume;
+        for &i in side.iter() {
+            let order = &orders[i].order;
+            let limit: Euint128 = cpi::as_euint128(op(), order.price as u128)?;
+            let eligible: Ebool = if order.side == 0 {
+                cpi::e_ge(op(), limit, clearing_price, 0)?
+            } else {
+                cpi::e_ge(op(), clearing_price, limit, 0)?
+            };
+            let remaining = Euint128(order.remaining_handle);
+            let offered: Euint128 = cpi::e_select(op(), eligible, remaining, zero, 0)?;
+            let fits: Ebool = cpi::e_ge(op(), left, offered, 0)?;
+            let fill: Euint128 = cpi::e_select(op(), fits, offered, left, 0)?;
+            left = cpi::e_sub(op(), left, fill, 0)?;
+            fills[i] = Some(fill);
+        }
+    }
+
+    let mut grants: Vec<(Pubkey, u128)> = Vec::with_capacity(orders.len());
+    let mut audited: Vec<u128> = vec![clearing_price.0, volume.0];
+    for (entry, fill) in orders.iter_mut().zip(fills) {
+        let order = &mut entry.order;
+        let remaining = Euint128(order.remaining_handle);
+        let (refund, vault, destination, mint) = if order.side == 0 {
+            // The bid escrowed its limit on the whole size; all but the fill's cost goes back.
+            let limit: Euint128 = cpi::as_euint128(op(), order.price as u128)?;
+            let escrowed: Euint128 = cpi::e_mul(op(), remaining, limit, 0)?;
+            let refund = match fill {
+                Some(fill) => {
+                    let cost: Euint128 = cpi::e_mul(op(), fill, clearing_price, 0)?;
+                    cpi::e_sub(op(), escrowed, cost, 0)?
+                }
+                None => escrowed,
+            };
+            (refund, &ctx.accounts.inco_quote_vault, entry.quote_inco, state.inco_quote_mint)
+        } else {
+            let refund = match fill {
+                Some(fill) => cpi::e_sub(op(), remaining, fill, 0)?,
+                None => remaining,
+            };
+            (refund, &ctx.accounts.inco_base_vault, entry.base_inco, state.inco_base_mint)
+        };
+        ensure_inco_account(destination, order.owner, mint)?;
+        inco_token_cpi::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.inco_token_program.to_account_info(),
+                IncoTransfer {
+                    source: vault.to_account_info(),
+                    destination: destination.clone(),
+                    authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                    inco_lightning_program: inco.clone(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+                &[vault_seeds],
+            ),
+            handle_ciphertext(refund.0),
+            HANDLE_INPUT_TYPE,
+        )?;
+
+        if let Some(fill) = fill {
+            let payout = if order.side == 0 {
+                fill
+            } else {
+                cpi::e_mul(op(), fill, clearing_price, 0)?
+            };
+            let claim = accrue_claim(&inco, &signer, order, payout)?;
+            grants.push((order.owner, claim.0));
+            audited.push(fill.0);
+        }
+        order.is_open = false;
+        order.remaining_handle = 0;
+        state.open_orders = state.open_orders.saturating_sub(1);
+        // As in `cancel_order`, an order with a claim keeps counting until it is claimed.
+        if !order.is_filled {
+            state.open_escrows = state.open_escrows.saturating_sub(1);
+        }
+    }
+
+    let auditor_accounts = allow_auditor(
+        state.auditor,
+        allowance_accounts,
+        &audited,
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    allow_owners(
+        &allowance_accounts[..allowance_accounts.len() - auditor_accounts],
+        &grants,
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    for entry in orders.iter() {
+        entry.order.exit(ctx.program_id)?;
+    }
+
+    let result = &mut ctx.accounts.auction_result;
+    result.market = state_key;
+    result.auction_id = state.auction_id;
+    result.clearing_price_handle = clearing_price.0;
+    result.volume_handle = volume.0;
+    result.start_slot = state.auction_start_slot;
+    result.end_slot = slot;
+    result.orders = (bids.len() + asks.len()) as u32;
+    result.bump = ctx.bumps.auction_result;
+    result._padding = [0u8; 3];
+
+    state.auction_id = state.auction_id.wrapping_add(1);
+    state.auction_start_slot = slot;
+
+    // Order fees collected since the last auction go to the matcher.
+    let state_info = state.to_account_info();
+    let fees = state_info
+        .lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(state_info.data_len()));
+    **state_info.try_borrow_mut_lamports()? -= fees;
+    **ctx.accounts.matcher.to_account_info().try_borrow_mut_lamports()? += fees;
+    Ok(())
+}
+
+/// One order of the auction book with its owner's Inco accounts.
+struct AuctionOrder<'info> {
+    order: Account<'info, Order>,
+    base_inco: &'info AccountInfo<'info>,
+    quote_inco: &'info AccountInfo<'info>,
+    // False when the owner's access pass no longer admits it.
+    admitted: bool,
+}
+
+#[derive(Accounts)]
+pub struct RunAuction<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        init,
+        payer = matcher,
+        space = 8 + AuctionResult::LEN,
+        seeds = [b"auction_result_v1", state.key().as_ref(), &state.auction_id.to_le_bytes()],
+        bump
+    )]
+    pub auction_result: Account<'info, AuctionResult>,
+    #[account(mut)]
+    pub matcher: Signer<'info>,
+    #[account(
+        seeds = [b"inco_vault_authority_v12", state.key().as_ref()],
+        bump,
+        address = state.inco_vault_authority
+    )]
+    /// CHECK: PDA authority for Inco vaults
+    pub inco_vault_authority: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_base_vault)]
+    pub inco_base_vault: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_quote_vault)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub inco_token_program: Program<'info, IncoToken>,
+    /// CHECK: Inco Lightning program for encrypted operations
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+}
+
+fn ensure_inco_account(
+    info: &AccountInfo<'_>,
+    expected_owner: Pubkey,
+    expected_mint: Pubkey,
+) -> Result<()> {
+    if info.owner != &INCO_TOKEN_ID {
+        return err!(OrderbookError::InvalidIncoProgramOwner);
+    }
+    let data = info.try_borrow_data()?;
+    let decoded = IncoAccount::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(OrderbookError::InvalidIncoAccountData))?;
+
+    if decoded.owner != expected_owner {
+        return err!(OrderbookError::InvalidIncoAccountOwner);
+    }
+    if decoded.mint != expected_mint {
+        return err!(OrderbookError::InvalidIncoAccountMint);
+    }
+    Ok(())
+}
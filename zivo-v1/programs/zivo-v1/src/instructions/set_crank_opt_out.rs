@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::OrderbookError;
+use crate::state::{Order, OrderbookState};
+
+/// Lets the owner keep an order away from `crank_claim`, e.g. to claim to a different account or
+/// keep the order PDA around. The owner can still claim it with `maker_claim_filled_order`.
+pub(crate) fn handler(ctx: Context<SetCrankOptOut>, opt_out: bool) -> Result<()> {
+    let order = &mut ctx.accounts.order;
+
+    if order.owner != ctx.accounts.trader.key() {
+        return err!(OrderbookError::InvalidOrderOwner);
+    }
+    if order.is_claimed {
+        return err!(OrderbookError::OrderAlreadyClaimed);
+    }
+
+    order.crank_opt_out = opt_out;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetCrankOptOut<'info> {
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        mut,
+        seeds = [b"order_v1", state.key().as_ref(), trader.key().as_ref(), &order.seq.to_le_bytes()],
+        bump = order.bump
+    )]
+    pub order: Account<'info, Order>,
+    pub trader: Signer<'info>,
+}
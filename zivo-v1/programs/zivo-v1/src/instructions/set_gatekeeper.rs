@@ -0,0 +1,20 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::OrderbookError;
+use crate::state::OrderbookState;
+
+pub(crate) fn handler(ctx: Context<SetGatekeeper>, gatekeeper: Pubkey) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    if ctx.accounts.admin.key() != state.admin {
+        return err!(OrderbookError::UnauthorizedMatcher);
+    }
+    state.gatekeeper = gatekeeper;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetGatekeeper<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    pub admin: Signer<'info>,
+}
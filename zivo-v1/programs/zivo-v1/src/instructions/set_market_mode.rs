@@ -0,0 +1,35 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::OrderbookError;
+use crate::state::{OrderbookState, MARKET_MODE_BATCH_AUCTION, MARKET_MODE_CONTINUOUS};
+
+pub(crate) fn handler(ctx: Context<SetMarketMode>, market_mode: u8, auction_window_slots: u64) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    if ctx.accounts.admin.key() != state.admin {
+        return err!(OrderbookError::UnauthorizedMatcher);
+    }
+    if market_mode == MARKET_MODE_BATCH_AUCTION {
+        if auction_window_slots == 0 {
+            return err!(OrderbookError::InvalidMarketMode);
+        }
+    } else if market_mode != MARKET_MODE_CONTINUOUS {
+        return err!(OrderbookError::InvalidMarketMode);
+    }
+
+    // Batch books only hold orders `run_auction` can price and close, so continuous-mode orders
+    // (possibly dark, pegged or grouped) must be gone first.
+    if market_mode == MARKET_MODE_BATCH_AUCTION && state.open_orders > 0 {
+        return err!(OrderbookError::OpenOrdersOnBook);
+    }
+    state.market_mode = market_mode;
+    state.auction_window_slots = auction_window_slots;
+    state.auction_start_slot = Clock::get()?.slot;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetMarketMode<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    pub admin: Signer<'info>,
+}
@@ -0,0 +1,23 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::OrderbookError;
+use crate::state::{OrderbookState, MARKET_STATUS_CLOSED, MARKET_STATUS_OPEN};
+
+pub(crate) fn handler(ctx: Context<SetMarketStatus>, status: u8) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    if ctx.accounts.admin.key() != state.admin {
+        return err!(OrderbookError::UnauthorizedMatcher);
+    }
+    if status != MARKET_STATUS_OPEN && status != MARKET_STATUS_CLOSED {
+        return err!(OrderbookError::InvalidMarketStatus);
+    }
+    state.status = status;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetMarketStatus<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    pub admin: Signer<'info>,
+}
@@ -0,0 +1,28 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::OrderbookError;
+use crate::state::OrderbookState;
+
+pub(crate) fn handler(
+    ctx: Context<SetPriceFeed>,
+    max_staleness_slots: u64,
+    max_confidence_bps: u16,
+) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    if ctx.accounts.admin.key() != state.admin {
+        return err!(OrderbookError::UnauthorizedMatcher);
+    }
+    state.price_feed = ctx.accounts.price_feed.key();
+    state.max_staleness_slots = max_staleness_slots;
+    state.max_confidence_bps = max_confidence_bps;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetPriceFeed<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    /// CHECK: reference price account, read with the `PriceFeedData` layout at match time
+    pub price_feed: UncheckedAccount<'info>,
+    pub admin: Signer<'info>,
+}
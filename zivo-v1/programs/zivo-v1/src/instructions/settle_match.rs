@@ -0,0 +1,302 @@
+use anchor_lang::prelude::*;
+use inco_lightning::{program::IncoLightning, types::Euint128, ID as INCO_LIGHTNING_ID};
+use inco_token::{
+    cpi as inco_token_cpi,
+    cpi::accounts::IncoTransfer,
+    program::IncoToken,
+    IncoAccount,
+    ID as INCO_TOKEN_ID,
+};
+
+use crate::errors::OrderbookError;
+use crate::instructions::create_order_group::{fill_group_leg, pay_group_release};
+use crate::instructions::grant_order_view::{allow_auditor, allow_owners};
+use crate::instructions::issue_access_pass::ensure_access_pass;
+use crate::instructions::match_order::{accrue_claim, pay_bid_refund};
+use crate::state::{
+    handle_ciphertext, AccessPass, Order, OrderGroup, OrderbookState, PendingMatch,
+    HANDLE_INPUT_TYPE, MARKET_MODE_BATCH_AUCTION, MARKET_STATUS_OPEN, PENDING_MATCH_TIMEOUT_SLOTS,
+};
+
+/// Second half of a two-phase match. Moves the taker's fill out of the vault, returns the bid's
+/// prepared refund, stores the maker's claim, and applies the handles computed by
+/// `prepare_match` to both orders; both legs are the prepared fill handles, so nothing
+/// relayer-supplied moves funds. Fails if either order changed since preparation or the pending
+/// match timed out; such a match can only be discarded. The maker's new claim handle is only known here, so its owner and auditor
+/// allowances are granted here rather than by `prepare_match`.
+pub(crate) fn handler<'info>(ctx: Context<'_, '_, '_, 'info, SettleMatch<'info>>) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    let pending = &ctx.accounts.pending_match;
+    let order = &mut ctx.accounts.maker_order;
+    let taker_order = &mut ctx.accounts.taker_order;
+
+    if ctx.accounts.matcher.key() != state.admin {
+        return err!(OrderbookError::UnauthorizedMatcher);
+    }
+    if state.status != MARKET_STATUS_OPEN {
+        return err!(OrderbookError::MarketClosed);
+    }
+    // A match prepared before the market switched to batch mode must not bypass the auction.
+    if state.market_mode == MARKET_MODE_BATCH_AUCTION {
+        return err!(OrderbookError::InvalidMarketMode);
+    }
+    if Clock::get()?.slot > pending.prepared_slot.saturating_add(PENDING_MATCH_TIMEOUT_SLOTS) {
+        return err!(OrderbookError::PendingMatchExpired);
+    }
+    if !pending.snapshot_matches(order, taker_order) {
+        return err!(OrderbookError::PendingMatchStale);
+    }
+    // The taker's pass may have been revoked since `prepare_match`.
+    ensure_access_pass(
+        state,
+        &ctx.accounts.taker_access_pass,
+        taker_order.owner,
+        ctx.program_id,
+    )?;
+    let inco = ctx.accounts.inco_lightning_program.to_account_info();
+    let signer = ctx.accounts.matcher.to_account_info();
+    let maker_release = fill_group_leg(&inco, &signer, order, &mut ctx.accounts.maker_group)?;
+    let taker_release =
+        fill_group_leg(&inco, &signer, taker_order, &mut ctx.accounts.taker_group)?;
+
+    if order.is_dark {
+        // Dark fills execute at the maker's encrypted limit price.
+        order.exec_price_handle = order.price_handle;
+        taker_order.exec_price_handle = order.price_handle;
+    }
+    order.remaining_handle = pending.maker_remaining_handle;
+    taker_order.remaining_handle = pending.taker_remaining_handle;
+
+    ensure_inco_account(
+        &ctx.accounts.inco_base_vault,
+        state.inco_vault_authority,
+        state.inco_base_mint,
+    )?;
+    ensure_inco_account(
+        &ctx.accounts.inco_quote_vault,
+        state.inco_vault_authority,
+        state.inco_quote_mint,
+    )?;
+
+    let vault_authority_bump = ctx.bumps.inco_vault_authority;
+    let state_key = state.key();
+    let vault_seeds: &[&[u8]] = &[
+        b"inco_vault_authority_v12",
+        state_key.as_ref(),
+        &[vault_authority_bump],
+    ];
+
+    // Siblings of a leg that just filled are cancelled: the rest of its group's escrow goes
+    // back to the group owner now.
+    for (release, owner_quote_inco, owner_base_inco) in [
+        (maker_release, &ctx.accounts.maker_quote_inco, &ctx.accounts.maker_base_inco),
+        (taker_release, &ctx.accounts.taker_quote_inco, &ctx.accounts.taker_base_inco),
+    ] {
+        if let Some(release) = release {
+            pay_group_release(
+                &release,
+                state,
+                owner_quote_inco,
+                owner_base_inco,
+                &ctx.accounts.inco_quote_vault,
+                &ctx.accounts.inco_base_vault,
+                &ctx.accounts.inco_vault_authority.to_account_info(),
+                vault_seeds,
+                &ctx.accounts.inco_token_program.to_account_info(),
+                &inco,
+                &ctx.accounts.system_program.to_account_info(),
+            )?;
+        }
+    }
+
+    let claim = if order.side == 1 {
+        // Maker ask: base escrowed in base vault, taker quote escrowed in quote vault.
+        ensure_inco_account(
+            &ctx.accounts.taker_base_inco,
+            taker_order.owner,
+            state.inco_base_mint,
+        )?;
+        ensure_inco_account(
+            &ctx.accounts.maker_quote_inco,
+            order.owner,
+            state.inco_quote_mint,
+        )?;
+
+        inco_token_cpi::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.inco_token_program.to_account_info(),
+                IncoTransfer {
+                    source: ctx.accounts.inco_base_vault.to_account_info(),
+                    destination: ctx.accounts.taker_base_inco.to_account_info(),
+                    authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                    inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+                &[vault_seeds],
+            ),
+            handle_ciphertext(pending.fill_handle),
+            HANDLE_INPUT_TYPE,
+        )?;
+
+        accrue_claim(&inco, &signer, order, Euint128(pending.fill_quote_handle))?
+    } else {
+        // Maker bid: quote escrowed in quote vault, taker base escrowed in base vault.
+        ensure_inco_account(
+            &ctx.accounts.taker_quote_inco,
+            taker_order.owner,
+            state.inco_quote_mint,
+        )?;
+        ensure_inco_account(
+            &ctx.accounts.maker_base_inco,
+            order.owner,
+            state.inco_base_mint,
+        )?;
+
+        inco_token_cpi::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.inco_token_program.to_account_info(),
+                IncoTransfer {
+                    source: ctx.accounts.inco_quote_vault.to_account_info(),
+                    destination: ctx.accounts.taker_quote_inco.to_account_info(),
+                    authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                    inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+                &[vault_seeds],
+            ),
+            handle_ciphertext(pending.fill_quote_handle),
+            HANDLE_INPUT_TYPE,
+        )?;
+
+        accrue_claim(&inco, &signer, order, Euint128(pending.fill_handle))?
+    };
+
+    if pending.bid_refund_handle != 0 {
+        let (bidder, bidder_quote_inco) = if order.side == 0 {
+            (order.owner, &ctx.accounts.maker_quote_inco)
+        } else {
+            (taker_order.owner, &ctx.accounts.taker_quote_inco)
+        };
+        pay_bid_refund(
+            Euint128(pending.bid_refund_handle),
+            bidder,
+            state,
+            bidder_quote_inco,
+            &ctx.accounts.inco_quote_vault,
+            &ctx.accounts.inco_vault_authority.to_account_info(),
+            vault_seeds,
+            &ctx.accounts.inco_token_program.to_account_info(),
+            &inco,
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+    }
+
+    // Remaining accounts: [maker claim allowance, maker owner], then the auditor pair if the
+    // market has one.
+    let auditor_accounts = allow_auditor(
+        state.auditor,
+        ctx.remaining_accounts,
+        &[claim.0],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    let owner_accounts = &ctx.remaining_accounts[..ctx.remaining_accounts.len() - auditor_accounts];
+    allow_owners(
+        owner_accounts,
+        &[(order.owner, claim.0)],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    // As in `match_order`, both orders stay open with their remaining size.
+    // A dark match has no public price to report.
+    if !order.is_dark {
+        state.last_trade_price = pending.price;
+    }
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SettleMatch<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        mut,
+        close = matcher,
+        has_one = matcher,
+        constraint = pending_match.market == state.key() @ OrderbookError::InvalidPendingMatch,
+        seeds = [b"pending_match_v1", maker_order.key().as_ref(), taker_order.key().as_ref()],
+        bump = pending_match.bump
+    )]
+    pub pending_match: Account<'info, PendingMatch>,
+    #[account(mut)]
+    pub maker_order: Account<'info, Order>,
+    #[account(mut)]
+    pub taker_order: Account<'info, Order>,
+    #[account(mut)]
+    pub matcher: Signer<'info>,
+    /// Required when the market has a gatekeeper.
+    pub taker_access_pass: Option<Account<'info, AccessPass>>,
+    /// Required when the maker order belongs to an order group.
+    #[account(mut)]
+    pub maker_group: Option<Account<'info, OrderGroup>>,
+    /// Required when the taker order belongs to an order group.
+    #[account(mut)]
+    pub taker_group: Option<Account<'info, OrderGroup>>,
+    #[account(
+        mut,
+        seeds = [b"inco_vault_authority_v12", state.key().as_ref()],
+        bump,
+        address = state.inco_vault_authority
+    )]
+    /// CHECK: PDA authority for Inco vaults
+    pub inco_vault_authority: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_base_vault)]
+    pub inco_base_vault: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_quote_vault)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    /// CHECK: Maker Inco accounts
+    #[account(mut)]
+    pub maker_base_inco: UncheckedAccount<'info>,
+    /// CHECK: Maker Inco accounts
+    #[account(mut)]
+    pub maker_quote_inco: UncheckedAccount<'info>,
+    /// CHECK: Taker Inco accounts
+    #[account(mut)]
+    pub taker_base_inco: UncheckedAccount<'info>,
+    /// CHECK: Taker Inco accounts
+    #[account(mut)]
+    pub taker_quote_inco: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub inco_token_program: Program<'info, IncoToken>,
+    /// CHECK: Inco Lightning program
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+}
+
+fn ensure_inco_account(
+    account: &UncheckedAccount<'_>,
+    expected_owner: Pubkey,
+    expected_mint: Pubkey,
+) -> Result<()> {
+    let info = account.to_account_info();
+    if info.owner != &INCO_TOKEN_ID {
+        return err!(OrderbookError::InvalidIncoProgramOwner);
+    }
+    let data = info.try_borrow_data()?;
+    let decoded = IncoAccount::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(OrderbookError::InvalidIncoAccountData))?;
+
+    if decoded.owner != expected_owner {
+        return err!(OrderbookError::InvalidIncoAccountOwner);
+    }
+    if decoded.mint != expected_mint {
+        return err!(OrderbookError::InvalidIncoAccountMint);
+    }
+    Ok(())
+}
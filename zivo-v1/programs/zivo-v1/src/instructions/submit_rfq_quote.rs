@@ -0,0 +1,210 @@
+use anchor_lang::prelude::*;
+use inco_lightning::{
+    cpi,
+    cpi::accounts::Operation,
+    program::IncoLightning,
+    types::Euint128,
+    ID as INCO_LIGHTNING_ID,
+};
+use inco_token::{
+    cpi as inco_token_cpi,
+    cpi::accounts::IncoTransfer,
+    program::IncoToken,
+    IncoAccount,
+    ID as INCO_TOKEN_ID,
+};
+
+use crate::errors::OrderbookError;
+use crate::instructions::grant_order_view::{allow_auditor, allow_owners};
+use crate::instructions::issue_access_pass::ensure_access_pass;
+use crate::instructions::place_order::{covered_escrow, OrderEscrow};
+use crate::state::{
+    ensure_user_input_type, handle_ciphertext, AccessPass, OrderbookState, RfqQuote, RfqRequest,
+    HANDLE_INPUT_TYPE, MAX_ESCROW_CIPHERTEXT_LEN, RFQ_STATUS_OPEN,
+};
+
+/// A maker answers an RFQ by escrowing the asset the taker asked for (`delivery`) and stating
+/// the encrypted amount it wants back (`payment`). Both stay encrypted; the taker is allowed to
+/// decrypt them through the optional owner-style pairs at the front of the remaining accounts.
+/// Only a delivery the maker's balance covers is escrowed; otherwise both legs are stored as 0.
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, SubmitRfqQuote<'info>>,
+    delivery_ciphertext: Vec<u8>,
+    payment_ciphertext: Vec<u8>,
+    input_type: u8,
+) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    let request = &mut ctx.accounts.rfq_request;
+    let inco = ctx.accounts.inco_lightning_program.to_account_info();
+    let signer = ctx.accounts.maker.to_account_info();
+
+    if request.market != state.key() {
+        return err!(OrderbookError::InvalidRfq);
+    }
+    if request.status != RFQ_STATUS_OPEN {
+        return err!(OrderbookError::RfqNotOpen);
+    }
+    if Clock::get()?.slot > request.expiry_slot {
+        return err!(OrderbookError::RfqExpired);
+    }
+    if ctx.accounts.maker.key() == request.taker {
+        return err!(OrderbookError::InvalidRfq);
+    }
+    ensure_access_pass(
+        state,
+        &ctx.accounts.access_pass,
+        ctx.accounts.maker.key(),
+        ctx.program_id,
+    )?;
+    if delivery_ciphertext.is_empty()
+        || payment_ciphertext.is_empty()
+        || delivery_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN
+        || payment_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN
+    {
+        return err!(OrderbookError::InvalidEscrowCiphertext);
+    }
+    ensure_user_input_type(input_type)?;
+
+    let delivery: Euint128 = cpi::new_euint128(
+        CpiContext::new(inco.clone(), Operation { signer: signer.clone() }),
+        delivery_ciphertext,
+        input_type,
+    )?;
+    let payment: Euint128 = cpi::new_euint128(
+        CpiContext::new(inco.clone(), Operation { signer: signer.clone() }),
+        payment_ciphertext,
+        input_type,
+    )?;
+
+    // Taker buying base gets base from the maker; taker selling base gets quote.
+    let (maker_source, vault, mint) = if request.side == 0 {
+        (
+            &ctx.accounts.maker_base_inco,
+            &ctx.accounts.inco_base_vault,
+            state.inco_base_mint,
+        )
+    } else {
+        (
+            &ctx.accounts.maker_quote_inco,
+            &ctx.accounts.inco_quote_vault,
+            state.inco_quote_mint,
+        )
+    };
+    ensure_inco_account(maker_source, ctx.accounts.maker.key(), mint)?;
+    ensure_inco_account(vault, state.inco_vault_authority, mint)?;
+
+    // The quote records what was actually escrowed: an uncovered delivery moves nothing, so both
+    // legs drop to 0 and accepting the quote trades nothing.
+    let OrderEscrow {
+        remaining: payment_handle,
+        escrow: delivery_handle,
+    } = covered_escrow(&inco, &signer, maker_source, payment, delivery)?;
+    inco_token_cpi::transfer(
+        CpiContext::new(
+            ctx.accounts.inco_token_program.to_account_info(),
+            IncoTransfer {
+                source: maker_source.to_account_info(),
+                destination: vault.to_account_info(),
+                authority: ctx.accounts.maker.to_account_info(),
+                inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+        ),
+        handle_ciphertext(delivery_handle.0),
+        HANDLE_INPUT_TYPE,
+    )?;
+
+    let quote = &mut ctx.accounts.rfq_quote;
+    quote.request = request.key();
+    quote.maker = ctx.accounts.maker.key();
+    quote.bump = ctx.bumps.rfq_quote;
+    quote._padding = [0u8; 7];
+    quote.delivery_handle = delivery_handle.0;
+    quote.payment_handle = payment_handle.0;
+
+    // Auditor allowances sit at the tail of the remaining accounts.
+    let auditor_accounts = allow_auditor(
+        state.auditor,
+        ctx.remaining_accounts,
+        &[delivery_handle.0, payment_handle.0],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    let taker_accounts = &ctx.remaining_accounts[..ctx.remaining_accounts.len() - auditor_accounts];
+
+    // The taker must be able to compare quotes: one pair for delivery, one for payment.
+    allow_owners(
+        taker_accounts,
+        &[(request.taker, delivery_handle.0), (request.taker, payment_handle.0)],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    request.open_quotes = request.open_quotes.saturating_add(1);
+    state.open_escrows = state.open_escrows.saturating_add(1);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SubmitRfqQuote<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        mut,
+        seeds = [b"rfq_request_v1", state.key().as_ref(), rfq_request.taker.as_ref(), &rfq_request.rfq_id.to_le_bytes()],
+        bump = rfq_request.bump
+    )]
+    pub rfq_request: Account<'info, RfqRequest>,
+    #[account(
+        init,
+        payer = maker,
+        space = 8 + RfqQuote::LEN,
+        seeds = [b"rfq_quote_v1", rfq_request.key().as_ref(), maker.key().as_ref()],
+        bump
+    )]
+    pub rfq_quote: Account<'info, RfqQuote>,
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    /// Required when the market has a gatekeeper.
+    pub access_pass: Option<Account<'info, AccessPass>>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_base_vault)]
+    pub inco_base_vault: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_quote_vault)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    /// CHECK: Maker Inco accounts
+    #[account(mut)]
+    pub maker_base_inco: UncheckedAccount<'info>,
+    /// CHECK: Maker Inco accounts
+    #[account(mut)]
+    pub maker_quote_inco: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub inco_token_program: Program<'info, IncoToken>,
+    /// CHECK: Inco Lightning program for encrypted operations
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+}
+
+fn ensure_inco_account(
+    account: &UncheckedAccount<'_>,
+    expected_owner: Pubkey,
+    expected_mint: Pubkey,
+) -> Result<()> {
+    let info = account.to_account_info();
+    if info.owner != &INCO_TOKEN_ID {
+        return err!(OrderbookError::InvalidIncoProgramOwner);
+    }
+    let data = info.try_borrow_data()?;
+    let decoded = IncoAccount::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(OrderbookError::InvalidIncoAccountData))?;
+
+    if decoded.owner != expected_owner {
+        return err!(OrderbookError::InvalidIncoAccountOwner);
+    }
+    if decoded.mint != expected_mint {
+        return err!(OrderbookError::InvalidIncoAccountMint);
+    }
+    Ok(())
+}
@@ -0,0 +1,219 @@
+use anchor_lang::prelude::*;
+use inco_token::{
+    cpi as inco_token_cpi,
+    cpi::accounts::IncoTransfer,
+    program::IncoToken,
+    IncoAccount,
+    ID as INCO_TOKEN_ID,
+};
+use inco_lightning::{program::IncoLightning, ID as INCO_LIGHTNING_ID};
+
+use crate::errors::OrderbookError;
+use crate::instructions::cancel_order::order_refund;
+use crate::instructions::create_order_group::{cancel_group_leg, pay_group_release};
+use crate::instructions::grant_order_view::allow_auditor;
+use crate::state::{
+    handle_ciphertext, Order, OrderGroup, OrderbookState, HANDLE_INPUT_TYPE,
+    MARKET_STATUS_CLOSED, MAX_ESCROW_CIPHERTEXT_LEN,
+};
+
+/// Permissionless: winds one order down on a closed market so `close_market` can run. An open
+/// order is refunded as in `cancel_order` (a group leg through its group, like a cancel), any
+/// unclaimed fill is paid to the owner as in `crank_claim`, and the order account is closed to
+/// whoever paid its rent. The vaults close with the market, so orders opted out of
+/// `crank_claim` are paid too. Orders already closed and claimed are only closed.
+///
+/// An open sibling leg of a group that is no longer active holds nothing; `sweep_order_group`
+/// closes it first.
+pub(crate) fn handler<'info>(ctx: Context<'_, '_, '_, 'info, SweepMarketOrder<'info>>) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    let order = &mut ctx.accounts.order;
+
+    if state.status != MARKET_STATUS_CLOSED {
+        return err!(OrderbookError::MarketNotClosed);
+    }
+    ensure_inco_account(&ctx.accounts.owner_base_inco, order.owner, state.inco_base_mint)?;
+    ensure_inco_account(&ctx.accounts.owner_quote_inco, order.owner, state.inco_quote_mint)?;
+    ensure_inco_account(
+        &ctx.accounts.inco_base_vault,
+        state.inco_vault_authority,
+        state.inco_base_mint,
+    )?;
+    ensure_inco_account(
+        &ctx.accounts.inco_quote_vault,
+        state.inco_vault_authority,
+        state.inco_quote_mint,
+    )?;
+    // Bids escrow and get refunded quote and claim base; asks the other way round.
+    let (refund_vault, refund_destination, claim_vault, claim_destination) = match order.side {
+        0 => (
+            &ctx.accounts.inco_quote_vault,
+            &ctx.accounts.owner_quote_inco,
+            &ctx.accounts.inco_base_vault,
+            &ctx.accounts.owner_base_inco,
+        ),
+        1 => (
+            &ctx.accounts.inco_base_vault,
+            &ctx.accounts.owner_base_inco,
+            &ctx.accounts.inco_quote_vault,
+            &ctx.accounts.owner_quote_inco,
+        ),
+        _ => return err!(OrderbookError::InvalidSide),
+    };
+
+    let vault_authority_bump = ctx.bumps.inco_vault_authority;
+    let state_key = state.key();
+    let vault_seeds: &[&[u8]] = &[
+        b"inco_vault_authority_v12",
+        state_key.as_ref(),
+        &[vault_authority_bump],
+    ];
+    let inco = ctx.accounts.inco_lightning_program.to_account_info();
+    let signer = ctx.accounts.cranker.to_account_info();
+    let holds_escrow = order.is_open || (order.is_filled && !order.is_claimed);
+
+    if order.is_open {
+        let release = cancel_group_leg(&inco, &signer, order, &mut ctx.accounts.order_group)?;
+        let refund = order_refund(&inco, &signer, order)?;
+        inco_token_cpi::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.inco_token_program.to_account_info(),
+                IncoTransfer {
+                    source: refund_vault.to_account_info(),
+                    destination: refund_destination.to_account_info(),
+                    authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                    inco_lightning_program: inco.clone(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+                &[vault_seeds],
+            ),
+            handle_ciphertext(refund.0),
+            HANDLE_INPUT_TYPE,
+        )?;
+        if let Some(release) = release {
+            pay_group_release(
+                &release,
+                state,
+                &ctx.accounts.owner_quote_inco,
+                &ctx.accounts.owner_base_inco,
+                &ctx.accounts.inco_quote_vault,
+                &ctx.accounts.inco_base_vault,
+                &ctx.accounts.inco_vault_authority.to_account_info(),
+                vault_seeds,
+                &ctx.accounts.inco_token_program.to_account_info(),
+                &inco,
+                &ctx.accounts.system_program.to_account_info(),
+            )?;
+        }
+        allow_auditor(
+            state.auditor,
+            ctx.remaining_accounts,
+            &[refund.0],
+            &inco,
+            &signer,
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        order.is_open = false;
+        order.remaining_handle = 0;
+        state.open_orders = state.open_orders.saturating_sub(1);
+    }
+
+    if order.is_filled && !order.is_claimed {
+        if order.claim_ciphertext.is_empty()
+            || order.claim_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN
+        {
+            return err!(OrderbookError::InvalidEscrowCiphertext);
+        }
+        inco_token_cpi::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.inco_token_program.to_account_info(),
+                IncoTransfer {
+                    source: claim_vault.to_account_info(),
+                    destination: claim_destination.to_account_info(),
+                    authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                    inco_lightning_program: inco.clone(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+                &[vault_seeds],
+            ),
+            order.claim_ciphertext.clone(),
+            order.claim_input_type,
+        )?;
+        order.is_claimed = true;
+    }
+
+    if holds_escrow {
+        state.open_escrows = state.open_escrows.saturating_sub(1);
+    }
+    order.close(ctx.accounts.rent_payer.to_account_info())
+}
+
+#[derive(Accounts)]
+pub struct SweepMarketOrder<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"order_v1", state.key().as_ref(), owner.key().as_ref(), &order.seq.to_le_bytes()],
+        bump = order.bump
+    )]
+    pub order: Account<'info, Order>,
+    /// Required when an open order belongs to an order group.
+    #[account(mut)]
+    pub order_group: Option<Account<'info, OrderGroup>>,
+    /// CHECK: order owner stored in order
+    pub owner: UncheckedAccount<'info>,
+    /// CHECK: account that paid the order's rent (the owner unless recorded otherwise)
+    #[account(mut, address = order.rent_recipient() @ OrderbookError::InvalidRentPayer)]
+    pub rent_payer: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"inco_vault_authority_v12", state.key().as_ref()],
+        bump,
+        address = state.inco_vault_authority
+    )]
+    /// CHECK: PDA authority for Inco vaults
+    pub inco_vault_authority: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_base_vault)]
+    pub inco_base_vault: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_quote_vault)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    /// CHECK: Owner Inco accounts
+    #[account(mut)]
+    pub owner_base_inco: UncheckedAccount<'info>,
+    /// CHECK: Owner Inco accounts
+    #[account(mut)]
+    pub owner_quote_inco: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub inco_token_program: Program<'info, IncoToken>,
+    /// CHECK: Inco Lightning program
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+}
+
+fn ensure_inco_account(
+    account: &UncheckedAccount<'_>,
+    expected_owner: Pubkey,
+    expected_mint: Pubkey,
+) -> Result<()> {
+    let info = account.to_account_info();
+    if info.owner != &INCO_TOKEN_ID {
+        return err!(OrderbookError::InvalidIncoProgramOwner);
+    }
+    let data = info.try_borrow_data()?;
+    let decoded = IncoAccount::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(OrderbookError::InvalidIncoAccountData))?;
+
+    if decoded.owner != expected_owner {
+        return err!(OrderbookError::InvalidIncoAccountOwner);
+    }
+    if decoded.mint != expected_mint {
+        return err!(OrderbookError::InvalidIncoAccountMint);
+    }
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::OrderbookError;
+use crate::state::{Order, OrderGroup, OrderbookState, GROUP_STATUS_ACTIVE, GROUP_STATUS_FILLED};
+
+/// Permissionless: tidies up a filled or cancelled group. The remaining accounts must be the
+/// group's leg orders in `legs` order (at most `MAX_GROUP_LEGS`). The group's escrow was already
+/// returned when it stopped being active, so open sibling legs only hold a stale reservation;
+/// they are closed here. Once no leg is open the group closes; while the filled leg is still
+/// open the group stays, and the sweep can be repeated after that leg resolves. Legs whose
+/// account is already gone are skipped.
+pub(crate) fn handler<'info>(ctx: Context<'_, '_, 'info, 'info, SweepOrderGroup<'info>>) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    let group = &mut ctx.accounts.order_group;
+
+    if group.market != state.key() || group.status == GROUP_STATUS_ACTIVE {
+        return err!(OrderbookError::InvalidOrderGroup);
+    }
+    if ctx.remaining_accounts.len() != group.legs.len() {
+        return err!(OrderbookError::InvalidOrderGroup);
+    }
+
+    let state_key = state.key();
+    let group_key = group.key();
+    let mut open_legs = 0usize;
+    for (seq, info) in group.legs.iter().zip(ctx.remaining_accounts.iter()) {
+        let (derived, _) = Pubkey::find_program_address(
+            &[
+                b"order_v1",
+                state_key.as_ref(),
+                group.owner.as_ref(),
+                &seq.to_le_bytes(),
+            ],
+            ctx.program_id,
+        );
+        if derived != info.key() {
+            return err!(OrderbookError::InvalidOrderPda);
+        }
+        // `sweep_market_order` may already have closed the leg on a closed market.
+        if info.data_is_empty() {
+            continue;
+        }
+        let mut order: Account<'info, Order> = Account::try_from(info)?;
+        if order.group != group_key {
+            return err!(OrderbookError::InvalidOrderGroup);
+        }
+
+        if order.is_open && group.status == GROUP_STATUS_FILLED && order.seq == group.filled_seq {
+            open_legs += 1;
+            continue;
+        }
+        order.remaining_handle = 0;
+        if order.is_open {
+            order.is_open = false;
+            state.open_escrows = state.open_escrows.saturating_sub(1);
+            state.open_orders = state.open_orders.saturating_sub(1);
+        }
+        order.exit(ctx.program_id)?;
+    }
+    if open_legs > 0 {
+        return Ok(());
+    }
+
+    state.open_escrows = state.open_escrows.saturating_sub(1);
+    group.close(ctx.accounts.owner.to_account_info())
+}
+
+#[derive(Accounts)]
+pub struct SweepOrderGroup<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"order_group_v1", state.key().as_ref(), owner.key().as_ref(), &order_group.group_id.to_le_bytes()],
+        bump = order_group.bump
+    )]
+    pub order_group: Account<'info, OrderGroup>,
+    /// CHECK: group owner stored in the group; receives the group rent
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+    pub cranker: Signer<'info>,
+}
@@ -0,0 +1,98 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::OrderbookError;
+use crate::instructions::place_order::add_book_order;
+use crate::instructions::issue_access_pass::ensure_access_pass;
+use crate::oracle::load_price_feed;
+use crate::state::{
+    AccessPass, Order, OrderbookState, TriggerOrder, MARKET_STATUS_OPEN,
+    TRIGGER_REF_PRICE_FEED, TRIGGER_WHEN_AT_OR_ABOVE,
+};
+
+/// Permissionless crank: once the feed price meets the trigger condition, the escrowed
+/// trigger order becomes a live resting `Order`. The trigger PDA (which prefunded the order
+/// rent at placement) is closed to the cranker.
+pub(crate) fn handler(ctx: Context<TriggerOrderCrank>) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    let trigger = &ctx.accounts.trigger_order;
+
+    if state.status != MARKET_STATUS_OPEN {
+        return err!(OrderbookError::MarketClosed);
+    }
+    // The owner must still hold a pass when the order goes live, not only when it was placed.
+    ensure_access_pass(
+        state,
+        &ctx.accounts.access_pass,
+        trigger.owner,
+        ctx.program_id,
+    )?;
+
+    // Triggers placed against the retired last-trade reference never fire; the owner cancels.
+    if trigger.reference != TRIGGER_REF_PRICE_FEED {
+        return err!(OrderbookError::InvalidTrigger);
+    }
+    let reference_price = load_price_feed(state, &ctx.accounts.price_feed)?.price;
+    if reference_price == 0 {
+        return err!(OrderbookError::TriggerNotMet);
+    }
+    let met = if trigger.condition == TRIGGER_WHEN_AT_OR_ABOVE {
+        reference_price >= trigger.trigger_price
+    } else {
+        reference_price <= trigger.trigger_price
+    };
+    if !met {
+        return err!(OrderbookError::TriggerNotMet);
+    }
+
+    ctx.accounts.order.set_inner(Order::init(
+        state,
+        trigger.owner,
+        trigger.side,
+        trigger.price,
+        trigger.remaining_handle,
+        ctx.bumps.order,
+        trigger.owner,
+    ));
+
+    state.order_seq = state.order_seq.wrapping_add(1);
+    add_book_order(
+        state,
+        trigger.side,
+        trigger.price,
+        &ctx.accounts.cranker.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct TriggerOrderCrank<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        mut,
+        close = cranker,
+        has_one = owner,
+        constraint = trigger_order.market == state.key() @ OrderbookError::InvalidTrigger,
+        seeds = [b"trigger_order_v1", state.key().as_ref(), owner.key().as_ref(), &trigger_order.seq.to_le_bytes()],
+        bump = trigger_order.bump
+    )]
+    pub trigger_order: Account<'info, TriggerOrder>,
+    #[account(
+        init,
+        payer = cranker,
+        space = 8 + Order::LEN,
+        seeds = [b"order_v1", state.key().as_ref(), owner.key().as_ref(), &state.order_seq.to_le_bytes()],
+        bump
+    )]
+    pub order: Account<'info, Order>,
+    /// CHECK: trigger owner stored in trigger order
+    pub owner: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+    /// CHECK: market reference price account, which every trigger fires from
+    pub price_feed: Option<UncheckedAccount<'info>>,
+    /// Required when the market has a gatekeeper.
+    pub access_pass: Option<Account<'info, AccessPass>>,
+    pub system_program: Program<'info, System>,
+}
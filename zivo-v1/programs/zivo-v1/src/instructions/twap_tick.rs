@@ -0,0 +1,154 @@
+use anchor_lang::prelude::*;
+use inco_lightning::{
+    cpi,
+    cpi::accounts::Operation,
+    program::IncoLightning,
+    types::{Ebool, Euint128},
+    ID as INCO_LIGHTNING_ID,
+};
+
+use crate::errors::OrderbookError;
+use crate::instructions::place_order::add_book_order;
+use crate::instructions::grant_order_view::{allow_auditor, allow_owners};
+use crate::instructions::issue_access_pass::ensure_access_pass;
+use crate::state::{AccessPass, Order, OrderbookState, TwapOrder, MARKET_STATUS_OPEN};
+
+/// Permissionless crank: releases the next slice of a TWAP parent as a live `Order` once
+/// `next_slot` is reached. The cranker is refunded the slice's prefunded rent, and the
+/// parent closes back to its owner after the last slice.
+pub(crate) fn handler<'info>(ctx: Context<'_, '_, '_, 'info, TwapTick<'info>>) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    let twap = &mut ctx.accounts.twap_order;
+    let inco = ctx.accounts.inco_lightning_program.to_account_info();
+    let signer = ctx.accounts.cranker.to_account_info();
+
+    if state.status != MARKET_STATUS_OPEN {
+        return err!(OrderbookError::MarketClosed);
+    }
+    if twap.slices_remaining == 0 {
+        return err!(OrderbookError::InvalidTwapParams);
+    }
+    // Each slice is a new order, so the owner must still hold a pass.
+    ensure_access_pass(
+        state,
+        &ctx.accounts.access_pass,
+        twap.owner,
+        ctx.program_id,
+    )?;
+    let slot = Clock::get()?.slot;
+    if slot < twap.next_slot {
+        return err!(OrderbookError::TwapSliceNotDue);
+    }
+
+    // The last slice releases whatever is left; earlier ones take min(slice, unreleased).
+    let slice: Euint128 = if twap.slices_remaining == 1 {
+        Euint128(twap.unreleased_handle)
+    } else {
+        let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+        let has_slice: Ebool = cpi::e_ge(
+            cpi_ctx,
+            Euint128(twap.unreleased_handle),
+            Euint128(twap.slice_handle),
+            0,
+        )?;
+        let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+        cpi::e_select(
+            cpi_ctx,
+            has_slice,
+            Euint128(twap.slice_handle),
+            Euint128(twap.unreleased_handle),
+            0,
+        )?
+    };
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let unreleased: Euint128 = cpi::e_sub(cpi_ctx, Euint128(twap.unreleased_handle), slice, 0)?;
+
+    ctx.accounts.order.set_inner(Order::init(
+        state,
+        twap.owner,
+        twap.side,
+        twap.price,
+        slice.0,
+        ctx.bumps.order,
+        twap.owner,
+    ));
+
+    twap.unreleased_handle = unreleased.0;
+    twap.slices_remaining -= 1;
+    twap.next_slot = slot.saturating_add(twap.interval_slots);
+
+    // Auditor allowances sit at the tail of the remaining accounts.
+    let auditor_accounts = allow_auditor(
+        state.auditor,
+        ctx.remaining_accounts,
+        &[slice.0, unreleased.0],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    let owner_accounts = &ctx.remaining_accounts[..ctx.remaining_accounts.len() - auditor_accounts];
+
+    // The owner must be able to decrypt the released slice and the unreleased remainder.
+    allow_owners(
+        owner_accounts,
+        &[(twap.owner, slice.0), (twap.owner, unreleased.0)],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    // The cranker paid the slice order's rent; refund it from the parent's prefund.
+    let order_rent = Rent::get()?.minimum_balance(8 + Order::LEN);
+    let twap_info = twap.to_account_info();
+    **twap_info.try_borrow_mut_lamports()? -= order_rent;
+    **ctx.accounts.cranker.to_account_info().try_borrow_mut_lamports()? += order_rent;
+
+    if twap.slices_remaining == 0 {
+        twap.close(ctx.accounts.owner.to_account_info())?;
+        state.open_escrows = state.open_escrows.saturating_sub(1);
+    }
+
+    state.order_seq = state.order_seq.wrapping_add(1);
+    add_book_order(
+        state,
+        twap.side,
+        twap.price,
+        &ctx.accounts.cranker.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    state.open_escrows = state.open_escrows.saturating_add(1);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct TwapTick<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        mut,
+        has_one = owner,
+        constraint = twap_order.market == state.key() @ OrderbookError::InvalidTwapParams,
+        seeds = [b"twap_order_v1", state.key().as_ref(), owner.key().as_ref(), &twap_order.seq.to_le_bytes()],
+        bump = twap_order.bump
+    )]
+    pub twap_order: Account<'info, TwapOrder>,
+    #[account(
+        init,
+        payer = cranker,
+        space = 8 + Order::LEN,
+        seeds = [b"order_v1", state.key().as_ref(), owner.key().as_ref(), &state.order_seq.to_le_bytes()],
+        bump
+    )]
+    pub order: Account<'info, Order>,
+    /// CHECK: TWAP owner stored in the parent; receives the parent's rent after the last slice
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+    /// Required when the market has a gatekeeper.
+    pub access_pass: Option<Account<'info, AccessPass>>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: Inco Lightning program for encrypted operations
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+}
@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::OrderbookError;
+use crate::instructions::initialize_protocol::validate_config;
+use crate::state::ProtocolConfig;
+
+pub(crate) fn handler(
+    ctx: Context<UpdateProtocolConfig>,
+    new_admin: Pubkey,
+    default_maker_fee_bps: u16,
+    default_taker_fee_bps: u16,
+    market_creation_policy: u8,
+    listing_fee_lamports: u64,
+) -> Result<()> {
+    let config = &mut ctx.accounts.protocol_config;
+    if ctx.accounts.admin.key() != config.admin {
+        return err!(OrderbookError::UnauthorizedProtocolAdmin);
+    }
+    validate_config(default_maker_fee_bps, default_taker_fee_bps, market_creation_policy)?;
+
+    config.admin = new_admin;
+    config.treasury = ctx.accounts.treasury.key();
+    config.default_maker_fee_bps = default_maker_fee_bps;
+    config.default_taker_fee_bps = default_taker_fee_bps;
+    config.market_creation_policy = market_creation_policy;
+    config.listing_fee_lamports = listing_fee_lamports;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UpdateProtocolConfig<'info> {
+    #[account(mut, seeds = [b"protocol_config_v1"], bump = protocol_config.bump)]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    /// CHECK: receives listing fees
+    pub treasury: UncheckedAccount<'info>,
+    pub admin: Signer<'info>,
+}
@@ -0,0 +1,205 @@
+use anchor_lang::prelude::*;
+use inco_lightning::{
+    cpi,
+    cpi::accounts::Operation,
+    program::IncoLightning,
+    types::{Ebool, Euint128},
+    ID as INCO_LIGHTNING_ID,
+};
+use inco_token::{
+    cpi as inco_token_cpi,
+    cpi::accounts::IncoTransfer,
+    program::IncoToken,
+    IncoAccount,
+    ID as INCO_TOKEN_ID,
+};
+
+use crate::errors::OrderbookError;
+use crate::instructions::grant_order_view::{allow_auditor, allow_owners};
+use crate::state::{
+    ensure_user_input_type, handle_ciphertext, OrderbookState, TraderNonces, HANDLE_INPUT_TYPE,
+    MAX_ESCROW_CIPHERTEXT_LEN,
+};
+
+/// Returns part of a signed-order balance to the trader. When the balance does not cover the
+/// requested amount, the debit is zero; the vault transfers the debited handle, so an uncovered
+/// withdrawal moves nothing. An empty `amount_ciphertext` withdraws the whole balance of that
+/// side; once both sides are withdrawn this way, the balance no longer counts as an open escrow.
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, WithdrawSignedEscrow<'info>>,
+    side: u8,
+    amount_ciphertext: Vec<u8>,
+    input_type: u8,
+) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    let inco = ctx.accounts.inco_lightning_program.to_account_info();
+    let signer = ctx.accounts.trader.to_account_info();
+
+    if amount_ciphertext.len() > MAX_ESCROW_CIPHERTEXT_LEN {
+        return err!(OrderbookError::InvalidEscrowCiphertext);
+    }
+    let full = amount_ciphertext.is_empty();
+
+    let (trader_dest, vault, mint) = if side == 0 {
+        (
+            &ctx.accounts.trader_quote_inco,
+            &ctx.accounts.inco_quote_vault,
+            state.inco_quote_mint,
+        )
+    } else if side == 1 {
+        (
+            &ctx.accounts.trader_base_inco,
+            &ctx.accounts.inco_base_vault,
+            state.inco_base_mint,
+        )
+    } else {
+        return err!(OrderbookError::InvalidSide);
+    };
+    ensure_inco_account(trader_dest, ctx.accounts.trader.key(), mint)?;
+    ensure_inco_account(vault, state.inco_vault_authority, mint)?;
+
+    let nonces = &mut ctx.accounts.trader_nonces;
+    let current = if side == 0 {
+        nonces.quote_escrow_handle
+    } else {
+        nonces.base_escrow_handle
+    };
+    if current == 0 {
+        return err!(OrderbookError::InvalidEscrowCiphertext);
+    }
+    ensure_user_input_type(input_type)?;
+    let (debit, balance) = if full {
+        (Euint128(current), Euint128(0))
+    } else {
+        let amount: Euint128 = cpi::new_euint128(
+            CpiContext::new(inco.clone(), Operation { signer: signer.clone() }),
+            amount_ciphertext,
+            input_type,
+        )?;
+        let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+        let covered: Ebool = cpi::e_ge(cpi_ctx, Euint128(current), amount, 0)?;
+        let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+        let zero: Euint128 = cpi::as_euint128(cpi_ctx, 0)?;
+        let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+        let debit: Euint128 = cpi::e_select(cpi_ctx, covered, amount, zero, 0)?;
+        let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+        let balance: Euint128 = cpi::e_sub(cpi_ctx, Euint128(current), debit, 0)?;
+        (debit, balance)
+    };
+    if side == 0 {
+        nonces.quote_escrow_handle = balance.0;
+    } else {
+        nonces.base_escrow_handle = balance.0;
+    }
+    if full && nonces.base_escrow_handle == 0 && nonces.quote_escrow_handle == 0 {
+        state.open_escrows = state.open_escrows.saturating_sub(1);
+    }
+
+    let vault_authority_bump = ctx.bumps.inco_vault_authority;
+    let state_key = state.key();
+    let vault_seeds: &[&[u8]] = &[
+        b"inco_vault_authority_v12",
+        state_key.as_ref(),
+        &[vault_authority_bump],
+    ];
+    inco_token_cpi::transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.inco_token_program.to_account_info(),
+            IncoTransfer {
+                source: vault.to_account_info(),
+                destination: trader_dest.to_account_info(),
+                authority: ctx.accounts.inco_vault_authority.to_account_info(),
+                inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+            &[vault_seeds],
+        ),
+        handle_ciphertext(debit.0),
+        HANDLE_INPUT_TYPE,
+    )?;
+
+    if full {
+        return Ok(());
+    }
+
+    // Auditor allowances sit at the tail of the remaining accounts.
+    let auditor_accounts = allow_auditor(
+        state.auditor,
+        ctx.remaining_accounts,
+        &[balance.0],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    let owner_accounts = &ctx.remaining_accounts[..ctx.remaining_accounts.len() - auditor_accounts];
+
+    // The owner must be able to decrypt its balance: one leading [allowance, owner] pair.
+    allow_owners(
+        owner_accounts,
+        &[(ctx.accounts.trader.key(), balance.0)],
+        &inco,
+        &signer,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct WithdrawSignedEscrow<'info> {
+    #[account(mut)]
+    pub state: Account<'info, OrderbookState>,
+    #[account(
+        mut,
+        seeds = [b"trader_nonces_v1", state.key().as_ref(), trader.key().as_ref()],
+        bump = trader_nonces.bump
+    )]
+    pub trader_nonces: Account<'info, TraderNonces>,
+    #[account(mut)]
+    pub trader: Signer<'info>,
+    #[account(
+        seeds = [b"inco_vault_authority_v12", state.key().as_ref()],
+        bump,
+        address = state.inco_vault_authority
+    )]
+    /// CHECK: PDA authority for Inco vaults
+    pub inco_vault_authority: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_base_vault)]
+    pub inco_base_vault: UncheckedAccount<'info>,
+    /// CHECK: Inco vault accounts (owned by inco-token program)
+    #[account(mut, address = state.inco_quote_vault)]
+    pub inco_quote_vault: UncheckedAccount<'info>,
+    /// CHECK: Trader Inco accounts
+    #[account(mut)]
+    pub trader_base_inco: UncheckedAccount<'info>,
+    /// CHECK: Trader Inco accounts
+    #[account(mut)]
+    pub trader_quote_inco: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub inco_token_program: Program<'info, IncoToken>,
+    /// CHECK: Inco Lightning program for encrypted operations
+    #[account(address = INCO_LIGHTNING_ID)]
+    pub inco_lightning_program: Program<'info, IncoLightning>,
+}
+
+fn ensure_inco_account(
+    account: &UncheckedAccount<'_>,
+    expected_owner: Pubkey,
+    expected_mint: Pubkey,
+) -> Result<()> {
+    let info = account.to_account_info();
+    if info.owner != &INCO_TOKEN_ID {
+        return err!(OrderbookError::InvalidIncoProgramOwner);
+    }
+    let data = info.try_borrow_data()?;
+    let decoded = IncoAccount::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(OrderbookError::InvalidIncoAccountData))?;
+
+    if decoded.owner != expected_owner {
+        return err!(OrderbookError::InvalidIncoAccountOwner);
+    }
+    if decoded.mint != expected_mint {
+        return err!(OrderbookError::InvalidIncoAccountMint);
+    }
+    Ok(())
+}
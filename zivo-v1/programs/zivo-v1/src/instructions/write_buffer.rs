@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::OrderbookError;
+use crate::state::{CiphertextBuffer, MAX_ESCROW_CIPHERTEXT_LEN};
+
+/// Writes `chunk` at `offset`. The offset may not skip past the current end; anything after the
+/// chunk is dropped, so rewriting from 0 reuses a buffer for a new ciphertext.
+pub(crate) fn handler(ctx: Context<WriteBuffer>, offset: u32, chunk: Vec<u8>) -> Result<()> {
+    let buffer = &mut ctx.accounts.buffer;
+    let offset = offset as usize;
+    if chunk.is_empty() || offset > buffer.data.len() {
+        return err!(OrderbookError::CiphertextBufferOverflow);
+    }
+    let end = offset
+        .checked_add(chunk.len())
+        .ok_or(OrderbookError::CiphertextBufferOverflow)?;
+    if end > MAX_ESCROW_CIPHERTEXT_LEN {
+        return err!(OrderbookError::CiphertextBufferOverflow);
+    }
+
+    buffer.data.truncate(offset);
+    buffer.data.extend_from_slice(&chunk);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct WriteBuffer<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"ciphertext_buffer_v1", owner.key().as_ref(), &buffer.buffer_id.to_le_bytes()],
+        bump = buffer.bump
+    )]
+    pub buffer: Account<'info, CiphertextBuffer>,
+    pub owner: Signer<'info>,
+}
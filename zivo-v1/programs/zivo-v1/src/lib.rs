@@ -1,17 +1,65 @@
+// `#[program]` expands to IDL account instructions that call `AccountInfo::realloc`, deprecated
+// under the pinned Anchor 0.31, and, with `cpi`, to a helper per instruction taking all of its
+// arguments. Neither expansion can carry an attribute of its own.
+#![allow(deprecated)]
+#![cfg_attr(feature = "cpi", allow(clippy::too_many_arguments))]
+
 use anchor_lang::prelude::*;
+use light_sdk::cpi::CpiSigner;
+use light_sdk::derive_light_cpi_signer;
 
 pub mod errors;
 pub mod instructions;
+pub mod oracle;
+pub mod signed_order;
 pub mod state;
 
 pub use instructions::*;
+pub use signed_order::SignedOrder;
 
 declare_id!("HmJaFzPNVVgmp9kghKZZJ82stGyEt7SZYYm2TBfLLA3L");
 
+pub const LIGHT_CPI_SIGNER: CpiSigner =
+    derive_light_cpi_signer!("HmJaFzPNVVgmp9kghKZZJ82stGyEt7SZYYm2TBfLLA3L");
+
 #[program]
 pub mod zivo_orderbook_program {
     use super::*;
 
+    pub fn initialize_protocol(
+        ctx: Context<InitializeProtocol>,
+        default_maker_fee_bps: u16,
+        default_taker_fee_bps: u16,
+        market_creation_policy: u8,
+        listing_fee_lamports: u64,
+    ) -> Result<()> {
+        instructions::initialize_protocol::handler(
+            ctx,
+            default_maker_fee_bps,
+            default_taker_fee_bps,
+            market_creation_policy,
+            listing_fee_lamports,
+        )
+    }
+
+    pub fn update_protocol_config(
+        ctx: Context<UpdateProtocolConfig>,
+        new_admin: Pubkey,
+        default_maker_fee_bps: u16,
+        default_taker_fee_bps: u16,
+        market_creation_policy: u8,
+        listing_fee_lamports: u64,
+    ) -> Result<()> {
+        instructions::update_protocol_config::handler(
+            ctx,
+            new_admin,
+            default_maker_fee_bps,
+            default_taker_fee_bps,
+            market_creation_policy,
+            listing_fee_lamports,
+        )
+    }
+
     pub fn initialize(ctx: Context<Initialize>, require_attestation: bool) -> Result<()> {
         instructions::initialize::handler(ctx, require_attestation)
     }
@@ -20,66 +68,520 @@ pub mod zivo_orderbook_program {
         instructions::initialize_deposit::handler(ctx)
     }
 
-    pub fn place_order(
-        ctx: Context<PlaceOrder>,
+    pub fn init_buffer(ctx: Context<InitBuffer>, buffer_id: u64) -> Result<()> {
+        instructions::init_buffer::handler(ctx, buffer_id)
+    }
+
+    pub fn write_buffer(ctx: Context<WriteBuffer>, offset: u32, chunk: Vec<u8>) -> Result<()> {
+        instructions::write_buffer::handler(ctx, offset, chunk)
+    }
+
+    pub fn close_buffer(ctx: Context<CloseBuffer>) -> Result<()> {
+        instructions::close_buffer::handler(ctx)
+    }
+
+    pub fn place_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, PlaceOrder<'info>>,
         side: u8,
         price: u64,
         size_ciphertext: Vec<u8>,
         input_type: u8,
-        escrow_ciphertext: Vec<u8>,
-        escrow_input_type: u8,
     ) -> Result<()> {
-        instructions::place_order::handler(
+        instructions::place_order::handler(ctx, side, price, size_ciphertext, input_type)
+    }
+
+    pub fn place_order_with_payer<'info>(
+        ctx: Context<'_, '_, '_, 'info, PlaceOrderWithPayer<'info>>,
+        side: u8,
+        price: u64,
+        size_ciphertext: Vec<u8>,
+        input_type: u8,
+    ) -> Result<()> {
+        instructions::place_order_with_payer::handler(
+            ctx,
+            side,
+            price,
+            size_ciphertext,
+            input_type,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn place_order_from_note<'info>(
+        ctx: Context<'_, '_, '_, 'info, PlaceOrderFromNote<'info>>,
+        side: u8,
+        price: u64,
+        size_ciphertext: Vec<u8>,
+        input_type: u8,
+        proof_data: Vec<u8>,
+        nullifier: [u8; 32],
+        light_proof: light_sdk::instruction::ValidityProof,
+        address_tree_info: light_sdk::instruction::PackedAddressTreeInfo,
+        output_state_tree_index: u8,
+        system_accounts_offset: u8,
+    ) -> Result<()> {
+        instructions::place_order_from_note::handler(
             ctx,
             side,
             price,
             size_ciphertext,
             input_type,
-            escrow_ciphertext,
+            proof_data,
+            nullifier,
+            light_proof,
+            address_tree_info,
+            output_state_tree_index,
+            system_accounts_offset,
+        )
+    }
+
+    pub fn place_dark_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, PlaceDarkOrder<'info>>,
+        side: u8,
+        price_ciphertext: Vec<u8>,
+        size_ciphertext: Vec<u8>,
+        input_type: u8,
+    ) -> Result<()> {
+        instructions::place_dark_order::handler(
+            ctx,
+            side,
+            price_ciphertext,
+            size_ciphertext,
+            input_type,
+        )
+    }
+
+    pub fn place_quote<'info>(
+        ctx: Context<'_, '_, '_, 'info, PlaceQuote<'info>>,
+        bid_price: u64,
+        ask_price: u64,
+        bid_size_ciphertext: Vec<u8>,
+        ask_size_ciphertext: Vec<u8>,
+        input_type: u8,
+    ) -> Result<()> {
+        instructions::place_quote::handler(
+            ctx,
+            bid_price,
+            ask_price,
+            bid_size_ciphertext,
+            ask_size_ciphertext,
+            input_type,
+        )
+    }
+
+    pub fn requote<'info>(
+        ctx: Context<'_, '_, '_, 'info, Requote<'info>>,
+        bid_price: u64,
+        ask_price: u64,
+    ) -> Result<()> {
+        instructions::requote::handler(ctx, bid_price, ask_price)
+    }
+
+    pub fn open_rfq<'info>(
+        ctx: Context<'_, '_, '_, 'info, OpenRfq<'info>>,
+        rfq_id: u64,
+        side: u8,
+        expiry_slot: u64,
+        size_ciphertext: Vec<u8>,
+        input_type: u8,
+    ) -> Result<()> {
+        instructions::open_rfq::handler(ctx, rfq_id, side, expiry_slot, size_ciphertext, input_type)
+    }
+
+    pub fn submit_rfq_quote<'info>(
+        ctx: Context<'_, '_, '_, 'info, SubmitRfqQuote<'info>>,
+        delivery_ciphertext: Vec<u8>,
+        payment_ciphertext: Vec<u8>,
+        input_type: u8,
+    ) -> Result<()> {
+        instructions::submit_rfq_quote::handler(
+            ctx,
+            delivery_ciphertext,
+            payment_ciphertext,
+            input_type,
+        )
+    }
+
+    pub fn accept_rfq_quote<'info>(
+        ctx: Context<'_, '_, '_, 'info, AcceptRfqQuote<'info>>,
+    ) -> Result<()> {
+        instructions::accept_rfq_quote::handler(ctx)
+    }
+
+    pub fn refund_rfq_quote(ctx: Context<RefundRfqQuote>) -> Result<()> {
+        instructions::refund_rfq_quote::handler(ctx)
+    }
+
+    pub fn cancel_rfq(ctx: Context<CancelRfq>) -> Result<()> {
+        instructions::cancel_rfq::handler(ctx)
+    }
+
+    pub fn close_rfq(ctx: Context<CloseRfq>) -> Result<()> {
+        instructions::close_rfq::handler(ctx)
+    }
+
+    pub fn create_order_group(
+        ctx: Context<CreateOrderGroup>,
+        group_id: u64,
+        base_escrow_ciphertext: Vec<u8>,
+        quote_escrow_ciphertext: Vec<u8>,
+        escrow_input_type: u8,
+    ) -> Result<()> {
+        instructions::create_order_group::handler(
+            ctx,
+            group_id,
+            base_escrow_ciphertext,
+            quote_escrow_ciphertext,
             escrow_input_type,
         )
     }
 
-    pub fn cancel_order(
-        ctx: Context<CancelOrder>,
-        remaining_ciphertext: Vec<u8>,
+    pub fn place_group_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, PlaceGroupOrder<'info>>,
+        side: u8,
+        price: u64,
+        size_ciphertext: Vec<u8>,
         input_type: u8,
     ) -> Result<()> {
-        instructions::cancel_order::handler(ctx, remaining_ciphertext, input_type)
+        instructions::place_group_order::handler(ctx, side, price, size_ciphertext, input_type)
     }
 
-    pub fn close_order(ctx: Context<CloseOrder>) -> Result<()> {
+    pub fn sweep_order_group<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SweepOrderGroup<'info>>,
+    ) -> Result<()> {
+        instructions::sweep_order_group::handler(ctx)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn place_trigger_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, PlaceTriggerOrder<'info>>,
+        side: u8,
+        price: u64,
+        trigger_price: u64,
+        reference: u8,
+        condition: u8,
+        size_ciphertext: Vec<u8>,
+        input_type: u8,
+    ) -> Result<()> {
+        instructions::place_trigger_order::handler(
+            ctx,
+            side,
+            price,
+            trigger_price,
+            reference,
+            condition,
+            size_ciphertext,
+            input_type,
+        )
+    }
+
+    pub fn trigger_order(ctx: Context<TriggerOrderCrank>) -> Result<()> {
+        instructions::trigger_order::handler(ctx)
+    }
+
+    pub fn cancel_trigger_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, CancelTriggerOrder<'info>>,
+    ) -> Result<()> {
+        instructions::cancel_trigger_order::handler(ctx)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn place_twap_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, PlaceTwapOrder<'info>>,
+        side: u8,
+        price: u64,
+        interval_slots: u64,
+        slice_count: u32,
+        total_size_ciphertext: Vec<u8>,
+        slice_size_ciphertext: Vec<u8>,
+        input_type: u8,
+    ) -> Result<()> {
+        instructions::place_twap_order::handler(
+            ctx,
+            side,
+            price,
+            interval_slots,
+            slice_count,
+            total_size_ciphertext,
+            slice_size_ciphertext,
+            input_type,
+        )
+    }
+
+    pub fn twap_tick<'info>(ctx: Context<'_, '_, '_, 'info, TwapTick<'info>>) -> Result<()> {
+        instructions::twap_tick::handler(ctx)
+    }
+
+    pub fn cancel_twap_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, CancelTwapOrder<'info>>,
+    ) -> Result<()> {
+        instructions::cancel_twap_order::handler(ctx)
+    }
+
+    pub fn peg_order(ctx: Context<PegOrder>, peg_type: u8, peg_offset: i64) -> Result<()> {
+        instructions::peg_order::handler(ctx, peg_type, peg_offset)
+    }
+
+    pub fn cancel_order<'info>(ctx: Context<'_, '_, '_, 'info, CancelOrder<'info>>) -> Result<()> {
+        instructions::cancel_order::handler(ctx)
+    }
+
+    pub fn close_order<'info>(ctx: Context<'_, '_, '_, 'info, CloseOrder<'info>>) -> Result<()> {
         instructions::close_order::handler(ctx)
     }
 
-    pub fn match_order(
-        ctx: Context<MatchOrder>,
+    pub fn match_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, MatchOrder<'info>>,
         taker_side: u8,
         taker_price: u64,
         taker_req_base_ciphertext: Vec<u8>,
-        fill_base_ciphertext: Vec<u8>,
-        fill_quote_ciphertext: Vec<u8>,
         input_type: u8,
-        claim_plaintext_amount: u64,
     ) -> Result<()> {
         instructions::match_order::handler(
             ctx,
             taker_side,
             taker_price,
             taker_req_base_ciphertext,
-            fill_base_ciphertext,
-            fill_quote_ciphertext,
             input_type,
-            claim_plaintext_amount,
         )
     }
 
+    pub fn prepare_match<'info>(
+        ctx: Context<'_, '_, '_, 'info, PrepareMatch<'info>>,
+        taker_side: u8,
+        taker_price: u64,
+        taker_req_base_ciphertext: Vec<u8>,
+        input_type: u8,
+    ) -> Result<()> {
+        instructions::prepare_match::handler(
+            ctx,
+            taker_side,
+            taker_price,
+            taker_req_base_ciphertext,
+            input_type,
+        )
+    }
+
+    pub fn settle_match<'info>(ctx: Context<'_, '_, '_, 'info, SettleMatch<'info>>) -> Result<()> {
+        instructions::settle_match::handler(ctx)
+    }
+
+    pub fn discard_pending_match(ctx: Context<DiscardPendingMatch>) -> Result<()> {
+        instructions::discard_pending_match::handler(ctx)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn place_compressed_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, PlaceCompressedOrder<'info>>,
+        side: u8,
+        price: u64,
+        size_ciphertext: Vec<u8>,
+        input_type: u8,
+        proof: light_sdk::instruction::ValidityProof,
+        address_tree_info: light_sdk::instruction::PackedAddressTreeInfo,
+        output_state_tree_index: u8,
+        system_accounts_offset: u8,
+    ) -> Result<()> {
+        instructions::place_compressed_order::handler(
+            ctx,
+            side,
+            price,
+            size_ciphertext,
+            input_type,
+            proof,
+            address_tree_info,
+            output_state_tree_index,
+            system_accounts_offset,
+        )
+    }
+
+    pub fn cancel_compressed_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, CancelCompressedOrder<'info>>,
+        order: state::CompressedOrder,
+        order_meta: light_sdk::instruction::account_meta::CompressedAccountMeta,
+        proof: light_sdk::instruction::ValidityProof,
+        system_accounts_offset: u8,
+    ) -> Result<()> {
+        instructions::cancel_compressed_order::handler(
+            ctx,
+            order,
+            order_meta,
+            proof,
+            system_accounts_offset,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn match_compressed_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, MatchCompressedOrder<'info>>,
+        taker_price: u64,
+        taker_req_base_ciphertext: Vec<u8>,
+        input_type: u8,
+        maker_order: state::CompressedOrder,
+        maker_meta: light_sdk::instruction::account_meta::CompressedAccountMeta,
+        taker_order: state::CompressedOrder,
+        taker_meta: light_sdk::instruction::account_meta::CompressedAccountMeta,
+        proof: light_sdk::instruction::ValidityProof,
+        system_accounts_offset: u8,
+    ) -> Result<()> {
+        instructions::match_compressed_order::handler(
+            ctx,
+            taker_price,
+            taker_req_base_ciphertext,
+            input_type,
+            maker_order,
+            maker_meta,
+            taker_order,
+            taker_meta,
+            proof,
+            system_accounts_offset,
+        )
+    }
+
+    pub fn claim_compressed_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClaimCompressedOrder<'info>>,
+        order: state::CompressedOrder,
+        order_meta: light_sdk::instruction::account_meta::CompressedAccountMeta,
+        proof: light_sdk::instruction::ValidityProof,
+        system_accounts_offset: u8,
+    ) -> Result<()> {
+        instructions::claim_compressed_order::handler(
+            ctx,
+            order,
+            order_meta,
+            proof,
+            system_accounts_offset,
+        )
+    }
+
+    pub fn grant_trading_delegate(
+        ctx: Context<GrantTradingDelegate>,
+        delegate: Pubkey,
+        expiry_ts: i64,
+        markets: Vec<Pubkey>,
+        actions: u8,
+        max_orders: u32,
+    ) -> Result<()> {
+        instructions::grant_trading_delegate::handler(
+            ctx,
+            delegate,
+            expiry_ts,
+            markets,
+            actions,
+            max_orders,
+        )
+    }
+
+    pub fn revoke_trading_delegate(ctx: Context<RevokeTradingDelegate>) -> Result<()> {
+        instructions::revoke_trading_delegate::handler(ctx)
+    }
+
+    pub fn delegate_place_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, DelegatePlaceOrder<'info>>,
+        side: u8,
+        price: u64,
+        size_ciphertext: Vec<u8>,
+        input_type: u8,
+    ) -> Result<()> {
+        instructions::delegate_place_order::handler(ctx, side, price, size_ciphertext, input_type)
+    }
+
+    pub fn delegate_cancel_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, DelegateCancelOrder<'info>>,
+    ) -> Result<()> {
+        instructions::delegate_cancel_order::handler(ctx)
+    }
+
+    pub fn deposit_signed_escrow<'info>(
+        ctx: Context<'_, '_, '_, 'info, DepositSignedEscrow<'info>>,
+        side: u8,
+        escrow_ciphertext: Vec<u8>,
+        input_type: u8,
+    ) -> Result<()> {
+        instructions::deposit_signed_escrow::handler(ctx, side, escrow_ciphertext, input_type)
+    }
+
+    pub fn wrap_and_deposit<'info>(
+        ctx: Context<'_, '_, '_, 'info, WrapAndDeposit<'info>>,
+        side: u8,
+        escrow_ciphertext: Vec<u8>,
+        input_type: u8,
+        amount: u64,
+    ) -> Result<()> {
+        instructions::wrap_and_deposit::handler(ctx, side, escrow_ciphertext, input_type, amount)
+    }
+
+    pub fn withdraw_signed_escrow<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawSignedEscrow<'info>>,
+        side: u8,
+        amount_ciphertext: Vec<u8>,
+        input_type: u8,
+    ) -> Result<()> {
+        instructions::withdraw_signed_escrow::handler(ctx, side, amount_ciphertext, input_type)
+    }
+
+    pub fn cancel_signed_order(ctx: Context<CancelSignedOrder>, nonce: u64) -> Result<()> {
+        instructions::cancel_signed_order::handler(ctx, nonce)
+    }
+
+    pub fn close_signed_fill(ctx: Context<CloseSignedFill>) -> Result<()> {
+        instructions::close_signed_fill::handler(ctx)
+    }
+
+    pub fn match_signed_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, MatchSignedOrder<'info>>,
+        signed_order: SignedOrder,
+        taker_req_base_ciphertext: Vec<u8>,
+        input_type: u8,
+    ) -> Result<()> {
+        instructions::match_signed_order::handler(
+            ctx,
+            signed_order,
+            taker_req_base_ciphertext,
+            input_type,
+        )
+    }
+
+    pub fn grant_order_view(ctx: Context<GrantOrderView>) -> Result<()> {
+        instructions::grant_order_view::handler(ctx)
+    }
+
     pub fn maker_claim_filled_order(
         ctx: Context<MakerClaimFilledOrder>,
     ) -> Result<()> {
         instructions::maker_claim_filled_order::handler(ctx)
     }
 
+    pub fn maker_claim_to_note<'info>(
+        ctx: Context<'_, '_, '_, 'info, MakerClaimToNote<'info>>,
+        commitment: [u8; 32],
+        proof: light_sdk::instruction::ValidityProof,
+        address_tree_info: light_sdk::instruction::PackedAddressTreeInfo,
+        output_state_tree_index: u8,
+        system_accounts_offset: u8,
+    ) -> Result<()> {
+        instructions::maker_claim_to_note::handler(
+            ctx,
+            commitment,
+            proof,
+            address_tree_info,
+            output_state_tree_index,
+            system_accounts_offset,
+        )
+    }
+
+    pub fn claim_and_unwrap(ctx: Context<ClaimAndUnwrap>, plaintext_amount: u64) -> Result<()> {
+        instructions::claim_and_unwrap::handler(ctx, plaintext_amount)
+    }
+
+    pub fn crank_claim(ctx: Context<CrankClaim>) -> Result<()> {
+        instructions::crank_claim::handler(ctx)
+    }
+
+    pub fn set_crank_opt_out(ctx: Context<SetCrankOptOut>, opt_out: bool) -> Result<()> {
+        instructions::set_crank_opt_out::handler(ctx, opt_out)
+    }
+
     pub fn reset_state(ctx: Context<ResetState>) -> Result<()> {
         instructions::reset_state::handler(ctx)
     }
@@ -87,4 +589,74 @@ pub mod zivo_orderbook_program {
     pub fn bump_order_seq(ctx: Context<BumpOrderSeq>) -> Result<()> {
         instructions::bump_order_seq::handler(ctx)
     }
+
+    pub fn set_market_status(ctx: Context<SetMarketStatus>, status: u8) -> Result<()> {
+        instructions::set_market_status::handler(ctx, status)
+    }
+
+    pub fn set_market_mode(
+        ctx: Context<SetMarketMode>,
+        market_mode: u8,
+        auction_window_slots: u64,
+    ) -> Result<()> {
+        instructions::set_market_mode::handler(ctx, market_mode, auction_window_slots)
+    }
+
+    pub fn set_price_feed(
+        ctx: Context<SetPriceFeed>,
+        max_staleness_slots: u64,
+        max_confidence_bps: u16,
+    ) -> Result<()> {
+        instructions::set_price_feed::handler(ctx, max_staleness_slots, max_confidence_bps)
+    }
+
+    pub fn run_auction<'info>(ctx: Context<'_, '_, 'info, 'info, RunAuction<'info>>) -> Result<()> {
+        instructions::run_auction::handler(ctx)
+    }
+
+    pub fn sweep_market_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, SweepMarketOrder<'info>>,
+    ) -> Result<()> {
+        instructions::sweep_market_order::handler(ctx)
+    }
+
+    pub fn close_auction_result(ctx: Context<CloseAuctionResult>) -> Result<()> {
+        instructions::close_auction_result::handler(ctx)
+    }
+
+    pub fn release_delegate_market(ctx: Context<ReleaseDelegateMarket>) -> Result<()> {
+        instructions::release_delegate_market::handler(ctx)
+    }
+
+    pub fn close_market<'info>(ctx: Context<'_, '_, 'info, 'info, CloseMarket<'info>>) -> Result<()> {
+        instructions::close_market::handler(ctx)
+    }
+
+    pub fn migrate_market(ctx: Context<MigrateMarket>) -> Result<()> {
+        instructions::migrate_market::handler(ctx)
+    }
+
+    pub fn migrate_order(ctx: Context<MigrateOrder>) -> Result<()> {
+        instructions::migrate_order::handler(ctx)
+    }
+
+    pub fn set_gatekeeper(ctx: Context<SetGatekeeper>, gatekeeper: Pubkey) -> Result<()> {
+        instructions::set_gatekeeper::handler(ctx, gatekeeper)
+    }
+
+    pub fn propose_auditor(ctx: Context<ProposeAuditor>, auditor: Pubkey) -> Result<()> {
+        instructions::propose_auditor::handler(ctx, auditor)
+    }
+
+    pub fn apply_auditor(ctx: Context<ApplyAuditor>) -> Result<()> {
+        instructions::apply_auditor::handler(ctx)
+    }
+
+    pub fn issue_access_pass(ctx: Context<IssueAccessPass>) -> Result<()> {
+        instructions::issue_access_pass::handler(ctx)
+    }
+
+    pub fn revoke_access_pass(ctx: Context<RevokeAccessPass>) -> Result<()> {
+        instructions::revoke_access_pass::handler(ctx)
+    }
 }
@@ -0,0 +1,80 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::OrderbookError;
+use crate::state::{Order, OrderbookState, PEG_BEST_ASK, PEG_BEST_BID, PEG_MIDPOINT, PEG_NONE};
+
+/// Raw layout of a reference price account. Any program (or a local test stand-in) can own it;
+/// the market trusts whichever account its admin configured as `price_feed`.
+/// Fields are little-endian u64s starting at offset 0.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct PriceFeedData {
+    pub price: u64,
+    pub confidence: u64,
+    pub best_bid: u64,
+    pub best_ask: u64,
+    pub publish_slot: u64,
+}
+
+impl PriceFeedData {
+    pub const LEN: usize = 8 * 5;
+}
+
+/// Reads the market price feed and enforces the staleness and confidence limits.
+pub fn load_price_feed(
+    state: &OrderbookState,
+    price_feed: &Option<UncheckedAccount<'_>>,
+) -> Result<PriceFeedData> {
+    let info = match price_feed {
+        Some(info) => info.to_account_info(),
+        None => return err!(OrderbookError::MissingPriceFeed),
+    };
+    if state.price_feed == Pubkey::default() || info.key() != state.price_feed {
+        return err!(OrderbookError::InvalidPriceFeed);
+    }
+    let data = info.try_borrow_data()?;
+    if data.len() < PriceFeedData::LEN {
+        return err!(OrderbookError::InvalidPriceFeed);
+    }
+    let feed = PriceFeedData::deserialize(&mut &data[..PriceFeedData::LEN])
+        .map_err(|_| error!(OrderbookError::InvalidPriceFeed))?;
+
+    let slot = Clock::get()?.slot;
+    // A publish slot in the future is as untrustworthy as a stale one.
+    if feed.publish_slot > slot || slot - feed.publish_slot > state.max_staleness_slots {
+        return err!(OrderbookError::StalePriceFeed);
+    }
+    if (feed.confidence as u128) * 10_000 > (state.max_confidence_bps as u128) * (feed.price as u128) {
+        return err!(OrderbookError::PriceFeedConfidenceTooWide);
+    }
+    Ok(feed)
+}
+
+/// Effective limit price of an order at match time. Pegged orders follow the feed and are
+/// capped by a non-zero `price` (bids never pay more, asks never sell for less). Pegged bids
+/// always carry that cap, since their quote escrow was sized at `price`.
+pub fn effective_price(order: &Order, feed: Option<&PriceFeedData>) -> Result<u64> {
+    if order.peg_type == PEG_NONE {
+        return Ok(order.price);
+    }
+    let feed = match feed {
+        Some(feed) => feed,
+        None => return err!(OrderbookError::MissingPriceFeed),
+    };
+    let reference = match order.peg_type {
+        PEG_MIDPOINT => feed.best_bid / 2 + feed.best_ask / 2 + (feed.best_bid % 2 + feed.best_ask % 2) / 2,
+        PEG_BEST_BID => feed.best_bid,
+        PEG_BEST_ASK => feed.best_ask,
+        _ => return err!(OrderbookError::InvalidPegType),
+    };
+    let pegged = reference
+        .checked_add_signed(order.peg_offset)
+        .ok_or(error!(OrderbookError::InvalidPriceFeed))?;
+    if order.price == 0 {
+        return Ok(pegged);
+    }
+    Ok(if order.side == 0 {
+        pegged.min(order.price)
+    } else {
+        pegged.max(order.price)
+    })
+}
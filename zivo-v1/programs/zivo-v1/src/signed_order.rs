@@ -0,0 +1,88 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+
+use crate::errors::OrderbookError;
+
+/// Prefix of every signed order message so a signature can never be replayed as another payload.
+pub const SIGNED_ORDER_DOMAIN: &[u8] = b"zivo-signed-order-v1";
+
+/// Maker order signed off-chain and submitted by the relayer through `match_signed_order`.
+/// The signed message is `SIGNED_ORDER_DOMAIN || borsh(SignedOrder)`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct SignedOrder {
+    pub market: Pubkey,
+    pub maker: Pubkey,
+    pub side: u8,
+    pub price: u64,
+    pub size_ciphertext: Vec<u8>,
+    pub input_type: u8,
+    pub nonce: u64,
+    pub expiry_slot: u64,
+}
+
+impl SignedOrder {
+    pub fn message(&self) -> Result<Vec<u8>> {
+        let mut message = SIGNED_ORDER_DOMAIN.to_vec();
+        self.serialize(&mut message)?;
+        Ok(message)
+    }
+}
+
+// Ed25519SignatureOffsets as laid out by the ed25519 precompile: seven little-endian u16s
+// after a two byte header (signature count + padding).
+const ED25519_HEADER_LEN: usize = 2;
+const ED25519_OFFSETS_LEN: usize = 14;
+const ED25519_PUBKEY_LEN: usize = 32;
+const ED25519_SIGNATURE_LEN: usize = 64;
+
+/// Checks that the instruction right before the current one is an ed25519 precompile call
+/// verifying exactly `message` under `signer`, with all data inline in that instruction.
+pub fn verify_ed25519_signature(
+    instructions_sysvar: &AccountInfo<'_>,
+    signer: &Pubkey,
+    message: &[u8],
+) -> Result<()> {
+    let current = load_current_index_checked(instructions_sysvar)?;
+    if current == 0 {
+        return err!(OrderbookError::InvalidSignature);
+    }
+    let ix = load_instruction_at_checked((current - 1) as usize, instructions_sysvar)?;
+    if ix.program_id != ed25519_program::ID || !ix.accounts.is_empty() {
+        return err!(OrderbookError::InvalidSignature);
+    }
+    let data = &ix.data;
+    if data.len() < ED25519_HEADER_LEN + ED25519_OFFSETS_LEN || data[0] != 1 {
+        return err!(OrderbookError::InvalidSignature);
+    }
+
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let offsets = ED25519_HEADER_LEN;
+    let signature_offset = read_u16(offsets) as usize;
+    let signature_ix = read_u16(offsets + 2);
+    let pubkey_offset = read_u16(offsets + 4) as usize;
+    let pubkey_ix = read_u16(offsets + 6);
+    let message_offset = read_u16(offsets + 8) as usize;
+    let message_len = read_u16(offsets + 10) as usize;
+    let message_ix = read_u16(offsets + 12);
+
+    // u16::MAX means "this instruction"; anything else could point at unrelated data.
+    if signature_ix != u16::MAX || pubkey_ix != u16::MAX || message_ix != u16::MAX {
+        return err!(OrderbookError::InvalidSignature);
+    }
+    if signature_offset + ED25519_SIGNATURE_LEN > data.len()
+        || pubkey_offset + ED25519_PUBKEY_LEN > data.len()
+        || message_offset + message_len > data.len()
+    {
+        return err!(OrderbookError::InvalidSignature);
+    }
+    if &data[pubkey_offset..pubkey_offset + ED25519_PUBKEY_LEN] != signer.as_ref() {
+        return err!(OrderbookError::InvalidSignature);
+    }
+    if &data[message_offset..message_offset + message_len] != message {
+        return err!(OrderbookError::InvalidSignature);
+    }
+    Ok(())
+}
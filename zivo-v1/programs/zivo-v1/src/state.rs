@@ -1,6 +1,63 @@
 use anchor_lang::prelude::*;
+use light_sdk::{LightDiscriminator, LightHasher};
+
+use crate::errors::OrderbookError;
+
+pub use inco_token::{handle_ciphertext, HANDLE_INPUT_TYPE};
 
 pub const MAX_ESCROW_CIPHERTEXT_LEN: usize = 512;
+// Paid to the cranker out of the reclaimed order rent in `crank_claim`.
+pub const CRANK_CLAIM_REWARD_LAMPORTS: u64 = 5_000;
+
+pub const MARKET_STATUS_OPEN: u8 = 0;
+pub const MARKET_STATUS_CLOSED: u8 = 1;
+
+pub const MARKET_MODE_CONTINUOUS: u8 = 0;
+pub const MARKET_MODE_BATCH_AUCTION: u8 = 1;
+// `run_auction` settles and closes every open order in one instruction, so a batch market caps
+// its book. Six orders with their Inco accounts, access passes and owner and auditor allowance
+// pairs stay within a transaction's 64 account locks.
+pub const MAX_AUCTION_ORDERS: u64 = 6;
+// Taken from whoever places an order on a batch book and paid to the matcher by `run_auction`.
+// It does not depend on the encrypted size, so holding the capped book with zero-size orders
+// costs as much as with real ones.
+pub const AUCTION_ORDER_FEE_LAMPORTS: u64 = 5_000_000;
+
+pub const PEG_NONE: u8 = 0;
+pub const PEG_MIDPOINT: u8 = 1;
+pub const PEG_BEST_BID: u8 = 2;
+pub const PEG_BEST_ASK: u8 = 3;
+
+// The only trigger reference. `last_trade_price` is not one: fills are encrypted and may be zero,
+// so crossing empty orders would let anyone print it. Reference 0 (last trade) is retired.
+pub const TRIGGER_REF_PRICE_FEED: u8 = 1;
+pub const TRIGGER_WHEN_AT_OR_ABOVE: u8 = 0;
+pub const TRIGGER_WHEN_AT_OR_BELOW: u8 = 1;
+
+pub const GROUP_STATUS_ACTIVE: u8 = 0;
+pub const GROUP_STATUS_FILLED: u8 = 1;
+pub const GROUP_STATUS_CANCELLED: u8 = 2;
+pub const MAX_GROUP_LEGS: usize = 4;
+
+pub const RFQ_STATUS_OPEN: u8 = 0;
+pub const RFQ_STATUS_FILLED: u8 = 1;
+pub const RFQ_STATUS_CANCELLED: u8 = 2;
+
+// Sliding window of cancellable signed-order nonces per trader and market: 64 words of 64 bits.
+pub const NONCE_BITMAP_WORDS: usize = 64;
+
+pub const DELEGATE_ACTION_PLACE: u8 = 1 << 0;
+pub const DELEGATE_ACTION_CANCEL: u8 = 1 << 1;
+pub const MAX_DELEGATE_MARKETS: usize = 8;
+
+// Slots a prepared match may wait for settlement before anyone can discard it.
+pub const PENDING_MATCH_TIMEOUT_SLOTS: u64 = 150;
+
+pub const MARKET_CREATION_PERMISSIONED: u8 = 0;
+pub const MARKET_CREATION_PERMISSIONLESS: u8 = 1;
+pub const MAX_FEE_BPS: u16 = 10_000;
+// Delay between proposing and applying a market auditor change.
+pub const AUDITOR_TIMELOCK_SECONDS: i64 = 2 * 24 * 60 * 60;
 
 #[account]
 #[derive(Default)]
@@ -8,13 +65,39 @@ pub struct OrderbookState {
     pub admin: Pubkey,
     pub order_seq: u64,
     pub require_attestation: u8,
-    pub _reserved: [u8; 7],
+    pub status: u8,
+    pub maker_fee_bps: u16,
+    pub taker_fee_bps: u16,
+    pub _reserved: [u8; 2],
     pub inco_base_mint: Pubkey,
     pub inco_quote_mint: Pubkey,
     pub inco_vault_authority: Pubkey,
     pub inco_base_vault: Pubkey,
     pub inco_quote_vault: Pubkey,
-    pub _padding: [u8; 8],
+    // Objects still holding vault funds: open or unclaimed orders, trigger and TWAP parents,
+    // order groups, RFQ quotes and funded signed-order balances.
+    pub open_escrows: u64,
+    // Pubkey::default() means the market is not allowlisted.
+    pub gatekeeper: Pubkey,
+    // Pubkey::default() means no auditor; changes go through the auditor timelock.
+    pub auditor: Pubkey,
+    pub pending_auditor: Pubkey,
+    pub pending_auditor_activation_ts: i64,
+    pub market_mode: u8,
+    pub _mode_padding: [u8; 7],
+    pub auction_window_slots: u64,
+    // Id of the next auction; `run_auction` records its `AuctionResult` under this id.
+    pub auction_id: u64,
+    pub auction_start_slot: u64,
+    // Reference price account for pegged and trigger orders; Pubkey::default() disables both.
+    pub price_feed: Pubkey,
+    pub max_staleness_slots: u64,
+    pub max_confidence_bps: u16,
+    pub _feed_padding: [u8; 6],
+    // Price of the last lit match, informational only: the fill behind it may be zero.
+    pub last_trade_price: u64,
+    // Open `Order` accounts on the book; `run_auction` must see them all.
+    pub open_orders: u64,
 }
 
 impl OrderbookState {
@@ -23,9 +106,38 @@ impl OrderbookState {
         + 32
         + 8
         + 1
-        + 7
+        + 1
+        + 2
+        + 2
+        + 2
         + (32 * 5)
+        + 8
+        + 32
+        + 32
+        + 32
+        + 8
+        + 1
+        + 7
+        + 8
+        + 8
+        + 8
+        + 32
+        + 8
+        + 2
+        + 6
+        + 8
         + 8;
+
+    /// Counts a newly placed order. In batch mode the book is capped at `MAX_AUCTION_ORDERS`.
+    pub fn add_open_order(&mut self) -> Result<()> {
+        if self.market_mode == MARKET_MODE_BATCH_AUCTION
+            && self.open_orders >= MAX_AUCTION_ORDERS
+        {
+            return err!(OrderbookError::AuctionBookFull);
+        }
+        self.open_orders = self.open_orders.saturating_add(1);
+        Ok(())
+    }
 }
 
 #[account]
@@ -36,14 +148,29 @@ pub struct Order {
     pub is_filled: bool,
     pub is_claimed: bool,
     pub claim_input_type: u8,
-    pub claim_plaintext_amount: u64,
     pub claim_ciphertext: Vec<u8>,
-    pub _padding: [u8; 1],
+    pub is_dark: bool,
     pub price: u64,
     pub seq: u64,
     pub remaining_handle: u128,
     pub bump: u8,
-    pub _reserved: [u8; 7],
+    pub peg_type: u8,
+    // Set on the bid/ask legs created by `place_quote`; only those can be requoted.
+    pub is_quote: bool,
+    // Set by the owner to keep `crank_claim` from claiming (and closing) the order.
+    pub crank_opt_out: bool,
+    pub _reserved: [u8; 4],
+    // Dark orders keep `price` at 0 and carry the limit price as an encrypted handle.
+    pub price_handle: u128,
+    pub exec_price_handle: u128,
+    pub auction_id: u64,
+    // Pegged orders price off the market feed; `price` is then an optional protective limit.
+    pub peg_offset: i64,
+    // Pubkey::default() for standalone orders; otherwise the `OrderGroup` backing the escrow.
+    pub group: Pubkey,
+    // Account that paid the order's rent and gets it back when the order is closed.
+    // Pubkey::default() means the owner, as for orders placed before it was recorded.
+    pub rent_payer: Pubkey,
 }
 
 impl Order {
@@ -53,7 +180,6 @@ impl Order {
         + 1
         + 1
         + 1
-        + 8
         + 4
         + MAX_ESCROW_CIPHERTEXT_LEN
         + 1
@@ -61,7 +187,51 @@ impl Order {
         + 8
         + 16
         + 1
-        + 7;
+        + 1
+        + 1
+        + 1
+        + 4
+        + 16
+        + 16
+        + 8
+        + 8
+        + 32
+        + 32;
+
+    /// A newly placed lit order resting at `price` with `remaining_handle` escrowed, taking the
+    /// next sequence number and the current auction from `state`. Dark, quote and group orders
+    /// override their own fields on the result.
+    pub fn init(
+        state: &OrderbookState,
+        owner: Pubkey,
+        side: u8,
+        price: u64,
+        remaining_handle: u128,
+        bump: u8,
+        rent_payer: Pubkey,
+    ) -> Self {
+        Self {
+            owner,
+            side,
+            is_open: true,
+            price,
+            seq: state.order_seq,
+            remaining_handle,
+            bump,
+            auction_id: state.auction_id,
+            rent_payer,
+            ..Self::default()
+        }
+    }
+
+    /// Account that receives the order's rent when it is closed.
+    pub fn rent_recipient(&self) -> Pubkey {
+        if self.rent_payer == Pubkey::default() {
+            self.owner
+        } else {
+            self.rent_payer
+        }
+    }
 }
 
 impl Default for Order {
@@ -73,14 +243,22 @@ impl Default for Order {
             is_filled: false,
             is_claimed: false,
             claim_input_type: 0,
-            claim_plaintext_amount: 0,
             claim_ciphertext: Vec::new(),
-            _padding: [0u8; 1],
+            is_dark: false,
             price: 0,
             seq: 0,
             remaining_handle: 0,
             bump: 0,
-            _reserved: [0u8; 7],
+            peg_type: PEG_NONE,
+            is_quote: false,
+            crank_opt_out: false,
+            _reserved: [0u8; 4],
+            price_handle: 0,
+            exec_price_handle: 0,
+            auction_id: 0,
+            peg_offset: 0,
+            group: Pubkey::default(),
+            rent_payer: Pubkey::default(),
         }
     }
 }
@@ -112,3 +290,390 @@ pub struct MatchAttestation {
 impl MatchAttestation {
     pub const LEN: usize = 16 + 16 + 16 + 1 + 7;
 }
+
+#[account]
+#[derive(Default)]
+pub struct ProtocolConfig {
+    pub admin: Pubkey,
+    pub treasury: Pubkey,
+    pub default_maker_fee_bps: u16,
+    pub default_taker_fee_bps: u16,
+    pub market_creation_policy: u8,
+    pub bump: u8,
+    pub _padding: [u8; 2],
+    pub listing_fee_lamports: u64,
+}
+
+impl ProtocolConfig {
+    pub const LEN: usize = 32 + 32 + 2 + 2 + 1 + 1 + 2 + 8;
+}
+
+#[account]
+#[derive(Default)]
+pub struct MarketRegistry {
+    pub bump: u8,
+    pub _padding: [u8; 7],
+    pub markets: Vec<Pubkey>,
+}
+
+impl MarketRegistry {
+    // Empty registry; `initialize` and `close_market` resize it by one key per market.
+    pub const LEN: usize = 1 + 7 + 4;
+
+    pub fn space(markets: usize) -> usize {
+        8 + Self::LEN + 32 * markets
+    }
+}
+
+#[account]
+#[derive(Default)]
+pub struct AccessPass {
+    pub market: Pubkey,
+    pub trader: Pubkey,
+    // Gatekeeper that issued the pass; it stops admitting the trader once the gatekeeper changes.
+    pub issuer: Pubkey,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+impl AccessPass {
+    pub const LEN: usize = 32 + 32 + 32 + 1 + 7;
+}
+
+#[account]
+#[derive(Default)]
+pub struct AuctionResult {
+    pub market: Pubkey,
+    pub auction_id: u64,
+    // Encrypted: the price is chosen from encrypted volumes, so it is only known as a handle.
+    pub clearing_price_handle: u128,
+    pub volume_handle: u128,
+    pub start_slot: u64,
+    pub end_slot: u64,
+    // Orders admitted to the auction; the rest of the book was only refunded.
+    pub orders: u32,
+    pub bump: u8,
+    pub _padding: [u8; 3],
+}
+
+impl AuctionResult {
+    pub const LEN: usize = 32 + 8 + 16 + 16 + 8 + 8 + 4 + 1 + 3;
+}
+
+#[account]
+#[derive(Default)]
+pub struct TriggerOrder {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub side: u8,
+    pub reference: u8,
+    pub condition: u8,
+    pub bump: u8,
+    pub _padding: [u8; 4],
+    pub price: u64,
+    pub trigger_price: u64,
+    pub seq: u64,
+    pub remaining_handle: u128,
+}
+
+impl TriggerOrder {
+    pub const LEN: usize = 32 + 32 + 1 + 1 + 1 + 1 + 4 + 8 + 8 + 8 + 16;
+}
+
+#[account]
+#[derive(Default)]
+pub struct OrderGroup {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub status: u8,
+    pub bump: u8,
+    pub _padding: [u8; 6],
+    pub group_id: u64,
+    // Seq of the leg that filled first; only meaningful once status is GROUP_STATUS_FILLED.
+    pub filled_seq: u64,
+    // Escrow not yet reserved by a leg, per side; 0 when the group escrowed nothing on that side.
+    // Both drop to 0 once the group is filled or cancelled and its escrow has been released.
+    pub base_escrow_handle: u128,
+    pub quote_escrow_handle: u128,
+    // Sum of the legs' reservations per side; 0 while no leg reserved on that side.
+    pub base_reserved_handle: u128,
+    pub quote_reserved_handle: u128,
+    pub legs: Vec<u64>,
+}
+
+impl OrderGroup {
+    pub const LEN: usize =
+        32 + 32 + 1 + 1 + 6 + 8 + 8 + 16 + 16 + 16 + 16 + 4 + (8 * MAX_GROUP_LEGS);
+}
+
+#[account]
+#[derive(Default)]
+pub struct TwapOrder {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub side: u8,
+    pub bump: u8,
+    pub _padding: [u8; 2],
+    pub slices_remaining: u32,
+    pub price: u64,
+    pub seq: u64,
+    pub interval_slots: u64,
+    pub next_slot: u64,
+    // Escrowed size not yet released into the book.
+    pub unreleased_handle: u128,
+    pub slice_handle: u128,
+}
+
+impl TwapOrder {
+    pub const LEN: usize = 32 + 32 + 1 + 1 + 2 + 4 + 8 + 8 + 8 + 8 + 16 + 16;
+}
+
+#[account]
+#[derive(Default)]
+pub struct RfqRequest {
+    pub market: Pubkey,
+    pub taker: Pubkey,
+    // Taker side: 0 = taker buys base, 1 = taker sells base.
+    pub side: u8,
+    pub status: u8,
+    pub bump: u8,
+    pub _padding: [u8; 1],
+    // Quotes not yet accepted or refunded; `close_rfq` needs this at 0.
+    pub open_quotes: u32,
+    pub rfq_id: u64,
+    pub expiry_slot: u64,
+    pub size_handle: u128,
+    pub accepted_quote: Pubkey,
+}
+
+impl RfqRequest {
+    pub const LEN: usize = 32 + 32 + 1 + 1 + 1 + 1 + 4 + 8 + 8 + 16 + 32;
+}
+
+#[account]
+pub struct RfqQuote {
+    pub request: Pubkey,
+    pub maker: Pubkey,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+    // What the maker escrowed for the taker, and what the maker asks in return. Both are 0 when
+    // the maker's balance did not cover the delivery.
+    pub delivery_handle: u128,
+    pub payment_handle: u128,
+}
+
+impl RfqQuote {
+    pub const LEN: usize = 32 + 32 + 1 + 7 + 16 + 16;
+}
+
+#[account]
+pub struct TraderNonces {
+    pub market: Pubkey,
+    pub trader: Pubkey,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+    // First nonce the bitmap covers, a multiple of 64; every nonce below it counts as used.
+    pub nonce_base: u64,
+    // Encrypted balances backing signed orders: quote for bids, base for asks. 0 = none yet.
+    pub quote_escrow_handle: u128,
+    pub base_escrow_handle: u128,
+    // Bit `n` set = nonce `nonce_base + n` cancelled.
+    pub nonce_bitmap: [u64; NONCE_BITMAP_WORDS],
+}
+
+impl TraderNonces {
+    pub const LEN: usize = 32 + 32 + 1 + 7 + 8 + 16 + 16 + (8 * NONCE_BITMAP_WORDS);
+
+    pub fn is_nonce_used(&self, nonce: u64) -> bool {
+        if nonce < self.nonce_base {
+            return true;
+        }
+        let offset = nonce - self.nonce_base;
+        if offset >= (NONCE_BITMAP_WORDS * 64) as u64 {
+            return false;
+        }
+        self.nonce_bitmap[(offset / 64) as usize] & (1u64 << (offset % 64)) != 0
+    }
+
+    /// Marks `nonce` used. A nonce past the window slides it forward by whole words so the nonce
+    /// lands in the last word; the nonces that fall below the new base count as used.
+    pub fn use_nonce(&mut self, nonce: u64) -> Result<()> {
+        if self.is_nonce_used(nonce) {
+            return err!(OrderbookError::NonceAlreadyUsed);
+        }
+        let words = NONCE_BITMAP_WORDS as u64;
+        if nonce - self.nonce_base >= words * 64 {
+            let new_base = (nonce / 64 + 1 - words) * 64;
+            let shift = ((new_base - self.nonce_base) / 64) as usize;
+            if shift >= NONCE_BITMAP_WORDS {
+                self.nonce_bitmap = [0u64; NONCE_BITMAP_WORDS];
+            } else {
+                self.nonce_bitmap.copy_within(shift.., 0);
+                self.nonce_bitmap[NONCE_BITMAP_WORDS - shift..].fill(0);
+            }
+            self.nonce_base = new_base;
+        }
+        let offset = nonce - self.nonce_base;
+        self.nonce_bitmap[(offset / 64) as usize] |= 1u64 << (offset % 64);
+        Ok(())
+    }
+}
+
+impl Default for TraderNonces {
+    fn default() -> Self {
+        Self {
+            market: Pubkey::default(),
+            trader: Pubkey::default(),
+            bump: 0,
+            _padding: [0u8; 7],
+            nonce_base: 0,
+            quote_escrow_handle: 0,
+            base_escrow_handle: 0,
+            nonce_bitmap: [0u64; NONCE_BITMAP_WORDS],
+        }
+    }
+}
+
+/// Size still fillable on one signed order (`signed_fill_v1` + market + maker + nonce), created
+/// by its first fill so a partially filled order keeps its remainder.
+#[account]
+#[derive(Default)]
+pub struct SignedOrderFill {
+    pub market: Pubkey,
+    pub maker: Pubkey,
+    // Matcher that paid the rent; `close_signed_fill` returns it there.
+    pub payer: Pubkey,
+    pub nonce: u64,
+    pub expiry_slot: u64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+    pub remaining_handle: u128,
+}
+
+impl SignedOrderFill {
+    pub const LEN: usize = 32 + 32 + 32 + 8 + 8 + 1 + 7 + 16;
+}
+
+#[account]
+#[derive(Default)]
+pub struct TradingDelegate {
+    pub owner: Pubkey,
+    pub delegate: Pubkey,
+    pub expiry_ts: i64,
+    // Bitmask of DELEGATE_ACTION_* flags; withdrawals are never delegated.
+    pub actions: u8,
+    pub bump: u8,
+    pub _padding: [u8; 2],
+    // 0 means no cap on the number of orders the delegate may place.
+    pub max_orders: u32,
+    pub orders_placed: u32,
+    pub _reserved: [u8; 4],
+    pub markets: Vec<Pubkey>,
+}
+
+impl TradingDelegate {
+    pub const LEN: usize = 32 + 32 + 8 + 1 + 1 + 2 + 4 + 4 + 4 + 4 + (32 * MAX_DELEGATE_MARKETS);
+}
+
+#[account]
+#[derive(Default)]
+pub struct CiphertextBuffer {
+    pub owner: Pubkey,
+    pub buffer_id: u64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+    // Filled in chunks by write_buffer; consumed in place of an inline ciphertext argument.
+    pub data: Vec<u8>,
+}
+
+impl CiphertextBuffer {
+    pub const LEN: usize = 32 + 8 + 1 + 7 + 4 + MAX_ESCROW_CIPHERTEXT_LEN;
+}
+
+#[account]
+#[derive(Default)]
+pub struct PendingMatch {
+    pub market: Pubkey,
+    pub maker_order: Pubkey,
+    pub taker_order: Pubkey,
+    pub matcher: Pubkey,
+    pub bump: u8,
+    pub taker_side: u8,
+    pub maker_peg_type: u8,
+    pub taker_peg_type: u8,
+    pub _padding: [u8; 4],
+    pub price: u64,
+    pub prepared_slot: u64,
+    // Order state seen by prepare_match; settle_match refuses if either order moved since.
+    pub maker_price: u64,
+    pub taker_limit_price: u64,
+    pub maker_peg_offset: i64,
+    pub taker_peg_offset: i64,
+    pub maker_remaining_before: u128,
+    pub taker_remaining_before: u128,
+    // Results of the encrypted match arithmetic.
+    pub fill_handle: u128,
+    pub fill_quote_handle: u128,
+    pub maker_remaining_handle: u128,
+    pub taker_remaining_handle: u128,
+    // Quote returned to the bidder at settlement; 0 when the bid executes at its escrow price.
+    pub bid_refund_handle: u128,
+}
+
+impl PendingMatch {
+    pub const LEN: usize = 32 + 32 + 32 + 32 + 1 + 1 + 1 + 1 + 4 + (8 * 6) + (16 * 7);
+
+    pub fn snapshot(&mut self, maker: &Order, taker: &Order) {
+        self.maker_peg_type = maker.peg_type;
+        self.taker_peg_type = taker.peg_type;
+        self.maker_price = maker.price;
+        self.taker_limit_price = taker.price;
+        self.maker_peg_offset = maker.peg_offset;
+        self.taker_peg_offset = taker.peg_offset;
+        self.maker_remaining_before = maker.remaining_handle;
+        self.taker_remaining_before = taker.remaining_handle;
+    }
+
+    /// True while both orders are open and unchanged since `snapshot`.
+    pub fn snapshot_matches(&self, maker: &Order, taker: &Order) -> bool {
+        maker.is_open
+            && taker.is_open
+            && maker.peg_type == self.maker_peg_type
+            && taker.peg_type == self.taker_peg_type
+            && maker.price == self.maker_price
+            && taker.price == self.taker_limit_price
+            && maker.peg_offset == self.maker_peg_offset
+            && taker.peg_offset == self.taker_peg_offset
+            && maker.remaining_handle == self.maker_remaining_before
+            && taker.remaining_handle == self.taker_remaining_before
+    }
+}
+
+// Compressed order kept in a Light state tree instead of a rent-paying `Order` PDA. Its address
+// is derived from the same `order_v1` seeds; continuous, lit, unpegged and ungrouped only.
+#[event]
+#[derive(Clone, Debug, Default, LightDiscriminator, LightHasher)]
+pub struct CompressedOrder {
+    #[hash]
+    pub market: Pubkey,
+    #[hash]
+    pub owner: Pubkey,
+    pub side: u8,
+    pub is_open: bool,
+    pub is_filled: bool,
+    pub claim_input_type: u8,
+    pub price: u64,
+    pub seq: u64,
+    pub remaining_handle: u128,
+    #[hash]
+    pub claim_ciphertext: Vec<u8>,
+}
+
+/// Rejects `HANDLE_INPUT_TYPE` on a caller-supplied ciphertext. Only the program may name an
+/// existing handle; a caller could otherwise pass any handle it can see, such as a vault balance.
+pub fn ensure_user_input_type(input_type: u8) -> Result<()> {
+    if input_type == HANDLE_INPUT_TYPE {
+        return err!(OrderbookError::ReservedInputType);
+    }
+    Ok(())
+}
@@ -160,12 +160,27 @@ async function main(): Promise<void> {
   await ensureIncoAccount(baseVault, baseMint, incoVaultAuthority);
   await ensureIncoAccount(quoteVault, quoteMint, incoVaultAuthority);
 
+  const [protocolConfig] = PublicKey.findProgramAddressSync(
+    [Buffer.from("protocol_config_v1")],
+    program.programId,
+  );
+  const [marketRegistry] = PublicKey.findProgramAddressSync(
+    [Buffer.from("market_registry_v1")],
+    program.programId,
+  );
+
   const stateInfo = await provider.connection.getAccountInfo(statePda);
   if (!stateInfo) {
+    const protocol = (await (program.account as any).protocolConfig.fetch(
+      protocolConfig,
+    )) as { treasury: PublicKey };
     const sig = await (program.methods
       .initialize(requireAttestation)
       .accounts({
         state: statePda,
+        protocolConfig,
+        marketRegistry,
+        treasury: protocol.treasury,
         incoVaultAuthority,
         incoBaseVault: baseVault.publicKey,
         incoQuoteVault: quoteVault.publicKey,
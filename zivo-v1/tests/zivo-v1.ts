@@ -3,16 +3,41 @@ import BN from "bn.js";
 import fs from "fs";
 import path from "path";
 import {
+  AccountMeta,
+  AddressLookupTableAccount,
+  AddressLookupTableProgram,
   ComputeBudgetProgram,
+  Ed25519Program,
   Keypair,
   LAMPORTS_PER_SOL,
   PublicKey,
   SendTransactionError,
+  SystemProgram,
+  Transaction,
+  TransactionInstruction,
+  TransactionMessage,
+  VersionedTransaction,
 } from "@solana/web3.js";
+import { decrypt } from "@inco/solana-sdk/attested-decrypt";
 import { encryptValue } from "@inco/solana-sdk/encryption";
 import { hexToBuffer } from "@inco/solana-sdk/utils";
+import {
+  bn,
+  createRpc,
+  deriveAddressSeedV2,
+  deriveAddressV2,
+  featureFlags,
+  getDefaultAddressTreeInfo,
+  getLightSystemAccountMetasV2,
+  hashvToBn254FieldSizeBe,
+  selectStateTreeInfo,
+  SystemAccountMetaConfig,
+  VERSION,
+} from "@lightprotocol/stateless.js";
+import nacl from "tweetnacl";
 
 import type { ZivoOrderbookProgram } from "../target/types/zivo_orderbook_program";
+import type { ZivoCpiTrader } from "../target/types/zivo_cpi_trader";
 
 const INCO_LIGHTNING_PROGRAM_ID = new PublicKey(
   "5sjEbPiqgZrYwR31ahR6Uk9wf5awoX61YGg7jExQSwaj",
@@ -21,6 +46,25 @@ const INCO_TOKEN_PROGRAM_ID = new PublicKey(
   "4cyJHzecVWuU2xux6bCAPAhALKQT8woBh4Vx3AGEGe5N",
 );
 
+const BPF_LOADER_UPGRADEABLE_PROGRAM_ID = new PublicKey(
+  "BPFLoaderUpgradeab1e11111111111111111111111",
+);
+const SPL_TOKEN_PROGRAM_ID = new PublicKey(
+  "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+);
+
+// Mirrors `SIGNED_ORDER_DOMAIN` in the program's signed_order.rs.
+const SIGNED_ORDER_DOMAIN = Buffer.from("zivo-signed-order-v1");
+// Mirrors `CRANK_CLAIM_REWARD_LAMPORTS` in the program's state.rs.
+const CRANK_CLAIM_REWARD_LAMPORTS = 5_000;
+// Mirrors `AUCTION_ORDER_FEE_LAMPORTS` in the program's state.rs.
+const AUCTION_ORDER_FEE_LAMPORTS = 5_000_000;
+// Mirrors `PENDING_MATCH_TIMEOUT_SLOTS` in the program's state.rs.
+const PENDING_MATCH_TIMEOUT_SLOTS = 150;
+// Mirrors the `DELEGATE_ACTION_*` flags in the program's state.rs.
+const DELEGATE_ACTION_PLACE = 1 << 0;
+const DELEGATE_ACTION_CANCEL = 1 << 1;
+
 const KEY_DIR = path.resolve("tests", "keys");
 
 function loadOrCreateKeypair(name: string): Keypair {
@@ -116,12 +160,43 @@ function buildIncoIdl(): anchor.Idl {
           { name: "input_type", type: "u8" },
         ],
       },
+      {
+        name: "transfer",
+        discriminator: [163, 52, 200, 231, 140, 3, 69, 186],
+        accounts: [
+          { name: "source", writable: true },
+          { name: "destination", writable: true },
+          { name: "authority", writable: true, signer: true },
+          {
+            name: "inco_lightning_program",
+            address: INCO_LIGHTNING_PROGRAM_ID.toString(),
+          },
+          {
+            name: "system_program",
+            address: "11111111111111111111111111111111",
+          },
+        ],
+        args: [
+          { name: "ciphertext", type: "bytes" },
+          { name: "input_type", type: "u8" },
+        ],
+      },
     ],
     accounts: [],
     types: [],
   } as anchor.Idl;
 }
 
+// `inco_token::HANDLE_INPUT_TYPE`: the ciphertext argument is a 16-byte little-endian handle.
+const HANDLE_INPUT_TYPE = 2;
+
+function handleCiphertext(handle: bigint): Buffer {
+  const bytes = Buffer.alloc(16);
+  bytes.writeBigUInt64LE(handle & ((1n << 64n) - 1n), 0);
+  bytes.writeBigUInt64LE(handle >> 64n, 8);
+  return bytes;
+}
+
 async function encryptAmount(
   amount: bigint,
 ): Promise<{ ciphertext: Buffer; inputType: number }> {
@@ -129,6 +204,45 @@ async function encryptAmount(
   return { ciphertext: hexToBuffer(encryptedHex), inputType: 0 };
 }
 
+// Plaintext of `handle`; `owner` must hold an Inco allowance on it.
+async function decryptHandle(handle: BN | bigint, owner: Keypair): Promise<bigint> {
+  const result = await decrypt([handle.toString()], {
+    address: owner.publicKey,
+    signMessage: async (message: Uint8Array) =>
+      nacl.sign.detached(message, owner.secretKey),
+  });
+  return BigInt(result.plaintexts[0]);
+}
+
+type SignedOrder = {
+  market: PublicKey;
+  maker: PublicKey;
+  side: number;
+  price: BN;
+  sizeCiphertext: Buffer;
+  inputType: number;
+  nonce: BN;
+  expirySlot: BN;
+};
+
+// `SIGNED_ORDER_DOMAIN || borsh(SignedOrder)`, the bytes the maker signs.
+function signedOrderMessage(order: SignedOrder): Buffer {
+  const sizeLen = Buffer.alloc(4);
+  sizeLen.writeUInt32LE(order.sizeCiphertext.length);
+  return Buffer.concat([
+    SIGNED_ORDER_DOMAIN,
+    order.market.toBuffer(),
+    order.maker.toBuffer(),
+    Buffer.from([order.side]),
+    order.price.toArrayLike(Buffer, "le", 8),
+    sizeLen,
+    order.sizeCiphertext,
+    Buffer.from([order.inputType]),
+    order.nonce.toArrayLike(Buffer, "le", 8),
+    order.expirySlot.toArrayLike(Buffer, "le", 8),
+  ]);
+}
+
 describe("zivo-v1 orderbook", () => {
   const provider = anchor.AnchorProvider.env();
   anchor.setProvider(provider);
@@ -136,6 +250,8 @@ describe("zivo-v1 orderbook", () => {
   const program = anchor.workspace
     .ZivoOrderbookProgram as anchor.Program<ZivoOrderbookProgram>;
   const incoProgram = new anchor.Program(buildIncoIdl(), provider);
+  const cpiTrader = anchor.workspace
+    .ZivoCpiTrader as anchor.Program<ZivoCpiTrader>;
 
   let statePda: PublicKey;
   let incoVaultAuthority: PublicKey;
@@ -268,6 +384,50 @@ describe("zivo-v1 orderbook", () => {
     )[0];
   }
 
+  function allowancePda(handle: bigint, allowed: PublicKey): PublicKey {
+    const handleBytes = Buffer.alloc(16);
+    handleBytes.writeBigUInt64LE(handle & 0xffff_ffff_ffff_ffffn, 0);
+    handleBytes.writeBigUInt64LE(handle >> 64n, 8);
+    return PublicKey.findProgramAddressSync(
+      [handleBytes, allowed.toBuffer()],
+      INCO_LIGHTNING_PROGRAM_ID,
+    )[0];
+  }
+
+  // `[allowance, owner]` pairs for the handles `allow_owners` logs before failing.
+  function ownerAllowancePairs(logs: string[]): AccountMeta[] {
+    return logs.flatMap((line) => {
+      const m = line.match(/owner allowance required: (\w+) (\d+)/);
+      if (!m) {
+        return [];
+      }
+      const owner = new PublicKey(m[1]);
+      return [
+        { pubkey: allowancePda(BigInt(m[2]), owner), isSigner: false, isWritable: true },
+        { pubkey: owner, isSigner: false, isWritable: false },
+      ];
+    });
+  }
+
+  // New handles are only known once the instruction runs: simulate it without owner pairs,
+  // read the handles `allow_owners` logs before failing, and pass their allowance PDAs.
+  // `preInstructions` must match the ones the method itself carries (e.g. an ed25519 check).
+  async function withOwnerAllowances(
+    method: any,
+    feePayer: PublicKey,
+    preInstructions: TransactionInstruction[] = [],
+  ): Promise<any> {
+    const tx = new Transaction().add(
+      ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+      ...preInstructions,
+      await method.instruction(),
+    );
+    tx.feePayer = feePayer;
+    tx.recentBlockhash = (await provider.connection.getLatestBlockhash()).blockhash;
+    const sim = await provider.connection.simulateTransaction(tx);
+    return method.remainingAccounts(ownerAllowancePairs(sim.value.logs ?? []));
+  }
+
   type OrderMeta = {
     side: number;
     price: number;
@@ -312,6 +472,746 @@ describe("zivo-v1 orderbook", () => {
     }
   }
 
+  async function expectError(
+    label: string,
+    action: Promise<unknown>,
+    code: string,
+  ): Promise<void> {
+    try {
+      await action;
+    } catch (err: any) {
+      const logs: string[] = err?.logs ?? err?.transactionLogs ?? [];
+      if (
+        err?.error?.errorCode?.code === code ||
+        logs.some((line) => line.includes(`Error Code: ${code}`))
+      ) {
+        console.log(`${label}: rejected with ${code}`);
+        return;
+      }
+      throw err;
+    }
+    throw new Error(`${label} should fail with ${code}`);
+  }
+
+  type Market = {
+    state: PublicKey;
+    vaultAuthority: PublicKey;
+    baseVault: PublicKey;
+    quoteVault: PublicKey;
+    baseMint: PublicKey;
+    quoteMint: PublicKey;
+  };
+
+  // The shared market set up by the first test.
+  const mainMarket = (): Market => ({
+    state: statePda,
+    vaultAuthority: incoVaultAuthority,
+    baseVault: baseVault.publicKey,
+    quoteVault: quoteVault.publicKey,
+    baseMint: baseMint.publicKey,
+    quoteMint: quoteMint.publicKey,
+  });
+
+  // Places a one-base-token order at `price`, escrowing quote for bids and base for asks.
+  async function placeLimitOrder(
+    label: string,
+    trader: Keypair,
+    traderBase: Keypair,
+    traderQuote: Keypair,
+    side: number,
+    price: BN,
+    market: Market = mainMarket(),
+    accessPass: PublicKey | null = null,
+  ): Promise<PublicKey> {
+    const sizeCipher = await encryptAmount(tradeBaseAmount);
+    const seq = new BN(
+      (await program.account.orderbookState.fetch(market.state)).orderSeq.toString(),
+    );
+    const order = orderPda(market.state, trader.publicKey, seq);
+    await sendTx(
+      label,
+      await withOwnerAllowances(
+        program.methods
+          .placeOrder(
+            side,
+            price,
+            sizeCipher.ciphertext,
+            sizeCipher.inputType,
+          )
+          .accounts({
+            state: market.state,
+            order,
+            trader: trader.publicKey,
+            accessPass,
+            sizeBuffer: null,
+            incoVaultAuthority: market.vaultAuthority,
+            incoBaseVault: market.baseVault,
+            incoQuoteVault: market.quoteVault,
+            traderBaseInco: traderBase.publicKey,
+            traderQuoteInco: traderQuote.publicKey,
+            incoBaseMint: market.baseMint,
+            incoQuoteMint: market.quoteMint,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+            incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+          }),
+        trader.publicKey,
+      ),
+      [trader],
+    );
+    return order;
+  }
+
+  // Fills seller1's resting ask with buyer1's bid as taker, for `amount` base (one token by default).
+  // With `reqBaseBuffer`, the requested size is read from that matcher buffer instead of inline.
+  async function matchSellerAsk(
+    label: string,
+    ask: PublicKey,
+    bid: PublicKey,
+    price: BN,
+    amount: bigint = tradeBaseAmount,
+    reqBaseBuffer: PublicKey | null = null,
+  ): Promise<void> {
+    const takerBase = reqBaseBuffer
+      ? { ciphertext: Buffer.alloc(0), inputType: 0 }
+      : await encryptAmount(amount);
+    await sendTx(
+      label,
+      await withOwnerAllowances(
+        program.methods
+          .matchOrder(0, price, takerBase.ciphertext, takerBase.inputType)
+          .accounts({
+            state: statePda,
+            makerOrder: ask,
+            takerOrder: bid,
+            owner: seller1.publicKey,
+            matcher: payer.publicKey,
+            taker: buyer1.publicKey,
+            takerAccessPass: null,
+            priceFeed: null,
+            makerGroup: null,
+            takerGroup: null,
+            takerReqBaseBuffer: reqBaseBuffer,
+            incoVaultAuthority,
+            incoBaseVault: baseVault.publicKey,
+            incoQuoteVault: quoteVault.publicKey,
+            makerBaseInco: seller1Base.publicKey,
+            makerQuoteInco: seller1Quote.publicKey,
+            takerBaseInco: buyer1Base.publicKey,
+            takerQuoteInco: buyer1Quote.publicKey,
+            incoBaseMint: baseMint.publicKey,
+            incoQuoteMint: quoteMint.publicKey,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+            incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+          }),
+        payer.publicKey,
+      ),
+      [payer],
+      true,
+    );
+  }
+
+  function bufferPda(owner: PublicKey, bufferId: BN): PublicKey {
+    return PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("ciphertext_buffer_v1"),
+        owner.toBuffer(),
+        Buffer.from(bufferId.toArray("le", 8)),
+      ],
+      program.programId,
+    )[0];
+  }
+
+  // Creates `owner`'s buffer `bufferId` and writes `ciphertext` into it `chunkLen` bytes per
+  // transaction. Returns the buffer address.
+  async function writeCiphertextBuffer(
+    label: string,
+    owner: Keypair,
+    bufferId: BN,
+    ciphertext: Buffer,
+    chunkLen: number,
+  ): Promise<PublicKey> {
+    const buffer = bufferPda(owner.publicKey, bufferId);
+    await sendTx(
+      `${label}_init`,
+      program.methods.initBuffer(bufferId).accounts({
+        buffer,
+        owner: owner.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }),
+      [owner],
+    );
+    for (let offset = 0; offset < ciphertext.length; offset += chunkLen) {
+      await sendTx(
+        `${label}_write_${offset}`,
+        program.methods
+          .writeBuffer(offset, ciphertext.subarray(offset, offset + chunkLen))
+          .accounts({ buffer, owner: owner.publicKey }),
+        [owner],
+      );
+    }
+    return buffer;
+  }
+
+  async function cancelLimitOrder(
+    label: string,
+    order: PublicKey,
+    trader: Keypair,
+    traderBase: Keypair,
+    traderQuote: Keypair,
+    market: Market = mainMarket(),
+  ): Promise<void> {
+    await sendTx(
+      label,
+      program.methods
+        .cancelOrder()
+        .accounts({
+          state: market.state,
+          order,
+          orderGroup: null,
+          trader: trader.publicKey,
+          incoVaultAuthority: market.vaultAuthority,
+          incoBaseVault: market.baseVault,
+          incoQuoteVault: market.quoteVault,
+          traderBaseInco: traderBase.publicKey,
+          traderQuoteInco: traderQuote.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+          incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+        }),
+      [trader],
+      true,
+    );
+  }
+
+  // A zeroed `PriceFeedData` account standing in for the market feed: price 0, published at
+  // slot 0. Returns the feed address.
+  async function setZeroedPriceFeed(
+    label: string,
+    market: Market,
+    maxStalenessSlots: BN,
+  ): Promise<PublicKey> {
+    const feed = Keypair.generate();
+    await sendTx(
+      label,
+      program.methods
+        .setPriceFeed(maxStalenessSlots, 100)
+        .accounts({ state: market.state, priceFeed: feed.publicKey, admin: payer.publicKey })
+        .preInstructions([
+          SystemProgram.createAccount({
+            fromPubkey: payer.publicKey,
+            newAccountPubkey: feed.publicKey,
+            space: 40,
+            lamports: await provider.connection.getMinimumBalanceForRentExemption(40),
+            programId: SystemProgram.programId,
+          }),
+        ]),
+      [payer, feed],
+    );
+    return feed.publicKey;
+  }
+
+    // A fresh market on new mints, initialized with `payer` as admin.
+  async function createMarket(label: string): Promise<Market> {
+    const mints = [Keypair.generate(), Keypair.generate()];
+    const vaults = [Keypair.generate(), Keypair.generate()];
+    await initializeIncoMint(mints[0], baseDecimals);
+    await initializeIncoMint(mints[1], quoteDecimals);
+    const [state] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("orderbook_market_v1"),
+        mints[0].publicKey.toBuffer(),
+        mints[1].publicKey.toBuffer(),
+      ],
+      program.programId,
+    );
+    const [vaultAuthority] = PublicKey.findProgramAddressSync(
+      [Buffer.from("inco_vault_authority_v12"), state.toBuffer()],
+      program.programId,
+    );
+    await initializeIncoAccount(vaults[0], mints[0].publicKey, vaultAuthority);
+    await initializeIncoAccount(vaults[1], mints[1].publicKey, vaultAuthority);
+    const market: Market = {
+      state,
+      vaultAuthority,
+      baseVault: vaults[0].publicKey,
+      quoteVault: vaults[1].publicKey,
+      baseMint: mints[0].publicKey,
+      quoteMint: mints[1].publicKey,
+    };
+
+    const [protocolConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("protocol_config_v1")],
+      program.programId,
+    );
+    const [marketRegistry] = PublicKey.findProgramAddressSync(
+      [Buffer.from("market_registry_v1")],
+      program.programId,
+    );
+    const protocol = await program.account.protocolConfig.fetch(protocolConfig);
+    await sendTx(
+      `${label}_initialize`,
+      program.methods.initialize(false).accounts({
+        state,
+        protocolConfig,
+        marketRegistry,
+        treasury: protocol.treasury,
+        incoVaultAuthority: vaultAuthority,
+        incoBaseVault: market.baseVault,
+        incoQuoteVault: market.quoteVault,
+        incoBaseMint: market.baseMint,
+        incoQuoteMint: market.quoteMint,
+        admin: payer.publicKey,
+        payer: payer.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+      }),
+      [payer],
+    );
+    const created = await program.account.orderbookState.fetch(state);
+    if (
+      created.makerFeeBps !== protocol.defaultMakerFeeBps ||
+      created.takerFeeBps !== protocol.defaultTakerFeeBps
+    ) {
+      throw new Error("a new market should take the protocol's default fees");
+    }
+    return market;
+  }
+
+  // Encrypted balance handle of an Inco account (`IncoAccount.amount`, bytes 72..88 LE).
+  async function incoBalanceHandle(account: PublicKey): Promise<bigint> {
+    const info = await provider.connection.getAccountInfo(account);
+    if (!info) {
+      throw new Error(`inco account ${account.toBase58()} not found`);
+    }
+    const bytes = info.data.subarray(72, 88);
+    return bytes.readBigUInt64LE(0) | (bytes.readBigUInt64LE(8) << 64n);
+  }
+
+  // Compressed orders ride in v0 transactions: the Light system accounts and validity proof do
+  // not fit a legacy transaction next to the Inco accounts.
+  async function sendV0(
+    label: string,
+    instructions: TransactionInstruction[],
+    signers: Keypair[],
+    lookupTable: AddressLookupTableAccount,
+  ): Promise<string> {
+    const { blockhash, lastValidBlockHeight } =
+      await provider.connection.getLatestBlockhash();
+    const message = new TransactionMessage({
+      payerKey: signers[0].publicKey,
+      recentBlockhash: blockhash,
+      instructions: [
+        ComputeBudgetProgram.setComputeUnitLimit({ units: 1_000_000 }),
+        ...instructions,
+      ],
+    }).compileToV0Message([lookupTable]);
+    const tx = new VersionedTransaction(message);
+    tx.sign(signers);
+    try {
+      const sig = await provider.connection.sendTransaction(tx);
+      await provider.connection.confirmTransaction(
+        { signature: sig, blockhash, lastValidBlockHeight },
+        "confirmed",
+      );
+      console.log(`${label}.tx: ${explorerBase}${sig}?cluster=devnet`);
+      return sig;
+    } catch (err) {
+      if (err instanceof SendTransactionError) {
+        console.log(`${label}.error.logs:`, err.logs ?? []);
+      }
+      throw err;
+    }
+  }
+
+  // The v0 counterpart of `withOwnerAllowances`: `build` makes the instruction for a given list
+  // of owner pairs, which lead the remaining accounts.
+  async function sendV0WithOwnerAllowances(
+    label: string,
+    build: (pairs: AccountMeta[]) => Promise<TransactionInstruction>,
+    signers: Keypair[],
+    lookupTable: AddressLookupTableAccount,
+  ): Promise<string> {
+    const { blockhash } = await provider.connection.getLatestBlockhash();
+    const message = new TransactionMessage({
+      payerKey: signers[0].publicKey,
+      recentBlockhash: blockhash,
+      instructions: [
+        ComputeBudgetProgram.setComputeUnitLimit({ units: 1_000_000 }),
+        await build([]),
+      ],
+    }).compileToV0Message([lookupTable]);
+    const sim = await provider.connection.simulateTransaction(
+      new VersionedTransaction(message),
+      { sigVerify: false },
+    );
+    const pairs = ownerAllowancePairs(sim.value.logs ?? []);
+    return sendV0(label, [await build(pairs)], signers, lookupTable);
+  }
+
+  async function createLookupTable(
+    label: string,
+    addresses: PublicKey[],
+  ): Promise<AddressLookupTableAccount> {
+    const unique = [...new Set(addresses.map((a) => a.toBase58()))].map(
+      (a) => new PublicKey(a),
+    );
+    const [createIx, table] = AddressLookupTableProgram.createLookupTable({
+      authority: payer.publicKey,
+      payer: payer.publicKey,
+      recentSlot: await provider.connection.getSlot("confirmed"),
+    });
+    await provider.sendAndConfirm(new Transaction().add(createIx), [payer]);
+    for (let i = 0; i < unique.length; i += 20) {
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          AddressLookupTableProgram.extendLookupTable({
+            payer: payer.publicKey,
+            authority: payer.publicKey,
+            lookupTable: table,
+            addresses: unique.slice(i, i + 20),
+          }),
+        ),
+        [payer],
+      );
+    }
+    // A table can only be used from the slot after its last extension.
+    const extendedAt = await provider.connection.getSlot("confirmed");
+    while ((await provider.connection.getSlot("confirmed")) <= extendedAt) {
+      await new Promise((resolve) => setTimeout(resolve, 400));
+    }
+    const account = (await provider.connection.getAddressLookupTable(table)).value;
+    if (!account) {
+      throw new Error(`${label}: lookup table ${table.toBase58()} not found`);
+    }
+    return account;
+  }
+
+  function getLightRpc() {
+    return createRpc(
+      process.env.LIGHT_RPC_URL,
+      process.env.LIGHT_COMPRESSION_URL,
+      process.env.LIGHT_PROVER_URL,
+    );
+  }
+
+  const lightSystemAccounts = (): AccountMeta[] =>
+    getLightSystemAccountMetasV2(SystemAccountMetaConfig.new(program.programId));
+
+  // Mirrors the `order_v1` address `place_compressed_order` derives in the Light address tree.
+  function compressedOrderAddress(
+    state: PublicKey,
+    owner: PublicKey,
+    seq: BN,
+  ): PublicKey {
+    const seed = deriveAddressSeedV2([
+      Buffer.from("order_v1"),
+      state.toBuffer(),
+      owner.toBuffer(),
+      Buffer.from(seq.toArray("le", 8)),
+    ]);
+    return deriveAddressV2(seed, getDefaultAddressTreeInfo().tree, program.programId);
+  }
+
+  type CompressedOrderAccount = {
+    order: any;
+    hash: BN;
+    tree: PublicKey;
+    queue: PublicKey;
+    address: number[];
+  };
+
+  // The indexed compressed order at `address`, waiting until the indexer has caught up with
+  // `ready` (by default, until the account exists). Returns null once the account is closed.
+  async function fetchCompressedOrder(
+    address: PublicKey,
+    ready: (account: CompressedOrderAccount | null) => boolean = (a) => a !== null,
+  ): Promise<CompressedOrderAccount | null> {
+    const rpc = getLightRpc();
+    let account: CompressedOrderAccount | null = null;
+    for (let i = 0; i < 20; i++) {
+      const compressed = await rpc.getCompressedAccount(bn(address.toBytes()));
+      account = compressed
+        ? {
+            order: program.coder.types.decode(
+              "compressedOrder",
+              Buffer.from(compressed.data!.data),
+            ),
+            hash: compressed.hash,
+            tree: new PublicKey(compressed.treeInfo.tree),
+            queue: new PublicKey(compressed.treeInfo.queue),
+            address: Array.from(address.toBytes()),
+          }
+        : null;
+      if (ready(account)) {
+        return account;
+      }
+      await new Promise((resolve) => setTimeout(resolve, 1000));
+    }
+    throw new Error(`compressed order ${address.toBase58()} did not reach the expected state`);
+  }
+
+  // Validity proof and account metas for updating or closing `accounts`. The trees sit after
+  // the Light system accounts as `[state_queue, state_tree]`, and outputs go to the queue.
+  async function compressedOrderProof(
+    accounts: CompressedOrderAccount[],
+  ): Promise<{ proof: any; metas: any[] }> {
+    const result = await getLightRpc().getValidityProofV0(
+      accounts.map((a) => ({ hash: a.hash, tree: a.tree, queue: a.queue })),
+      [],
+    );
+    return {
+      proof: { 0: result.compressedProof },
+      metas: accounts.map((a, i) => ({
+        treeInfo: {
+          rootIndex: result.rootIndices[i],
+          proveByIndex: result.proveByIndices[i],
+          merkleTreePubkeyIndex: 1,
+          queuePubkeyIndex: 0,
+          leafIndex: result.leafIndices[i],
+        },
+        address: a.address,
+        outputStateTreeIndex: 0,
+      })),
+    };
+  }
+
+  // Places a one-base-token compressed order at `price` into `stateTree`. Returns its address.
+  async function placeCompressedOrder(
+    label: string,
+    trader: Keypair,
+    traderBase: PublicKey,
+    traderQuote: PublicKey,
+    side: number,
+    price: BN,
+    market: Market,
+    stateTree: { tree: PublicKey; queue: PublicKey },
+    lookupTable: AddressLookupTableAccount,
+  ): Promise<PublicKey> {
+    const sizeCipher = await encryptAmount(tradeBaseAmount);
+    const seq = new BN(
+      (await program.account.orderbookState.fetch(market.state)).orderSeq.toString(),
+    );
+    const address = compressedOrderAddress(market.state, trader.publicKey, seq);
+    const addressTree = getDefaultAddressTreeInfo();
+    const result = await getLightRpc().getValidityProofV0(
+      [],
+      [{ tree: addressTree.tree, queue: addressTree.queue, address: bn(address.toBytes()) }],
+    );
+    // Tree accounts, without repeats: address tree, address queue, state queue, state tree.
+    const sharedQueue = addressTree.queue.equals(addressTree.tree);
+    const addressTreeInfo = {
+      addressMerkleTreePubkeyIndex: 0,
+      addressQueuePubkeyIndex: sharedQueue ? 0 : 1,
+      rootIndex: result.rootIndices[0],
+    };
+    await sendV0WithOwnerAllowances(
+      label,
+      (pairs) =>
+        program.methods
+          .placeCompressedOrder(
+            side,
+            price,
+            sizeCipher.ciphertext,
+            sizeCipher.inputType,
+            { 0: result.compressedProof },
+            addressTreeInfo,
+            sharedQueue ? 1 : 2,
+            pairs.length,
+          )
+          .accounts({
+            state: market.state,
+            trader: trader.publicKey,
+            accessPass: null,
+            incoVaultAuthority: market.vaultAuthority,
+            incoBaseVault: market.baseVault,
+            incoQuoteVault: market.quoteVault,
+            traderBaseInco: traderBase,
+            traderQuoteInco: traderQuote,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+            incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+            addressTree: addressTree.tree,
+            addressQueue: addressTree.queue,
+            stateQueue: stateTree.queue,
+            stateTree: stateTree.tree,
+          })
+          .remainingAccounts([...pairs, ...lightSystemAccounts()])
+          .instruction(),
+      [trader],
+      lookupTable,
+    );
+    return address;
+  }
+
+  // zivo-wrap is a separate workspace; its IDL comes from its own `anchor build`.
+  function loadZivoWrap(): anchor.Program | null {
+    const idlPath = path.resolve("..", "zivo-wrap", "target", "idl", "zivo_wrap.json");
+    if (!fs.existsSync(idlPath)) {
+      return null;
+    }
+    return new anchor.Program(JSON.parse(fs.readFileSync(idlPath, "utf8")), provider);
+  }
+
+  type ShieldedPool = {
+    pool: PublicKey;
+    vault: PublicKey;
+    splMint: PublicKey;
+    vaultToken: PublicKey;
+    incoMint: PublicKey;
+    poolInco: PublicKey;
+  };
+
+  // A zivo-wrap vault and shielded pool over a fresh SPL mint and `incoMint`, with `payer` as
+  // pool authority and zivo-wrap itself standing in for the verifier.
+  async function createShieldedPool(
+    label: string,
+    zivoWrap: anchor.Program,
+    incoMint: PublicKey,
+  ): Promise<ShieldedPool> {
+    const splMint = Keypair.generate();
+    const vaultToken = Keypair.generate();
+    const poolInco = Keypair.generate();
+    const [vault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), splMint.publicKey.toBuffer(), incoMint.toBuffer()],
+      zivoWrap.programId,
+    );
+    const [pool] = PublicKey.findProgramAddressSync(
+      [Buffer.from("shielded_pool"), splMint.publicKey.toBuffer(), incoMint.toBuffer()],
+      zivoWrap.programId,
+    );
+    const connection = provider.connection;
+    // SPL InitializeMint2 (20) and InitializeAccount3 (18), without a spl-token dependency.
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: payer.publicKey,
+          newAccountPubkey: splMint.publicKey,
+          lamports: await connection.getMinimumBalanceForRentExemption(82),
+          space: 82,
+          programId: SPL_TOKEN_PROGRAM_ID,
+        }),
+        new TransactionInstruction({
+          programId: SPL_TOKEN_PROGRAM_ID,
+          keys: [{ pubkey: splMint.publicKey, isSigner: false, isWritable: true }],
+          data: Buffer.concat([
+            Buffer.from([20, quoteDecimals]),
+            payer.publicKey.toBuffer(),
+            Buffer.from([0]),
+          ]),
+        }),
+        SystemProgram.createAccount({
+          fromPubkey: payer.publicKey,
+          newAccountPubkey: vaultToken.publicKey,
+          lamports: await connection.getMinimumBalanceForRentExemption(165),
+          space: 165,
+          programId: SPL_TOKEN_PROGRAM_ID,
+        }),
+        new TransactionInstruction({
+          programId: SPL_TOKEN_PROGRAM_ID,
+          keys: [
+            { pubkey: vaultToken.publicKey, isSigner: false, isWritable: true },
+            { pubkey: splMint.publicKey, isSigner: false, isWritable: false },
+          ],
+          data: Buffer.concat([Buffer.from([18]), vault.toBuffer()]),
+        }),
+      ),
+      [payer, splMint, vaultToken],
+    );
+    await initializeIncoAccount(poolInco, incoMint, vault);
+    await sendTx(
+      `${label}_wrap_vault`,
+      zivoWrap.methods.initializeVault().accounts({
+        vault,
+        splTokenMint: splMint.publicKey,
+        incoTokenMint: incoMint,
+        vaultTokenAccount: vaultToken.publicKey,
+        authority: payer.publicKey,
+        systemProgram: SystemProgram.programId,
+      }),
+      [payer],
+    );
+    await sendTx(
+      `${label}_shielded_pool`,
+      zivoWrap.methods.initShieldedPool(26).accounts({
+        shieldedPool: pool,
+        vault,
+        splTokenMint: splMint.publicKey,
+        incoTokenMint: incoMint,
+        stateTree: Keypair.generate().publicKey,
+        addressTree: Keypair.generate().publicKey,
+        nullifierQueue: Keypair.generate().publicKey,
+        authority: payer.publicKey,
+        systemProgram: SystemProgram.programId,
+      }),
+      [payer],
+    );
+    await sendTx(
+      `${label}_configure_pool`,
+      zivoWrap.methods.configureShieldedPool().accounts({
+        shieldedPool: pool,
+        vault,
+        poolIncoAccount: poolInco.publicKey,
+        verifierProgram: zivoWrap.programId,
+        authority: payer.publicKey,
+        systemProgram: SystemProgram.programId,
+      }),
+      [payer],
+    );
+    return {
+      pool,
+      vault,
+      splMint: splMint.publicKey,
+      vaultToken: vaultToken.publicKey,
+      incoMint,
+      poolInco: poolInco.publicKey,
+    };
+  }
+
+  // 32-byte big-endian field, reduced below the BN254 modulus like `hash_to_field`.
+  const randomField = (): Buffer => {
+    const bytes = Keypair.generate().publicKey.toBuffer();
+    bytes[0] = 0;
+    return bytes;
+  };
+
+  // `hashv_to_bn254_field_size_be` of a key, as zivo-wrap binds recipients and mints.
+  const keyField = (key: PublicKey): Buffer =>
+    Buffer.from(hashvToBn254FieldSizeBe([key.toBytes()]));
+
+  // A note-spend payload: a placeholder Groth16 proof followed by the circuit's public inputs
+  // root, nullifier, recipient, amount, mint, commitment, leaf, each a 32-byte big-endian field.
+  // Inputs left out are random.
+  function noteSpendPayload(inputs: {
+    root: Buffer;
+    nullifier: Buffer;
+    amount: bigint;
+    recipient?: PublicKey;
+    mint?: PublicKey;
+    commitment?: Buffer;
+    leaf?: Buffer;
+  }): Buffer {
+    const amount = Buffer.alloc(32);
+    amount.writeBigUInt64BE(inputs.amount, 24);
+    return Buffer.concat([
+      Buffer.alloc(256),
+      inputs.root,
+      inputs.nullifier,
+      inputs.recipient ? keyField(inputs.recipient) : randomField(),
+      amount,
+      inputs.mint ? keyField(inputs.mint) : randomField(),
+      inputs.commitment ?? randomField(),
+      inputs.leaf ?? randomField(),
+    ]);
+  }
+
+  const noteBackingPda = (pool: PublicKey, commitment: Buffer, wrapProgram: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("note_backing"), pool.toBuffer(), commitment],
+      wrapProgram,
+    )[0];
+
   before(async () => {
     const incoProgramAccount = await provider.connection.getAccountInfo(
       INCO_TOKEN_PROGRAM_ID,
@@ -455,11 +1355,51 @@ describe("zivo-v1 orderbook", () => {
     await mintToInco(baseMint.publicKey, seller1Base.publicKey, 5_000_000n);
     await mintToInco(baseMint.publicKey, seller2Base.publicKey, 5_000_000n);
 
+    const [protocolConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("protocol_config_v1")],
+      program.programId,
+    );
+    const [marketRegistry] = PublicKey.findProgramAddressSync(
+      [Buffer.from("market_registry_v1")],
+      program.programId,
+    );
+    // Only the program upgrade authority (the deploy wallet) may initialize the protocol.
+    const [programData] = PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      BPF_LOADER_UPGRADEABLE_PROGRAM_ID,
+    );
+    if (!(await provider.connection.getAccountInfo(protocolConfig))) {
+      const protocolTx = await program.methods
+        .initializeProtocol(0, 0, 0, new BN(0))
+        .accounts({
+          protocolConfig,
+          marketRegistry,
+          treasury: payer.publicKey,
+          admin: payer.publicKey,
+          program: program.programId,
+          programData,
+          payer: payer.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([payer])
+        .rpc();
+      console.log(
+        "initialize_protocol.tx:",
+        `${explorerBase}${protocolTx}?cluster=devnet`,
+      );
+    }
+
     if (!stateInfo) {
+      const protocol = await program.account.protocolConfig.fetch(
+        protocolConfig,
+      );
       const initTx = await program.methods
         .initialize(false)
         .accounts({
           state: statePda,
+          protocolConfig,
+          marketRegistry,
+          treasury: protocol.treasury,
           incoVaultAuthority,
           incoBaseVault: baseVault.publicKey,
           incoQuoteVault: quoteVault.publicKey,
@@ -521,6 +1461,7 @@ describe("zivo-v1 orderbook", () => {
           deposit: depositAddress,
           userBaseInco: baseAcct.publicKey,
           userQuoteInco: quoteAcct.publicKey,
+          accessPass: null,
           systemProgram: anchor.web3.SystemProgram.programId,
         })
         .signers([payer, user])
@@ -528,13 +1469,43 @@ describe("zivo-v1 orderbook", () => {
     }
   });
 
-  it("places orders and matches (partial-fill ready)", async () => {
-    const stateInfo = await provider.connection.getAccountInfo(statePda);
-    if (!stateInfo) {
-      console.log("place/settle: state PDA missing; run initialize test first");
-      return;
-    }
-    if (!stateInfo.owner.equals(program.programId)) {
+  it("moves exactly a handle's value under the handle input type", async () => {
+    // Every vault payout, claim and pool spend names a handle with HANDLE_INPUT_TYPE; check
+    // the deployed inco-token reads it that way before relying on it.
+    const source = Keypair.generate();
+    const destination = Keypair.generate();
+    await initializeIncoAccount(source, quoteMint.publicKey, buyer1.publicKey);
+    await initializeIncoAccount(destination, quoteMint.publicKey, buyer1.publicKey);
+    await topUpIncoAccount(source.publicKey, quoteMint.publicKey, 1_234n);
+    const sourceHandle = await incoBalanceHandle(source.publicKey);
+
+    await sendTx(
+      "handle_input_transfer",
+      incoProgram.methods
+        .transfer(handleCiphertext(sourceHandle), HANDLE_INPUT_TYPE)
+        .accounts({
+          source: source.publicKey,
+          destination: destination.publicKey,
+          authority: buyer1.publicKey,
+        }),
+      [buyer1],
+    );
+    const moved = await decryptHandle(await incoBalanceHandle(destination.publicKey), buyer1);
+    const left = await decryptHandle(await incoBalanceHandle(source.publicKey), buyer1);
+    if (moved !== 1_234n || left !== 0n) {
+      throw new Error(
+        `a handle transfer should move the handle's 1234, moved ${moved} and left ${left}`,
+      );
+    }
+  });
+
+  it("places orders and matches (partial-fill ready)", async () => {
+    const stateInfo = await provider.connection.getAccountInfo(statePda);
+    if (!stateInfo) {
+      console.log("place/settle: state PDA missing; run initialize test first");
+      return;
+    }
+    if (!stateInfo.owner.equals(program.programId)) {
       console.log(
         `place/settle: state owned by ${stateInfo.owner.toBase58()}; skip or redeploy with program-owned state`,
       );
@@ -552,8 +1523,6 @@ describe("zivo-v1 orderbook", () => {
     );
     const price = new BN(100);
     const sizeCipher = await encryptAmount(tradeBaseAmount);
-    const quoteEscrow = await encryptAmount(tradeQuoteAmount);
-    const baseEscrow = await encryptAmount(tradeBaseAmount);
 
     // Top-up balances every run so retries are stable
     await topUpIncoAccount(
@@ -592,30 +1561,33 @@ describe("zivo-v1 orderbook", () => {
 
     await sendTx(
       "place_bid",
-      program.methods
-        .placeOrder(
-          0,
-          price,
-          sizeCipher.ciphertext,
-          sizeCipher.inputType,
-          quoteEscrow.ciphertext,
-          quoteEscrow.inputType,
-        )
-        .accounts({
-          state: statePda,
-          order: bidOrder,
-          trader: buyer1.publicKey,
-          incoVaultAuthority,
-          incoBaseVault: baseVault.publicKey,
-          incoQuoteVault: quoteVault.publicKey,
-          traderBaseInco: buyer1Base.publicKey,
-          traderQuoteInco: buyer1Quote.publicKey,
-          incoBaseMint: baseMint.publicKey,
-          incoQuoteMint: quoteMint.publicKey,
-          systemProgram: anchor.web3.SystemProgram.programId,
-          incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
-          incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
-        }),
+      await withOwnerAllowances(
+        program.methods
+          .placeOrder(
+            0,
+            price,
+            sizeCipher.ciphertext,
+            sizeCipher.inputType,
+          )
+          .accounts({
+            state: statePda,
+            order: bidOrder,
+            trader: buyer1.publicKey,
+            accessPass: null,
+            sizeBuffer: null,
+            incoVaultAuthority,
+            incoBaseVault: baseVault.publicKey,
+            incoQuoteVault: quoteVault.publicKey,
+            traderBaseInco: buyer1Base.publicKey,
+            traderQuoteInco: buyer1Quote.publicKey,
+            incoBaseMint: baseMint.publicKey,
+            incoQuoteMint: quoteMint.publicKey,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+            incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+          }),
+        buyer1.publicKey,
+      ),
       [buyer1],
     );
     orderMetas.push({
@@ -640,30 +1612,33 @@ describe("zivo-v1 orderbook", () => {
 
     await sendTx(
       "place_ask",
-      program.methods
-        .placeOrder(
-          1,
-          price,
-          sizeCipher.ciphertext,
-          sizeCipher.inputType,
-          baseEscrow.ciphertext,
-          baseEscrow.inputType,
-        )
-        .accounts({
-          state: statePda,
-          order: askOrder,
-          trader: seller1.publicKey,
-          incoVaultAuthority,
-          incoBaseVault: baseVault.publicKey,
-          incoQuoteVault: quoteVault.publicKey,
-          traderBaseInco: seller1Base.publicKey,
-          traderQuoteInco: seller1Quote.publicKey,
-          incoBaseMint: baseMint.publicKey,
-          incoQuoteMint: quoteMint.publicKey,
-          systemProgram: anchor.web3.SystemProgram.programId,
-          incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
-          incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
-        }),
+      await withOwnerAllowances(
+        program.methods
+          .placeOrder(
+            1,
+            price,
+            sizeCipher.ciphertext,
+            sizeCipher.inputType,
+          )
+          .accounts({
+            state: statePda,
+            order: askOrder,
+            trader: seller1.publicKey,
+            accessPass: null,
+            sizeBuffer: null,
+            incoVaultAuthority,
+            incoBaseVault: baseVault.publicKey,
+            incoQuoteVault: quoteVault.publicKey,
+            traderBaseInco: seller1Base.publicKey,
+            traderQuoteInco: seller1Quote.publicKey,
+            incoBaseMint: baseMint.publicKey,
+            incoQuoteMint: quoteMint.publicKey,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+            incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+          }),
+        seller1.publicKey,
+      ),
       [seller1],
     );
     orderMetas.push({
@@ -684,7 +1659,6 @@ describe("zivo-v1 orderbook", () => {
     const maker = selectMaker(orderMetas, 1);
     const takerSide = maker.side === 1 ? 0 : 1;
     const matchBaseCiphertext = sizeCipher.ciphertext.slice(0, 80);
-    const matchQuoteCiphertext = quoteEscrow.ciphertext.slice(0, 80);
 
     const takerMeta = orderMetas.find((o) => o.side === takerSide);
     if (!takerMeta) {
@@ -693,39 +1667,42 @@ describe("zivo-v1 orderbook", () => {
 
     await sendTx(
       "match_order",
-      program.methods
-        .matchOrder(
-          takerSide,
-          new BN(maker.price),
-          matchBaseCiphertext,
-          matchBaseCiphertext,
-          matchQuoteCiphertext,
-          sizeCipher.inputType,
-          new BN(
-            (maker.side === 1 ? tradeQuoteAmount : tradeBaseAmount).toString(),
-          ),
-        )
-        .accounts({
-          state: statePda,
-          makerOrder: maker.order,
-          takerOrder: takerMeta.order,
-          owner: maker.owner,
-          matcher: payer.publicKey,
-          taker: buyer1.publicKey,
-          incoVaultAuthority,
-          incoBaseVault: baseVault.publicKey,
-          incoQuoteVault: quoteVault.publicKey,
-          makerBaseInco: seller1Base.publicKey,
-          makerQuoteInco: seller1Quote.publicKey,
-          takerBaseInco: buyer1Base.publicKey,
-          takerQuoteInco: buyer1Quote.publicKey,
-          incoBaseMint: baseMint.publicKey,
-          incoQuoteMint: quoteMint.publicKey,
-          systemProgram: anchor.web3.SystemProgram.programId,
-          incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
-          incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
-        }),
-      [payer, buyer1],
+      await withOwnerAllowances(
+        program.methods
+          .matchOrder(
+            takerSide,
+            new BN(maker.price),
+            matchBaseCiphertext,
+            sizeCipher.inputType,
+          )
+          .accounts({
+            state: statePda,
+            makerOrder: maker.order,
+            takerOrder: takerMeta.order,
+            owner: maker.owner,
+            matcher: payer.publicKey,
+            taker: buyer1.publicKey,
+            takerAccessPass: null,
+            priceFeed: null,
+            makerGroup: null,
+            takerGroup: null,
+            takerReqBaseBuffer: null,
+            incoVaultAuthority,
+            incoBaseVault: baseVault.publicKey,
+            incoQuoteVault: quoteVault.publicKey,
+            makerBaseInco: seller1Base.publicKey,
+            makerQuoteInco: seller1Quote.publicKey,
+            takerBaseInco: buyer1Base.publicKey,
+            takerQuoteInco: buyer1Quote.publicKey,
+            incoBaseMint: baseMint.publicKey,
+            incoQuoteMint: quoteMint.publicKey,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+            incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+          }),
+        payer.publicKey,
+      ),
+      [payer],
       true,
     );
     console.log(
@@ -739,20 +1716,53 @@ describe("zivo-v1 orderbook", () => {
     const takerOrderAccount = (await program.account.order.fetch(
       takerMeta.order,
     )) as any;
-    if (!makerOrderAccount.isFilled || makerOrderAccount.isOpen) {
-      throw new Error("maker order not closed/filled after match");
-    }
-    if (!takerOrderAccount.isFilled || takerOrderAccount.isOpen) {
-      throw new Error("taker order not closed/filled after match");
+    if (!makerOrderAccount.isFilled || !makerOrderAccount.isOpen) {
+      throw new Error("maker order should stay open with a fill after match");
     }
-    if (takerOrderAccount.isClaimed !== true) {
-      throw new Error("taker order should be claimed after match");
+    if (!takerOrderAccount.isOpen || takerOrderAccount.isFilled) {
+      throw new Error("taker order should stay open after match");
     }
     if (makerOrderAccount.isClaimed !== false) {
       throw new Error("maker order should be unclaimed after match");
     }
 
+    // The claim is only ever a handle: no plaintext field on the order or in the match args.
+    const orderFields = (program.idl.types ?? [])
+      .find((type) => type.name.toLowerCase() === "order")!;
+    const matchArgs = program.idl.instructions
+      .find((ix) => ix.name === "matchOrder")!
+      .args.map((arg) => arg.name);
+    if (
+      "claimPlaintextAmount" in makerOrderAccount ||
+      JSON.stringify(orderFields).includes("Plaintext") ||
+      matchArgs.some((name) => /plaintext/i.test(name))
+    ) {
+      throw new Error("the maker claim must not be stored or passed in plaintext");
+    }
+    if (
+      makerOrderAccount.claimInputType !== HANDLE_INPUT_TYPE ||
+      makerOrderAccount.claimCiphertext.length !== 16
+    ) {
+      throw new Error("the maker claim should be a 16-byte handle");
+    }
     const makerSigner = maker.owner.equals(buyer1.publicKey) ? buyer1 : seller1;
+    const takerSignerForClaim = makerSigner === buyer1 ? seller1 : buyer1;
+    // Maker bids claim base; maker asks claim size * price in quote.
+    const claimHandle = new BN(Buffer.from(makerOrderAccount.claimCiphertext), "le");
+    const expectedClaim = maker.side === 0 ? tradeBaseAmount : tradeQuoteAmount;
+    if ((await decryptHandle(claimHandle, makerSigner)) !== expectedClaim) {
+      throw new Error(`the maker should decrypt its ${expectedClaim} claim`);
+    }
+    let takerReadClaim = true;
+    try {
+      await decryptHandle(claimHandle, takerSignerForClaim);
+    } catch {
+      takerReadClaim = false;
+    }
+    if (takerReadClaim) {
+      throw new Error("only the maker (and auditor) should be allowed to decrypt the claim");
+    }
+
     const makerBaseInco =
       makerSigner.publicKey.equals(buyer1.publicKey)
         ? buyer1Base.publicKey
@@ -785,11 +1795,3501 @@ describe("zivo-v1 orderbook", () => {
       true,
     );
 
+    // An open maker claims while it rests; its claim resets for later fills.
     const makerOrderAfterClaim = (await program.account.order.fetch(
       maker.order,
     )) as any;
-    if (makerOrderAfterClaim.isClaimed !== true) {
-      throw new Error("maker order should be claimed after claim ix");
+    if (
+      !makerOrderAfterClaim.isOpen ||
+      makerOrderAfterClaim.isFilled ||
+      makerOrderAfterClaim.isClaimed
+    ) {
+      throw new Error("maker order should stay open with no pending claim after claim ix");
+    }
+    await cancelLimitOrder(
+      "cancel_maker",
+      maker.order,
+      makerSigner,
+      makerSigner === buyer1 ? buyer1Base : seller1Base,
+      makerSigner === buyer1 ? buyer1Quote : seller1Quote,
+    );
+
+    // The taker order stays open after its fill; cancelling it refunds what is left (0 here).
+    const takerSigner = takerMeta.owner.equals(buyer1.publicKey) ? buyer1 : seller1;
+    await sendTx(
+      "cancel_taker",
+      program.methods
+        .cancelOrder()
+        .accounts({
+          state: statePda,
+          order: takerMeta.order,
+          orderGroup: null,
+          trader: takerSigner.publicKey,
+          incoVaultAuthority,
+          incoBaseVault: baseVault.publicKey,
+          incoQuoteVault: quoteVault.publicKey,
+          traderBaseInco: takerSigner === buyer1
+            ? buyer1Base.publicKey
+            : seller1Base.publicKey,
+          traderQuoteInco: takerSigner === buyer1
+            ? buyer1Quote.publicKey
+            : seller1Quote.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+          incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+        }),
+      [takerSigner],
+      true,
+    );
+    const takerAfterCancel = (await program.account.order.fetch(
+      takerMeta.order,
+    )) as any;
+    if (takerAfterCancel.isOpen) {
+      throw new Error("taker order should be closed after cancel");
+    }
+  });
+
+  it("guards the protocol config and grows the market registry per market", async () => {
+    const [protocolConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("protocol_config_v1")],
+      program.programId,
+    );
+    const [marketRegistry] = PublicKey.findProgramAddressSync(
+      [Buffer.from("market_registry_v1")],
+      program.programId,
+    );
+    const [programData] = PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      BPF_LOADER_UPGRADEABLE_PROGRAM_ID,
+    );
+    const original = await program.account.protocolConfig.fetch(protocolConfig);
+    if (!original.admin.equals(payer.publicKey)) {
+      throw new Error("initialize_protocol should make the upgrade authority the protocol admin");
+    }
+
+    // The singleton exists once; a second initialize_protocol cannot recreate it.
+    let reinitialized = true;
+    try {
+      await program.methods
+        .initializeProtocol(0, 0, 0, new BN(0))
+        .accounts({
+          protocolConfig,
+          marketRegistry,
+          treasury: buyer1.publicKey,
+          admin: buyer1.publicKey,
+          program: program.programId,
+          programData,
+          payer: buyer1.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([buyer1])
+        .rpc();
+    } catch {
+      reinitialized = false;
+    }
+    if (reinitialized) {
+      throw new Error("initialize_protocol should not run twice");
+    }
+
+    const update = (
+      admin: Keypair,
+      newAdmin: PublicKey,
+      makerFee: number,
+      takerFee: number,
+      policy: number,
+      listingFee: BN,
+      treasury: PublicKey,
+    ) =>
+      program.methods
+        .updateProtocolConfig(newAdmin, makerFee, takerFee, policy, listingFee)
+        .accounts({ protocolConfig, treasury, admin: admin.publicKey })
+        .signers([admin])
+        .rpc();
+    await expectError(
+      "protocol_update_stranger",
+      update(buyer1, buyer1.publicKey, 0, 0, 0, new BN(0), buyer1.publicKey),
+      "UnauthorizedProtocolAdmin",
+    );
+    await expectError(
+      "protocol_update_fee_too_high",
+      update(payer, payer.publicKey, 10_001, 0, 0, new BN(0), payer.publicKey),
+      "InvalidFee",
+    );
+    await expectError(
+      "protocol_update_bad_policy",
+      update(payer, payer.publicKey, 0, 0, 2, new BN(0), payer.publicKey),
+      "InvalidMarketCreationPolicy",
+    );
+
+    // Permissionless listing for a fee; new markets take the updated default fees.
+    const listingFee = new BN(1_000_000);
+    await update(payer, payer.publicKey, 5, 7, 1, listingFee, buyer2.publicKey);
+    const updated = await program.account.protocolConfig.fetch(protocolConfig);
+    if (
+      updated.defaultMakerFeeBps !== 5 ||
+      updated.defaultTakerFeeBps !== 7 ||
+      updated.marketCreationPolicy !== 1 ||
+      !updated.listingFeeLamports.eq(listingFee) ||
+      !updated.treasury.equals(buyer2.publicKey)
+    ) {
+      throw new Error("update_protocol_config should store every field");
+    }
+
+    const registryBefore = await program.account.marketRegistry.fetch(marketRegistry);
+    const treasuryBefore = await provider.connection.getBalance(buyer2.publicKey);
+    try {
+      const { state } = await createMarket("registry_growth");
+      const treasuryAfter = await provider.connection.getBalance(buyer2.publicKey);
+      if (treasuryAfter - treasuryBefore !== listingFee.toNumber()) {
+        throw new Error("a permissionless listing should pay the fee to the treasury");
+      }
+      // One key appended per market, and the account grows by exactly that key.
+      const registry = await program.account.marketRegistry.fetch(marketRegistry);
+      if (
+        registry.markets.length !== registryBefore.markets.length + 1 ||
+        !registry.markets[registry.markets.length - 1].equals(state)
+      ) {
+        throw new Error("initialize should append the new market to the registry");
+      }
+      const registryInfo = await provider.connection.getAccountInfo(marketRegistry);
+      if (registryInfo?.data.length !== 8 + 12 + 32 * registry.markets.length) {
+        throw new Error("the registry should grow by one key per market");
+      }
+    } finally {
+      await update(
+        payer,
+        original.admin,
+        original.defaultMakerFeeBps,
+        original.defaultTakerFeeBps,
+        original.marketCreationPolicy,
+        original.listingFeeLamports,
+        original.treasury,
+      );
+    }
+  });
+
+  it("keeps a partially filled maker open for the rest of its size", async () => {
+    const price = new BN(100);
+    const half = tradeBaseAmount / 2n;
+    await topUpIncoAccount(buyer1Quote.publicKey, quoteMint.publicKey, topUpQuote);
+    await topUpIncoAccount(seller1Base.publicKey, baseMint.publicKey, topUpBase);
+
+    const ask = await placeLimitOrder(
+      "partial_place_ask",
+      seller1,
+      seller1Base,
+      seller1Quote,
+      1,
+      price,
+    );
+    const bid = await placeLimitOrder(
+      "partial_place_bid",
+      buyer1,
+      buyer1Base,
+      buyer1Quote,
+      0,
+      price,
+    );
+
+    await matchSellerAsk("partial_match_first", ask, bid, price, half);
+    const askAfterFirst = (await program.account.order.fetch(ask)) as any;
+    if (!askAfterFirst.isOpen || !askAfterFirst.isFilled) {
+      throw new Error("a partially filled maker should stay open with a pending claim");
+    }
+    const askLeft = await decryptHandle(askAfterFirst.remainingHandle, seller1);
+    if (askLeft !== tradeBaseAmount - half) {
+      throw new Error(`maker should keep ${tradeBaseAmount - half} base, has ${askLeft}`);
+    }
+
+    // The remainder fills in a second match; both fills accrue on the same claim.
+    await matchSellerAsk("partial_match_second", ask, bid, price, half);
+    const askAfterSecond = (await program.account.order.fetch(ask)) as any;
+    if (await decryptHandle(askAfterSecond.remainingHandle, seller1) !== 0n) {
+      throw new Error("maker remainder should be used up by the second fill");
+    }
+    const bidAfter = (await program.account.order.fetch(bid)) as any;
+    if (await decryptHandle(bidAfter.remainingHandle, buyer1) !== 0n) {
+      throw new Error("taker remainder should be used up by both fills");
+    }
+
+    await cancelLimitOrder("partial_cancel_ask", ask, seller1, seller1Base, seller1Quote);
+    await cancelLimitOrder("partial_cancel_bid", bid, buyer1, buyer1Base, buyer1Quote);
+    await sendTx(
+      "partial_claim",
+      program.methods.makerClaimFilledOrder().accounts({
+        state: statePda,
+        order: ask,
+        owner: seller1.publicKey,
+        maker: seller1.publicKey,
+        incoVaultAuthority,
+        incoBaseVault: baseVault.publicKey,
+        incoQuoteVault: quoteVault.publicKey,
+        makerBaseInco: seller1Base.publicKey,
+        makerQuoteInco: seller1Quote.publicKey,
+        incoBaseMint: baseMint.publicKey,
+        incoQuoteMint: quoteMint.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+        incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+      }),
+      [seller1],
+      true,
+    );
+    if (!((await program.account.order.fetch(ask)) as any).isClaimed) {
+      throw new Error("a cancelled maker should be settled by its claim");
+    }
+  });
+
+  it("matches from a requested size written to a buffer in chunks", async () => {
+    const price = new BN(100);
+    await topUpIncoAccount(buyer1Quote.publicKey, quoteMint.publicKey, topUpQuote);
+    await topUpIncoAccount(seller1Base.publicKey, baseMint.publicKey, topUpBase);
+
+    const ask = await placeLimitOrder(
+      "buffer_place_ask",
+      seller1,
+      seller1Base,
+      seller1Quote,
+      1,
+      price,
+    );
+    const bid = await placeLimitOrder(
+      "buffer_place_bid",
+      buyer1,
+      buyer1Base,
+      buyer1Quote,
+      0,
+      price,
+    );
+
+    const reqBase = await encryptAmount(tradeBaseAmount);
+    const bufferId = new BN(Date.now());
+    const buffer = await writeCiphertextBuffer(
+      "buffer_req_base",
+      payer,
+      bufferId,
+      reqBase.ciphertext,
+      128,
+    );
+    const stored = (await program.account.ciphertextBuffer.fetch(buffer)) as any;
+    if (!Buffer.from(stored.data).equals(reqBase.ciphertext)) {
+      throw new Error("the chunks should reassemble into the original ciphertext");
+    }
+    await expectError(
+      "buffer_write_gap",
+      program.methods
+        .writeBuffer(reqBase.ciphertext.length + 1, Buffer.from([0]))
+        .accounts({ buffer, owner: payer.publicKey })
+        .signers([payer])
+        .rpc(),
+      "CiphertextBufferOverflow",
+    );
+
+    // A buffer replaces the inline argument; passing both is rejected.
+    await expectError(
+      "buffer_match_with_inline",
+      program.methods
+        .matchOrder(0, price, reqBase.ciphertext, reqBase.inputType)
+        .accounts({
+          state: statePda,
+          makerOrder: ask,
+          takerOrder: bid,
+          owner: seller1.publicKey,
+          matcher: payer.publicKey,
+          taker: buyer1.publicKey,
+          takerAccessPass: null,
+          priceFeed: null,
+          makerGroup: null,
+          takerGroup: null,
+          takerReqBaseBuffer: buffer,
+          incoVaultAuthority,
+          incoBaseVault: baseVault.publicKey,
+          incoQuoteVault: quoteVault.publicKey,
+          makerBaseInco: seller1Base.publicKey,
+          makerQuoteInco: seller1Quote.publicKey,
+          takerBaseInco: buyer1Base.publicKey,
+          takerQuoteInco: buyer1Quote.publicKey,
+          incoBaseMint: baseMint.publicKey,
+          incoQuoteMint: quoteMint.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+          incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+        })
+        .signers([payer])
+        .rpc(),
+      "InvalidCiphertextBuffer",
+    );
+
+    await matchSellerAsk("buffer_match", ask, bid, price, tradeBaseAmount, buffer);
+    const askAfter = (await program.account.order.fetch(ask)) as any;
+    if (await decryptHandle(askAfter.remainingHandle, seller1) !== 0n) {
+      throw new Error("the buffered size should fill the whole ask");
+    }
+
+    await sendTx(
+      "buffer_close",
+      program.methods.closeBuffer().accounts({ buffer, owner: payer.publicKey }),
+      [payer],
+    );
+    if (await provider.connection.getAccountInfo(buffer)) {
+      throw new Error("close_buffer should delete the buffer");
+    }
+    await cancelLimitOrder("buffer_cancel_ask", ask, seller1, seller1Base, seller1Quote);
+    await cancelLimitOrder("buffer_cancel_bid", bid, buyer1, buyer1Base, buyer1Quote);
+  });
+
+  it("places a zero-size order when the balance does not cover the escrow", async () => {
+    // A fresh, empty quote account cannot fund a bid of `tradeQuoteAmount`.
+    const emptyQuote = Keypair.generate();
+    await initializeIncoAccount(emptyQuote, quoteMint.publicKey, buyer2.publicKey);
+    const bid = await placeLimitOrder(
+      "unfunded_place_bid",
+      buyer2,
+      buyer2Base,
+      emptyQuote,
+      0,
+      new BN(100),
+    );
+    const placed = (await program.account.order.fetch(bid)) as any;
+    if (await decryptHandle(placed.remainingHandle, buyer2) !== 0n) {
+      throw new Error("an uncovered bid should rest with size 0");
+    }
+    await cancelLimitOrder("unfunded_cancel_bid", bid, buyer2, buyer2Base, emptyQuote);
+  });
+
+  it("rejects handle input types and refunds a cancel as a computed handle", async () => {
+    // Naming the vault's balance handle as the size must not let a caller reuse it.
+    const vaultHandle = await incoBalanceHandle(quoteVault.publicKey);
+    const handleBytes = Buffer.alloc(16);
+    handleBytes.writeBigUInt64LE(vaultHandle & ((1n << 64n) - 1n), 0);
+    handleBytes.writeBigUInt64LE(vaultHandle >> 64n, 8);
+    const seq = new BN(
+      (await program.account.orderbookState.fetch(statePda)).orderSeq.toString(),
+    );
+    await expectError(
+      "handle_input_place",
+      program.methods
+        .placeOrder(0, new BN(100), handleBytes, 2)
+        .accounts({
+          state: statePda,
+          order: orderPda(statePda, buyer1.publicKey, seq),
+          trader: buyer1.publicKey,
+          accessPass: null,
+          sizeBuffer: null,
+          incoVaultAuthority,
+          incoBaseVault: baseVault.publicKey,
+          incoQuoteVault: quoteVault.publicKey,
+          traderBaseInco: buyer1Base.publicKey,
+          traderQuoteInco: buyer1Quote.publicKey,
+          incoBaseMint: baseMint.publicKey,
+          incoQuoteMint: quoteMint.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+          incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+        })
+        .signers([buyer1])
+        .rpc(),
+      "ReservedInputType",
+    );
+
+    // The cancel passes the refund as a 16-byte LE handle; the full quote escrow must come back.
+    await topUpIncoAccount(buyer1Quote.publicKey, quoteMint.publicKey, topUpQuote);
+    const bid = await placeLimitOrder(
+      "refund_place_bid",
+      buyer1,
+      buyer1Base,
+      buyer1Quote,
+      0,
+      new BN(100),
+    );
+    const before = await decryptHandle(await incoBalanceHandle(buyer1Quote.publicKey), buyer1);
+    await cancelLimitOrder("refund_cancel_bid", bid, buyer1, buyer1Base, buyer1Quote);
+    const after = await decryptHandle(await incoBalanceHandle(buyer1Quote.publicKey), buyer1);
+    if (after - before !== tradeQuoteAmount) {
+      throw new Error(`cancel should refund ${tradeQuoteAmount} quote, refunded ${after - before}`);
+    }
+  });
+
+  it("refunds what is left when an order is closed", async () => {
+    await topUpIncoAccount(buyer1Quote.publicKey, quoteMint.publicKey, topUpQuote);
+    const bid = await placeLimitOrder(
+      "close_place_bid",
+      buyer1,
+      buyer1Base,
+      buyer1Quote,
+      0,
+      new BN(100),
+    );
+    const openBefore = (
+      await program.account.orderbookState.fetch(statePda)
+    ).openEscrows.toNumber();
+    const before = await decryptHandle(await incoBalanceHandle(buyer1Quote.publicKey), buyer1);
+    await sendTx(
+      "close_bid",
+      program.methods
+        .closeOrder()
+        .accounts({
+          state: statePda,
+          order: bid,
+          owner: buyer1.publicKey,
+          incoVaultAuthority,
+          incoBaseVault: baseVault.publicKey,
+          incoQuoteVault: quoteVault.publicKey,
+          ownerBaseInco: buyer1Base.publicKey,
+          ownerQuoteInco: buyer1Quote.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+          incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+        }),
+      [buyer1],
+      true,
+    );
+    const after = await decryptHandle(await incoBalanceHandle(buyer1Quote.publicKey), buyer1);
+    if (after - before !== tradeQuoteAmount) {
+      throw new Error(`close should refund ${tradeQuoteAmount} quote, refunded ${after - before}`);
+    }
+    // With no fill to claim, the closed order no longer holds vault funds.
+    const openAfter = (
+      await program.account.orderbookState.fetch(statePda)
+    ).openEscrows.toNumber();
+    if (openBefore - openAfter !== 1) {
+      throw new Error("closing an unfilled order should release its open escrow");
+    }
+  });
+
+  it("lets an order owner grant a viewer decryption of its remaining size", async () => {
+    await topUpIncoAccount(buyer1Quote.publicKey, quoteMint.publicKey, topUpQuote);
+    const bid = await placeLimitOrder(
+      "view_place_bid",
+      buyer1,
+      buyer1Base,
+      buyer1Quote,
+      0,
+      new BN(100),
+    );
+    const handle = BigInt(
+      (await program.account.order.fetch(bid)).remainingHandle.toString(),
+    );
+    const grant = (trader: Keypair) =>
+      program.methods.grantOrderView().accounts({
+        state: statePda,
+        order: bid,
+        trader: trader.publicKey,
+        allowanceAccount: allowancePda(handle, buyer2.publicKey),
+        viewer: buyer2.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+      });
+
+    // The order PDA is derived from its owner, so nobody else can grant on it.
+    await expectError(
+      "view_grant_not_owner",
+      grant(seller1).signers([seller1]).rpc(),
+      "ConstraintSeeds",
+    );
+    await sendTx("view_grant", grant(buyer1), [buyer1]);
+    if ((await decryptHandle(handle, buyer2)) !== tradeBaseAmount) {
+      throw new Error(`viewer should decrypt a remaining size of ${tradeBaseAmount}`);
+    }
+
+    await cancelLimitOrder("view_cancel_bid", bid, buyer1, buyer1Base, buyer1Quote);
+  });
+
+  it("escrows a dark bid at its encrypted price and refunds it on cancel", async () => {
+    await topUpIncoAccount(buyer1Quote.publicKey, quoteMint.publicKey, topUpQuote);
+    const balance = async () =>
+      decryptHandle(await incoBalanceHandle(buyer1Quote.publicKey), buyer1);
+    const priceCipher = await encryptAmount(100n);
+    const sizeCipher = await encryptAmount(tradeBaseAmount);
+    const seq = new BN(
+      (await program.account.orderbookState.fetch(statePda)).orderSeq.toString(),
+    );
+    const bid = orderPda(statePda, buyer1.publicKey, seq);
+
+    const before = await balance();
+    await sendTx(
+      "dark_place_bid",
+      await withOwnerAllowances(
+        program.methods
+          .placeDarkOrder(0, priceCipher.ciphertext, sizeCipher.ciphertext, sizeCipher.inputType)
+          .accounts({
+            state: statePda,
+            order: bid,
+            trader: buyer1.publicKey,
+            accessPass: null,
+            incoVaultAuthority,
+            incoBaseVault: baseVault.publicKey,
+            incoQuoteVault: quoteVault.publicKey,
+            traderBaseInco: buyer1Base.publicKey,
+            traderQuoteInco: buyer1Quote.publicKey,
+            incoBaseMint: baseMint.publicKey,
+            incoQuoteMint: quoteMint.publicKey,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+            incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+          }),
+        buyer1.publicKey,
+      ),
+      [buyer1],
+      true,
+    );
+
+    // The public price stays 0; only the owner can read the limit.
+    const placed = await program.account.order.fetch(bid);
+    if (!placed.isDark || !placed.price.isZero()) {
+      throw new Error("a dark order should keep its public price at 0");
+    }
+    if ((await decryptHandle(placed.priceHandle, buyer1)) !== 100n) {
+      throw new Error("the owner should decrypt the dark limit price");
+    }
+    const escrowed = before - (await balance());
+    if (escrowed !== tradeQuoteAmount) {
+      throw new Error(`dark bid should escrow ${tradeQuoteAmount} quote, escrowed ${escrowed}`);
+    }
+
+    await cancelLimitOrder("dark_cancel_bid", bid, buyer1, buyer1Base, buyer1Quote);
+    if ((await balance()) !== before) {
+      throw new Error("cancelling the dark bid should refund its whole escrow");
+    }
+  });
+
+  it("refunds a dark bid's escrow above the ask price when it fills", async () => {
+    await topUpIncoAccount(buyer1Quote.publicKey, quoteMint.publicKey, topUpQuote);
+    await topUpIncoAccount(seller1Base.publicKey, baseMint.publicKey, topUpBase);
+    const balance = async () =>
+      decryptHandle(await incoBalanceHandle(buyer1Quote.publicKey), buyer1);
+    const placeDark = async (
+      label: string,
+      trader: Keypair,
+      side: number,
+      price: bigint,
+      traderBase: Keypair,
+      traderQuote: Keypair,
+    ): Promise<PublicKey> => {
+      const priceCipher = await encryptAmount(price);
+      const sizeCipher = await encryptAmount(tradeBaseAmount);
+      const seq = new BN(
+        (await program.account.orderbookState.fetch(statePda)).orderSeq.toString(),
+      );
+      const order = orderPda(statePda, trader.publicKey, seq);
+      await sendTx(
+        label,
+        await withOwnerAllowances(
+          program.methods
+            .placeDarkOrder(side, priceCipher.ciphertext, sizeCipher.ciphertext, sizeCipher.inputType)
+            .accounts({
+              state: statePda,
+              order,
+              trader: trader.publicKey,
+              accessPass: null,
+              incoVaultAuthority,
+              incoBaseVault: baseVault.publicKey,
+              incoQuoteVault: quoteVault.publicKey,
+              traderBaseInco: traderBase.publicKey,
+              traderQuoteInco: traderQuote.publicKey,
+              incoBaseMint: baseMint.publicKey,
+              incoQuoteMint: quoteMint.publicKey,
+              systemProgram: anchor.web3.SystemProgram.programId,
+              incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+              incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+            }),
+          trader.publicKey,
+        ),
+        [trader],
+        true,
+      );
+      return order;
+    };
+
+    const ask = await placeDark("dark_refund_ask", seller1, 1, 90n, seller1Base, seller1Quote);
+    const before = await balance();
+    const bid = await placeDark("dark_refund_bid", buyer1, 0, 100n, buyer1Base, buyer1Quote);
+    if (before - (await balance()) !== tradeQuoteAmount) {
+      throw new Error(`dark bid should escrow ${tradeQuoteAmount} quote at its own price`);
+    }
+
+    // The fill executes at the maker ask's 90; the bid gets the other 10 per unit back now.
+    await matchSellerAsk("dark_refund_match", ask, bid, new BN(0));
+    const paid = before - (await balance());
+    if (paid !== tradeBaseAmount * 90n) {
+      throw new Error(`dark bid should pay ${tradeBaseAmount * 90n} quote after the match, paid ${paid}`);
+    }
+
+    await cancelLimitOrder("dark_refund_cancel_bid", bid, buyer1, buyer1Base, buyer1Quote);
+    if (before - (await balance()) !== paid) {
+      throw new Error("cancelling the filled dark bid should not refund anything more");
+    }
+    await cancelLimitOrder("dark_refund_cancel_ask", ask, seller1, seller1Base, seller1Quote);
+  });
+
+  it("refunds a cancelled trigger order from its stored remaining size", async () => {
+    const market = await createMarket("trigger_cancel");
+    const buyerBase = Keypair.generate();
+    const buyerQuote = Keypair.generate();
+    await initializeIncoAccount(buyerBase, market.baseMint, buyer1.publicKey);
+    await initializeIncoAccount(buyerQuote, market.quoteMint, buyer1.publicKey);
+    await mintToInco(market.quoteMint, buyerQuote.publicKey, tradeQuoteAmount);
+    await setZeroedPriceFeed("trigger_cancel_feed", market, new BN(1_000));
+    const balance = async () =>
+      decryptHandle(await incoBalanceHandle(buyerQuote.publicKey), buyer1);
+    const seq = new BN(
+      (await program.account.orderbookState.fetch(market.state)).orderSeq.toString(),
+    );
+    const [trigger] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("trigger_order_v1"),
+        market.state.toBuffer(),
+        buyer1.publicKey.toBuffer(),
+        Buffer.from(seq.toArray("le", 8)),
+      ],
+      program.programId,
+    );
+    const vaultAccounts = {
+      incoVaultAuthority: market.vaultAuthority,
+      incoBaseVault: market.baseVault,
+      incoQuoteVault: market.quoteVault,
+      traderBaseInco: buyerBase.publicKey,
+      traderQuoteInco: buyerQuote.publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+      incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+    };
+    const sizeCipher = await encryptAmount(tradeBaseAmount);
+    const place = (reference: number) =>
+      withOwnerAllowances(
+        program.methods
+          .placeTriggerOrder(
+            0,
+            new BN(100),
+            new BN(1_000_000),
+            reference,
+            0,
+            sizeCipher.ciphertext,
+            sizeCipher.inputType,
+          )
+          .accounts({
+            state: market.state,
+            triggerOrder: trigger,
+            trader: buyer1.publicKey,
+            accessPass: null,
+            incoBaseMint: market.baseMint,
+            incoQuoteMint: market.quoteMint,
+            ...vaultAccounts,
+          }),
+        buyer1.publicKey,
+      );
+
+    // The last trade price is not a trigger reference: encrypted fills can be zero.
+    await expectError(
+      "trigger_place_last_trade",
+      (await place(0)).signers([buyer1]).rpc(),
+      "InvalidTrigger",
+    );
+
+    const before = await balance();
+    // A bid that only fires once the feed reaches a price it never prints.
+    await sendTx("trigger_place_bid", await place(1), [buyer1], true);
+    if (before - (await balance()) !== tradeQuoteAmount) {
+      throw new Error(`trigger bid should escrow ${tradeQuoteAmount} quote`);
+    }
+
+    await sendTx(
+      "trigger_cancel_bid",
+      program.methods
+        .cancelTriggerOrder()
+        .accounts({
+          state: market.state,
+          triggerOrder: trigger,
+          trader: buyer1.publicKey,
+          ...vaultAccounts,
+        }),
+      [buyer1],
+      true,
+    );
+    if ((await balance()) !== before) {
+      throw new Error("trigger cancel should refund the full escrow");
+    }
+    if (await provider.connection.getAccountInfo(trigger)) {
+      throw new Error("trigger PDA should be closed by the cancel");
+    }
+  });
+
+  it("fires a trigger order only from a fresh, non-zero feed price", async () => {
+    const market = await createMarket("trigger_fire");
+    const buyerBase = Keypair.generate();
+    const buyerQuote = Keypair.generate();
+    await initializeIncoAccount(buyerBase, market.baseMint, buyer1.publicKey);
+    await initializeIncoAccount(buyerQuote, market.quoteMint, buyer1.publicKey);
+    await mintToInco(market.quoteMint, buyerQuote.publicKey, tradeQuoteAmount);
+    const feed = await setZeroedPriceFeed("trigger_fire_feed", market, new BN(1_000));
+    const seq = new BN(
+      (await program.account.orderbookState.fetch(market.state)).orderSeq.toString(),
+    );
+    const [trigger] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("trigger_order_v1"),
+        market.state.toBuffer(),
+        buyer1.publicKey.toBuffer(),
+        Buffer.from(seq.toArray("le", 8)),
+      ],
+      program.programId,
+    );
+    const vaultAccounts = {
+      incoVaultAuthority: market.vaultAuthority,
+      incoBaseVault: market.baseVault,
+      incoQuoteVault: market.quoteVault,
+      traderBaseInco: buyerBase.publicKey,
+      traderQuoteInco: buyerQuote.publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+      incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+    };
+    const sizeCipher = await encryptAmount(tradeBaseAmount);
+    // Fires once the feed price is at or above 1.
+    await sendTx(
+      "trigger_fire_place",
+      await withOwnerAllowances(
+        program.methods
+          .placeTriggerOrder(
+            0,
+            new BN(100),
+            new BN(1),
+            1,
+            0,
+            sizeCipher.ciphertext,
+            sizeCipher.inputType,
+          )
+          .accounts({
+            state: market.state,
+            triggerOrder: trigger,
+            trader: buyer1.publicKey,
+            accessPass: null,
+            incoBaseMint: market.baseMint,
+            incoQuoteMint: market.quoteMint,
+            ...vaultAccounts,
+          }),
+        buyer1.publicKey,
+      ),
+      [buyer1],
+      true,
+    );
+    const crank = () =>
+      program.methods
+        .triggerOrder()
+        .accounts({
+          state: market.state,
+          triggerOrder: trigger,
+          order: orderPda(market.state, buyer1.publicKey, seq),
+          owner: buyer1.publicKey,
+          cranker: payer.publicKey,
+          priceFeed: feed,
+          accessPass: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([payer])
+        .rpc();
+
+    // The zeroed stand-in was published at slot 0, long past the staleness limit.
+    await expectError("trigger_fire_stale", crank(), "StalePriceFeed");
+    // With the limit lifted the feed is fresh, but a zero price never fires.
+    await sendTx(
+      "trigger_fire_unlimited_feed",
+      program.methods
+        .setPriceFeed(new BN("18446744073709551615"), 100)
+        .accounts({ state: market.state, priceFeed: feed, admin: payer.publicKey }),
+      [payer],
+    );
+    await expectError("trigger_fire_zero_price", crank(), "TriggerNotMet");
+    if (!(await provider.connection.getAccountInfo(trigger))) {
+      throw new Error("an unfired trigger should keep its PDA");
+    }
+
+    await sendTx(
+      "trigger_fire_cancel",
+      program.methods
+        .cancelTriggerOrder()
+        .accounts({
+          state: market.state,
+          triggerOrder: trigger,
+          trader: buyer1.publicKey,
+          ...vaultAccounts,
+        }),
+      [buyer1],
+      true,
+    );
+  });
+
+  it("refunds a cancelled TWAP from its unreleased size", async () => {
+    const half = tradeBaseAmount / 2n;
+    await topUpIncoAccount(seller1Base.publicKey, baseMint.publicKey, topUpBase);
+    const balance = async () =>
+      decryptHandle(await incoBalanceHandle(seller1Base.publicKey), seller1);
+    const seq = new BN(
+      (await program.account.orderbookState.fetch(statePda)).orderSeq.toString(),
+    );
+    const [twap] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("twap_order_v1"),
+        statePda.toBuffer(),
+        seller1.publicKey.toBuffer(),
+        Buffer.from(seq.toArray("le", 8)),
+      ],
+      program.programId,
+    );
+    const vaultAccounts = {
+      incoVaultAuthority,
+      incoBaseVault: baseVault.publicKey,
+      incoQuoteVault: quoteVault.publicKey,
+      traderBaseInco: seller1Base.publicKey,
+      traderQuoteInco: seller1Quote.publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+      incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+    };
+
+    const before = await balance();
+    const totalCipher = await encryptAmount(tradeBaseAmount);
+    const sliceCipher = await encryptAmount(half);
+    // Two half slices; the interval keeps the second one from coming due during the test.
+    await sendTx(
+      "twap_place_ask",
+      await withOwnerAllowances(
+        program.methods
+          .placeTwapOrder(
+            1,
+            new BN(100),
+            new BN(1_000_000),
+            2,
+            totalCipher.ciphertext,
+            sliceCipher.ciphertext,
+            totalCipher.inputType,
+          )
+          .accounts({
+            state: statePda,
+            twapOrder: twap,
+            trader: seller1.publicKey,
+            accessPass: null,
+            incoBaseMint: baseMint.publicKey,
+            incoQuoteMint: quoteMint.publicKey,
+            ...vaultAccounts,
+          }),
+        seller1.publicKey,
+      ),
+      [seller1],
+      true,
+    );
+    if (before - (await balance()) !== tradeBaseAmount) {
+      throw new Error(`TWAP ask should escrow ${tradeBaseAmount} base`);
+    }
+
+    const sliceSeq = new BN(
+      (await program.account.orderbookState.fetch(statePda)).orderSeq.toString(),
+    );
+    const slice = orderPda(statePda, seller1.publicKey, sliceSeq);
+    await sendTx(
+      "twap_tick_first",
+      await withOwnerAllowances(
+        program.methods.twapTick().accounts({
+          state: statePda,
+          twapOrder: twap,
+          order: slice,
+          owner: seller1.publicKey,
+          cranker: payer.publicKey,
+          accessPass: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+        }),
+        payer.publicKey,
+      ),
+      [payer],
+      true,
+    );
+
+    // The owner prefunded the slice rent, so it gets it back when the slice closes, not the cranker.
+    if (!((await program.account.order.fetch(slice)) as any).rentPayer.equals(seller1.publicKey)) {
+      throw new Error("twap_tick should record the owner as the slice's rent payer");
+    }
+
+    // The parent refunds only the unreleased half; the released slice refunds the rest.
+    await sendTx(
+      "twap_cancel_parent",
+      program.methods.cancelTwapOrder().accounts({
+        state: statePda,
+        twapOrder: twap,
+        trader: seller1.publicKey,
+        ...vaultAccounts,
+      }),
+      [seller1],
+      true,
+    );
+    if (before - (await balance()) !== half) {
+      throw new Error(`TWAP cancel should refund the unreleased ${half} base`);
+    }
+    await cancelLimitOrder("twap_cancel_slice", slice, seller1, seller1Base, seller1Quote);
+    if ((await balance()) !== before) {
+      throw new Error("cancelling the released slice should refund the rest of the escrow");
+    }
+  });
+
+  it("re-escrows a requoted bid at its new price", async () => {
+    await topUpIncoAccount(buyer1Quote.publicKey, quoteMint.publicKey, topUpQuote);
+    await topUpIncoAccount(buyer1Base.publicKey, baseMint.publicKey, topUpBase);
+    const quoteBalance = async () =>
+      decryptHandle(await incoBalanceHandle(buyer1Quote.publicKey), buyer1);
+    const seq = new BN(
+      (await program.account.orderbookState.fetch(statePda)).orderSeq.toString(),
+    );
+    const bid = orderPda(statePda, buyer1.publicKey, seq);
+    const ask = orderPda(statePda, buyer1.publicKey, seq.addn(1));
+
+    const before = await quoteBalance();
+    const bidSize = await encryptAmount(tradeBaseAmount);
+    const askSize = await encryptAmount(tradeBaseAmount);
+    await sendTx(
+      "quote_place",
+      await withOwnerAllowances(
+        program.methods
+          .placeQuote(
+            new BN(90),
+            new BN(110),
+            bidSize.ciphertext,
+            askSize.ciphertext,
+            bidSize.inputType,
+          )
+          .accounts({
+            state: statePda,
+            bidOrder: bid,
+            askOrder: ask,
+            trader: buyer1.publicKey,
+            accessPass: null,
+            incoVaultAuthority,
+            incoBaseVault: baseVault.publicKey,
+            incoQuoteVault: quoteVault.publicKey,
+            traderBaseInco: buyer1Base.publicKey,
+            traderQuoteInco: buyer1Quote.publicKey,
+            incoBaseMint: baseMint.publicKey,
+            incoQuoteMint: quoteMint.publicKey,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+            incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+          }),
+        buyer1.publicKey,
+      ),
+      [buyer1],
+      true,
+    );
+    if (before - (await quoteBalance()) !== tradeBaseAmount * 90n) {
+      throw new Error("quote bid should escrow size * 90");
+    }
+
+    const requote = (bidOrder: PublicKey, bidPrice: number) =>
+      program.methods.requote(new BN(bidPrice), new BN(110)).accounts({
+        state: statePda,
+        bidOrder,
+        askOrder: ask,
+        owner: buyer1.publicKey,
+        incoVaultAuthority,
+        incoQuoteVault: quoteVault.publicKey,
+        ownerQuoteInco: buyer1Quote.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+        incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+      });
+
+    // Raising the bid debits the difference; the vault never backs less than size * price.
+    await sendTx(
+      "quote_requote_up",
+      await withOwnerAllowances(requote(bid, 95), buyer1.publicKey),
+      [buyer1],
+      true,
+    );
+    if (before - (await quoteBalance()) !== tradeBaseAmount * 95n) {
+      throw new Error("requoted bid should escrow size * 95");
+    }
+
+    // A plain limit bid is not one leg of this quote.
+    const limitBid = await placeLimitOrder(
+      "quote_place_limit_bid",
+      buyer1,
+      buyer1Base,
+      buyer1Quote,
+      0,
+      new BN(95),
+    );
+    await expectError(
+      "quote_requote_foreign_bid",
+      requote(limitBid, 96).signers([buyer1]).rpc(),
+      "InvalidQuote",
+    );
+    await cancelLimitOrder("quote_cancel_limit_bid", limitBid, buyer1, buyer1Base, buyer1Quote);
+
+    await cancelLimitOrder("quote_cancel_bid", bid, buyer1, buyer1Base, buyer1Quote);
+    await cancelLimitOrder("quote_cancel_ask", ask, buyer1, buyer1Base, buyer1Quote);
+    if ((await quoteBalance()) !== before) {
+      throw new Error("cancelling the requoted bid should refund its whole escrow");
+    }
+  });
+
+  it("returns the rest of an order group's escrow when a leg is cancelled", async () => {
+    await topUpIncoAccount(buyer1Quote.publicKey, quoteMint.publicKey, topUpQuote);
+    const groupId = new BN(Date.now());
+    const [group] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("order_group_v1"),
+        statePda.toBuffer(),
+        buyer1.publicKey.toBuffer(),
+        Buffer.from(groupId.toArray("le", 8)),
+      ],
+      program.programId,
+    );
+    // Enough quote for two one-token bids at 100; the group records the debited handle.
+    const quoteEscrow = await encryptAmount(2n * tradeQuoteAmount);
+    await sendTx(
+      "group_create",
+      program.methods
+        .createOrderGroup(groupId, Buffer.alloc(0), quoteEscrow.ciphertext, quoteEscrow.inputType)
+        .accounts({
+          state: statePda,
+          orderGroup: group,
+          trader: buyer1.publicKey,
+          accessPass: null,
+          incoVaultAuthority,
+          incoBaseVault: baseVault.publicKey,
+          incoQuoteVault: quoteVault.publicKey,
+          traderBaseInco: buyer1Base.publicKey,
+          traderQuoteInco: buyer1Quote.publicKey,
+          incoBaseMint: baseMint.publicKey,
+          incoQuoteMint: quoteMint.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+          incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+        }),
+      [buyer1],
+      true,
+    );
+
+    const legs: PublicKey[] = [];
+    for (const label of ["group_leg_a", "group_leg_b"]) {
+      const size = await encryptAmount(tradeBaseAmount);
+      const seq = new BN(
+        (await program.account.orderbookState.fetch(statePda)).orderSeq.toString(),
+      );
+      const order = orderPda(statePda, buyer1.publicKey, seq);
+      await sendTx(
+        label,
+        await withOwnerAllowances(
+          program.methods
+            .placeGroupOrder(0, new BN(100), size.ciphertext, size.inputType)
+            .accounts({
+              state: statePda,
+              orderGroup: group,
+              order,
+              trader: buyer1.publicKey,
+              accessPass: null,
+              systemProgram: anchor.web3.SystemProgram.programId,
+              incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+            }),
+          buyer1.publicKey,
+        ),
+        [buyer1],
+      );
+      legs.push(order);
+    }
+
+    // Cancelling one unfilled leg cancels the group: its own refund and the sibling's
+    // reservation both come back in the same instruction.
+    const before = await decryptHandle(await incoBalanceHandle(buyer1Quote.publicKey), buyer1);
+    await sendTx(
+      "group_cancel_leg",
+      program.methods
+        .cancelOrder()
+        .accounts({
+          state: statePda,
+          order: legs[0],
+          orderGroup: group,
+          trader: buyer1.publicKey,
+          incoVaultAuthority,
+          incoBaseVault: baseVault.publicKey,
+          incoQuoteVault: quoteVault.publicKey,
+          traderBaseInco: buyer1Base.publicKey,
+          traderQuoteInco: buyer1Quote.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+          incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+        }),
+      [buyer1],
+      true,
+    );
+    const after = await decryptHandle(await incoBalanceHandle(buyer1Quote.publicKey), buyer1);
+    if (after - before !== 2n * tradeQuoteAmount) {
+      throw new Error(`group cancel should return ${2n * tradeQuoteAmount}, got ${after - before}`);
+    }
+    const cancelled = (await program.account.orderGroup.fetch(group)) as any;
+    if (cancelled.status !== 2 || !new BN(cancelled.quoteEscrowHandle.toString()).isZero()) {
+      throw new Error("a cancelled group should hold no escrow");
+    }
+
+    // The sweep only closes the inert sibling and the group; it moves no funds.
+    await sendTx(
+      "group_sweep",
+      program.methods
+        .sweepOrderGroup()
+        .accounts({
+          state: statePda,
+          orderGroup: group,
+          owner: buyer1.publicKey,
+          cranker: payer.publicKey,
+        })
+        .remainingAccounts(
+          legs.map((pubkey) => ({ pubkey, isSigner: false, isWritable: true })),
+        ),
+      [payer],
+    );
+    if (await provider.connection.getAccountInfo(group)) {
+      throw new Error("a swept group with no open leg should be closed");
+    }
+    const sibling = (await program.account.order.fetch(legs[1])) as any;
+    if (sibling.isOpen) {
+      throw new Error("the sweep should close the sibling leg");
+    }
+  });
+
+  it("cranks a filled maker claim to the owner and pays the cranker", async () => {
+    const price = new BN(100);
+    await topUpIncoAccount(buyer1Quote.publicKey, quoteMint.publicKey, topUpQuote);
+    await topUpIncoAccount(seller1Base.publicKey, baseMint.publicKey, topUpBase);
+
+    const bid = await placeLimitOrder(
+      "crank_place_bid",
+      buyer1,
+      buyer1Base,
+      buyer1Quote,
+      0,
+      price,
+    );
+    const ask = await placeLimitOrder(
+      "crank_place_ask",
+      seller1,
+      seller1Base,
+      seller1Quote,
+      1,
+      price,
+    );
+    const crank = (order: PublicKey, rentPayer: PublicKey = seller1.publicKey) =>
+      program.methods
+        .crankClaim()
+        .accounts({
+          state: statePda,
+          order,
+          owner: seller1.publicKey,
+          rentPayer,
+          cranker: payer.publicKey,
+          incoVaultAuthority,
+          incoBaseVault: baseVault.publicKey,
+          incoQuoteVault: quoteVault.publicKey,
+          ownerBaseInco: seller1Base.publicKey,
+          ownerQuoteInco: seller1Quote.publicKey,
+          incoBaseMint: baseMint.publicKey,
+          incoQuoteMint: quoteMint.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+          incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+        })
+        .preInstructions([
+          ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+        ])
+        .signers([payer])
+        .rpc();
+
+    await expectError("crank_unfilled", crank(ask), "OrderNotFilled");
+
+    // A resting maker's partial fill is cranked out while the order stays on the book.
+    await matchSellerAsk("crank_match_partial", ask, bid, price, tradeBaseAmount / 2n);
+
+    const setOptOut = (optOut: boolean) =>
+      program.methods
+        .setCrankOptOut(optOut)
+        .accounts({ state: statePda, order: ask, trader: seller1.publicKey })
+        .signers([seller1])
+        .rpc();
+    await setOptOut(true);
+    await expectError("crank_opted_out", crank(ask), "CrankClaimOptedOut");
+    await setOptOut(false);
+
+    const restingRent = await provider.connection.getBalance(ask);
+    const quoteBefore = await incoBalanceHandle(seller1Quote.publicKey);
+    await crank(ask);
+    const resting = (await program.account.order.fetch(ask)) as any;
+    if (!resting.isOpen || resting.isFilled || resting.claimCiphertext.length !== 0) {
+      throw new Error("crank_claim should leave the resting maker open with a fresh claim");
+    }
+    if ((await incoBalanceHandle(seller1Quote.publicKey)) === quoteBefore) {
+      throw new Error("crank_claim should pay the resting maker's fill to its owner");
+    }
+    if ((await provider.connection.getBalance(ask)) !== restingRent) {
+      throw new Error("crank_claim should not take a reward from a resting order");
+    }
+    await expectError("crank_claimed_resting", crank(ask), "OrderNotFilled");
+
+    // Once the rest fills and the order is cancelled, the crank settles and closes it.
+    await matchSellerAsk("crank_match_rest", ask, bid, price, tradeBaseAmount / 2n);
+    await cancelLimitOrder(
+      "crank_cancel_ask",
+      ask,
+      seller1,
+      seller1Base,
+      seller1Quote,
+    );
+
+    // The owner paid this order's rent, so only the owner can receive it.
+    if (!((await program.account.order.fetch(ask)) as any).rentPayer.equals(seller1.publicKey)) {
+      throw new Error("place_order should record the trader as the order's rent payer");
+    }
+    await expectError("crank_foreign_rent_payer", crank(ask, payer.publicKey), "InvalidRentPayer");
+
+    const orderRent = await provider.connection.getBalance(ask);
+    const ownerBefore = await provider.connection.getBalance(seller1.publicKey);
+    const sig = await crank(ask);
+    console.log(`crank_claim.tx: ${explorerBase}${sig}?cluster=devnet`);
+
+    if (await provider.connection.getAccountInfo(ask)) {
+      throw new Error("crank_claim should close the maker order");
+    }
+    const ownerAfter = await provider.connection.getBalance(seller1.publicKey);
+    if (ownerAfter - ownerBefore !== orderRent - CRANK_CLAIM_REWARD_LAMPORTS) {
+      throw new Error(
+        `owner should get the order rent minus the crank reward, got ${
+          ownerAfter - ownerBefore
+        } of ${orderRent}`,
+      );
+    }
+
+    await cancelLimitOrder(
+      "crank_cancel_bid",
+      bid,
+      buyer1,
+      buyer1Base,
+      buyer1Quote,
+    );
+  });
+
+  it("sweeps a closed market's orders and accounts before closing it", async () => {
+    // A throwaway market, so closing it leaves the shared one untouched.
+    const market = await createMarket("close_market");
+    const { state, vaultAuthority } = market;
+    const [marketRegistry] = PublicKey.findProgramAddressSync(
+      [Buffer.from("market_registry_v1")],
+      program.programId,
+    );
+
+    const sellerBase = Keypair.generate();
+    const sellerQuote = Keypair.generate();
+    await initializeIncoAccount(sellerBase, market.baseMint, seller1.publicKey);
+    await initializeIncoAccount(sellerQuote, market.quoteMint, seller1.publicKey);
+    await mintToInco(market.baseMint, sellerBase.publicKey, tradeBaseAmount);
+    const sellerBaseBalance = async () =>
+      decryptHandle(await incoBalanceHandle(sellerBase.publicKey), seller1);
+
+    const price = new BN(100);
+    const ask = await placeLimitOrder(
+      "close_market_place_ask",
+      seller1,
+      sellerBase,
+      sellerQuote,
+      1,
+      price,
+      market,
+    );
+
+    // A delegate grant scoped to this market only; it is dead once the market closes.
+    const delegate = Keypair.generate().publicKey;
+    const [tradingDelegate] = PublicKey.findProgramAddressSync(
+      [Buffer.from("trading_delegate_v1"), seller1.publicKey.toBuffer(), delegate.toBuffer()],
+      program.programId,
+    );
+    await sendTx(
+      "close_market_grant_delegate",
+      program.methods
+        .grantTradingDelegate(
+          delegate,
+          new BN(Math.floor(Date.now() / 1000) + 3_600),
+          [state],
+          DELEGATE_ACTION_CANCEL,
+          0,
+        )
+        .accounts({
+          tradingDelegate,
+          owner: seller1.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        }),
+      [seller1],
+    );
+    const releaseDelegate = () =>
+      program.methods
+        .releaseDelegateMarket()
+        .accounts({
+          state,
+          tradingDelegate,
+          owner: seller1.publicKey,
+          caller: buyer1.publicKey,
+        })
+        .signers([buyer1])
+        .rpc();
+
+    const sweep = () =>
+      program.methods
+        .sweepMarketOrder()
+        .accounts({
+          state,
+          order: ask,
+          orderGroup: null,
+          owner: seller1.publicKey,
+          rentPayer: seller1.publicKey,
+          cranker: payer.publicKey,
+          incoVaultAuthority: vaultAuthority,
+          incoBaseVault: market.baseVault,
+          incoQuoteVault: market.quoteVault,
+          ownerBaseInco: sellerBase.publicKey,
+          ownerQuoteInco: sellerQuote.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+          incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+        })
+        .signers([payer])
+        .rpc();
+    // An access pass issued after the ask was placed, revocable by anyone once the market closes.
+    const [accessPass] = PublicKey.findProgramAddressSync(
+      [Buffer.from("access_pass_v1"), state.toBuffer(), seller1.publicKey.toBuffer()],
+      program.programId,
+    );
+    await sendTx(
+      "close_market_set_gatekeeper",
+      program.methods.setGatekeeper(payer.publicKey).accounts({
+        state,
+        admin: payer.publicKey,
+      }),
+      [payer],
+    );
+    await sendTx(
+      "close_market_issue_pass",
+      program.methods.issueAccessPass().accounts({
+        state,
+        accessPass,
+        trader: seller1.publicKey,
+        gatekeeper: payer.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }),
+      [payer],
+    );
+    const revokePass = () =>
+      program.methods
+        .revokeAccessPass()
+        .accounts({
+          state,
+          accessPass,
+          authority: buyer1.publicKey,
+          issuer: payer.publicKey,
+        })
+        .signers([buyer1])
+        .rpc();
+
+    await expectError("sweep_open_market", sweep(), "MarketNotClosed");
+    await expectError("release_delegate_open_market", releaseDelegate(), "MarketNotClosed");
+    await expectError("revoke_pass_open_market", revokePass(), "UnauthorizedGatekeeper");
+
+    await sendTx(
+      "close_market_set_closed",
+      program.methods.setMarketStatus(1).accounts({
+        state,
+        admin: payer.publicKey,
+      }),
+      [payer],
+    );
+
+    // A matcher buffer left over from the market, closed together with it.
+    const buffer = await writeCiphertextBuffer(
+      "close_market_buffer",
+      payer,
+      new BN(Date.now()),
+      Buffer.from([1, 2, 3]),
+      128,
+    );
+    const closeMarket = () =>
+      program.methods
+        .closeMarket()
+        .accounts({
+          state,
+          admin: payer.publicKey,
+          marketRegistry,
+          incoVaultAuthority: vaultAuthority,
+          incoBaseVault: market.baseVault,
+          incoQuoteVault: market.quoteVault,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts([{ pubkey: buffer, isWritable: true, isSigner: false }])
+        .signers([payer])
+        .rpc();
+    await expectError("close_market_with_order", closeMarket(), "OpenOrdersOnBook");
+
+    // Anyone may sweep: the ask is refunded to its owner and its account closed.
+    const sig = await sweep();
+    console.log(`sweep_market_order.tx: ${explorerBase}${sig}?cluster=devnet`);
+    if (await provider.connection.getAccountInfo(ask)) {
+      throw new Error("sweep_market_order should close the order");
+    }
+    if (await sellerBaseBalance() !== tradeBaseAmount) {
+      throw new Error("sweep_market_order should refund the whole ask escrow");
+    }
+    const swept = await program.account.orderbookState.fetch(state);
+    if (!swept.openOrders.isZero() || !swept.openEscrows.isZero()) {
+      throw new Error("sweep_market_order should release the order's counters");
+    }
+
+    await releaseDelegate();
+    if (await provider.connection.getAccountInfo(tradingDelegate)) {
+      throw new Error("a grant left without markets should be closed");
+    }
+    await revokePass();
+    if (await provider.connection.getAccountInfo(accessPass)) {
+      throw new Error("anyone should be able to revoke a closed market's pass");
+    }
+
+    const closeSig = await closeMarket();
+    console.log(`close_market.tx: ${explorerBase}${closeSig}?cluster=devnet`);
+    if (await provider.connection.getAccountInfo(state)) {
+      throw new Error("close_market should close the market state");
+    }
+    if (await provider.connection.getAccountInfo(buffer)) {
+      throw new Error("close_market should close the admin's buffers");
+    }
+    const registry = await program.account.marketRegistry.fetch(marketRegistry);
+    if (registry.markets.some((m: PublicKey) => m.equals(state))) {
+      throw new Error("close_market should drop the market from the registry");
+    }
+    // The registry is sized to its markets: 8-byte discriminator, bump and padding, vec length.
+    const registryInfo = await provider.connection.getAccountInfo(marketRegistry);
+    if (registryInfo?.data.length !== 8 + 12 + 32 * registry.markets.length) {
+      throw new Error("close_market should shrink the registry by one market");
+    }
+  });
+
+  it("holds a proposed market auditor behind the timelock", async () => {
+    // A throwaway market, so the shared one never needs auditor allowances.
+    const { state } = await createMarket("auditor");
+    const auditor = Keypair.generate().publicKey;
+    const propose = (admin: Keypair) =>
+      program.methods
+        .proposeAuditor(auditor)
+        .accounts({ state, admin: admin.publicKey })
+        .signers([admin])
+        .rpc();
+    const apply = () =>
+      program.methods
+        .applyAuditor()
+        .accounts({ state, admin: payer.publicKey })
+        .signers([payer])
+        .rpc();
+
+    await expectError("auditor_apply_unproposed", apply(), "NoPendingAuditor");
+    await expectError("auditor_propose_not_admin", propose(buyer1), "UnauthorizedMatcher");
+    await propose(payer);
+
+    const pending = await program.account.orderbookState.fetch(state);
+    if (!pending.pendingAuditor.equals(auditor)) {
+      throw new Error("propose_auditor should record the pending auditor");
+    }
+    const twoDays = 2 * 24 * 60 * 60;
+    if (pending.pendingAuditorActivationTs.toNumber() < Date.now() / 1000 + twoDays - 600) {
+      throw new Error("the pending auditor should activate only after the timelock");
+    }
+    await expectError("auditor_apply_early", apply(), "AuditorTimelockActive");
+    if (!(await program.account.orderbookState.fetch(state)).auditor.equals(PublicKey.default)) {
+      throw new Error("the auditor must not change before the timelock elapses");
+    }
+  });
+
+  it("pegs a resting bid only once the market has a price feed", async () => {
+    const market = await createMarket("peg");
+    const buyerBase = Keypair.generate();
+    const buyerQuote = Keypair.generate();
+    await initializeIncoAccount(buyerBase, market.baseMint, buyer1.publicKey);
+    await initializeIncoAccount(buyerQuote, market.quoteMint, buyer1.publicKey);
+    await mintToInco(market.quoteMint, buyerQuote.publicKey, tradeQuoteAmount);
+
+    const bid = await placeLimitOrder(
+      "peg_place_bid",
+      buyer1,
+      buyerBase,
+      buyerQuote,
+      0,
+      new BN(100),
+      market,
+    );
+    const peg = (pegType: number) =>
+      program.methods
+        .pegOrder(pegType, new BN(0))
+        .accounts({ state: market.state, order: bid, trader: buyer1.publicKey })
+        .signers([buyer1])
+        .rpc();
+
+    await expectError("peg_without_feed", peg(1), "MissingPriceFeed");
+
+    // Pegging only checks that a feed is set.
+    await setZeroedPriceFeed("peg_create_feed", market, new BN(1_000));
+    await expectError("peg_invalid_type", peg(9), "InvalidPegType");
+    await peg(1);
+    if ((await program.account.order.fetch(bid)).pegType !== 1) {
+      throw new Error("peg_order should record the midpoint peg");
+    }
+
+    await cancelLimitOrder("peg_cancel_bid", bid, buyer1, buyerBase, buyerQuote, market);
+  });
+
+  it("clears a batch auction over the whole book and settles it at one price", async () => {
+    const market = await createMarket("auction");
+    const sellerBase = Keypair.generate();
+    const sellerQuote = Keypair.generate();
+    const buyerBase = Keypair.generate();
+    const buyerQuote = Keypair.generate();
+    await initializeIncoAccount(sellerBase, market.baseMint, seller1.publicKey);
+    await initializeIncoAccount(sellerQuote, market.quoteMint, seller1.publicKey);
+    await initializeIncoAccount(buyerBase, market.baseMint, buyer1.publicKey);
+    await initializeIncoAccount(buyerQuote, market.quoteMint, buyer1.publicKey);
+    await mintToInco(market.baseMint, sellerBase.publicKey, tradeBaseAmount);
+    await mintToInco(market.quoteMint, buyerQuote.publicKey, 2n * tradeQuoteAmount);
+
+    const windowSlots = 2;
+    // Continuous-mode orders must leave the book before the market switches.
+    const early = await placeLimitOrder(
+      "auction_place_early",
+      buyer1,
+      buyerBase,
+      buyerQuote,
+      0,
+      new BN(100),
+      market,
+    );
+    await expectError(
+      "auction_set_mode_with_orders",
+      program.methods
+        .setMarketMode(1, new BN(windowSlots))
+        .accounts({ state: market.state, admin: payer.publicKey })
+        .signers([payer])
+        .rpc(),
+      "OpenOrdersOnBook",
+    );
+    await cancelLimitOrder("auction_cancel_early", early, buyer1, buyerBase, buyerQuote, market);
+    await sendTx(
+      "auction_set_mode",
+      program.methods.setMarketMode(1, new BN(windowSlots)).accounts({
+        state: market.state,
+        admin: payer.publicKey,
+      }),
+      [payer],
+    );
+    // A bid at price 0 escrows nothing, so it could hold a place in the capped book for free.
+    await expectError(
+      "auction_zero_price_bid",
+      placeLimitOrder("auction_zero_price_bid", buyer1, buyerBase, buyerQuote, 0, new BN(0), market),
+      "ZeroPriceBid",
+    );
+    const stateLamports = () => provider.connection.getBalance(market.state);
+    const feesBefore = await stateLamports();
+    const ask = await placeLimitOrder(
+      "auction_place_ask",
+      seller1,
+      sellerBase,
+      sellerQuote,
+      1,
+      new BN(100),
+      market,
+    );
+    const bid = await placeLimitOrder(
+      "auction_place_bid",
+      buyer1,
+      buyerBase,
+      buyerQuote,
+      0,
+      new BN(120),
+      market,
+    );
+    // Every order on the batch book paid the auction fee into the market account.
+    if ((await stateLamports()) - feesBefore !== 2 * AUCTION_ORDER_FEE_LAMPORTS) {
+      throw new Error("each batch order should pay the auction order fee");
+    }
+
+    const marketState = await program.account.orderbookState.fetch(market.state);
+    const auctionId = new BN(marketState.auctionId.toString());
+    const windowEnd = marketState.auctionStartSlot.toNumber() + windowSlots;
+    while ((await provider.connection.getSlot()) < windowEnd) {
+      await new Promise((resolve) => setTimeout(resolve, 400));
+    }
+    const [auctionResult] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("auction_result_v1"),
+        market.state.toBuffer(),
+        Buffer.from(auctionId.toArray("le", 8)),
+      ],
+      program.programId,
+    );
+    const runAuction = (book: PublicKey[][]) =>
+      program.methods
+        .runAuction()
+        .accounts({
+          state: market.state,
+          auctionResult,
+          matcher: payer.publicKey,
+          incoVaultAuthority: market.vaultAuthority,
+          incoBaseVault: market.baseVault,
+          incoQuoteVault: market.quoteVault,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+          incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+        })
+        .remainingAccounts(
+          book.flat().map((pubkey) => ({ pubkey, isSigner: false, isWritable: true })),
+        );
+
+    // Leaving an open order out of the auction is rejected.
+    await expectError(
+      "auction_partial_book",
+      runAuction([[ask, sellerBase.publicKey, sellerQuote.publicKey]]).signers([payer]).rpc(),
+      "IncompleteAuctionOrders",
+    );
+
+    // Both limits cross; the volume ties, so the auction clears at the lower price, 100.
+    const before = await decryptHandle(await incoBalanceHandle(buyerQuote.publicKey), buyer1);
+    await sendTx(
+      "auction_run",
+      await withOwnerAllowances(
+        runAuction([
+          [ask, sellerBase.publicKey, sellerQuote.publicKey],
+          [bid, buyerBase.publicKey, buyerQuote.publicKey],
+        ]),
+        payer.publicKey,
+      ),
+      [payer],
+      true,
+    );
+    const after = await decryptHandle(await incoBalanceHandle(buyerQuote.publicKey), buyer1);
+    // Quote is size * price: the bid escrowed at 120 and pays 100.
+    const improvement = tradeBaseAmount * 20n;
+    if (after - before !== improvement) {
+      throw new Error(`bid should get ${improvement} quote back, got ${after - before}`);
+    }
+    // Every order leaves the book with the auction; fills stay on the claims.
+    for (const order of [ask, bid]) {
+      const settled = await program.account.order.fetch(order);
+      if (settled.isOpen || !settled.isFilled) {
+        throw new Error("the auction should close both orders with their fills on the claims");
+      }
+    }
+    const cleared = await program.account.orderbookState.fetch(market.state);
+    if (!cleared.openOrders.isZero()) {
+      throw new Error("the auction should leave an empty book");
+    }
+    // The matcher collected the fees; the market keeps only its rent.
+    if ((await stateLamports()) !== feesBefore) {
+      throw new Error("run_auction should pay the collected order fees to the matcher");
+    }
+    // The claim stores its handle as 16 little-endian bytes; the bidder was granted it.
+    const bidClaim = new BN(
+      Buffer.from((await program.account.order.fetch(bid)).claimCiphertext),
+      "le",
+    );
+    if ((await decryptHandle(bidClaim, buyer1)) !== tradeBaseAmount) {
+      throw new Error(`the bid's claim should hold its ${tradeBaseAmount} base fill`);
+    }
+
+    // The seller's fill accrued on its claim at the clearing price.
+    await sendTx(
+      "auction_claim_ask",
+      program.methods.makerClaimFilledOrder().accounts({
+        state: market.state,
+        order: ask,
+        owner: seller1.publicKey,
+        maker: seller1.publicKey,
+        incoVaultAuthority: market.vaultAuthority,
+        incoBaseVault: market.baseVault,
+        incoQuoteVault: market.quoteVault,
+        makerBaseInco: sellerBase.publicKey,
+        makerQuoteInco: sellerQuote.publicKey,
+        incoBaseMint: market.baseMint,
+        incoQuoteMint: market.quoteMint,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+        incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+      }),
+      [seller1],
+      true,
+    );
+    const proceeds = await decryptHandle(await incoBalanceHandle(sellerQuote.publicKey), seller1);
+    if (proceeds !== tradeQuoteAmount) {
+      throw new Error(`ask should receive ${tradeQuoteAmount} quote, got ${proceeds}`);
+    }
+
+    // Delisting: the auction record and both orders are reclaimed once the market is closed.
+    const closeResult = () =>
+      program.methods
+        .closeAuctionResult()
+        .accounts({
+          state: market.state,
+          auctionResult,
+          admin: payer.publicKey,
+          caller: buyer1.publicKey,
+        })
+        .signers([buyer1])
+        .rpc();
+    await expectError("auction_result_open_market", closeResult(), "MarketNotClosed");
+    await sendTx(
+      "auction_set_closed",
+      program.methods.setMarketStatus(1).accounts({
+        state: market.state,
+        admin: payer.publicKey,
+      }),
+      [payer],
+    );
+    await closeResult();
+    if (await provider.connection.getAccountInfo(auctionResult)) {
+      throw new Error("close_auction_result should close the record");
+    }
+    const baseBefore = await decryptHandle(await incoBalanceHandle(buyerBase.publicKey), buyer1);
+    for (const [order, owner, base, quote] of [
+      [ask, seller1, sellerBase, sellerQuote],
+      [bid, buyer1, buyerBase, buyerQuote],
+    ] as [PublicKey, Keypair, Keypair, Keypair][]) {
+      await sendTx(
+        "auction_sweep_order",
+        program.methods.sweepMarketOrder().accounts({
+          state: market.state,
+          order,
+          orderGroup: null,
+          owner: owner.publicKey,
+          rentPayer: owner.publicKey,
+          cranker: payer.publicKey,
+          incoVaultAuthority: market.vaultAuthority,
+          incoBaseVault: market.baseVault,
+          incoQuoteVault: market.quoteVault,
+          ownerBaseInco: base.publicKey,
+          ownerQuoteInco: quote.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+          incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+        }),
+        [payer],
+      );
+      if (await provider.connection.getAccountInfo(order)) {
+        throw new Error("sweep_market_order should close every order");
+      }
+    }
+    // The bid's unclaimed fill is paid out by the sweep.
+    const baseAfter = await decryptHandle(await incoBalanceHandle(buyerBase.publicKey), buyer1);
+    if (baseAfter - baseBefore !== tradeBaseAmount) {
+      throw new Error(`the sweep should pay the bid's ${tradeBaseAmount} base claim`);
+    }
+    if (!(await program.account.orderbookState.fetch(market.state)).openEscrows.isZero()) {
+      throw new Error("the swept market should hold no escrow");
+    }
+  });
+
+  it("gates order placement behind access passes once a gatekeeper is set", async () => {
+    const price = new BN(100);
+    const [accessPass] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("access_pass_v1"),
+        statePda.toBuffer(),
+        buyer1.publicKey.toBuffer(),
+      ],
+      program.programId,
+    );
+    const setGatekeeper = (gatekeeper: PublicKey) =>
+      sendTx(
+        "set_gatekeeper",
+        program.methods.setGatekeeper(gatekeeper).accounts({
+          state: statePda,
+          admin: payer.publicKey,
+        }),
+        [payer],
+      );
+    const placeBid = (label: string, pass: PublicKey | null) =>
+      placeLimitOrder(
+        label,
+        buyer1,
+        buyer1Base,
+        buyer1Quote,
+        0,
+        price,
+        mainMarket(),
+        pass,
+      );
+
+    await topUpIncoAccount(buyer1Quote.publicKey, quoteMint.publicKey, topUpQuote);
+    await setGatekeeper(payer.publicKey);
+    try {
+      await expectError(
+        "place_without_pass",
+        placeBid("place_without_pass", null),
+        "MissingAccessPass",
+      );
+
+      await sendTx(
+        "issue_access_pass",
+        program.methods.issueAccessPass().accounts({
+          state: statePda,
+          accessPass,
+          trader: buyer1.publicKey,
+          gatekeeper: payer.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        }),
+        [payer],
+      );
+      const bid = await placeBid("place_with_pass", accessPass);
+      await cancelLimitOrder(
+        "cancel_with_pass",
+        bid,
+        buyer1,
+        buyer1Base,
+        buyer1Quote,
+      );
+
+      // A new gatekeeper does not honour passes issued by the previous one.
+      await setGatekeeper(Keypair.generate().publicKey);
+      await expectError(
+        "place_with_stale_pass",
+        placeBid("place_with_stale_pass", accessPass),
+        "InvalidAccessPass",
+      );
+
+      // The issuer can still revoke once the gatekeeper is unset.
+      await setGatekeeper(PublicKey.default);
+      await sendTx(
+        "revoke_access_pass",
+        program.methods.revokeAccessPass().accounts({
+          state: statePda,
+          accessPass,
+          authority: payer.publicKey,
+          issuer: payer.publicKey,
+        }),
+        [payer],
+      );
+      if (await provider.connection.getAccountInfo(accessPass)) {
+        throw new Error("revoke_access_pass should close the pass");
+      }
+      await setGatekeeper(payer.publicKey);
+      await expectError(
+        "place_after_revoke",
+        placeBid("place_after_revoke", null),
+        "MissingAccessPass",
+      );
+    } finally {
+      // Later tests trade on the same market without passes.
+      await setGatekeeper(PublicKey.default);
+    }
+  });
+
+  it("refunds a losing RFQ quote escrow after the RFQ is cancelled", async () => {
+    await topUpIncoAccount(seller1Base.publicKey, baseMint.publicKey, topUpBase);
+
+    const rfqId = new BN(Date.now());
+    const [rfqRequest] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("rfq_request_v1"),
+        statePda.toBuffer(),
+        buyer1.publicKey.toBuffer(),
+        Buffer.from(rfqId.toArray("le", 8)),
+      ],
+      program.programId,
+    );
+    const [rfqQuote] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("rfq_quote_v1"),
+        rfqRequest.toBuffer(),
+        seller1.publicKey.toBuffer(),
+      ],
+      program.programId,
+    );
+
+    const size = await encryptAmount(tradeBaseAmount);
+    const expirySlot = new BN((await provider.connection.getSlot()) + 1_000);
+    await sendTx(
+      "open_rfq",
+      program.methods
+        .openRfq(rfqId, 0, expirySlot, size.ciphertext, size.inputType)
+        .accounts({
+          state: statePda,
+          rfqRequest,
+          taker: buyer1.publicKey,
+          accessPass: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+        }),
+      [buyer1],
+      true,
+    );
+
+    // The maker escrows the base the taker asked for and names its quote price.
+    const delivery = await encryptAmount(tradeBaseAmount);
+    const payment = await encryptAmount(tradeQuoteAmount);
+    await sendTx(
+      "submit_rfq_quote",
+      await withOwnerAllowances(
+        program.methods
+          .submitRfqQuote(
+            delivery.ciphertext,
+            payment.ciphertext,
+            delivery.inputType,
+          )
+          .accounts({
+            state: statePda,
+            rfqRequest,
+            rfqQuote,
+            maker: seller1.publicKey,
+            accessPass: null,
+            incoBaseVault: baseVault.publicKey,
+            incoQuoteVault: quoteVault.publicKey,
+            makerBaseInco: seller1Base.publicKey,
+            makerQuoteInco: seller1Quote.publicKey,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+            incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+          }),
+        seller1.publicKey,
+      ),
+      [seller1],
+      true,
+    );
+    const escrowsWithQuote = (
+      await program.account.orderbookState.fetch(statePda)
+    ).openEscrows.toNumber();
+
+    const refund = () =>
+      program.methods
+        .refundRfqQuote()
+        .accounts({
+          state: statePda,
+          rfqRequest,
+          rfqQuote,
+          maker: seller1.publicKey,
+          cranker: payer.publicKey,
+          incoVaultAuthority,
+          incoBaseVault: baseVault.publicKey,
+          incoQuoteVault: quoteVault.publicKey,
+          makerBaseInco: seller1Base.publicKey,
+          makerQuoteInco: seller1Quote.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+          incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+        })
+        .preInstructions([
+          ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+        ])
+        .signers([payer])
+        .rpc();
+    await expectError("refund_open_rfq", refund(), "RfqStillOpen");
+
+    await sendTx(
+      "cancel_rfq",
+      program.methods.cancelRfq().accounts({
+        rfqRequest,
+        taker: buyer1.publicKey,
+      }),
+      [buyer1],
+    );
+    const closeRfq = () =>
+      program.methods
+        .closeRfq()
+        .accounts({ rfqRequest, taker: buyer1.publicKey })
+        .signers([buyer1])
+        .rpc();
+    await expectError("close_rfq_with_quote", closeRfq(), "RfqQuotesOutstanding");
+    const sig = await refund();
+    console.log(`refund_rfq_quote.tx: ${explorerBase}${sig}?cluster=devnet`);
+
+    if (await provider.connection.getAccountInfo(rfqQuote)) {
+      throw new Error("refund_rfq_quote should close the quote");
+    }
+    const escrowsAfter = (
+      await program.account.orderbookState.fetch(statePda)
+    ).openEscrows.toNumber();
+    if (escrowsAfter !== escrowsWithQuote - 1) {
+      throw new Error("refund_rfq_quote should release the quote escrow");
+    }
+
+    await closeRfq();
+    if (await provider.connection.getAccountInfo(rfqRequest)) {
+      throw new Error("close_rfq should close the request");
+    }
+  });
+
+  it("returns an RFQ delivery to the maker when the taker cannot pay", async () => {
+    await topUpIncoAccount(seller1Base.publicKey, baseMint.publicKey, topUpBase);
+    // A fresh, empty quote account cannot cover the maker's payment.
+    const emptyQuote = Keypair.generate();
+    await initializeIncoAccount(emptyQuote, quoteMint.publicKey, buyer2.publicKey);
+
+    const rfqId = new BN(Date.now());
+    const [rfqRequest] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("rfq_request_v1"),
+        statePda.toBuffer(),
+        buyer2.publicKey.toBuffer(),
+        Buffer.from(rfqId.toArray("le", 8)),
+      ],
+      program.programId,
+    );
+    const [rfqQuote] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("rfq_quote_v1"),
+        rfqRequest.toBuffer(),
+        seller1.publicKey.toBuffer(),
+      ],
+      program.programId,
+    );
+
+    const size = await encryptAmount(tradeBaseAmount);
+    const expirySlot = new BN((await provider.connection.getSlot()) + 1_000);
+    await sendTx(
+      "underfunded_open_rfq",
+      program.methods
+        .openRfq(rfqId, 0, expirySlot, size.ciphertext, size.inputType)
+        .accounts({
+          state: statePda,
+          rfqRequest,
+          taker: buyer2.publicKey,
+          accessPass: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+        }),
+      [buyer2],
+      true,
+    );
+
+    const makerBaseBefore = await decryptHandle(
+      await incoBalanceHandle(seller1Base.publicKey),
+      seller1,
+    );
+    const takerBaseBefore = await decryptHandle(
+      await incoBalanceHandle(buyer2Base.publicKey),
+      buyer2,
+    );
+    const delivery = await encryptAmount(tradeBaseAmount);
+    const payment = await encryptAmount(tradeQuoteAmount);
+    await sendTx(
+      "underfunded_submit_rfq_quote",
+      await withOwnerAllowances(
+        program.methods
+          .submitRfqQuote(
+            delivery.ciphertext,
+            payment.ciphertext,
+            delivery.inputType,
+          )
+          .accounts({
+            state: statePda,
+            rfqRequest,
+            rfqQuote,
+            maker: seller1.publicKey,
+            accessPass: null,
+            incoBaseVault: baseVault.publicKey,
+            incoQuoteVault: quoteVault.publicKey,
+            makerBaseInco: seller1Base.publicKey,
+            makerQuoteInco: seller1Quote.publicKey,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+            incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+          }),
+        seller1.publicKey,
+      ),
+      [seller1],
+      true,
+    );
+
+    await sendTx(
+      "underfunded_accept_rfq_quote",
+      program.methods.acceptRfqQuote().accounts({
+        state: statePda,
+        rfqRequest,
+        rfqQuote,
+        maker: seller1.publicKey,
+        taker: buyer2.publicKey,
+        incoVaultAuthority,
+        incoBaseVault: baseVault.publicKey,
+        incoQuoteVault: quoteVault.publicKey,
+        takerBaseInco: buyer2Base.publicKey,
+        takerQuoteInco: emptyQuote.publicKey,
+        makerBaseInco: seller1Base.publicKey,
+        makerQuoteInco: seller1Quote.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+        incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+      }),
+      [buyer2],
+      true,
+    );
+
+    const makerBaseAfter = await decryptHandle(
+      await incoBalanceHandle(seller1Base.publicKey),
+      seller1,
+    );
+    if (makerBaseAfter !== makerBaseBefore) {
+      throw new Error("an unpaid RFQ should return the delivery to the maker");
+    }
+    const takerBaseAfter = await decryptHandle(
+      await incoBalanceHandle(buyer2Base.publicKey),
+      buyer2,
+    );
+    if (takerBaseAfter !== takerBaseBefore) {
+      throw new Error("an unpaid RFQ should deliver nothing to the taker");
+    }
+
+    await sendTx(
+      "underfunded_close_rfq",
+      program.methods.closeRfq().accounts({ rfqRequest, taker: buyer2.publicKey }),
+      [buyer2],
+    );
+  });
+
+  it("fills a signed order across matches and rejects cancelled or expired ones", async () => {
+    const price = new BN(100);
+    await topUpIncoAccount(seller1Base.publicKey, baseMint.publicKey, topUpBase);
+    await topUpIncoAccount(buyer1Quote.publicKey, quoteMint.publicKey, topUpQuote);
+
+    const [makerNonces] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("trader_nonces_v1"),
+        statePda.toBuffer(),
+        seller1.publicKey.toBuffer(),
+      ],
+      program.programId,
+    );
+    const escrowAccounts = {
+      state: statePda,
+      traderNonces: makerNonces,
+      trader: seller1.publicKey,
+      incoBaseVault: baseVault.publicKey,
+      incoQuoteVault: quoteVault.publicKey,
+      traderBaseInco: seller1Base.publicKey,
+      traderQuoteInco: seller1Quote.publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+      incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+    };
+    const deposit = await encryptAmount(2n * tradeBaseAmount);
+    await sendTx(
+      "deposit_signed_escrow",
+      await withOwnerAllowances(
+        program.methods
+          .depositSignedEscrow(1, deposit.ciphertext, deposit.inputType)
+          .accounts({ ...escrowAccounts, accessPass: null }),
+        seller1.publicKey,
+      ),
+      [seller1],
+      true,
+    );
+
+    // The signed ask is twice the size of each resting bid, so it fills across two matches.
+    const size = await encryptAmount(2n * tradeBaseAmount);
+    const slot = await provider.connection.getSlot();
+    // Derive fresh nonces per run; cancelling one far ahead slides the window forward.
+    const baseNonce = Date.now() * 4;
+    const signedAsk = (nonce: number, expirySlot: number): SignedOrder => ({
+      market: statePda,
+      maker: seller1.publicKey,
+      side: 1,
+      price,
+      sizeCiphertext: size.ciphertext,
+      inputType: size.inputType,
+      nonce: new BN(nonce),
+      expirySlot: new BN(expirySlot),
+    });
+    const signedFillPda = (nonce: number) =>
+      PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("signed_fill_v1"),
+          statePda.toBuffer(),
+          seller1.publicKey.toBuffer(),
+          new BN(nonce).toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId,
+      )[0];
+    const matchSigned = async (label: string, order: SignedOrder, bid: PublicKey) => {
+      const preInstructions = [
+        ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+        Ed25519Program.createInstructionWithPrivateKey({
+          privateKey: seller1.secretKey,
+          message: signedOrderMessage(order),
+        }),
+      ];
+      const takerBase = await encryptAmount(tradeBaseAmount);
+      const method = program.methods
+        .matchSignedOrder(order, takerBase.ciphertext, takerBase.inputType)
+        .accounts({
+          state: statePda,
+          makerNonces,
+          signedFill: signedFillPda(order.nonce.toNumber()),
+          takerOrder: bid,
+          matcher: payer.publicKey,
+          makerAccessPass: null,
+          takerAccessPass: null,
+          instructionsSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+          incoVaultAuthority,
+          incoBaseVault: baseVault.publicKey,
+          incoQuoteVault: quoteVault.publicKey,
+          makerBaseInco: seller1Base.publicKey,
+          makerQuoteInco: seller1Quote.publicKey,
+          takerBaseInco: buyer1Base.publicKey,
+          takerQuoteInco: buyer1Quote.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+          incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+        });
+      // The ed25519 check must sit right before the match; the compute limit goes first.
+      return sendTx(
+        label,
+        (
+          await withOwnerAllowances(
+            method,
+            payer.publicKey,
+            preInstructions.slice(1),
+          )
+        ).preInstructions(preInstructions),
+        [payer],
+      );
+    };
+    const signedRemaining = async (nonce: number) =>
+      decryptHandle(
+        ((await program.account.signedOrderFill.fetch(signedFillPda(nonce))) as any)
+          .remainingHandle,
+        seller1,
+      );
+    const cancelNonce = (nonce: number) =>
+      sendTx(
+        "cancel_signed_order",
+        program.methods.cancelSignedOrder(new BN(nonce)).accounts({
+          state: statePda,
+          traderNonces: makerNonces,
+          trader: seller1.publicKey,
+        }),
+        [seller1],
+      );
+
+    const partial = signedAsk(baseNonce, slot + 1_000);
+    const firstBid = await placeLimitOrder(
+      "signed_place_bid",
+      buyer1,
+      buyer1Base,
+      buyer1Quote,
+      0,
+      price,
+    );
+    await matchSigned("match_signed_order", partial, firstBid);
+    if ((await signedRemaining(baseNonce)) !== tradeBaseAmount) {
+      throw new Error("a partial fill should keep the rest of the signed order");
+    }
+    const secondBid = await placeLimitOrder(
+      "signed_place_second_bid",
+      buyer1,
+      buyer1Base,
+      buyer1Quote,
+      0,
+      price,
+    );
+    await matchSigned("match_signed_remainder", partial, secondBid);
+    if ((await signedRemaining(baseNonce)) !== 0n) {
+      throw new Error("the signed order should not fill beyond its signed size");
+    }
+
+    // A live order's fill record stays until the order is cancelled or expires.
+    const closeFill = (nonce: number) =>
+      program.methods
+        .closeSignedFill()
+        .accounts({
+          state: statePda,
+          makerNonces,
+          signedFill: signedFillPda(nonce),
+          payer: payer.publicKey,
+        })
+        .rpc();
+    await expectError("close_live_signed_fill", closeFill(baseNonce), "SignedOrderStillLive");
+    await cancelNonce(baseNonce);
+    await expectError(
+      "match_signed_cancelled",
+      matchSigned("match_signed_cancelled", partial, secondBid),
+      "NonceAlreadyUsed",
+    );
+    await closeFill(baseNonce);
+    if (await provider.connection.getAccountInfo(signedFillPda(baseNonce))) {
+      throw new Error("close_signed_fill should close the fill record");
+    }
+
+    await expectError(
+      "match_signed_expired",
+      matchSigned("match_signed_expired", signedAsk(baseNonce + 2, slot - 1), secondBid),
+      "SignedOrderExpired",
+    );
+
+    // Cancelling past the 4096-nonce window slides it, so older nonces count as used.
+    await cancelNonce(baseNonce + 8_192);
+    await expectError(
+      "match_signed_below_window",
+      matchSigned(
+        "match_signed_below_window",
+        signedAsk(baseNonce + 1, slot + 1_000),
+        secondBid,
+      ),
+      "NonceAlreadyUsed",
+    );
+
+    for (const [label, bid] of [
+      ["signed_cancel_bid", firstBid],
+      ["signed_cancel_second_bid", secondBid],
+    ] as const) {
+      await cancelLimitOrder(label, bid, buyer1, buyer1Base, buyer1Quote);
+    }
+    // An empty amount withdraws whatever is left of the base balance.
+    await sendTx(
+      "withdraw_signed_escrow",
+      program.methods
+        .withdrawSignedEscrow(1, Buffer.alloc(0), 0)
+        .accounts({ ...escrowAccounts, incoVaultAuthority }),
+      [seller1],
+      true,
+    );
+  });
+
+  it("credits a signed-escrow deposit with the debited amount only", async () => {
+    const [traderNonces] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("trader_nonces_v1"),
+        statePda.toBuffer(),
+        buyer2.publicKey.toBuffer(),
+      ],
+      program.programId,
+    );
+    // A fresh, empty quote account cannot cover the deposit.
+    const emptyQuote = Keypair.generate();
+    await initializeIncoAccount(emptyQuote, quoteMint.publicKey, buyer2.publicKey);
+    const escrowAccounts = {
+      state: statePda,
+      traderNonces,
+      trader: buyer2.publicKey,
+      incoBaseVault: baseVault.publicKey,
+      incoQuoteVault: quoteVault.publicKey,
+      traderBaseInco: buyer2Base.publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+      incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+    };
+    const depositFrom = async (label: string, quoteInco: PublicKey) => {
+      const amount = await encryptAmount(tradeQuoteAmount);
+      await sendTx(
+        label,
+        await withOwnerAllowances(
+          program.methods
+            .depositSignedEscrow(0, amount.ciphertext, amount.inputType)
+            .accounts({ ...escrowAccounts, traderQuoteInco: quoteInco, accessPass: null }),
+          buyer2.publicKey,
+        ),
+        [buyer2],
+        true,
+      );
+      return decryptHandle(
+        ((await program.account.traderNonces.fetch(traderNonces)) as any).quoteEscrowHandle,
+        buyer2,
+      );
+    };
+
+    const start = (await program.account.traderNonces.fetchNullable(traderNonces)) as any;
+    const before =
+      start && !new BN(start.quoteEscrowHandle.toString()).isZero()
+        ? await decryptHandle(start.quoteEscrowHandle, buyer2)
+        : 0n;
+    if ((await depositFrom("unfunded_signed_deposit", emptyQuote.publicKey)) !== before) {
+      throw new Error("an uncovered deposit should credit nothing");
+    }
+    await topUpIncoAccount(buyer2Quote.publicKey, quoteMint.publicKey, topUpQuote);
+    const funded = await depositFrom("funded_signed_deposit", buyer2Quote.publicKey);
+    if (funded !== before + tradeQuoteAmount) {
+      throw new Error("a covered deposit should credit the deposited amount");
+    }
+
+    await sendTx(
+      "withdraw_deposit_test_escrow",
+      program.methods
+        .withdrawSignedEscrow(0, Buffer.alloc(0), 0)
+        .accounts({
+          ...escrowAccounts,
+          traderQuoteInco: buyer2Quote.publicKey,
+          incoVaultAuthority,
+        }),
+      [buyer2],
+      true,
+    );
+  });
+
+  it("holds trading delegates to their order limit, actions and revocation", async () => {
+    const price = new BN(100);
+    const delegate = loadOrCreateKeypair("delegate_1");
+    // The delegate pays the order rent.
+    if ((await provider.connection.getBalance(delegate.publicKey)) < 50_000_000) {
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: payer.publicKey,
+            toPubkey: delegate.publicKey,
+            lamports: 50_000_000,
+          }),
+        ),
+        [payer],
+      );
+    }
+
+    const [ownerNonces] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("trader_nonces_v1"),
+        statePda.toBuffer(),
+        buyer1.publicKey.toBuffer(),
+      ],
+      program.programId,
+    );
+    const [tradingDelegate] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("trading_delegate_v1"),
+        buyer1.publicKey.toBuffer(),
+        delegate.publicKey.toBuffer(),
+      ],
+      program.programId,
+    );
+    const escrowAccounts = {
+      state: statePda,
+      traderNonces: ownerNonces,
+      trader: buyer1.publicKey,
+      incoBaseVault: baseVault.publicKey,
+      incoQuoteVault: quoteVault.publicKey,
+      traderBaseInco: buyer1Base.publicKey,
+      traderQuoteInco: buyer1Quote.publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+      incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+    };
+    await topUpIncoAccount(buyer1Quote.publicKey, quoteMint.publicKey, topUpQuote);
+    const deposit = await encryptAmount(2n * tradeQuoteAmount);
+    await sendTx(
+      "delegate_owner_deposit",
+      await withOwnerAllowances(
+        program.methods
+          .depositSignedEscrow(0, deposit.ciphertext, deposit.inputType)
+          .accounts({ ...escrowAccounts, accessPass: null }),
+        buyer1.publicKey,
+      ),
+      [buyer1],
+      true,
+    );
+
+    const grant = (actions: number) =>
+      sendTx(
+        "grant_trading_delegate",
+        program.methods
+          .grantTradingDelegate(
+            delegate.publicKey,
+            new BN(Math.floor(Date.now() / 1000) + 3_600),
+            [statePda],
+            actions,
+            1,
+          )
+          .accounts({
+            tradingDelegate,
+            owner: buyer1.publicKey,
+            systemProgram: anchor.web3.SystemProgram.programId,
+          }),
+        [buyer1],
+      );
+    const delegatePlace = async (label: string): Promise<PublicKey> => {
+      const size = await encryptAmount(tradeBaseAmount);
+      const seq = new BN(
+        (await program.account.orderbookState.fetch(statePda)).orderSeq.toString(),
+      );
+      const order = orderPda(statePda, buyer1.publicKey, seq);
+      await sendTx(
+        label,
+        await withOwnerAllowances(
+          program.methods
+            .delegatePlaceOrder(0, price, size.ciphertext, size.inputType)
+            .accounts({
+              state: statePda,
+              order,
+              tradingDelegate,
+              ownerNonces,
+              owner: buyer1.publicKey,
+              delegate: delegate.publicKey,
+              accessPass: null,
+              systemProgram: anchor.web3.SystemProgram.programId,
+              incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+            }),
+          delegate.publicKey,
+        ),
+        [delegate],
+        true,
+      );
+      return order;
+    };
+    const delegateCancel = async (label: string, order: PublicKey) =>
+      sendTx(
+        label,
+        program.methods
+          .delegateCancelOrder()
+          .accounts({
+            state: statePda,
+            order,
+            orderGroup: null,
+            trader: buyer1.publicKey,
+            tradingDelegate,
+            delegate: delegate.publicKey,
+            incoVaultAuthority,
+            incoBaseVault: baseVault.publicKey,
+            incoQuoteVault: quoteVault.publicKey,
+            traderBaseInco: buyer1Base.publicKey,
+            traderQuoteInco: buyer1Quote.publicKey,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+            incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+          }),
+        [delegate],
+        true,
+      );
+
+    // Place-only grant for a single order.
+    await grant(DELEGATE_ACTION_PLACE);
+    const order = await delegatePlace("delegate_place_order");
+    const placed = (await program.account.order.fetch(order)) as any;
+    if (!placed.owner.equals(buyer1.publicKey)) {
+      throw new Error("a delegated order should belong to the owner");
+    }
+    await expectError(
+      "delegate_over_limit",
+      delegatePlace("delegate_over_limit"),
+      "DelegateOrderLimit",
+    );
+    await expectError(
+      "delegate_cancel_not_granted",
+      delegateCancel("delegate_cancel_not_granted", order),
+      "DelegateActionNotAllowed",
+    );
+
+    // Granting again replaces the grant, so cancelling is now allowed.
+    await grant(DELEGATE_ACTION_PLACE | DELEGATE_ACTION_CANCEL);
+    await delegateCancel("delegate_cancel_order", order);
+
+    await sendTx(
+      "revoke_trading_delegate",
+      program.methods.revokeTradingDelegate().accounts({
+        tradingDelegate,
+        owner: buyer1.publicKey,
+      }),
+      [buyer1],
+    );
+    await expectError(
+      "delegate_after_revoke",
+      delegatePlace("delegate_after_revoke"),
+      "AccountNotInitialized",
+    );
+
+    await sendTx(
+      "delegate_owner_withdraw",
+      program.methods
+        .withdrawSignedEscrow(0, Buffer.alloc(0), 0)
+        .accounts({ ...escrowAccounts, incoVaultAuthority }),
+      [buyer1],
+      true,
+    );
+  });
+
+  it("expires a prepared match that is not settled in time", async () => {
+    const price = new BN(100);
+    await topUpIncoAccount(buyer1Quote.publicKey, quoteMint.publicKey, topUpQuote);
+    await topUpIncoAccount(seller1Base.publicKey, baseMint.publicKey, topUpBase);
+
+    const bid = await placeLimitOrder(
+      "pending_place_bid",
+      buyer1,
+      buyer1Base,
+      buyer1Quote,
+      0,
+      price,
+    );
+    const ask = await placeLimitOrder(
+      "pending_place_ask",
+      seller1,
+      seller1Base,
+      seller1Quote,
+      1,
+      price,
+    );
+    const [pendingMatch] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pending_match_v1"), ask.toBuffer(), bid.toBuffer()],
+      program.programId,
+    );
+
+    const takerBase = await encryptAmount(tradeBaseAmount);
+    await sendTx(
+      "prepare_match",
+      await withOwnerAllowances(
+        program.methods
+          .prepareMatch(0, price, takerBase.ciphertext, takerBase.inputType)
+          .accounts({
+            state: statePda,
+            pendingMatch,
+            makerOrder: ask,
+            takerOrder: bid,
+            owner: seller1.publicKey,
+            matcher: payer.publicKey,
+            taker: buyer1.publicKey,
+            takerAccessPass: null,
+            priceFeed: null,
+            takerReqBaseBuffer: null,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+          }),
+        payer.publicKey,
+      ),
+      [payer],
+      true,
+    );
+
+    const discard = (caller: Keypair) =>
+      program.methods
+        .discardPendingMatch()
+        .accounts({
+          state: statePda,
+          pendingMatch,
+          matcher: payer.publicKey,
+          caller: caller.publicKey,
+        })
+        .signers([caller])
+        .rpc();
+    await expectError(
+      "discard_before_timeout",
+      discard(buyer1),
+      "PendingMatchNotExpired",
+    );
+
+    const { preparedSlot } = await program.account.pendingMatch.fetch(
+      pendingMatch,
+    );
+    const deadline = preparedSlot.toNumber() + PENDING_MATCH_TIMEOUT_SLOTS;
+    while ((await provider.connection.getSlot()) <= deadline) {
+      await new Promise((resolve) => setTimeout(resolve, 2_000));
+    }
+
+    await expectError(
+      "settle_after_timeout",
+      program.methods
+        .settleMatch()
+        .accounts({
+          state: statePda,
+          pendingMatch,
+          makerOrder: ask,
+          takerOrder: bid,
+          matcher: payer.publicKey,
+          takerAccessPass: null,
+          makerGroup: null,
+          takerGroup: null,
+          incoVaultAuthority,
+          incoBaseVault: baseVault.publicKey,
+          incoQuoteVault: quoteVault.publicKey,
+          makerBaseInco: seller1Base.publicKey,
+          makerQuoteInco: seller1Quote.publicKey,
+          takerBaseInco: buyer1Base.publicKey,
+          takerQuoteInco: buyer1Quote.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+          incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+        })
+        .signers([payer])
+        .rpc(),
+      "PendingMatchExpired",
+    );
+
+    // Past the timeout anyone may discard; the rent still goes to the matcher.
+    const sig = await discard(buyer1);
+    console.log(`discard_pending_match.tx: ${explorerBase}${sig}?cluster=devnet`);
+    if (await provider.connection.getAccountInfo(pendingMatch)) {
+      throw new Error("discard_pending_match should close the pending match");
+    }
+    for (const order of [ask, bid]) {
+      const account = (await program.account.order.fetch(order)) as any;
+      if (!account.isOpen || account.isFilled) {
+        throw new Error("an expired match should leave both orders untouched");
+      }
+    }
+
+    await cancelLimitOrder(
+      "pending_cancel_bid",
+      bid,
+      buyer1,
+      buyer1Base,
+      buyer1Quote,
+    );
+    await cancelLimitOrder(
+      "pending_cancel_ask",
+      ask,
+      seller1,
+      seller1Base,
+      seller1Quote,
+    );
+  });
+
+  it("places and cancels an order from a PDA trader through the CPI", async () => {
+    const [strategy] = PublicKey.findProgramAddressSync(
+      [Buffer.from("strategy"), payer.publicKey.toBuffer(), statePda.toBuffer()],
+      cpiTrader.programId,
+    );
+    if (!(await provider.connection.getAccountInfo(strategy))) {
+      await sendTx(
+        "initialize_strategy",
+        cpiTrader.methods.initializeStrategy().accounts({
+          strategy,
+          market: statePda,
+          authority: payer.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        }),
+        [payer],
+      );
+    }
+
+    // The strategy PDA owns its Inco accounts; the authority only pays rent.
+    const strategyBase = loadOrCreateKeypair(keyName("strategy_base"));
+    const strategyQuote = loadOrCreateKeypair(keyName("strategy_quote"));
+    for (const [acct, mint] of [
+      [strategyBase, baseMint.publicKey],
+      [strategyQuote, quoteMint.publicKey],
+    ] as [Keypair, PublicKey][]) {
+      if (!(await provider.connection.getAccountInfo(acct.publicKey))) {
+        await initializeIncoAccount(acct, mint, strategy);
+      }
+    }
+    await topUpIncoAccount(
+      strategyQuote.publicKey,
+      quoteMint.publicKey,
+      tradeQuoteAmount,
+    );
+
+    const price = new BN(100);
+    const sizeCipher = await encryptAmount(tradeBaseAmount);
+    const seq = new BN(
+      (await program.account.orderbookState.fetch(statePda)).orderSeq.toString(),
+    );
+    const order = orderPda(statePda, strategy, seq);
+    const marketAccounts = {
+      strategy,
+      authority: payer.publicKey,
+      market: statePda,
+      order,
+      incoVaultAuthority,
+      incoBaseVault: baseVault.publicKey,
+      incoQuoteVault: quoteVault.publicKey,
+      strategyBaseInco: strategyBase.publicKey,
+      strategyQuoteInco: strategyQuote.publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+      incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+      zivoProgram: program.programId,
+    };
+
+    await sendTx(
+      "cpi_place_bid",
+      await withOwnerAllowances(
+        cpiTrader.methods
+          .placeOrder(
+            0,
+            price,
+            sizeCipher.ciphertext,
+            sizeCipher.inputType,
+          )
+          .accounts({
+            ...marketAccounts,
+            incoBaseMint: baseMint.publicKey,
+            incoQuoteMint: quoteMint.publicKey,
+          }),
+        payer.publicKey,
+      ),
+      [payer],
+      true,
+    );
+    const placed = (await program.account.order.fetch(order)) as any;
+    if (!placed.owner.equals(strategy) || !placed.isOpen) {
+      throw new Error("CPI order should be open and owned by the strategy PDA");
+    }
+
+    // A bid's cancel refunds its unfilled quote escrow.
+    await sendTx(
+      "cpi_cancel_bid",
+      cpiTrader.methods
+        .cancelOrder()
+        .accounts(marketAccounts),
+      [payer],
+      true,
+    );
+    const cancelled = (await program.account.order.fetch(order)) as any;
+    if (cancelled.isOpen) {
+      throw new Error("CPI order should be closed after cancel");
+    }
+  });
+
+  it("keeps a partially filled compressed maker open and accrues its claim", async () => {
+    if (!process.env.LIGHT_RPC_URL) {
+      console.log("compressed: LIGHT_RPC_URL not set; skipping");
+      return;
+    }
+    (featureFlags as any).version = VERSION.V2;
+    const price = new BN(100);
+    const half = tradeBaseAmount / 2n;
+    const market = await createMarket("compressed");
+    const sellerBase = Keypair.generate();
+    const sellerQuote = Keypair.generate();
+    const buyerBase = Keypair.generate();
+    const buyerQuote = Keypair.generate();
+    await initializeIncoAccount(sellerBase, market.baseMint, seller1.publicKey);
+    await initializeIncoAccount(sellerQuote, market.quoteMint, seller1.publicKey);
+    await initializeIncoAccount(buyerBase, market.baseMint, buyer1.publicKey);
+    await initializeIncoAccount(buyerQuote, market.quoteMint, buyer1.publicKey);
+    await mintToInco(market.baseMint, sellerBase.publicKey, tradeBaseAmount);
+    await mintToInco(market.quoteMint, buyerQuote.publicKey, tradeQuoteAmount);
+
+    // Both orders must sit in the one state tree a match passes.
+    const stateTree = selectStateTreeInfo(await getLightRpc().getStateTreeInfos());
+    const trees = { tree: stateTree.tree, queue: stateTree.queue! };
+    const addressTree = getDefaultAddressTreeInfo();
+    const lookupTable = await createLookupTable("compressed_lut", [
+      program.programId,
+      INCO_TOKEN_PROGRAM_ID,
+      INCO_LIGHTNING_PROGRAM_ID,
+      anchor.web3.SystemProgram.programId,
+      market.state,
+      market.vaultAuthority,
+      market.baseVault,
+      market.quoteVault,
+      sellerBase.publicKey,
+      sellerQuote.publicKey,
+      buyerBase.publicKey,
+      buyerQuote.publicKey,
+      addressTree.tree,
+      addressTree.queue,
+      trees.tree,
+      trees.queue,
+      ...lightSystemAccounts().map((meta) => meta.pubkey),
+    ]);
+
+    const ask = await placeCompressedOrder(
+      "compressed_place_ask",
+      seller1,
+      sellerBase.publicKey,
+      sellerQuote.publicKey,
+      1,
+      price,
+      market,
+      trees,
+      lookupTable,
+    );
+    const bid = await placeCompressedOrder(
+      "compressed_place_bid",
+      buyer1,
+      buyerBase.publicKey,
+      buyerQuote.publicKey,
+      0,
+      price,
+      market,
+      trees,
+      lookupTable,
+    );
+
+    // Each match asks for half the ask, read from a matcher buffer to keep the v0 message small.
+    const reqBase = await encryptAmount(half);
+    const reqBaseBuffer = await writeCiphertextBuffer(
+      "compressed_req_base",
+      payer,
+      new BN(Date.now()),
+      reqBase.ciphertext,
+      256,
+    );
+    const claimHandle = (order: any): bigint => {
+      const bytes = Buffer.from(order.claimCiphertext);
+      return bytes.readBigUInt64LE(0) | (bytes.readBigUInt64LE(8) << 64n);
+    };
+    const matchHalf = async (label: string): Promise<void> => {
+      const maker = (await fetchCompressedOrder(ask))!;
+      const taker = (await fetchCompressedOrder(bid))!;
+      const { proof, metas } = await compressedOrderProof([maker, taker]);
+      await sendV0WithOwnerAllowances(
+        label,
+        (pairs) =>
+          program.methods
+            .matchCompressedOrder(
+              price,
+              Buffer.alloc(0),
+              reqBase.inputType,
+              maker.order,
+              metas[0],
+              taker.order,
+              metas[1],
+              proof,
+              pairs.length,
+            )
+            .accounts({
+              state: market.state,
+              matcher: payer.publicKey,
+              takerAccessPass: null,
+              takerReqBaseBuffer: reqBaseBuffer,
+              incoVaultAuthority: market.vaultAuthority,
+              incoBaseVault: market.baseVault,
+              incoQuoteVault: market.quoteVault,
+              takerBaseInco: buyerBase.publicKey,
+              takerQuoteInco: buyerQuote.publicKey,
+              systemProgram: anchor.web3.SystemProgram.programId,
+              incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+              incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+              stateQueue: maker.queue,
+              stateTree: maker.tree,
+            })
+            .remainingAccounts([...pairs, ...lightSystemAccounts()])
+            .instruction(),
+        [payer],
+        lookupTable,
+      );
+      // Wait for the indexer to pick up the rewritten maker.
+      await fetchCompressedOrder(ask, (a) => a !== null && !a.hash.eq(maker.hash));
+    };
+
+    await matchHalf("compressed_match_first");
+    const afterFirst = (await fetchCompressedOrder(ask))!.order;
+    if (!afterFirst.isOpen || !afterFirst.isFilled) {
+      throw new Error("a partially filled compressed maker should stay open with a claim");
+    }
+    if (await decryptHandle(afterFirst.remainingHandle, seller1) !== tradeBaseAmount - half) {
+      throw new Error("the compressed maker should keep the unfilled half");
+    }
+    if (await decryptHandle(claimHandle(afterFirst), seller1) !== tradeQuoteAmount / 2n) {
+      throw new Error("the first fill should leave half the quote on the claim");
+    }
+
+    // The second fill adds to the same claim instead of replacing it.
+    await matchHalf("compressed_match_second");
+    const afterSecond = (await fetchCompressedOrder(ask))!;
+    if (await decryptHandle(afterSecond.order.remainingHandle, seller1) !== 0n) {
+      throw new Error("the second fill should use up the compressed maker");
+    }
+    if (await decryptHandle(claimHandle(afterSecond.order), seller1) !== tradeQuoteAmount) {
+      throw new Error("both fills should accrue on the compressed maker's claim");
+    }
+
+    // Claiming an open order pays it and leaves the order on the book with no claim.
+    const claimProof = await compressedOrderProof([afterSecond]);
+    await sendV0(
+      "compressed_claim_ask",
+      [
+        await program.methods
+          .claimCompressedOrder(afterSecond.order, claimProof.metas[0], claimProof.proof, 0)
+          .accounts({
+            state: market.state,
+            maker: seller1.publicKey,
+            incoVaultAuthority: market.vaultAuthority,
+            incoBaseVault: market.baseVault,
+            incoQuoteVault: market.quoteVault,
+            makerBaseInco: sellerBase.publicKey,
+            makerQuoteInco: sellerQuote.publicKey,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+            incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+            stateQueue: afterSecond.queue,
+            stateTree: afterSecond.tree,
+          })
+          .remainingAccounts(lightSystemAccounts())
+          .instruction(),
+      ],
+      [seller1],
+      lookupTable,
+    );
+    const claimed = (await fetchCompressedOrder(
+      ask,
+      (a) => a !== null && !a.hash.eq(afterSecond.hash),
+    ))!;
+    if (!claimed.order.isOpen || claimed.order.isFilled) {
+      throw new Error("a claimed open compressed order should stay open without a claim");
+    }
+    const proceeds = await decryptHandle(
+      await incoBalanceHandle(sellerQuote.publicKey),
+      seller1,
+    );
+    if (proceeds !== tradeQuoteAmount) {
+      throw new Error(`seller should receive ${tradeQuoteAmount} quote, got ${proceeds}`);
+    }
+
+    for (const [label, address, trader, base, quote] of [
+      ["compressed_cancel_ask", ask, seller1, sellerBase, sellerQuote],
+      ["compressed_cancel_bid", bid, buyer1, buyerBase, buyerQuote],
+    ] as [string, PublicKey, Keypair, Keypair, Keypair][]) {
+      const account = (await fetchCompressedOrder(address))!;
+      const cancelProof = await compressedOrderProof([account]);
+      await sendV0(
+        label,
+        [
+          await program.methods
+            .cancelCompressedOrder(account.order, cancelProof.metas[0], cancelProof.proof, 0)
+            .accounts({
+              state: market.state,
+              trader: trader.publicKey,
+              incoVaultAuthority: market.vaultAuthority,
+              incoBaseVault: market.baseVault,
+              incoQuoteVault: market.quoteVault,
+              traderBaseInco: base.publicKey,
+              traderQuoteInco: quote.publicKey,
+              systemProgram: anchor.web3.SystemProgram.programId,
+              incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+              incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+              stateQueue: account.queue,
+              stateTree: account.tree,
+            })
+            .remainingAccounts(lightSystemAccounts())
+            .instruction(),
+        ],
+        [trader],
+        lookupTable,
+      );
+      await fetchCompressedOrder(address, (a) => a === null);
+    }
+    if ((await program.account.orderbookState.fetch(market.state)).openEscrows.toNumber() !== 0) {
+      throw new Error("cancelling both settled compressed orders should release their escrows");
+    }
+    await sendTx(
+      "compressed_close_buffer",
+      program.methods.closeBuffer().accounts({ buffer: reqBaseBuffer, owner: payer.publicKey }),
+      [payer],
+    );
+  });
+
+  it("refuses a note spend into an order unless its root, leaf and backing match the pool", async () => {
+    const zivoWrap = loadZivoWrap();
+    if (!zivoWrap) {
+      console.log("note_spend: zivo-wrap IDL not built; skipping");
+      return;
+    }
+    const market = await createMarket("note_spend");
+    const shielded = await createShieldedPool("note_spend", zivoWrap, market.quoteMint);
+    const noteOwner = Keypair.generate();
+    // Receives the note value the order does not escrow.
+    const refundInco = Keypair.generate();
+    await initializeIncoAccount(refundInco, shielded.incoMint, noteOwner.publicKey);
+    const root = randomField();
+    const nullifier = randomField();
+    const unused = Keypair.generate().publicKey;
+
+    const spend = async (label: string, payload: Buffer, commitment: Buffer = randomField()) => {
+      const sizeCipher = await encryptAmount(tradeBaseAmount);
+      const seq = new BN(
+        (await program.account.orderbookState.fetch(market.state)).orderSeq.toString(),
+      );
+      return sendTx(
+        label,
+        program.methods
+          .placeOrderFromNote(
+            0,
+            new BN(100),
+            sizeCipher.ciphertext,
+            sizeCipher.inputType,
+            payload,
+            Array.from(nullifier),
+            { 0: null },
+            { addressMerkleTreePubkeyIndex: 0, addressQueuePubkeyIndex: 0, rootIndex: 0 },
+            0,
+            0,
+          )
+          .accounts({
+            state: market.state,
+            order: orderPda(market.state, noteOwner.publicKey, seq),
+            noteOwner: noteOwner.publicKey,
+            payer: payer.publicKey,
+            accessPass: null,
+            incoVaultAuthority: market.vaultAuthority,
+            incoBaseVault: market.baseVault,
+            incoQuoteVault: market.quoteVault,
+            refundIncoAccount: refundInco.publicKey,
+            shieldedPool: shielded.pool,
+            wrapVault: shielded.vault,
+            splTokenMint: shielded.splMint,
+            incoTokenMint: shielded.incoMint,
+            poolIncoAccount: shielded.poolInco,
+            noteBacking: noteBackingPda(shielded.pool, commitment, zivoWrap.programId),
+            verifierProgram: zivoWrap.programId,
+            addressTree: unused,
+            addressQueue: unused,
+            stateQueue: unused,
+            stateTree: unused,
+            zivoWrapProgram: zivoWrap.programId,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+            incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+          }),
+        [payer, noteOwner],
+        true,
+      );
+    };
+
+    // A proof over a root the pool never recorded is refused before the verifier runs.
+    await expectError(
+      "note_spend_unknown_root",
+      spend("note_spend_unknown_root", noteSpendPayload({ root, nullifier, amount: 100n })),
+      "UnknownRoot",
+    );
+    await expectError(
+      "note_spend_root_by_stranger",
+      sendTx(
+        "note_spend_root_by_stranger",
+        zivoWrap.methods
+          .updatePoolRoot(Array.from(root))
+          .accounts({ shieldedPool: shielded.pool, authority: buyer1.publicKey }),
+        [buyer1],
+      ),
+      "ConstraintHasOne",
+    );
+    await sendTx(
+      "note_spend_update_root",
+      zivoWrap.methods
+        .updatePoolRoot(Array.from(root))
+        .accounts({ shieldedPool: shielded.pool, authority: payer.publicKey }),
+      [payer],
+    );
+    // With the root recorded, the spend gets past it to the next binding: a public nullifier
+    // other than the instruction's is refused.
+    await expectError(
+      "note_spend_recorded_root",
+      spend(
+        "note_spend_recorded_root",
+        noteSpendPayload({ root, nullifier: randomField(), amount: 100n }),
+      ),
+      "PublicInputMismatch",
+    );
+    // A proof whose leaf is some other note than its commitment is refused, so a prover cannot
+    // claim an arbitrary amount against any leaf already in the tree.
+    const commitment = randomField();
+    const bound = {
+      root,
+      nullifier,
+      amount: 100n,
+      recipient: noteOwner.publicKey,
+      mint: shielded.incoMint,
+    };
+    await expectError(
+      "note_spend_foreign_leaf",
+      spend(
+        "note_spend_foreign_leaf",
+        noteSpendPayload({ ...bound, commitment, leaf: randomField() }),
+        commitment,
+      ),
+      "PublicInputMismatch",
+    );
+    // With the leaf bound, a commitment the pool never backed is refused, as is a backing
+    // account for another commitment.
+    await expectError(
+      "note_spend_unbacked",
+      spend(
+        "note_spend_unbacked",
+        noteSpendPayload({ ...bound, commitment, leaf: commitment }),
+        commitment,
+      ),
+      "InvalidNoteBacking",
+    );
+    await expectError(
+      "note_spend_foreign_backing",
+      spend(
+        "note_spend_foreign_backing",
+        noteSpendPayload({ ...bound, commitment, leaf: commitment }),
+        randomField(),
+      ),
+      "InvalidNoteBacking",
+    );
+    if ((await program.account.orderbookState.fetch(market.state)).openEscrows.toNumber() !== 0) {
+      throw new Error("a refused note spend must not open an order escrow");
+    }
+  });
+
+  it("backs a deposited note only through the shielded pool's Inco account", async () => {
+    const zivoWrap = loadZivoWrap();
+    if (!zivoWrap) {
+      console.log("note_deposit: zivo-wrap IDL not built; skipping");
+      return;
+    }
+    const shielded = await createShieldedPool("note_deposit", zivoWrap, quoteMint.publicKey);
+    const source = Keypair.generate();
+    await initializeIncoAccount(source, quoteMint.publicKey, buyer1.publicKey);
+    await topUpIncoAccount(source.publicKey, quoteMint.publicKey, 500n);
+    const sourceHandle = await incoBalanceHandle(source.publicKey);
+    const poolBefore = await incoBalanceHandle(shielded.poolInco);
+    const commitment = randomField();
+    const unused = Keypair.generate().publicKey;
+    const deposit = (label: string, poolIncoAccount: PublicKey) =>
+      sendTx(
+        label,
+        zivoWrap.methods
+          .depositAndCommit(
+            new BN(sourceHandle.toString()),
+            Array.from(commitment),
+            { 0: null },
+            { addressMerkleTreePubkeyIndex: 0, addressQueuePubkeyIndex: 0, rootIndex: 0 },
+            0,
+            0,
+          )
+          .accounts({
+            shieldedPool: shielded.pool,
+            vault: shielded.vault,
+            splTokenMint: shielded.splMint,
+            incoTokenMint: shielded.incoMint,
+            poolIncoAccount,
+            noteBacking: noteBackingPda(shielded.pool, commitment, zivoWrap.programId),
+            sourceIncoAccount: source.publicKey,
+            authority: buyer1.publicKey,
+            user: buyer1.publicKey,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+            incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+            addressTree: unused,
+            addressQueue: unused,
+            stateQueue: unused,
+            stateTree: unused,
+          }),
+        [buyer1],
+        true,
+      );
+
+    // Value sent anywhere but the pinned pool account could not back the note.
+    await expectError(
+      "note_deposit_foreign_pool",
+      deposit("note_deposit_foreign_pool", source.publicKey),
+      "InvalidPoolAccount",
+    );
+    // The pinned account gets past to the Light accounts, which are missing.
+    await expectError(
+      "note_deposit_pool",
+      deposit("note_deposit_pool", shielded.poolInco),
+      "InvalidTreeConfig",
+    );
+    if (
+      (await incoBalanceHandle(source.publicKey)) !== sourceHandle ||
+      (await incoBalanceHandle(shielded.poolInco)) !== poolBefore ||
+      (await provider.connection.getAccountInfo(
+        noteBackingPda(shielded.pool, commitment, zivoWrap.programId),
+      )) !== null
+    ) {
+      throw new Error("a refused deposit must leave the balances alone and record no backing");
+    }
+  });
+
+  it("commits a maker claim to a note by handle, without an amount or owner", async () => {
+    const zivoWrap = loadZivoWrap();
+    if (!zivoWrap) {
+      console.log("claim_note: zivo-wrap IDL not built; skipping");
+      return;
+    }
+    const price = new BN(100);
+    await topUpIncoAccount(buyer1Quote.publicKey, quoteMint.publicKey, topUpQuote);
+    await topUpIncoAccount(seller1Base.publicKey, baseMint.publicKey, topUpBase);
+    const ask = await placeLimitOrder("claim_note_ask", seller1, seller1Base, seller1Quote, 1, price);
+    const bid = await placeLimitOrder("claim_note_bid", buyer1, buyer1Base, buyer1Quote, 0, price);
+    await matchSellerAsk("claim_note_match", ask, bid, price);
+    await cancelLimitOrder("claim_note_cancel_ask", ask, seller1, seller1Base, seller1Quote);
+    await cancelLimitOrder("claim_note_cancel_bid", bid, buyer1, buyer1Base, buyer1Quote);
+
+    // The instruction names only the client-side commitment and the Light arguments.
+    const args = program.idl.instructions
+      .find((ix) => ix.name === "makerClaimToNote")!
+      .args.map((arg) => arg.name);
+    if (args.some((name) => /amount|owner|blinding/i.test(name))) {
+      throw new Error(`maker_claim_to_note must not take an amount or owner, got ${args}`);
+    }
+
+    const shielded = await createShieldedPool("claim_note", zivoWrap, quoteMint.publicKey);
+    const commitment = randomField();
+    const backing = noteBackingPda(shielded.pool, commitment, zivoWrap.programId);
+    const unused = Keypair.generate().publicKey;
+    const claimToNote = (label: string, maker: Keypair) =>
+      sendTx(
+        label,
+        program.methods
+          .makerClaimToNote(
+            Array.from(commitment),
+            { 0: null },
+            { addressMerkleTreePubkeyIndex: 0, addressQueuePubkeyIndex: 0, rootIndex: 0 },
+            0,
+            0,
+          )
+          .accounts({
+            state: statePda,
+            order: ask,
+            owner: seller1.publicKey,
+            maker: maker.publicKey,
+            incoVaultAuthority,
+            incoBaseVault: baseVault.publicKey,
+            incoQuoteVault: quoteVault.publicKey,
+            shieldedPool: shielded.pool,
+            wrapVault: shielded.vault,
+            splTokenMint: shielded.splMint,
+            incoTokenMint: quoteMint.publicKey,
+            poolIncoAccount: shielded.poolInco,
+            noteBacking: backing,
+            addressTree: unused,
+            addressQueue: unused,
+            stateQueue: unused,
+            stateTree: unused,
+            zivoWrapProgram: zivoWrap.programId,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+            incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+          }),
+        [maker],
+        true,
+      );
+
+    await expectError(
+      "claim_note_stranger",
+      claimToNote("claim_note_stranger", buyer1),
+      "InvalidOrderOwner",
+    );
+    // The maker's claim reaches zivo-wrap, which moves the claim handle from the market vault
+    // under the vault authority; the missing Light accounts then revert the whole transaction.
+    const vaultBefore = await incoBalanceHandle(quoteVault.publicKey);
+    await expectError(
+      "claim_note_light",
+      claimToNote("claim_note_light", seller1),
+      "InvalidTreeConfig",
+    );
+    const order = (await program.account.order.fetch(ask)) as any;
+    if (
+      !order.isFilled ||
+      order.isClaimed ||
+      (await incoBalanceHandle(quoteVault.publicKey)) !== vaultBefore ||
+      (await provider.connection.getAccountInfo(backing)) !== null
+    ) {
+      throw new Error("a refused claim-to-note must leave the claim, the vault and the backing alone");
+    }
+  });
+
+  it("unwraps a maker claim only for its attested amount", async () => {
+    const zivoWrap = loadZivoWrap();
+    if (!zivoWrap) {
+      console.log("claim_unwrap: zivo-wrap IDL not built; skipping");
+      return;
+    }
+    const price = new BN(100);
+    await topUpIncoAccount(buyer1Quote.publicKey, quoteMint.publicKey, topUpQuote);
+    await topUpIncoAccount(seller1Base.publicKey, baseMint.publicKey, topUpBase);
+    const ask = await placeLimitOrder("claim_unwrap_ask", seller1, seller1Base, seller1Quote, 1, price);
+    const bid = await placeLimitOrder("claim_unwrap_bid", buyer1, buyer1Base, buyer1Quote, 0, price);
+    await matchSellerAsk("claim_unwrap_match", ask, bid, price);
+    await cancelLimitOrder("claim_unwrap_cancel_ask", ask, seller1, seller1Base, seller1Quote);
+    await cancelLimitOrder("claim_unwrap_cancel_bid", bid, buyer1, buyer1Base, buyer1Quote);
+
+    // The maker attests the plaintext of the claim handle stored on the order.
+    const claimBytes = Buffer.from(((await program.account.order.fetch(ask)) as any).claimCiphertext);
+    const claimHandle = claimBytes.readBigUInt64LE(0) | (claimBytes.readBigUInt64LE(8) << 64n);
+    const attestation = await decrypt([claimHandle.toString()], {
+      address: seller1.publicKey,
+      signMessage: async (message: Uint8Array) =>
+        nacl.sign.detached(message, seller1.secretKey),
+    });
+    const claimed = BigInt(attestation.plaintexts[0]);
+    if (claimed !== tradeBaseAmount * 100n) {
+      throw new Error(`maker claim should be ${tradeBaseAmount * 100n} quote, got ${claimed}`);
+    }
+
+    // A zivo-wrap vault over the quote mint, holding the claim's SPL, and the maker's SPL account.
+    const wrap = await createShieldedPool("claim_unwrap", zivoWrap, quoteMint.publicKey);
+    const makerSpl = Keypair.generate();
+    const mintAmount = Buffer.alloc(9);
+    mintAmount.writeUInt8(7, 0);
+    mintAmount.writeBigUInt64LE(claimed, 1);
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: payer.publicKey,
+          newAccountPubkey: makerSpl.publicKey,
+          lamports: await provider.connection.getMinimumBalanceForRentExemption(165),
+          space: 165,
+          programId: SPL_TOKEN_PROGRAM_ID,
+        }),
+        new TransactionInstruction({
+          programId: SPL_TOKEN_PROGRAM_ID,
+          keys: [
+            { pubkey: makerSpl.publicKey, isSigner: false, isWritable: true },
+            { pubkey: wrap.splMint, isSigner: false, isWritable: false },
+          ],
+          data: Buffer.concat([Buffer.from([18]), seller1.publicKey.toBuffer()]),
+        }),
+        new TransactionInstruction({
+          programId: SPL_TOKEN_PROGRAM_ID,
+          keys: [
+            { pubkey: wrap.splMint, isSigner: false, isWritable: true },
+            { pubkey: wrap.vaultToken, isSigner: false, isWritable: true },
+            { pubkey: payer.publicKey, isSigner: true, isWritable: false },
+          ],
+          data: mintAmount,
+        }),
+      ),
+      [payer, makerSpl],
+    );
+
+    const claimAndUnwrap = (label: string, amount: bigint) =>
+      sendTx(
+        label,
+        program.methods
+          .claimAndUnwrap(new BN(amount.toString()))
+          .accounts({
+            state: statePda,
+            order: ask,
+            owner: seller1.publicKey,
+            maker: seller1.publicKey,
+            incoVaultAuthority,
+            incoBaseVault: baseVault.publicKey,
+            incoQuoteVault: quoteVault.publicKey,
+            makerBaseInco: seller1Base.publicKey,
+            makerQuoteInco: seller1Quote.publicKey,
+            wrapVault: wrap.vault,
+            splTokenMint: wrap.splMint,
+            incoTokenMint: quoteMint.publicKey,
+            makerSplTokenAccount: makerSpl.publicKey,
+            wrapVaultTokenAccount: wrap.vaultToken,
+            zivoWrapProgram: zivoWrap.programId,
+            tokenProgram: SPL_TOKEN_PROGRAM_ID,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
+            incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+            instructionsSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+          })
+          .preInstructions(attestation.ed25519Instructions),
+        [seller1],
+        true,
+      );
+    const makerSplBalance = async () =>
+      BigInt((await provider.connection.getTokenAccountBalance(makerSpl.publicKey)).value.amount);
+
+    // Asking for more SPL than the attested claim is refused before anything moves.
+    let forged = false;
+    try {
+      await claimAndUnwrap("claim_unwrap_forged", claimed + 1n);
+    } catch {
+      forged = true;
+    }
+    if (!forged) {
+      throw new Error("claim_and_unwrap should refuse an amount other than the attested claim");
+    }
+    if (((await program.account.order.fetch(ask)) as any).isClaimed || (await makerSplBalance()) !== 0n) {
+      throw new Error("a refused claim_and_unwrap must leave the claim and the SPL alone");
+    }
+
+    await claimAndUnwrap("claim_unwrap", claimed);
+    if (!((await program.account.order.fetch(ask)) as any).isClaimed) {
+      throw new Error("claim_and_unwrap should settle the maker claim");
+    }
+    if ((await makerSplBalance()) !== claimed) {
+      throw new Error(`maker should receive ${claimed} SPL, got ${await makerSplBalance()}`);
     }
   });
 });
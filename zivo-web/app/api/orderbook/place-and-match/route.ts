@@ -13,11 +13,14 @@ import { getHeliusRpcEndpoint } from "@/utils/helius";
 
 export const runtime = "nodejs";
 
+// Each step's owner allowances come from simulating it after the previous step has landed, so
+// clients submit the wrap, the place and the match (with its unwrap) in separate requests. Any
+// subset is sent in order: pre, place, match, post.
 type PlaceAndMatchPayload = {
   preTxs?: string[];
   postTxs?: string[];
-  placeTx: string;
-  matchTx: string;
+  placeTx?: string;
+  matchTx?: string;
 };
 
 const loadAdminKeypair = () => {
@@ -195,9 +198,14 @@ export async function POST(request: Request) {
   let matchTxDebug: Transaction | VersionedTransaction | null = null;
   try {
     const body = (await request.json()) as PlaceAndMatchPayload;
-    if (!body?.placeTx || !body?.matchTx) {
+    if (
+      !body?.placeTx &&
+      !body?.matchTx &&
+      !body?.preTxs?.length &&
+      !body?.postTxs?.length
+    ) {
       return NextResponse.json(
-        { error: "Missing placeTx or matchTx" },
+        { error: "Missing transactions" },
         { status: 400 },
       );
     }
@@ -216,38 +224,46 @@ export async function POST(request: Request) {
 
     const preTxs = (body.preTxs ?? []).map(decodeTransaction);
     const postTxs = (body.postTxs ?? []).map(decodeTransaction);
-    const placeTx = decodeTransaction(body.placeTx);
-    const matchTx = decodeTransaction(body.matchTx);
+    const placeTx = body.placeTx ? decodeTransaction(body.placeTx) : null;
+    const matchTx = body.matchTx ? decodeTransaction(body.matchTx) : null;
     placeTxDebug = placeTx;
     matchTxDebug = matchTx;
 
     for (const tx of preTxs) {
       maybeSignWithAdmin(tx, admin);
     }
-    maybeSignWithAdmin(placeTx, admin);
+    if (placeTx) {
+      maybeSignWithAdmin(placeTx, admin);
+    }
 
-    const matchRequired = getRequiredSigners(matchTx);
-    const adminKey = admin.publicKey.toBase58();
-    if (!matchRequired.includes(adminKey)) {
-      return NextResponse.json(
-        {
-          error:
-            "ADMIN_MATCHER_KEYPAIR does not match the matcher key required by the market.",
-          required: matchRequired,
-          admin: adminKey,
-        },
-        { status: 400 },
-      );
+    if (matchTx) {
+      const matchRequired = getRequiredSigners(matchTx);
+      const adminKey = admin.publicKey.toBase58();
+      if (!matchRequired.includes(adminKey)) {
+        return NextResponse.json(
+          {
+            error:
+              "ADMIN_MATCHER_KEYPAIR does not match the matcher key required by the market.",
+            required: matchRequired,
+            admin: adminKey,
+          },
+          { status: 400 },
+        );
+      }
+      maybeSignWithAdmin(matchTx, admin);
     }
-    maybeSignWithAdmin(matchTx, admin);
 
     const preSignatures: string[] = [];
     for (const tx of preTxs) {
       preSignatures.push(await sendAndConfirm(connection, tx));
     }
 
-    const placeSignature = await sendAndConfirm(connection, placeTx);
-    const matchSignature = await sendAndConfirm(connection, matchTx);
+    const placeSignature = placeTx
+      ? await sendAndConfirm(connection, placeTx)
+      : undefined;
+    const matchSignature = matchTx
+      ? await sendAndConfirm(connection, matchTx)
+      : undefined;
 
     const postSignatures: string[] = [];
     for (const tx of postTxs) {
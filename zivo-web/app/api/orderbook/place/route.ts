@@ -11,9 +11,11 @@ import { getHeliusRpcEndpoint } from "@/utils/helius";
 
 export const runtime = "nodejs";
 
+// The place transaction can only be built once its wrap has landed (its owner allowances come
+// from a simulation), so clients submit `preTxs` and `placeTx` in separate requests.
 type PlacePayload = {
   preTxs?: string[];
-  placeTx: string;
+  placeTx?: string;
 };
 
 
@@ -102,8 +104,11 @@ export async function POST(request: Request) {
   let placeTxDebug: Transaction | VersionedTransaction | null = null;
   try {
     const body = (await request.json()) as PlacePayload;
-    if (!body?.placeTx) {
-      return NextResponse.json({ error: "Missing placeTx" }, { status: 400 });
+    if (!body?.placeTx && !body?.preTxs?.length) {
+      return NextResponse.json(
+        { error: "Missing placeTx or preTxs" },
+        { status: 400 },
+      );
     }
 
     const rpcUrl = (() => {
@@ -118,7 +123,7 @@ export async function POST(request: Request) {
     const connection = new Connection(rpcUrl, "confirmed");
 
     const preTxs = (body.preTxs ?? []).map(decodeTransaction);
-    const placeTx = decodeTransaction(body.placeTx);
+    const placeTx = body.placeTx ? decodeTransaction(body.placeTx) : null;
     placeTxDebug = placeTx;
 
     const preSignatures: string[] = [];
@@ -126,7 +131,9 @@ export async function POST(request: Request) {
       preSignatures.push(await sendAndConfirm(connection, tx));
     }
 
-    const placeSignature = await sendAndConfirm(connection, placeTx);
+    const placeSignature = placeTx
+      ? await sendAndConfirm(connection, placeTx)
+      : undefined;
 
     return NextResponse.json({ preSignatures, placeSignature });
   } catch (err) {
@@ -18,6 +18,7 @@ import { useAnchorWallet, useConnection, useWallet } from "@solana/wallet-adapte
 import { useQueryClient } from "@tanstack/react-query";
 import { PublicKey, SystemProgram, Transaction } from "@solana/web3.js";
 import { createCloseAccountInstruction } from "@solana/spl-token";
+import { decrypt } from "@inco/solana-sdk/attested-decrypt";
 import { buildUnwrapTransaction } from "@/utils/orderbook/build-wrap-transaction";
 import { getSplDecimalsForIncoMint, SPL_WRAPPED_SOL_MINT } from "@/utils/mints";
 import {
@@ -29,7 +30,7 @@ import { findExistingIncoAccount } from "@/utils/orderbook/hooks/inco-accounts";
 const OrdersPanel = () => {
   const program = useOrderbookProgram();
   const { connection } = useConnection();
-  const { publicKey, sendTransaction } = useWallet();
+  const { publicKey, sendTransaction, signMessage } = useWallet();
   const queryClient = useQueryClient();
   const anchorWallet = useAnchorWallet();
   const [claimNotice, setClaimNotice] = useState<string | null>(null);
@@ -143,7 +144,7 @@ const OrdersPanel = () => {
       isOpen: order.isOpen,
       isFilled: order.isFilled,
       isClaimed: order.isClaimed,
-      claimPlaintextAmount: order.claimPlaintextAmount,
+      claimHandle: order.claimHandle,
     }));
   }, [orders, orderbookState?.incoQuoteMint]);
 
@@ -206,19 +207,28 @@ const OrdersPanel = () => {
       return;
     }
 
-    if (!order.claimPlaintextAmount) {
+    if (!order.claimHandle) {
       setClaimNotice("Claim amount not available for this order.");
       return;
     }
-    const amountLamports = BigInt(order.claimPlaintextAmount);
-    if (amountLamports <= 0n) {
-      setClaimNotice("Claim amount is zero for this order.");
+    if (!signMessage) {
+      setClaimNotice("Wallet does not support message signing.");
       return;
     }
 
     setClaimPending(true);
     setClaimNotice(null);
     try {
+      // The claim is an encrypted handle only the maker can read; decrypt it to size the unwrap.
+      const decrypted = await decrypt([order.claimHandle], {
+        address: publicKey,
+        signMessage,
+      });
+      const amountLamports = BigInt(decrypted.plaintexts?.[0] ?? "0");
+      if (amountLamports <= 0n) {
+        throw new Error("Claim amount is zero for this order.");
+      }
+
       const orderAddress = new PublicKey(order.address);
       const orderOwner = new PublicKey(order.owner);
       const statePda = derivedStatePda;
@@ -471,8 +481,7 @@ const OrdersPanel = () => {
                   {row.isFilled === true &&
                   row.isOpen === false &&
                   row.isClaimed !== true &&
-                  row.claimPlaintextAmount != null &&
-                  BigInt(row.claimPlaintextAmount) > 0n &&
+                  row.claimHandle != null &&
                   publicKey &&
                   row.owner === publicKey.toBase58() ? (
                     <button
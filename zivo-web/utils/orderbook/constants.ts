@@ -14,6 +14,11 @@ export const QUOTE_MINT_PUBLIC_KEY = INCO_USDC_MINT; // Devnet Inco USDC (quote)
 export const ORDERBOOK_STATE_SEED = "orderbook_market_v1";
 export const INCO_VAULT_AUTHORITY_SEED = "inco_vault_authority_v12";
 export const DEPOSIT_SEED = "deposit_v9";
+export const PROTOCOL_CONFIG_SEED = "protocol_config_v1";
+export const MARKET_REGISTRY_SEED = "market_registry_v1";
 export const MATCH_RECORD_SEED = "match_record";
+// Ciphertext input type for an existing handle (16 little-endian bytes) rather than a client
+// encryption; order claims are stored this way.
+export const HANDLE_INPUT_TYPE = 2;
 
 export { INCO_LIGHTNING_PROGRAM_ID, INCO_TOKEN_PROGRAM_ID };
@@ -34,7 +34,7 @@ export type OrderView = {
   isOpen: boolean;
   isFilled?: boolean;
   isClaimed?: boolean;
-  claimPlaintextAmount?: string;
+  claimHandle?: string;
 };
 
 export type UseOrderbookStateParams = {
@@ -5,13 +5,13 @@ import { encryptValue } from "@inco/solana-sdk/encryption";
 
 import {
   deriveOrderbookStatePda,
+  fetchOrder,
   fetchOrderbookState,
   getDefaultBaseMint,
   getDefaultQuoteMint,
   type MatchOrderParams,
 } from "../methods";
 import {
-  ensureIncoAccount,
   fetchIncoMintDecimals,
   findExistingIncoAccount,
 } from "./inco-accounts";
@@ -19,18 +19,19 @@ import { useMatchOrder } from "./use-match-order";
 import { useOrderbookProgram } from "./use-orderbook-program";
 import { getSplDecimalsForIncoMint } from "@/utils/mints";
 
+// Matches a resting maker order against a resting taker order on the other side. Both escrows
+// are already in the vaults, so only the taker's requested size is encrypted here.
 export type MatchOrderWithIncoAccountsParams = {
   orderAddress: string;
   owner: string;
   side: "Bid" | "Ask";
+  takerOrderAddress: string;
   price: string;
   amount: string;
   baseMint?: PublicKey;
   quoteMint?: PublicKey;
 };
 
-const pow10 = (decimals: number): bigint => BigInt(10) ** BigInt(decimals);
-
 export const useMatchOrderWithIncoAccounts = () => {
   const { connection } = useConnection();
   const wallet = useAnchorWallet();
@@ -86,11 +87,8 @@ export const useMatchOrderWithIncoAccounts = () => {
       const priceInQuoteUnits = BigInt(
         Math.floor(priceValue * Math.pow(10, resolvedQuoteDecimals)),
       );
-      const quoteAmount =
-        (baseAmount * priceInQuoteUnits) / pow10(baseDecimals);
 
       const baseCiphertextHex = await encryptValue(baseAmount);
-      const quoteCiphertextHex = await encryptValue(quoteAmount);
 
       const makerOwner = new PublicKey(params.owner);
       const makerBaseInco = await findExistingIncoAccount(
@@ -110,28 +108,34 @@ export const useMatchOrderWithIncoAccounts = () => {
         throw new Error("Maker quote Inco account not found");
       }
 
-      const takerBaseInco = await ensureIncoAccount({
+      const takerSide = params.side === "Ask" ? 0 : 1;
+      const takerOrder = new PublicKey(params.takerOrderAddress);
+      const takerOrderAccount = await fetchOrder(program, takerOrder);
+      if (!takerOrderAccount.isOpen || takerOrderAccount.side !== takerSide) {
+        throw new Error("Taker order must be open on the other side of the book");
+      }
+      const taker = takerOrderAccount.owner;
+      const takerBaseInco = await findExistingIncoAccount(
         connection,
-        wallet,
-        owner: publicKey,
-        mint: stateAccount.incoBaseMint,
-      });
-      const takerQuoteInco = await ensureIncoAccount({
+        taker,
+        stateAccount.incoBaseMint,
+      );
+      const takerQuoteInco = await findExistingIncoAccount(
         connection,
-        wallet,
-        owner: publicKey,
-        mint: stateAccount.incoQuoteMint,
-      });
+        taker,
+        stateAccount.incoQuoteMint,
+      );
+      if (!takerBaseInco || !takerQuoteInco) {
+        throw new Error("Taker Inco accounts not found");
+      }
 
-      const takerSide = params.side === "Ask" ? 0 : 1;
-      const claimPlaintextAmount =
-        params.side === "Ask" ? quoteAmount : baseAmount;
       return matchOrder.mutateAsync({
         state,
         makerOrder: new PublicKey(params.orderAddress),
+        takerOrder,
         owner: makerOwner,
         matcher: publicKey,
-        taker: publicKey,
+        taker,
         incoVaultAuthority: stateAccount.incoVaultAuthority,
         incoBaseVault: stateAccount.incoBaseVault,
         incoQuoteVault: stateAccount.incoQuoteVault,
@@ -144,10 +148,7 @@ export const useMatchOrderWithIncoAccounts = () => {
         takerSide,
         takerPrice: priceInQuoteUnits,
         takerReqBaseCiphertext: Buffer.from(baseCiphertextHex, "hex"),
-        fillBaseCiphertext: Buffer.from(baseCiphertextHex, "hex"),
-        fillQuoteCiphertext: Buffer.from(quoteCiphertextHex, "hex"),
         inputType: 0,
-        claimPlaintextAmount,
       } satisfies Omit<MatchOrderParams, "program">);
     },
   });
@@ -7,6 +7,7 @@ import {
   deriveOrderPda,
   getDefaultBaseMint,
   getDefaultQuoteMint,
+  orderClaimHandle,
   type OrderAccount,
 } from "../methods";
 import { useOrderbookProgram } from "./use-orderbook-program";
@@ -29,17 +30,13 @@ const toOrderView = (order: OrderAccount, address: PublicKey): OrderView => {
     is_filled?: boolean | number;
     isClaimed?: boolean | number;
     is_claimed?: boolean | number;
-    claimPlaintextAmount?: { toString?: () => string };
-    claim_plaintext_amount?: { toString?: () => string };
   };
   const isOpenValue = readBool(anyOrder.isOpen);
   const filledValue =
     readBool(anyOrder.isFilled) ?? readBool(anyOrder.is_filled);
   const claimedValue =
     readBool(anyOrder.isClaimed) ?? readBool(anyOrder.is_claimed);
-  const claimPlaintextAmount =
-    anyOrder.claimPlaintextAmount?.toString?.() ??
-    anyOrder.claim_plaintext_amount?.toString?.();
+  const claimHandle = orderClaimHandle(order);
 
   return {
     address: address.toBase58(),
@@ -51,7 +48,7 @@ const toOrderView = (order: OrderAccount, address: PublicKey): OrderView => {
     isOpen: isOpenValue ?? false,
     isFilled: filledValue,
     isClaimed: claimedValue,
-    claimPlaintextAmount,
+    claimHandle: claimHandle == null ? undefined : claimHandle.toString(),
   };
 };
 
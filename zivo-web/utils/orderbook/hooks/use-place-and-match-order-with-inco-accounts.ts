@@ -7,16 +7,16 @@ import {
 import {
   ComputeBudgetProgram,
   PublicKey,
-  SystemProgram,
   Transaction,
   TransactionMessage,
   VersionedTransaction,
 } from "@solana/web3.js";
 import { createCloseAccountInstruction } from "@solana/spl-token";
 import { encryptValue } from "@inco/solana-sdk/encryption";
-import BN from "bn.js";
 
 import {
+  buildMatchOrderInstruction,
+  buildPlaceOrderInstruction,
   deriveOrderPda,
   deriveOrderbookStatePda,
   fetchOrderbookState,
@@ -27,7 +27,6 @@ import {
   buildUnwrapTransaction,
   buildWrapTransaction,
 } from "../build-wrap-transaction";
-import { INCO_LIGHTNING_PROGRAM_ID, INCO_TOKEN_PROGRAM_ID } from "../constants";
 import {
   fetchIncoMintDecimals,
   findExistingIncoAccount,
@@ -48,7 +47,25 @@ type PlaceAndMatchOrderResult = {
 };
 
 const PLACE_AND_MATCH_ENDPOINT = "/api/orderbook/place-and-match";
-const pow10 = (decimals: number): bigint => BigInt(10) ** BigInt(decimals);
+const serializeSigned = (tx: Transaction | VersionedTransaction): string =>
+  Buffer.from(
+    tx instanceof Transaction
+      ? tx.serialize({ requireAllSignatures: false, verifySignatures: false })
+      : tx.serialize(),
+  ).toString("base64");
+
+const submit = async <T>(body: Record<string, unknown>): Promise<T> => {
+  const response = await fetch(PLACE_AND_MATCH_ENDPOINT, {
+    method: "POST",
+    headers: { "content-type": "application/json" },
+    body: JSON.stringify(body),
+  });
+  if (!response.ok) {
+    const errorBody = await response.json().catch(() => null);
+    throw new Error(errorBody?.error ?? "Backend failed to submit match txs");
+  }
+  return (await response.json()) as T;
+};
 
 export const usePlaceAndMatchOrderWithIncoAccounts = () => {
   const { connection } = useConnection();
@@ -120,19 +137,10 @@ export const usePlaceAndMatchOrderWithIncoAccounts = () => {
         const baseAmount = BigInt(
           Math.floor(amountValue * Math.pow(10, resolvedBaseDecimals)),
         );
-        const quoteAmount =
-          (baseAmount * priceInQuoteUnits) / pow10(baseDecimals);
 
         const sizeCiphertextHex = await encryptValue(baseAmount);
-        const baseCiphertextHex = sizeCiphertextHex;
-        const quoteCiphertextHex = await encryptValue(quoteAmount);
 
         const takerSide = params.makerSide === "Ask" ? 0 : 1;
-        const placeSide = takerSide;
-        const escrowAmount = placeSide === 0 ? quoteAmount : baseAmount;
-        const escrowCiphertextHex = await encryptValue(escrowAmount);
-
-        const [order] = deriveOrderPda(state, publicKey, stateAccount.orderSeq);
 
         const takerBaseInco = await findExistingIncoAccount(
           connection,
@@ -207,6 +215,13 @@ export const usePlaceAndMatchOrderWithIncoAccounts = () => {
           feePayer: stateAccount.admin,
         });
 
+        wrapTx.recentBlockhash = (await connection.getLatestBlockhash()).blockhash;
+        wrapTx.feePayer = publicKey;
+        const [signedWrap] = await signAllTransactions([wrapTx]);
+        const { preSignatures } = await submit<{ preSignatures?: string[] }>({
+          preTxs: [serializeSigned(signedWrap)],
+        });
+
         const unwrapMint =
           takerSide === 0
             ? stateAccount.incoBaseMint
@@ -250,34 +265,45 @@ export const usePlaceAndMatchOrderWithIncoAccounts = () => {
           );
         }
 
-        const placeTx = await program.methods
-          .placeOrder(
-            placeSide,
-            new BN(priceInQuoteUnits.toString()),
-            Buffer.from(sizeCiphertextHex, "hex"),
-            0,
-            Buffer.from(escrowCiphertextHex, "hex"),
-            0,
-          )
-          .preInstructions([computeIx])
-          .accounts({
-            state,
-            order,
-            trader: publicKey,
-            incoVaultAuthority: stateAccount.incoVaultAuthority,
-            incoBaseVault: stateAccount.incoBaseVault,
-            incoQuoteVault: stateAccount.incoQuoteVault,
-            traderBaseInco: takerBaseInco,
-            traderQuoteInco: takerQuoteInco,
-            incoBaseMint: stateAccount.incoBaseMint,
-            incoQuoteMint: stateAccount.incoQuoteMint,
-            systemProgram: SystemProgram.programId,
-            incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
-            incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
-          })
-          .transaction();
+        // Each step's owner allowances are simulated against the state the previous step left,
+        // so the place is built once the wrap has landed and the match once the place has.
+        const placeState = await fetchOrderbookState(program, state);
+        const [order] = deriveOrderPda(state, publicKey, placeState.orderSeq);
+        const placeIx = await buildPlaceOrderInstruction({
+          program,
+          state,
+          order,
+          trader: publicKey,
+          incoVaultAuthority: stateAccount.incoVaultAuthority,
+          incoBaseVault: stateAccount.incoBaseVault,
+          incoQuoteVault: stateAccount.incoQuoteVault,
+          traderBaseInco: takerBaseInco,
+          traderQuoteInco: takerQuoteInco,
+          incoBaseMint: stateAccount.incoBaseMint,
+          incoQuoteMint: stateAccount.incoQuoteMint,
+          side: takerSide,
+          price: priceInQuoteUnits,
+          sizeCiphertext: Buffer.from(sizeCiphertextHex, "hex"),
+          inputType: 0,
+        });
+        const placeTx = new Transaction().add(computeIx, placeIx);
+        const { blockhash } = await connection.getLatestBlockhash();
+        placeTx.recentBlockhash = blockhash;
+        placeTx.feePayer = publicKey;
+        unwrapBuild.tx.recentBlockhash = blockhash;
+        unwrapBuild.tx.feePayer = publicKey;
+
+        const [signedPlace, signedUnwrap] = await signAllTransactions([
+          placeTx,
+          unwrapBuild.tx,
+        ]);
+        const { placeSignature } = await submit<{ placeSignature: string }>({
+          placeTx: serializeSigned(signedPlace),
+        });
 
-        const matchAccounts = {
+        // The taker is no longer a match signer: the matcher signs and pays on the backend.
+        const matchIx = await buildMatchOrderInstruction({
+          program,
           state,
           makerOrder: new PublicKey(params.makerOrderAddress),
           takerOrder: order,
@@ -293,41 +319,19 @@ export const usePlaceAndMatchOrderWithIncoAccounts = () => {
           takerQuoteInco,
           incoBaseMint: stateAccount.incoBaseMint,
           incoQuoteMint: stateAccount.incoQuoteMint,
-          systemProgram: SystemProgram.programId,
-          incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
-          incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
-        };
-
-        const { blockhash } = await connection.getLatestBlockhash();
-        wrapTx.recentBlockhash = blockhash;
-        wrapTx.feePayer = publicKey;
-        placeTx.recentBlockhash = blockhash;
-        placeTx.feePayer = publicKey;
-
-        const claimPlaintextAmount =
-          params.makerSide === "Ask" ? quoteAmount : baseAmount;
-        const matchIx = await program.methods
-          .matchOrder(
-            takerSide,
-            new BN(priceInQuoteUnits.toString()),
-            Buffer.from(baseCiphertextHex, "hex"),
-            Buffer.from(baseCiphertextHex, "hex"),
-            Buffer.from(quoteCiphertextHex, "hex"),
-            0,
-            new BN(claimPlaintextAmount.toString()),
-          )
-          .accounts(matchAccounts)
-          .instruction();
+          takerSide,
+          takerPrice: priceInQuoteUnits,
+          takerReqBaseCiphertext: Buffer.from(sizeCiphertextHex, "hex"),
+          inputType: 0,
+        });
 
         const matchTx = new Transaction();
         matchTx.add(computeIx, matchIx);
-        matchTx.recentBlockhash = blockhash;
-        matchTx.feePayer = publicKey;
-        unwrapBuild.tx.recentBlockhash = blockhash;
-        unwrapBuild.tx.feePayer = publicKey;
+        matchTx.recentBlockhash = (await connection.getLatestBlockhash()).blockhash;
+        matchTx.feePayer = stateAccount.admin;
 
         const lut = await fetchOrderbookLut(connection);
-        const matchToSign =
+        const matchToSend =
           lut && matchTx.feePayer && matchTx.recentBlockhash
             ? new VersionedTransaction(
                 new TransactionMessage({
@@ -338,67 +342,18 @@ export const usePlaceAndMatchOrderWithIncoAccounts = () => {
               )
             : matchTx;
 
-        const [signedWrap, signedPlace, signedMatch, signedUnwrap] =
-          await signAllTransactions([
-            wrapTx,
-            placeTx,
-            matchToSign,
-            unwrapBuild.tx,
-          ]);
-
-        const wrapTxBase64 = Buffer.from(
-          signedWrap.serialize({
-            requireAllSignatures: false,
-            verifySignatures: false,
-          }),
-        ).toString("base64");
-        const placeTxBase64 = Buffer.from(
-          signedPlace.serialize({
-            requireAllSignatures: false,
-            verifySignatures: false,
-          }),
-        ).toString("base64");
-
-        const matchTxBase64 = Buffer.from(
-          signedMatch.serialize({
-            requireAllSignatures: false,
-            verifySignatures: false,
-          }),
-        ).toString("base64");
-        const unwrapTxBase64 = Buffer.from(
-          signedUnwrap.serialize({
-            requireAllSignatures: false,
-            verifySignatures: false,
-          }),
-        ).toString("base64");
-
-        const response = await fetch(PLACE_AND_MATCH_ENDPOINT, {
-          method: "POST",
-          headers: { "content-type": "application/json" },
-          body: JSON.stringify({
-            preTxs: [wrapTxBase64],
-            placeTx: placeTxBase64,
-            matchTx: matchTxBase64,
-            postTxs: [unwrapTxBase64],
-          }),
-        });
-
-        if (!response.ok) {
-          const errorBody = await response.json().catch(() => null);
-          throw new Error(errorBody?.error ?? "Backend failed to submit match txs");
-        }
-
-        const payload = (await response.json()) as {
-          preSignatures?: string[];
+        const payload = await submit<{
           postSignatures?: string[];
-          placeSignature: string;
           matchSignature: string;
-        };
+        }>({
+          matchTx: serializeSigned(matchToSend),
+          postTxs: [serializeSigned(signedUnwrap)],
+        });
 
         return {
-          placeSignature: payload.placeSignature,
+          placeSignature,
           matchSignature: payload.matchSignature,
-          preSignatures: payload.preSignatures,
+          preSignatures,
           postSignatures: payload.postSignatures,
           order,
           traderBaseInco: takerBaseInco,
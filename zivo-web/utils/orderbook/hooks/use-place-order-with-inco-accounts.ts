@@ -4,11 +4,15 @@ import {
   useConnection,
   useWallet,
 } from "@solana/wallet-adapter-react";
-import { BN } from "@coral-xyz/anchor";
-import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  ComputeBudgetProgram,
+  PublicKey,
+  Transaction,
+} from "@solana/web3.js";
 import { encryptValue } from "@inco/solana-sdk/encryption";
 
 import {
+  buildPlaceOrderInstruction,
   deriveOrderbookStatePda,
   deriveOrderPda,
   fetchOrderbookState,
@@ -16,7 +20,6 @@ import {
   getDefaultQuoteMint,
 } from "../methods";
 import { buildWrapTransaction } from "../build-wrap-transaction";
-import { INCO_LIGHTNING_PROGRAM_ID, INCO_TOKEN_PROGRAM_ID } from "../constants";
 import {
   fetchIncoMintDecimals,
   findExistingIncoAccount,
@@ -27,6 +30,24 @@ import { getSplDecimalsForIncoMint } from "@/utils/mints";
 
 const API_ENDPOINT = "/api/orderbook/place";
 
+const serializeSigned = (tx: Transaction): string =>
+  Buffer.from(
+    tx.serialize({ requireAllSignatures: false, verifySignatures: false }),
+  ).toString("base64");
+
+const submit = async <T>(body: Record<string, unknown>): Promise<T> => {
+  const response = await fetch(API_ENDPOINT, {
+    method: "POST",
+    headers: { "content-type": "application/json" },
+    body: JSON.stringify(body),
+  });
+  if (!response.ok) {
+    const errorBody = await response.json().catch(() => null);
+    throw new Error(errorBody?.error ?? "Backend failed to submit txs");
+  }
+  return (await response.json()) as T;
+};
+
 export const usePlaceOrderWithIncoAccounts = () => {
   const { connection } = useConnection();
   const wallet = useAnchorWallet();
@@ -86,18 +107,11 @@ export const usePlaceOrderWithIncoAccounts = () => {
       const baseAmount = BigInt(
         Math.floor(amountValue * Math.pow(10, resolvedBaseDecimals)),
       );
-      const quoteAmount = BigInt(
-        Math.floor(amountValue * priceValue * Math.pow(10, resolvedQuoteDecimals)),
-      );
       const priceInQuoteUnits = Math.floor(
         priceValue * Math.pow(10, resolvedQuoteDecimals),
       );
 
       const sizeCiphertextHex = await encryptValue(baseAmount);
-      const escrowAmount = params.side === "sell" ? baseAmount : quoteAmount;
-      const escrowCiphertextHex = await encryptValue(escrowAmount);
-
-      const [order] = deriveOrderPda(state, publicKey, stateAccount.orderSeq);
 
       const baseIncoAccount = await findExistingIncoAccount(
         connection,
@@ -141,78 +155,49 @@ export const usePlaceOrderWithIncoAccounts = () => {
         feePayer: stateAccount.admin,
       });
 
-      const placeTx = await program.methods
-        .placeOrder(
-          params.side === "buy" ? 0 : 1,
-          new BN(priceInQuoteUnits.toString()),
-          Buffer.from(sizeCiphertextHex, "hex"),
-          0,
-          Buffer.from(escrowCiphertextHex, "hex"),
-          0,
-        )
-        .accounts({
-          state,
-          order,
-          trader: publicKey,
-          incoVaultAuthority: stateAccount.incoVaultAuthority,
-          incoBaseVault: stateAccount.incoBaseVault,
-          incoQuoteVault: stateAccount.incoQuoteVault,
-          traderBaseInco: baseIncoAccount,
-          traderQuoteInco: quoteIncoAccount,
-          incoBaseMint: stateAccount.incoBaseMint,
-          incoQuoteMint: stateAccount.incoQuoteMint,
-          systemProgram: SystemProgram.programId,
-          incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
-          incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
-        })
-        .transaction();
-
       const { blockhash } = await connection.getLatestBlockhash();
       wrapTx.recentBlockhash = blockhash;
       wrapTx.feePayer = publicKey;
-      placeTx.recentBlockhash = blockhash;
-      placeTx.feePayer = publicKey;
-
-      const [signedWrap, signedPlace] = await signAllTransactions([
-        wrapTx,
-        placeTx,
-      ]);
-
-      const wrapTxBase64 = Buffer.from(
-        signedWrap.serialize({
-          requireAllSignatures: false,
-          verifySignatures: false,
-        }),
-      ).toString("base64");
-      const placeTxBase64 = Buffer.from(
-        signedPlace.serialize({
-          requireAllSignatures: false,
-          verifySignatures: false,
-        }),
-      ).toString("base64");
-
-      const response = await fetch(API_ENDPOINT, {
-        method: "POST",
-        headers: { "content-type": "application/json" },
-        body: JSON.stringify({
-          preTxs: [wrapTxBase64],
-          placeTx: placeTxBase64,
-        }),
+      const [signedWrap] = await signAllTransactions([wrapTx]);
+      const { preSignatures } = await submit<{ preSignatures?: string[] }>({
+        preTxs: [serializeSigned(signedWrap)],
       });
 
-      if (!response.ok) {
-        const errorBody = await response.json().catch(() => null);
-        throw new Error(errorBody?.error ?? "Backend failed to submit txs");
-      }
-
-      const payload = (await response.json()) as {
-        preSignatures?: string[];
-        placeSignature: string;
-      };
+      // The escrow is read from the wrapped balance, so the place (and the owner allowance for
+      // its remaining size) is only built once the wrap has landed.
+      const placeState = await fetchOrderbookState(program, state);
+      const [order] = deriveOrderPda(state, publicKey, placeState.orderSeq);
+      const placeIx = await buildPlaceOrderInstruction({
+        program,
+        state,
+        order,
+        trader: publicKey,
+        incoVaultAuthority: stateAccount.incoVaultAuthority,
+        incoBaseVault: stateAccount.incoBaseVault,
+        incoQuoteVault: stateAccount.incoQuoteVault,
+        traderBaseInco: baseIncoAccount,
+        traderQuoteInco: quoteIncoAccount,
+        incoBaseMint: stateAccount.incoBaseMint,
+        incoQuoteMint: stateAccount.incoQuoteMint,
+        side: params.side === "buy" ? 0 : 1,
+        price: priceInQuoteUnits,
+        sizeCiphertext: Buffer.from(sizeCiphertextHex, "hex"),
+        inputType: 0,
+      });
+      const placeTx = new Transaction().add(
+        ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+        placeIx,
+      );
+      placeTx.recentBlockhash = (await connection.getLatestBlockhash()).blockhash;
+      placeTx.feePayer = publicKey;
+      const [signedPlace] = await signAllTransactions([placeTx]);
+      const payload = await submit<{ placeSignature: string }>({
+        placeTx: serializeSigned(signedPlace),
+      });
 
       return {
         signature: payload.placeSignature,
-        preSignatures: payload.preSignatures,
+        preSignatures,
         order,
         traderBaseInco: baseIncoAccount,
         traderQuoteInco: quoteIncoAccount,
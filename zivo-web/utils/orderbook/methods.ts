@@ -1,19 +1,26 @@
 import { BN } from "@coral-xyz/anchor";
 import {
+  ComputeBudgetProgram,
   PublicKey,
   SystemProgram,
+  Transaction,
+  TransactionInstruction,
 } from "@solana/web3.js";
 
 import {
   BASE_MINT_PUBLIC_KEY,
   DEPOSIT_SEED,
+  HANDLE_INPUT_TYPE,
   INCO_LIGHTNING_PROGRAM_ID,
   INCO_TOKEN_PROGRAM_ID,
   INCO_VAULT_AUTHORITY_SEED,
+  MARKET_REGISTRY_SEED,
   ORDERBOOK_PROGRAM_ID,
   ORDERBOOK_STATE_SEED,
+  PROTOCOL_CONFIG_SEED,
   QUOTE_MINT_PUBLIC_KEY,
 } from "./constants";
+import { simulateOwnerAllowances } from "./owner-allowances";
 import type { OrderbookProgram } from "./program";
 
 const ORDER_SEED = "order_v1";
@@ -53,11 +60,22 @@ export type OrderAccount = {
   isOpen: boolean;
   isFilled: boolean;
   isClaimed: boolean;
-  claimPlaintextAmount: BN;
+  claimInputType: number;
+  claimCiphertext: Buffer;
   price: BN;
   seq: BN;
   remainingHandle: BN;
   bump: number;
+  rentPayer: PublicKey;
+};
+
+// The handle an order's encrypted claim pays out, or null when it has none.
+export const orderClaimHandle = (order: OrderAccount): bigint | null => {
+  const bytes = Buffer.from(order.claimCiphertext ?? []);
+  if (order.claimInputType !== HANDLE_INPUT_TYPE || bytes.length !== 16) {
+    return null;
+  }
+  return bytes.readBigUInt64LE(0) | (bytes.readBigUInt64LE(8) << 64n);
 };
 
 export type DepositAccount = {
@@ -86,6 +104,18 @@ export const deriveOrderbookStatePda = (
     ORDERBOOK_PROGRAM_ID,
   );
 
+export const deriveProtocolConfigPda = (): [PublicKey, number] =>
+  PublicKey.findProgramAddressSync(
+    [Buffer.from(PROTOCOL_CONFIG_SEED)],
+    ORDERBOOK_PROGRAM_ID,
+  );
+
+export const deriveMarketRegistryPda = (): [PublicKey, number] =>
+  PublicKey.findProgramAddressSync(
+    [Buffer.from(MARKET_REGISTRY_SEED)],
+    ORDERBOOK_PROGRAM_ID,
+  );
+
 export const deriveIncoVaultAuthorityPda = (
   state: PublicKey,
 ): [PublicKey, number] =>
@@ -144,6 +174,7 @@ export const fetchDepositAccount = async (
 export type InitializeOrderbookParams = {
   program: OrderbookProgram;
   state: PublicKey;
+  treasury: PublicKey;
   incoVaultAuthority: PublicKey;
   incoBaseVault: PublicKey;
   incoQuoteVault: PublicKey;
@@ -157,6 +188,7 @@ export type InitializeOrderbookParams = {
 export const initializeOrderbook = async ({
   program,
   state,
+  treasury,
   incoVaultAuthority,
   incoBaseVault,
   incoQuoteVault,
@@ -170,6 +202,9 @@ export const initializeOrderbook = async ({
     .initialize(requireAttestation)
     .accounts({
       state,
+      protocolConfig: deriveProtocolConfigPda()[0],
+      marketRegistry: deriveMarketRegistryPda()[0],
+      treasury,
       incoVaultAuthority,
       incoBaseVault,
       incoQuoteVault,
@@ -190,6 +225,7 @@ export type InitializeDepositParams = {
   deposit: PublicKey;
   userBaseInco: PublicKey;
   userQuoteInco: PublicKey;
+  accessPass?: PublicKey | null;
 };
 
 export const initializeDeposit = async ({
@@ -200,6 +236,7 @@ export const initializeDeposit = async ({
   deposit,
   userBaseInco,
   userQuoteInco,
+  accessPass = null,
 }: InitializeDepositParams): Promise<string> =>
   program.methods
     .initializeDeposit()
@@ -210,15 +247,33 @@ export const initializeDeposit = async ({
       deposit,
       userBaseInco,
       userQuoteInco,
+      accessPass,
       systemProgram: SystemProgram.programId,
     })
     .rpc();
 
+const sendWithComputeBudget = async (
+  program: OrderbookProgram,
+  instruction: TransactionInstruction,
+): Promise<string> => {
+  if (!program.provider.sendAndConfirm) {
+    throw new Error("Orderbook provider cannot send transactions");
+  }
+  return program.provider.sendAndConfirm(
+    new Transaction().add(
+      ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+      instruction,
+    ),
+  );
+};
+
 export type PlaceOrderParams = {
   program: OrderbookProgram;
   state: PublicKey;
   order: PublicKey;
   trader: PublicKey;
+  accessPass?: PublicKey | null;
+  sizeBuffer?: PublicKey | null;
   incoVaultAuthority: PublicKey;
   incoBaseVault: PublicKey;
   incoQuoteVault: PublicKey;
@@ -230,15 +285,15 @@ export type PlaceOrderParams = {
   price: U64Like;
   sizeCiphertext: Buffer | Uint8Array | number[];
   inputType: number;
-  escrowCiphertext: Buffer | Uint8Array | number[];
-  escrowInputType: number;
 };
 
-export const placeOrder = async ({
+export const buildPlaceOrderInstruction = async ({
   program,
   state,
   order,
   trader,
+  accessPass = null,
+  sizeBuffer = null,
   incoVaultAuthority,
   incoBaseVault,
   incoQuoteVault,
@@ -250,15 +305,15 @@ export const placeOrder = async ({
   price,
   sizeCiphertext,
   inputType,
-  escrowCiphertext,
-  escrowInputType,
-}: PlaceOrderParams): Promise<string> =>
-  program.methods
-    .placeOrder(side, new BN(toBigInt(price).toString()), sizeCiphertext, inputType, escrowCiphertext, escrowInputType)
+}: PlaceOrderParams): Promise<TransactionInstruction> => {
+  const method = program.methods
+    .placeOrder(side, new BN(toBigInt(price).toString()), sizeCiphertext, inputType)
     .accounts({
       state,
       order,
       trader,
+      accessPass,
+      sizeBuffer,
       incoVaultAuthority,
       incoBaseVault,
       incoQuoteVault,
@@ -269,41 +324,49 @@ export const placeOrder = async ({
       systemProgram: SystemProgram.programId,
       incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
       incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
-    })
-    .rpc();
+    });
+  const ownerAllowances = await simulateOwnerAllowances(
+    program.provider.connection,
+    await method.instruction(),
+    trader,
+  );
+  return method.remainingAccounts(ownerAllowances).instruction();
+};
+
+export const placeOrder = async (params: PlaceOrderParams): Promise<string> =>
+  sendWithComputeBudget(params.program, await buildPlaceOrderInstruction(params));
 
 export type CancelOrderParams = {
   program: OrderbookProgram;
   state: PublicKey;
   order: PublicKey;
+  orderGroup?: PublicKey | null;
   trader: PublicKey;
   incoVaultAuthority: PublicKey;
   incoBaseVault: PublicKey;
   incoQuoteVault: PublicKey;
   traderBaseInco: PublicKey;
   traderQuoteInco: PublicKey;
-  remainingCiphertext: Buffer | Uint8Array | number[];
-  inputType: number;
 };
 
 export const cancelOrder = async ({
   program,
   state,
   order,
+  orderGroup = null,
   trader,
   incoVaultAuthority,
   incoBaseVault,
   incoQuoteVault,
   traderBaseInco,
   traderQuoteInco,
-  remainingCiphertext,
-  inputType,
 }: CancelOrderParams): Promise<string> =>
   program.methods
-    .cancelOrder(remainingCiphertext, inputType)
+    .cancelOrder()
     .accounts({
       state,
       order,
+      orderGroup,
       trader,
       incoVaultAuthority,
       incoBaseVault,
@@ -321,6 +384,11 @@ export type CloseOrderParams = {
   state: PublicKey;
   order: PublicKey;
   owner: PublicKey;
+  incoVaultAuthority: PublicKey;
+  incoBaseVault: PublicKey;
+  incoQuoteVault: PublicKey;
+  ownerBaseInco: PublicKey;
+  ownerQuoteInco: PublicKey;
 };
 
 export const closeOrder = async ({
@@ -328,6 +396,11 @@ export const closeOrder = async ({
   state,
   order,
   owner,
+  incoVaultAuthority,
+  incoBaseVault,
+  incoQuoteVault,
+  ownerBaseInco,
+  ownerQuoteInco,
 }: CloseOrderParams): Promise<string> =>
   program.methods
     .closeOrder()
@@ -335,6 +408,13 @@ export const closeOrder = async ({
       state,
       order,
       owner,
+      incoVaultAuthority,
+      incoBaseVault,
+      incoQuoteVault,
+      ownerBaseInco,
+      ownerQuoteInco,
+      systemProgram: SystemProgram.programId,
+      incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
       incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
     })
     .rpc();
@@ -343,9 +423,15 @@ export type MatchOrderParams = {
   program: OrderbookProgram;
   state: PublicKey;
   makerOrder: PublicKey;
+  takerOrder: PublicKey;
   owner: PublicKey;
   matcher: PublicKey;
   taker: PublicKey;
+  takerAccessPass?: PublicKey | null;
+  priceFeed?: PublicKey | null;
+  makerGroup?: PublicKey | null;
+  takerGroup?: PublicKey | null;
+  takerReqBaseBuffer?: PublicKey | null;
   incoVaultAuthority: PublicKey;
   incoBaseVault: PublicKey;
   incoQuoteVault: PublicKey;
@@ -358,19 +444,24 @@ export type MatchOrderParams = {
   takerSide: number;
   takerPrice: U64Like;
   takerReqBaseCiphertext: Buffer | Uint8Array | number[];
-  fillBaseCiphertext: Buffer | Uint8Array | number[];
-  fillQuoteCiphertext: Buffer | Uint8Array | number[];
   inputType: number;
-  claimPlaintextAmount: U64Like;
 };
 
-export const matchOrder = async ({
+// The taker order must already be on the book: the fill is paid from both escrows, and the
+// owner allowances are read from a simulation of the match itself.
+export const buildMatchOrderInstruction = async ({
   program,
   state,
   makerOrder,
+  takerOrder,
   owner,
   matcher,
   taker,
+  takerAccessPass = null,
+  priceFeed = null,
+  makerGroup = null,
+  takerGroup = null,
+  takerReqBaseBuffer = null,
   incoVaultAuthority,
   incoBaseVault,
   incoQuoteVault,
@@ -383,27 +474,27 @@ export const matchOrder = async ({
   takerSide,
   takerPrice,
   takerReqBaseCiphertext,
-  fillBaseCiphertext,
-  fillQuoteCiphertext,
   inputType,
-  claimPlaintextAmount,
-}: MatchOrderParams): Promise<string> =>
-  program.methods
+}: MatchOrderParams): Promise<TransactionInstruction> => {
+  const method = program.methods
     .matchOrder(
       takerSide,
       new BN(toBigInt(takerPrice).toString()),
       takerReqBaseCiphertext,
-      fillBaseCiphertext,
-      fillQuoteCiphertext,
       inputType,
-      new BN(toBigInt(claimPlaintextAmount).toString()),
     )
     .accounts({
       state,
       makerOrder,
+      takerOrder,
       owner,
       matcher,
       taker,
+      takerAccessPass,
+      priceFeed,
+      makerGroup,
+      takerGroup,
+      takerReqBaseBuffer,
       incoVaultAuthority,
       incoBaseVault,
       incoQuoteVault,
@@ -416,8 +507,17 @@ export const matchOrder = async ({
       systemProgram: SystemProgram.programId,
       incoTokenProgram: INCO_TOKEN_PROGRAM_ID,
       incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
-    })
-    .rpc();
+    });
+  const ownerAllowances = await simulateOwnerAllowances(
+    program.provider.connection,
+    await method.instruction(),
+    matcher,
+  );
+  return method.remainingAccounts(ownerAllowances).instruction();
+};
+
+export const matchOrder = async (params: MatchOrderParams): Promise<string> =>
+  sendWithComputeBudget(params.program, await buildMatchOrderInstruction(params));
 
 export type BumpOrderSeqParams = {
   program: OrderbookProgram;
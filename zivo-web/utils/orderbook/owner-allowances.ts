@@ -0,0 +1,45 @@
+import {
+  type AccountMeta,
+  ComputeBudgetProgram,
+  Connection,
+  PublicKey,
+  Transaction,
+  TransactionInstruction,
+} from "@solana/web3.js";
+
+import { getAllowancePda } from "@/utils/constants";
+
+const OWNER_ALLOWANCE_LOG = /owner allowance required: (\w+) (\d+)/;
+
+// `[allowance, owner]` pairs for the handles `allow_owners` logs before failing.
+export const ownerAllowancePairs = (logs: string[]): AccountMeta[] =>
+  logs.flatMap((line) => {
+    const match = line.match(OWNER_ALLOWANCE_LOG);
+    if (!match) return [];
+    const owner = new PublicKey(match[1]);
+    const [allowance] = getAllowancePda(BigInt(match[2]), owner);
+    return [
+      { pubkey: allowance, isSigner: false, isWritable: true },
+      { pubkey: owner, isSigner: false, isWritable: false },
+    ];
+  });
+
+// New order handles are only known once the instruction runs: simulate it without owner pairs,
+// read the handles `allow_owners` logs before failing, and return their allowance PDAs. The
+// simulation runs against current state, so any transaction the instruction depends on (a wrap,
+// the taker's place) must have landed first.
+export const simulateOwnerAllowances = async (
+  connection: Connection,
+  instruction: TransactionInstruction,
+  feePayer: PublicKey,
+): Promise<AccountMeta[]> => {
+  const tx = new Transaction().add(
+    ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+    instruction,
+  );
+  tx.feePayer = feePayer;
+  tx.recentBlockhash = (await connection.getLatestBlockhash()).blockhash;
+  const sim = await connection.simulateTransaction(tx);
+  return ownerAllowancePairs(sim.value.logs ?? []);
+};
+
@@ -18,4 +18,18 @@ pub enum WrapError {
     ProofNotVerified,
     #[msg("Nullifier already spent")]
     NullifierAlreadySpent,
+    #[msg("Verifier program does not match the shielded pool")]
+    InvalidVerifier,
+    #[msg("Proof public inputs do not match the instruction")]
+    PublicInputMismatch,
+    #[msg("Proof root is not a recent shielded pool root")]
+    UnknownRoot,
+    #[msg("Pool Inco account does not match the shielded pool")]
+    InvalidPoolAccount,
+    #[msg("Shielded pool has no verifier or pool Inco account configured")]
+    PoolNotConfigured,
+    #[msg("Shielded pool is already configured")]
+    PoolAlreadyConfigured,
+    #[msg("Note backing does not match the proof commitment")]
+    InvalidNoteBacking,
 }
@@ -0,0 +1,90 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer};
+use anchor_lang::Discriminator;
+
+use crate::errors::WrapError;
+use crate::state::{ShieldedPoolConfig, Vault};
+
+/// Sets the verifier program that note spends must call and the vault-owned Inco account that
+/// backs pool notes. A pool created before these fields were appended is first grown to the
+/// current size, with the authority paying the extra rent; its existing fields are untouched.
+/// Both keys are set once, since notes already backed by the pool account depend on it.
+pub(crate) fn handler(ctx: Context<ConfigureShieldedPool>) -> Result<()> {
+    let pool_info = ctx.accounts.shielded_pool.to_account_info();
+    {
+        let data = pool_info.try_borrow_data()?;
+        require!(
+            data.len() >= 8 + ShieldedPoolConfig::LEGACY_LEN,
+            ErrorCode::AccountDidNotDeserialize
+        );
+        require!(
+            data[..8] == *ShieldedPoolConfig::DISCRIMINATOR,
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        // `authority` is the first field in every layout.
+        require!(
+            data[8..40] == ctx.accounts.authority.key().to_bytes(),
+            ErrorCode::ConstraintHasOne
+        );
+    }
+
+    let space = 8 + ShieldedPoolConfig::LEN;
+    if pool_info.data_len() < space {
+        let top_up = Rent::get()?
+            .minimum_balance(space)
+            .saturating_sub(pool_info.lamports());
+        if top_up > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.authority.to_account_info(),
+                        to: pool_info.clone(),
+                    },
+                ),
+                top_up,
+            )?;
+        }
+        pool_info.resize(space)?;
+    }
+
+    let mut pool = ShieldedPoolConfig::try_deserialize(&mut &pool_info.try_borrow_data()?[..])?;
+    require!(pool.is_initialized, WrapError::PoolNotInitialized);
+    require!(!pool.is_configured(), WrapError::PoolAlreadyConfigured);
+    require_keys_eq!(pool.vault, ctx.accounts.vault.key(), ErrorCode::ConstraintHasOne);
+    require_keys_eq!(
+        ctx.accounts.pool_inco_account.mint,
+        pool.inco_token_mint,
+        WrapError::InvalidIncoMint
+    );
+
+    pool.verifier_program = ctx.accounts.verifier_program.key();
+    pool.pool_inco_account = ctx.accounts.pool_inco_account.key();
+    pool.try_serialize(&mut &mut pool_info.try_borrow_mut_data()?[..])?;
+
+    msg!("Shielded pool configured");
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ConfigureShieldedPool<'info> {
+    /// CHECK: Shielded pool config in the current or the pre-verifier layout; the handler checks
+    /// its discriminator and authority before growing and decoding it.
+    #[account(mut, owner = crate::ID)]
+    pub shielded_pool: UncheckedAccount<'info>,
+
+    pub vault: Account<'info, Vault>,
+
+    /// Inco account owned by the vault that holds the value of pool-backed notes.
+    #[account(constraint = pool_inco_account.owner == vault.key() @ WrapError::InvalidPoolAccount)]
+    pub pool_inco_account: Account<'info, inco_token::IncoAccount>,
+
+    /// CHECK: Noir verifier program (Sunspot Groth16) that note spends must call
+    #[account(executable)]
+    pub verifier_program: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,196 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::Mint;
+use inco_lightning::cpi::{self as inco_cpi, accounts::Operation};
+use inco_lightning::types::{Ebool, Euint128};
+use light_sdk::{
+    account::LightAccount,
+    address::v2::derive_address,
+    cpi::{v2::CpiAccounts, LightCpiInstruction},
+    instruction::{PackedAddressTreeInfo, ValidityProof},
+};
+use light_sdk::cpi::InvokeLightSystemProgram;
+use light_sdk::cpi::v2::LightSystemProgramCpi;
+use inco_token::{handle_ciphertext, HANDLE_INPUT_TYPE};
+
+use crate::errors::WrapError;
+use crate::state::{CommitmentAccount, NoteBacking, ShieldedPoolConfig, Vault};
+
+const LIGHT_SYSTEM_ACCOUNTS_LEN: usize = 6;
+
+/// Moves the encrypted amount `value_handle` from `source_inco_account` into the pool's Inco
+/// account and appends `commitment`, computed by the client, as a pool-backed note. Neither the
+/// amount nor the note owner appears in the instruction: the amount stays a handle and the owner
+/// is hidden in the commitment. `NoteBacking` records what actually moved (`value_handle` when the
+/// source balance covers it, 0 otherwise), and `spend_note_to_inco` pays out at most that, so a
+/// commitment claiming more than was deposited spends to nothing. `authority` owns the source
+/// and must be allowed on `value_handle`; a program moving a handle it holds (such as a zivo-v1
+/// maker claim) signs as its vault authority.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, DepositAndCommit<'info>>,
+    value_handle: u128,
+    commitment: [u8; 32],
+    proof: ValidityProof,
+    address_tree_info: PackedAddressTreeInfo,
+    output_state_tree_index: u8,
+    system_accounts_offset: u8,
+) -> Result<()> {
+    let vault = &ctx.accounts.vault;
+    let pool = &ctx.accounts.shielded_pool;
+
+    require!(vault.is_initialized, WrapError::VaultNotInitialized);
+    require!(pool.is_initialized, WrapError::PoolNotInitialized);
+    require!(pool.is_configured(), WrapError::PoolNotConfigured);
+
+    // 1. Size what the transfer will move: the whole handle if the source covers it, else 0
+    let moved = covered_amount(
+        &ctx.accounts.inco_lightning_program.to_account_info(),
+        &ctx.accounts.authority.to_account_info(),
+        ctx.accounts.source_inco_account.amount,
+        Euint128(value_handle),
+    )?;
+
+    // 2. Move it into the pool account
+    let transfer_accounts = inco_token::cpi::accounts::IncoTransfer {
+        source: ctx.accounts.source_inco_account.to_account_info(),
+        destination: ctx.accounts.pool_inco_account.to_account_info(),
+        authority: ctx.accounts.authority.to_account_info(),
+        inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+        system_program: ctx.accounts.system_program.to_account_info(),
+    };
+    let transfer_ctx =
+        CpiContext::new(ctx.accounts.inco_token_program.to_account_info(), transfer_accounts);
+    inco_token::cpi::transfer(transfer_ctx, handle_ciphertext(moved.0), HANDLE_INPUT_TYPE)?;
+
+    // 3. Record the note's backing
+    let backing = &mut ctx.accounts.note_backing;
+    backing.value_handle = moved.0;
+    backing.bump = ctx.bumps.note_backing;
+
+    // 4. Append commitment to Light state tree (compressed account)
+    let system_start = system_accounts_offset as usize;
+    let system_end = system_start + LIGHT_SYSTEM_ACCOUNTS_LEN;
+    require!(
+        ctx.remaining_accounts.len() >= system_end,
+        WrapError::InvalidTreeConfig
+    );
+
+    let mut light_accounts: Vec<AccountInfo<'info>> = Vec::with_capacity(LIGHT_SYSTEM_ACCOUNTS_LEN + 4);
+    light_accounts.extend_from_slice(&ctx.remaining_accounts[system_start..system_end]);
+    light_accounts.push(ctx.accounts.address_tree.to_account_info());
+    if ctx.accounts.address_queue.key() != ctx.accounts.address_tree.key() {
+        light_accounts.push(ctx.accounts.address_queue.to_account_info());
+    }
+    light_accounts.push(ctx.accounts.state_queue.to_account_info());
+    light_accounts.push(ctx.accounts.state_tree.to_account_info());
+
+    let light_cpi_accounts =
+        CpiAccounts::new(ctx.accounts.user.as_ref(), &light_accounts, crate::LIGHT_CPI_SIGNER);
+    let address_tree_pubkey = address_tree_info
+        .get_tree_pubkey(&light_cpi_accounts)
+        .map_err(|_| WrapError::InvalidTreeConfig)?;
+
+    let (address, address_seed) = derive_address(
+        &[b"commitment", &commitment],
+        &address_tree_pubkey,
+        &crate::ID,
+    );
+
+    let mut commitment_account = LightAccount::<CommitmentAccount>::new_init(
+        &crate::ID,
+        Some(address),
+        output_state_tree_index,
+    );
+    commitment_account.commitment = commitment;
+
+    LightSystemProgramCpi::new_cpi(crate::LIGHT_CPI_SIGNER, proof)
+        .with_light_account(commitment_account)?
+        .with_new_addresses(&[
+            address_tree_info.into_new_address_params_assigned_packed(address_seed, Some(0)),
+        ])
+        .invoke(light_cpi_accounts)?;
+
+    Ok(())
+}
+
+/// `amount` when `balance` covers it and an encrypted 0 otherwise: what an Inco transfer of
+/// `amount` out of `balance` moves.
+pub(crate) fn covered_amount<'info>(
+    inco: &AccountInfo<'info>,
+    signer: &AccountInfo<'info>,
+    balance: Euint128,
+    amount: Euint128,
+) -> Result<Euint128> {
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let covered: Ebool = inco_cpi::e_ge(cpi_ctx, balance, amount, 0)?;
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    let zero: Euint128 = inco_cpi::as_euint128(cpi_ctx, 0)?;
+    let cpi_ctx = CpiContext::new(inco.clone(), Operation { signer: signer.clone() });
+    inco_cpi::e_select(cpi_ctx, covered, amount, zero, 0)
+}
+
+#[derive(Accounts)]
+#[instruction(value_handle: u128, commitment: [u8; 32])]
+pub struct DepositAndCommit<'info> {
+    #[account(
+        constraint = shielded_pool.spl_token_mint == spl_token_mint.key(),
+        constraint = shielded_pool.inco_token_mint == inco_token_mint.key()
+    )]
+    pub shielded_pool: Account<'info, ShieldedPoolConfig>,
+
+    #[account(
+        seeds = [b"vault", spl_token_mint.key().as_ref(), inco_token_mint.key().as_ref()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub spl_token_mint: Account<'info, Mint>,
+    pub inco_token_mint: Account<'info, inco_token::IncoMint>,
+
+    /// CHECK: Pool Inco account (owned by inco-token program), pinned by the pool
+    #[account(mut, address = shielded_pool.pool_inco_account @ WrapError::InvalidPoolAccount)]
+    pub pool_inco_account: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + NoteBacking::LEN,
+        seeds = [b"note_backing", shielded_pool.key().as_ref(), commitment.as_ref()],
+        bump
+    )]
+    pub note_backing: Account<'info, NoteBacking>,
+
+    #[account(
+        mut,
+        constraint = source_inco_account.mint == inco_token_mint.key() @ WrapError::InvalidIncoMint
+    )]
+    pub source_inco_account: Account<'info, inco_token::IncoAccount>,
+    /// Owner of `source_inco_account`.
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Inco Lightning program
+    #[account(address = inco_lightning::ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    /// CHECK: Inco Token program for CPI
+    #[account(address = inco_token::ID)]
+    pub inco_token_program: AccountInfo<'info>,
+
+    /// CHECK: Light address tree (mut)
+    #[account(mut)]
+    pub address_tree: AccountInfo<'info>,
+    /// CHECK: Light address queue (mut)
+    #[account(mut)]
+    pub address_queue: AccountInfo<'info>,
+    /// CHECK: Light state queue (mut)
+    #[account(mut)]
+    pub state_queue: AccountInfo<'info>,
+    /// CHECK: Light state tree (mut)
+    #[account(mut)]
+    pub state_tree: AccountInfo<'info>,
+
+}
@@ -2,7 +2,7 @@ use anchor_lang::prelude::*;
 use inco_lightning::cpi::accounts::Allow;
 use inco_lightning::cpi::allow;
 
-pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, GrantAllowance<'info>>) -> Result<()> {
+pub(crate) fn handler<'info>(ctx: Context<'_, '_, '_, 'info, GrantAllowance<'info>>) -> Result<()> {
     let user_inco_account = &ctx.accounts.user_inco_token_account;
 
     // Grant allowance to user if remaining_accounts provided
@@ -2,7 +2,7 @@ use anchor_lang::prelude::*;
 
 use crate::state::{ShieldedPoolConfig, Vault};
 
-pub fn handler(ctx: Context<InitShieldedPool>, tree_depth: u8) -> Result<()> {
+pub(crate) fn handler(ctx: Context<InitShieldedPool>, tree_depth: u8) -> Result<()> {
     let config = &mut ctx.accounts.shielded_pool;
 
     config.authority = ctx.accounts.authority.key();
@@ -3,7 +3,7 @@ use anchor_spl::token::{Mint, TokenAccount};
 
 use crate::state::Vault;
 
-pub fn handler(ctx: Context<InitializeVault>) -> Result<()> {
+pub(crate) fn handler(ctx: Context<InitializeVault>) -> Result<()> {
     let vault = &mut ctx.accounts.vault;
 
     vault.authority = ctx.accounts.authority.key();
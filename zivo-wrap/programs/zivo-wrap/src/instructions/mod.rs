@@ -6,6 +6,10 @@ pub mod unwrap_token;
 pub mod wrap_token;
 pub mod wrap_and_commit;
 pub mod unwrap_from_note;
+pub mod spend_note_to_inco;
+pub mod deposit_and_commit;
+pub mod update_pool_root;
+pub mod configure_shielded_pool;
 
 pub use grant_allowance::*;
 pub use initialize_vault::*;
@@ -15,3 +19,7 @@ pub use unwrap_token::*;
 pub use wrap_token::*;
 pub use wrap_and_commit::*;
 pub use unwrap_from_note::*;
+pub use spend_note_to_inco::*;
+pub use deposit_and_commit::*;
+pub use update_pool_root::*;
+pub use configure_shielded_pool::*;
@@ -0,0 +1,272 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::Mint;
+use inco_lightning::cpi::{self as inco_cpi, accounts::Operation};
+use inco_lightning::types::Euint128;
+use light_hasher::hash_to_field_size::hashv_to_bn254_field_size_be;
+use light_sdk::{
+    account::LightAccount,
+    address::v2::derive_address,
+    cpi::{v2::CpiAccounts, LightCpiInstruction},
+    instruction::{PackedAddressTreeInfo, ValidityProof},
+};
+use light_sdk::cpi::InvokeLightSystemProgram;
+use light_sdk::cpi::v2::LightSystemProgramCpi;
+use inco_token::{handle_ciphertext, HANDLE_INPUT_TYPE};
+
+use crate::errors::WrapError;
+use crate::instructions::deposit_and_commit::covered_amount;
+use crate::state::{NoteBacking, NullifierAccount, ShieldedPoolConfig, Vault};
+
+const LIGHT_SYSTEM_ACCOUNTS_LEN: usize = 6;
+
+// The circuit's public inputs, as 32-byte big-endian fields at the end of `proof_data`:
+// root, nullifier, recipient, amount, mint, commitment, leaf.
+const PUBLIC_INPUTS_LEN: usize = 7;
+const PI_ROOT: usize = 0;
+const PI_NULLIFIER: usize = 1;
+const PI_RECIPIENT: usize = 2;
+const PI_AMOUNT: usize = 3;
+const PI_MINT: usize = 4;
+const PI_COMMITMENT: usize = 5;
+const PI_LEAF: usize = 6;
+
+/// Spends a pool-backed note into any Inco account of the pool's mint (for example a market
+/// vault via CPI) without passing through the spender's wallet. The proof must come from the
+/// pool's verifier, and its public inputs must carry `nullifier`, the Inco mint and `note_owner`
+/// as recipient, a recent pool `root`, and a `leaf` equal to its `commitment` (the circuit opens
+/// the commitment to the proven amount and owner). `note_backing` must be the `NoteBacking` of
+/// that commitment, written by `deposit_and_commit`. The proof's `amount` moves out of the pool's
+/// Inco account when the backing covers it, and nothing otherwise, and the backing is closed to
+/// `user` (not to its depositor, whose key would link the deposit to this spend). The handle of
+/// what moved is returned so a calling program can size what it received.
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, SpendNoteToInco<'info>>,
+    proof_data: Vec<u8>,
+    nullifier: [u8; 32],
+    light_proof: ValidityProof,
+    address_tree_info: PackedAddressTreeInfo,
+    output_state_tree_index: u8,
+    system_accounts_offset: u8,
+) -> Result<u128> {
+    let vault = &ctx.accounts.vault;
+    let pool = &ctx.accounts.shielded_pool;
+
+    require!(vault.is_initialized, WrapError::VaultNotInitialized);
+    require!(pool.is_initialized, WrapError::PoolNotInitialized);
+    require!(pool.is_configured(), WrapError::PoolNotConfigured);
+
+    // 0. Bind the proof's public inputs to this spend
+    let inputs = public_inputs(&proof_data)?;
+    require!(pool.has_root(&inputs[PI_ROOT]), WrapError::UnknownRoot);
+    require!(inputs[PI_NULLIFIER] == nullifier, WrapError::PublicInputMismatch);
+    require!(
+        inputs[PI_RECIPIENT]
+            == hashv_to_bn254_field_size_be(&[ctx.accounts.note_owner.key().as_ref()]),
+        WrapError::PublicInputMismatch
+    );
+    require!(
+        inputs[PI_MINT]
+            == hashv_to_bn254_field_size_be(&[ctx.accounts.inco_token_mint.key().as_ref()]),
+        WrapError::PublicInputMismatch
+    );
+    let amount_field = &inputs[PI_AMOUNT];
+    require!(
+        amount_field[..24].iter().all(|b| *b == 0),
+        WrapError::PublicInputMismatch
+    );
+    let amount = u64::from_be_bytes(amount_field[24..].try_into().unwrap());
+    // The tree holds bare commitments, so the proven leaf is the commitment itself, and the
+    // commitment must be one the pool backs.
+    require!(inputs[PI_LEAF] == inputs[PI_COMMITMENT], WrapError::PublicInputMismatch);
+    let (backing_address, _) = Pubkey::find_program_address(
+        &[b"note_backing", pool.key().as_ref(), inputs[PI_COMMITMENT].as_ref()],
+        &crate::ID,
+    );
+    require_keys_eq!(
+        backing_address,
+        ctx.accounts.note_backing.key(),
+        WrapError::InvalidNoteBacking
+    );
+    let backing_info = ctx.accounts.note_backing.to_account_info();
+    require_keys_eq!(*backing_info.owner, crate::ID, WrapError::InvalidNoteBacking);
+    let backing = NoteBacking::try_deserialize(&mut &backing_info.try_borrow_data()?[..])
+        .map_err(|_| WrapError::InvalidNoteBacking)?;
+
+    // 1. Verify Noir proof via external verifier program (Sunspot Groth16)
+    let verify_ix = anchor_lang::solana_program::instruction::Instruction {
+        program_id: ctx.accounts.verifier_program.key(),
+        accounts: vec![],
+        data: proof_data,
+    };
+    anchor_lang::solana_program::program::invoke(&verify_ix, &[])?;
+
+    // 2. Create nullifier compressed account (prevents double spend)
+    let system_start = system_accounts_offset as usize;
+    let system_end = system_start + LIGHT_SYSTEM_ACCOUNTS_LEN;
+    require!(
+        ctx.remaining_accounts.len() >= system_end,
+        WrapError::InvalidTreeConfig
+    );
+
+    let mut light_accounts: Vec<AccountInfo<'info>> = Vec::with_capacity(LIGHT_SYSTEM_ACCOUNTS_LEN + 4);
+    light_accounts.extend_from_slice(&ctx.remaining_accounts[system_start..system_end]);
+    light_accounts.push(ctx.accounts.address_tree.to_account_info());
+    if ctx.accounts.address_queue.key() != ctx.accounts.address_tree.key() {
+        light_accounts.push(ctx.accounts.address_queue.to_account_info());
+    }
+    light_accounts.push(ctx.accounts.state_queue.to_account_info());
+    light_accounts.push(ctx.accounts.state_tree.to_account_info());
+
+    let light_cpi_accounts =
+        CpiAccounts::new(ctx.accounts.user.as_ref(), &light_accounts, crate::LIGHT_CPI_SIGNER);
+    let address_tree_pubkey = address_tree_info
+        .get_tree_pubkey(&light_cpi_accounts)
+        .map_err(|_| WrapError::InvalidTreeConfig)?;
+
+    let (nullifier_address, nullifier_seed) = derive_address(
+        &[b"nullifier", &nullifier],
+        &address_tree_pubkey,
+        &crate::ID,
+    );
+    let nullifier_account = LightAccount::<NullifierAccount>::new_init(
+        &crate::ID,
+        Some(nullifier_address),
+        output_state_tree_index,
+    );
+
+    LightSystemProgramCpi::new_cpi(crate::LIGHT_CPI_SIGNER, light_proof)
+        .with_light_account(nullifier_account)?
+        .with_new_addresses(&[
+            address_tree_info.into_new_address_params_assigned_packed(nullifier_seed, Some(0)),
+        ])
+        .invoke(light_cpi_accounts)?;
+
+    // 3. Move the proven note amount, capped by the note's backing, from the pool's Inco
+    // account, signed by the vault
+    let inco = ctx.accounts.inco_lightning_program.to_account_info();
+    let signer = ctx.accounts.note_owner.to_account_info();
+    let amount_handle = inco_cpi::as_euint128(
+        CpiContext::new(inco.clone(), Operation { signer: signer.clone() }),
+        amount as u128,
+    )?;
+    let moved = covered_amount(
+        &inco,
+        &signer,
+        Euint128(backing.value_handle),
+        amount_handle,
+    )?;
+
+    let seeds = &[
+        b"vault",
+        vault.spl_token_mint.as_ref(),
+        vault.inco_token_mint.as_ref(),
+        &[vault.bump],
+    ];
+    let signer_seeds = &[&seeds[..]];
+
+    inco_token::cpi::transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.inco_token_program.to_account_info(),
+            inco_token::cpi::accounts::IncoTransfer {
+                source: ctx.accounts.pool_inco_account.to_account_info(),
+                destination: ctx.accounts.destination_inco_account.to_account_info(),
+                authority: ctx.accounts.vault.to_account_info(),
+                inco_lightning_program: ctx.accounts.inco_lightning_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        handle_ciphertext(moved.0),
+        HANDLE_INPUT_TYPE,
+    )?;
+
+    // 4. Close the spent note's backing to `user`
+    let user_info = ctx.accounts.user.to_account_info();
+    let refund = backing_info.lamports();
+    **user_info.try_borrow_mut_lamports()? += refund;
+    **backing_info.try_borrow_mut_lamports()? = 0;
+    backing_info.assign(&anchor_lang::system_program::ID);
+    backing_info.resize(0)?;
+
+    Ok(moved.0)
+}
+
+/// Splits the public witness off the end of `proof_data`.
+fn public_inputs(proof_data: &[u8]) -> Result<[[u8; 32]; PUBLIC_INPUTS_LEN]> {
+    let start = proof_data
+        .len()
+        .checked_sub(PUBLIC_INPUTS_LEN * 32)
+        .ok_or(WrapError::PublicInputMismatch)?;
+    let mut inputs = [[0u8; 32]; PUBLIC_INPUTS_LEN];
+    for (i, input) in inputs.iter_mut().enumerate() {
+        input.copy_from_slice(&proof_data[start + i * 32..start + (i + 1) * 32]);
+    }
+    Ok(inputs)
+}
+
+#[derive(Accounts)]
+pub struct SpendNoteToInco<'info> {
+    #[account(
+        constraint = shielded_pool.spl_token_mint == spl_token_mint.key(),
+        constraint = shielded_pool.inco_token_mint == inco_token_mint.key()
+    )]
+    pub shielded_pool: Account<'info, ShieldedPoolConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", spl_token_mint.key().as_ref(), inco_token_mint.key().as_ref()],
+        bump = vault.bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub spl_token_mint: Account<'info, Mint>,
+    pub inco_token_mint: Account<'info, inco_token::IncoMint>,
+
+    /// CHECK: Pool Inco account (owned by inco-token program), pinned by the pool
+    #[account(mut, address = shielded_pool.pool_inco_account @ WrapError::InvalidPoolAccount)]
+    pub pool_inco_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = destination_inco_account.mint == inco_token_mint.key() @ WrapError::InvalidIncoMint
+    )]
+    pub destination_inco_account: Account<'info, inco_token::IncoAccount>,
+
+    /// CHECK: `NoteBacking` of the proof's commitment; the handler derives its address from the
+    /// public inputs before decoding it, and closes it to `user`.
+    #[account(mut)]
+    pub note_backing: UncheckedAccount<'info>,
+
+    /// Owner of the note; the proof's `recipient` must be its field hash.
+    pub note_owner: Signer<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: Noir verifier program (Sunspot Groth16), pinned by the pool
+    #[account(address = shielded_pool.verifier_program @ WrapError::InvalidVerifier)]
+    pub verifier_program: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Inco Lightning program
+    #[account(address = inco_lightning::ID)]
+    pub inco_lightning_program: AccountInfo<'info>,
+    /// CHECK: Inco Token program for CPI
+    #[account(address = inco_token::ID)]
+    pub inco_token_program: AccountInfo<'info>,
+
+    /// CHECK: Light address tree (mut)
+    #[account(mut)]
+    pub address_tree: AccountInfo<'info>,
+    /// CHECK: Light address queue (mut)
+    #[account(mut)]
+    pub address_queue: AccountInfo<'info>,
+    /// CHECK: Light state queue (mut)
+    #[account(mut)]
+    pub state_queue: AccountInfo<'info>,
+    /// CHECK: Light state tree (mut)
+    #[account(mut)]
+    pub state_tree: AccountInfo<'info>,
+
+}
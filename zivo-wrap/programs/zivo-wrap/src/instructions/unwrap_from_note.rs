@@ -14,7 +14,8 @@ use crate::state::{NullifierAccount, ShieldedPoolConfig, UnwrapEvent, Vault};
 
 const LIGHT_SYSTEM_ACCOUNTS_LEN: usize = 6;
 
-pub fn handler<'info>(
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn handler<'info>(
     ctx: Context<'_, '_, '_, 'info, UnwrapFromNote<'info>>,
     proof_data: Vec<u8>,
     nullifier: [u8; 32],
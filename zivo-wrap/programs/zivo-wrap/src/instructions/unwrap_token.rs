@@ -4,7 +4,7 @@ use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
 use crate::errors::WrapError;
 use crate::state::{UnwrapEvent, Vault};
 
-pub fn handler<'info>(
+pub(crate) fn handler<'info>(
     ctx: Context<'_, '_, '_, 'info, UnwrapToken<'info>>,
     ciphertext: Vec<u8>,
     input_type: u8,
@@ -0,0 +1,22 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::WrapError;
+use crate::state::ShieldedPoolConfig;
+
+/// Records a note tree root that spend proofs may use. The pool keeps the last
+/// `POOL_ROOT_HISTORY` roots so proofs built against a slightly older root still verify.
+pub(crate) fn handler(ctx: Context<UpdatePoolRoot>, root: [u8; 32]) -> Result<()> {
+    let pool = &mut ctx.accounts.shielded_pool;
+    require!(pool.is_initialized, WrapError::PoolNotInitialized);
+
+    pool.push_root(root);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UpdatePoolRoot<'info> {
+    #[account(mut, has_one = authority)]
+    pub shielded_pool: Account<'info, ShieldedPoolConfig>,
+
+    pub authority: Signer<'info>,
+}
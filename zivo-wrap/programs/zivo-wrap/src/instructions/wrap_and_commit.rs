@@ -14,7 +14,8 @@ use crate::state::{CommitmentAccount, ShieldedPoolConfig, Vault, WrapEvent};
 
 const LIGHT_SYSTEM_ACCOUNTS_LEN: usize = 6;
 
-pub fn handler<'info>(
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn handler<'info>(
     ctx: Context<'_, '_, '_, 'info, WrapAndCommit<'info>>,
     ciphertext: Vec<u8>,
     input_type: u8,
@@ -1,3 +1,9 @@
+// `#[program]` expands to IDL account instructions that call `AccountInfo::realloc`, deprecated
+// under the pinned Anchor 0.31, and, with `cpi`, to a helper per instruction taking all of its
+// arguments. Neither expansion can carry an attribute of its own.
+#![allow(deprecated)]
+#![cfg_attr(feature = "cpi", allow(clippy::too_many_arguments))]
+
 use anchor_lang::prelude::*;
 use light_sdk::cpi::CpiSigner;
 use light_sdk::derive_light_cpi_signer;
@@ -34,6 +40,15 @@ pub mod zivo_wrap {
         instructions::init_shielded_pool::handler(ctx, tree_depth)
     }
 
+    pub fn configure_shielded_pool(ctx: Context<ConfigureShieldedPool>) -> Result<()> {
+        instructions::configure_shielded_pool::handler(ctx)
+    }
+
+    pub fn update_pool_root(ctx: Context<UpdatePoolRoot>, root: [u8; 32]) -> Result<()> {
+        instructions::update_pool_root::handler(ctx, root)
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn wrap_and_commit<'info>(
         ctx: Context<'_, '_, '_, 'info, WrapAndCommit<'info>>,
         ciphertext: Vec<u8>,
@@ -58,6 +73,7 @@ pub mod zivo_wrap {
         )
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn shielded_transfer<'info>(
         ctx: Context<'_, '_, '_, 'info, ShieldedTransfer<'info>>,
         proof_data: Vec<u8>,
@@ -80,6 +96,7 @@ pub mod zivo_wrap {
         )
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn unwrap_from_note<'info>(
         ctx: Context<'_, '_, '_, 'info, UnwrapFromNote<'info>>,
         proof_data: Vec<u8>,
@@ -106,6 +123,46 @@ pub mod zivo_wrap {
         )
     }
 
+    pub fn spend_note_to_inco<'info>(
+        ctx: Context<'_, '_, '_, 'info, SpendNoteToInco<'info>>,
+        proof_data: Vec<u8>,
+        nullifier: [u8; 32],
+        light_proof: light_sdk::instruction::ValidityProof,
+        address_tree_info: light_sdk::instruction::PackedAddressTreeInfo,
+        output_state_tree_index: u8,
+        system_accounts_offset: u8,
+    ) -> Result<u128> {
+        instructions::spend_note_to_inco::handler(
+            ctx,
+            proof_data,
+            nullifier,
+            light_proof,
+            address_tree_info,
+            output_state_tree_index,
+            system_accounts_offset,
+        )
+    }
+
+    pub fn deposit_and_commit<'info>(
+        ctx: Context<'_, '_, '_, 'info, DepositAndCommit<'info>>,
+        value_handle: u128,
+        commitment: [u8; 32],
+        proof: light_sdk::instruction::ValidityProof,
+        address_tree_info: light_sdk::instruction::PackedAddressTreeInfo,
+        output_state_tree_index: u8,
+        system_accounts_offset: u8,
+    ) -> Result<()> {
+        instructions::deposit_and_commit::handler(
+            ctx,
+            value_handle,
+            commitment,
+            proof,
+            address_tree_info,
+            output_state_tree_index,
+            system_accounts_offset,
+        )
+    }
+
     pub fn grant_allowance<'info>(
         ctx: Context<'_, '_, '_, 'info, GrantAllowance<'info>>,
     ) -> Result<()> {
@@ -43,10 +43,53 @@ pub struct ShieldedPoolConfig {
     pub tree_depth: u8,
     pub is_initialized: bool,
     pub bump: u8,
+    // Fields below were appended after pools were deployed; `configure_shielded_pool` grows a
+    // pool created before them and sets the two keys.
+    /// Noir verifier program that note spends must call.
+    pub verifier_program: Pubkey,
+    /// Inco account owned by the vault that holds the value of pool-backed notes.
+    pub pool_inco_account: Pubkey,
+    /// Recent note tree roots posted by the authority; a spend proof must use one of them.
+    pub roots: [[u8; 32]; POOL_ROOT_HISTORY],
+    pub root_cursor: u8,
 }
 
+pub const POOL_ROOT_HISTORY: usize = 16;
+
 impl ShieldedPoolConfig {
-    pub const LEN: usize = 32 + 32 + 32 + 32 + 32 + 32 + 32 + 1 + 1 + 1;
+    /// Size of a pool created before `verifier_program` was appended.
+    pub const LEGACY_LEN: usize = 32 + 32 + 32 + 32 + 32 + 32 + 32 + 1 + 1 + 1;
+    pub const LEN: usize = Self::LEGACY_LEN + 32 + 32 + 32 * POOL_ROOT_HISTORY + 1;
+
+    /// Whether `configure_shielded_pool` has set the verifier and the pool Inco account.
+    pub fn is_configured(&self) -> bool {
+        self.verifier_program != Pubkey::default() && self.pool_inco_account != Pubkey::default()
+    }
+
+    /// Whether `root` is in the recent root history. The zero root is never known.
+    pub fn has_root(&self, root: &[u8; 32]) -> bool {
+        *root != [0u8; 32] && self.roots.iter().any(|known| known == root)
+    }
+
+    /// Records `root`, overwriting the oldest entry.
+    pub fn push_root(&mut self, root: [u8; 32]) {
+        let cursor = self.root_cursor as usize % POOL_ROOT_HISTORY;
+        self.roots[cursor] = root;
+        self.root_cursor = ((cursor + 1) % POOL_ROOT_HISTORY) as u8;
+    }
+}
+
+/// Value held in the pool Inco account for one note commitment, as an Inco handle. Written by
+/// `deposit_and_commit` and closed by the `spend_note_to_inco` that spends the note, which moves
+/// at most this value. Seeds: `[b"note_backing", pool, commitment]`.
+#[account]
+pub struct NoteBacking {
+    pub value_handle: u128,
+    pub bump: u8,
+}
+
+impl NoteBacking {
+    pub const LEN: usize = 16 + 1;
 }
 
 #[event]
@@ -334,6 +334,7 @@ describe("zivo-wrap", () => {
   let vaultTokenAccount: PublicKey;
   let userSplTokenAccount: PublicKey;
   let userIncoTokenAccount: Keypair;
+  let poolIncoTokenAccount: Keypair;
   let shieldedPoolPda: PublicKey;
   let lastCommitment: Uint8Array | null = null;
   let lutAccount: AddressLookupTableAccount | null = null;
@@ -519,11 +520,6 @@ describe("zivo-wrap", () => {
     };
   }
 
-  function toHex32(value: any): string {
-    const bytes = Buffer.from(value.toArray("be", 32));
-    return `0x${bytes.toString("hex")}`;
-  }
-
   async function buildProofPayloadFromLight(
     commitment: Uint8Array,
     note: NoteFields | null
@@ -540,31 +536,17 @@ describe("zivo-wrap", () => {
     const addressTree = DEFAULT_ADDRESS_TREE_INFO.tree;
     const address = deriveAddressV2(seed, addressTree, program.programId);
 
-    let compressed = await rpc.getCompressedAccount(bn(address.toBytes()));
+    const compressed = await rpc.getCompressedAccount(bn(address.toBytes()));
     if (!compressed) {
       throw new Error("compressed account not found for commitment");
     }
-    let proof;
-    for (let i = 0; i < 10; i++) {
-      try {
-        proof = await rpc.getCompressedAccountProof(compressed.hash);
-        break;
-      } catch (error) {
-        await new Promise((resolve) => setTimeout(resolve, 1000));
-      }
+    // The pool tree's leaves are bare commitments (the circuit requires leaf == commitment);
+    // this note sits alone at index 0, so every sibling is zero.
+    const siblings: string[] = Array.from({ length: 32 }, () => "0x0");
+    let rootField = note.noteHash;
+    for (let i = 0; i < 32; i++) {
+      rootField = poseidonHash2(rootField, bn(0));
     }
-    if (!proof) {
-      throw new Error("failed to fetch compressed account proof");
-    }
-
-    const siblings = proof.merkleProof.map((sib: any) => toHex32(sib));
-    if (siblings.length > 32) {
-      throw new Error(`merkleProof depth ${siblings.length} exceeds circuit depth 32`);
-    }
-    while (siblings.length < 32) {
-      siblings.push("0x0");
-    }
-
     const nullifierSecret = bn(randomFieldBytes());
     const nullifierHash = poseidonHash2(note.noteHash, nullifierSecret);
     const nullifierBytes = new Uint8Array(nullifierHash.toArray("be", 32));
@@ -575,9 +557,9 @@ describe("zivo-wrap", () => {
     const blindingHex = bnToHex32(note.blindingField);
     const nullifierSecretHex = bnToHex32(nullifierSecret);
     console.log("Noir proof inputs:", {
-      root: toHex32(proof.root),
-      leaf: toHex32(proof.hash),
-      index: proof.leafIndex ?? compressed.leafIndex,
+      root: bnToHex32(rootField),
+      leaf: commitmentHex,
+      index: 0,
       siblings: siblings.length,
       owner: ownerHex,
       mint: mintHex,
@@ -588,14 +570,14 @@ describe("zivo-wrap", () => {
     const proofPayload = generateProofPayload(
       { circuitDir: ZK_CIRCUIT_DIR, circuitName: ZK_CIRCUIT_NAME },
       {
-        root: toHex32(proof.root),
+        root: bnToHex32(rootField),
         nullifier: nullifierHex,
         recipient: ownerHex,
         amount: note.amountField.toString(),
         mint: mintHex,
         commitment: commitmentHex,
-        leaf: toHex32(proof.hash),
-        index: (proof.leafIndex ?? compressed.leafIndex).toString(),
+        leaf: commitmentHex,
+        index: "0",
         siblings,
         owner: ownerHex,
         blinding: blindingHex,
@@ -765,6 +747,25 @@ describe("zivo-wrap", () => {
         .rpc();
     }
 
+    // Create the shielded pool's Inco account (owned by vault PDA); it backs unspent notes
+    console.log("Creating pool Inco token account...");
+    poolIncoTokenAccount = loadOrCreateKeypair("pool-inco-token-account");
+    const poolIncoInfo = await connection.getAccountInfo(poolIncoTokenAccount.publicKey);
+    if (!poolIncoInfo) {
+      await incoTokenProgram.methods
+        .initializeAccount()
+        .accounts({
+          account: poolIncoTokenAccount.publicKey,
+          mint: incoMint.publicKey,
+          owner: vaultPda,
+          payer: walletKeypair.publicKey,
+          systemProgram: SystemProgram.programId,
+          incoLightningProgram: INCO_LIGHTNING_PROGRAM_ID,
+        } as any)
+        .signers([poolIncoTokenAccount])
+        .rpc();
+    }
+
     const userSplBalance = await getAccount(connection, userSplTokenAccount);
     console.log(`User SPL Balance: ${formatBalance(userSplBalance.amount.toString())} tokens`);
 
@@ -921,6 +922,52 @@ describe("zivo-wrap", () => {
         .rpc();
       console.log("Shielded pool tx:", tx);
     });
+
+    it("Should configure the shielded pool (verifier + pool Inco account)", async () => {
+      const pool = await program.account.shieldedPoolConfig.fetch(shieldedPoolPda).catch(() => null);
+      if (pool && !(pool as any).verifierProgram.equals(PublicKey.default)) {
+        console.log("Shielded pool already configured, skipping.");
+        return;
+      }
+      // Also grows a pool created before the verifier fields were appended.
+      const tx = await program.methods
+        .configureShieldedPool()
+        .accounts({
+          shieldedPool: shieldedPoolPda,
+          vault: vaultPda,
+          poolIncoAccount: poolIncoTokenAccount.publicKey,
+          verifierProgram: ZK_VERIFIER_PROGRAM_ID ?? program.programId,
+          authority: walletKeypair.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      console.log("Configure shielded pool tx:", tx);
+
+      const configured = await program.account.shieldedPoolConfig.fetch(shieldedPoolPda);
+      if (!(configured as any).poolIncoAccount.equals(poolIncoTokenAccount.publicKey)) {
+        throw new Error("configure_shielded_pool should pin the pool Inco account");
+      }
+      let reconfigured = false;
+      try {
+        await program.methods
+          .configureShieldedPool()
+          .accounts({
+            shieldedPool: shieldedPoolPda,
+            vault: vaultPda,
+            poolIncoAccount: poolIncoTokenAccount.publicKey,
+            verifierProgram: program.programId,
+            authority: walletKeypair.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+        reconfigured = true;
+      } catch (error: any) {
+        if (!String(error).includes("PoolAlreadyConfigured")) throw error;
+      }
+      if (reconfigured) {
+        throw new Error("configure_shielded_pool should refuse to repoint a configured pool");
+      }
+    });
   });
 
   describe("Wrap Token", () => {